@@ -0,0 +1,82 @@
+//! Compares looking up nodes with enough free cores and memory by scanning the nodes sorted by
+//! cores against the memory index of the NodeRegistry, on a cluster with 10k nodes.
+//!
+//! Run with `cargo bench --bench placement`
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+use std::time::Instant;
+
+use rand::Rng;
+use rand::SeedableRng;
+
+use dismem::registry::ClusterSpec;
+use dismem::registry::NodeRegistry;
+use dismem::registry::TopologyPattern;
+use dismem::SimRng;
+
+const NODES: usize = 10_000;
+const ROUNDS: usize = 20_000;
+
+/// Allocates or frees the resources of a random node, then looks up the first node that fits a
+/// random request. Both ways keep the sorted indices up to date in the same way outside of the
+/// timed region. Returns how long the lookups took and how many of them found a node
+fn run(indexed: bool) -> (Duration, usize) {
+    let spec = ClusterSpec {
+        nodes: NODES,
+        cores: 1..=64,
+        memory: 1..=512,
+        resources: BTreeMap::new(),
+        topology: TopologyPattern::FullyConnected,
+    };
+    let mut registry = NodeRegistry::generate(&spec, 42).unwrap();
+    let mut rng = SimRng::seed_from_u64(7);
+    let mut found = 0;
+    let mut elapsed = Duration::ZERO;
+    registry.ensure_sorted();
+
+    for _ in 0..ROUNDS {
+        let uid = rng.gen_range(0..NODES);
+        let node = &mut registry.nodes[uid];
+        if node.cores.current >= 1. && node.memory.current >= 8. {
+            node.allocate_job(1., 8.);
+        } else {
+            node.free_cores(node.cores.capacity - node.cores.current);
+            node.free_memory(node.memory.capacity - node.memory.current);
+        }
+
+        registry.touch(uid);
+        registry.ensure_sorted();
+
+        // VV: Requests with few cores but lots of memory are the worst case of the scan, those
+        // with more memory than any node has make it visit every node with enough cores
+        let cores = rng.gen_range(1..4) as f32;
+        let memory = rng.gen_range(256..640) as f32;
+
+        let started = Instant::now();
+        let first = if indexed {
+            registry.nodes_with_at_least(cores, memory).next()
+        } else {
+            let start = registry.idx_nodes_with_more_cores(cores);
+            registry.sorted_cores[start..]
+                .iter()
+                .copied()
+                .find(|uid| registry.nodes[*uid].memory.current >= memory)
+        };
+        elapsed += started.elapsed();
+        found += first.is_some() as usize;
+    }
+
+    (elapsed, found)
+}
+
+fn main() {
+    let (scan, scan_found) = run(false);
+    let (index, index_found) = run(true);
+    assert_eq!(scan_found, index_found);
+
+    println!("{} lookups on {} nodes, {} found a node", ROUNDS, NODES, scan_found);
+    println!("scan:    {:>10.3?}", scan);
+    println!("indexed: {:>10.3?}", index);
+    println!("speedup: {:>10.1}x", scan.as_secs_f64() / index.as_secs_f64());
+}
@@ -0,0 +1,149 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::job::Job;
+use crate::job::JobId;
+use crate::node::NodeId;
+use crate::profile;
+use crate::registry::NodeRegistry;
+use crate::scheduler::FirstFit;
+use crate::scheduler::Placement;
+use crate::scheduler::PolicyContext;
+use crate::scheduler::SchedulingPolicy;
+
+/// EASY backfilling: jobs start in the order they were queued until the first job that does not
+/// fit (the head). The head gets a reservation on the nodes that will have enough free resources
+/// for it the earliest, based on the estimated runtime of the running jobs. Jobs behind the head
+/// may start right away as long as they do not delay the reservation, i.e. they either finish
+/// (per their estimate) before the reservation starts or they do not use any of the reserved nodes.
+#[derive(Default)]
+pub struct Backfill {
+    /// VV: Maps the nodes that the head of the queue has reserved to the simulated time at which
+    /// the head is expected to start running on them
+    pub reservations: HashMap<NodeId, f32>,
+}
+
+impl Backfill {
+    /// Computes when and where the @head job could start if the running jobs finish when
+    /// their estimated runtime suggests
+    fn reserve_for(
+        registry: &NodeRegistry,
+        head: &Job,
+        context: &PolicyContext,
+    ) -> Option<(f32, HashSet<NodeId>)> {
+        let mut free = registry.free_resources();
+
+        // VV: Jobs that outlive their estimate are expected to finish any moment now
+        let mut running: Vec<(f32, &Job)> = context.jobs_running
+            .iter()
+            .map(|job| {
+                let ends = job.time_started.unwrap() + job.staging + job.estimate / job.speed;
+                (ends.max(context.now), job)
+            })
+            .collect();
+        running.sort_by(|a, b| a.0.partial_cmp(&b.0)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.1.uid.cmp(&b.1.uid)));
+
+        for (ends, job) in running {
+            free.release(job);
+
+            if let Some(nodes) = registry.fits_with(head, &free) {
+                return Some((ends, nodes));
+            }
+        }
+
+        None
+    }
+}
+
+impl SchedulingPolicy for Backfill {
+    fn select(
+        &mut self,
+        queued: &[Job],
+        registry: &mut NodeRegistry,
+        context: &PolicyContext,
+    ) -> Vec<(JobId, Placement)> {
+        let mut selected = vec![];
+
+        // VV: When nothing finished since the last decision the head of the queue is still blocked
+        let mut head = if context.unchanged > 0 { Some(0) } else { None };
+
+        if head.is_none() {
+            for (i, job) in queued.iter().enumerate() {
+                profile::count_attempt();
+                match FirstFit::job_place(registry, job) {
+                    Some(placement) => {
+                        placement.reserve(registry, job);
+                        selected.push((job.uid, placement));
+                    }
+                    None => {
+                        head = Some(i);
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.reservations.clear();
+
+        let head = match head {
+            Some(head) => head,
+            None => return selected,
+        };
+
+        let reservation = Self::reserve_for(registry, &queued[head], context);
+
+        if let Some((starts, nodes)) = &reservation {
+            for uid in nodes {
+                self.reservations.insert(*uid, *starts);
+            }
+        }
+
+        for job in queued.iter().skip(context.unchanged.max(head + 1)) {
+            profile::count_attempt();
+            let placement = match FirstFit::job_place(registry, job) {
+                Some(placement) => placement,
+                None => continue,
+            };
+
+            let delays_head = match &reservation {
+                Some((starts, nodes)) => {
+                    // VV: The job runs for its estimate at the speed of its node
+                    context.now + placement.staging(registry, job.input_data)
+                        + job.estimate / placement.speed(registry) > *starts
+                        && (nodes.contains(&placement.node_cores)
+                        || placement.node_memory.iter().any(|(uid, _)| nodes.contains(uid))
+                        || placement.gang_cores.iter().any(|(uid, _)| nodes.contains(uid)))
+                }
+                None => false,
+            };
+
+            if !delays_head {
+                placement.reserve(registry, job);
+                selected.push((job.uid, placement));
+            }
+        }
+
+        selected
+    }
+}
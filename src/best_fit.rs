@@ -0,0 +1,154 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+
+use crate::job::Job;
+use crate::job::JobId;
+use crate::node::Node;
+use crate::profile;
+use crate::registry::NodeRegistry;
+use crate::scheduler::FirstFit;
+use crate::scheduler::Placement;
+use crate::scheduler::PolicyContext;
+use crate::scheduler::SchedulingPolicy;
+
+/// Ranks the nodes that could host a job on their own, BestFit picks the highest score
+pub trait NodeScorer {
+    fn score(&self, node: &Node, job: &Job) -> f64;
+}
+
+/// Spreads jobs over the cluster by preferring the node with the most free memory
+#[derive(Default)]
+pub struct MostFreeMemory {}
+
+impl NodeScorer for MostFreeMemory {
+    fn score(&self, node: &Node, _job: &Job) -> f64 {
+        node.memory.current as f64
+    }
+}
+
+/// Packs jobs onto as few nodes as possible by preferring the node with the least free memory
+#[derive(Default)]
+pub struct LeastFreeMemory {}
+
+impl NodeScorer for LeastFreeMemory {
+    fn score(&self, node: &Node, _job: &Job) -> f64 {
+        -node.memory.current as f64
+    }
+}
+
+/// Prefers the nodes that are warm for the affinity group of the job (see Node::is_warm()) and
+/// ranks the rest, as well as the warm nodes among themselves, with the inner scorer
+pub struct WarmCache {
+    pub inner: Box<dyn NodeScorer>,
+}
+
+impl WarmCache {
+    // VV: Larger than the score of any node under the scorers that rank by free memory
+    pub const BONUS: f64 = 1e15;
+
+    pub fn new(inner: Box<dyn NodeScorer>) -> Self {
+        Self { inner }
+    }
+}
+
+impl NodeScorer for WarmCache {
+    fn score(&self, node: &Node, job: &Job) -> f64 {
+        let bonus = if node.is_warm(job) { Self::BONUS } else { 0.0 };
+        self.inner.score(node, job) + bonus
+    }
+}
+
+/// Visits queued jobs in order like FirstFit but places each job on the feasible node with the
+/// highest NodeScorer::score(), ties go to the node with the smallest uid. Jobs that do not fit on
+/// a single node (i.e. they need to borrow memory or are gang jobs that span nodes) fall back to
+/// the placement of FirstFit.
+///
+/// Every job costs a pass over all nodes.
+pub struct BestFit {
+    pub scorer: Box<dyn NodeScorer>,
+}
+
+impl BestFit {
+    pub fn new(scorer: Box<dyn NodeScorer>) -> Self {
+        Self { scorer }
+    }
+
+    /// Spreads jobs, see MostFreeMemory
+    pub fn spread() -> Self {
+        Self::new(Box::new(MostFreeMemory::default()))
+    }
+
+    /// Packs jobs, see LeastFreeMemory
+    pub fn pack() -> Self {
+        Self::new(Box::new(LeastFreeMemory::default()))
+    }
+
+    /// Wraps the scorer in WarmCache so that jobs prefer the nodes that are warm for them
+    pub fn prefer_warm(self) -> Self {
+        Self::new(Box::new(WarmCache::new(self.scorer)))
+    }
+
+    pub fn job_place(&self, registry: &mut NodeRegistry, job: &Job) -> Option<Placement> {
+        let mut best: Option<(f64, &Node)> = None;
+
+        for node in &registry.nodes {
+            profile::count_check();
+            if node.cores.current < job.cores || node.memory.current < job.memory
+                || !node.has_resources(&job.resources, 1.0) || !node.accepts(job) {
+                continue;
+            }
+
+            let score = self.scorer.score(node, job);
+            if best.is_none_or(|(best, _)| score > best) {
+                best = Some((score, node));
+            }
+        }
+
+        match best {
+            Some((_, node)) => Some(Placement {
+                node_cores: node.uid,
+                node_memory: vec![(node.uid, job.memory)],
+                gang_cores: vec![],
+            }),
+            None => FirstFit::job_place(registry, job),
+        }
+    }
+}
+
+impl SchedulingPolicy for BestFit {
+    fn select(
+        &mut self,
+        queued: &[Job],
+        registry: &mut NodeRegistry,
+        context: &PolicyContext,
+    ) -> Vec<(JobId, Placement)> {
+        let mut selected = vec![];
+
+        for job in queued.iter().skip(context.unchanged) {
+            profile::count_attempt();
+            if let Some(placement) = self.job_place(registry, job) {
+                placement.reserve(registry, job);
+                selected.push((job.uid, placement));
+            }
+        }
+
+        selected
+    }
+}
@@ -229,7 +229,7 @@ fn digest_task_events_into_book<P: AsRef<Path>>(
     let mut num_full_tasks = 0;
     let mut num_dropped_tasks = 0;
 
-    let file = File::open(&path)
+    let file = File::open(path)
         .context(format!("Unable to open input file {}", path.display()))?;
 
     let mut latest = 0.0;
@@ -340,7 +340,7 @@ fn digest_task_events_into_book<P: AsRef<Path>>(
                     // VV: Must have been the wind ...
                     continue
                 }
-                let mut task = draft.get_mut(&key).unwrap();
+                let task = draft.get_mut(&key).unwrap();
 
                 task.time_started = timestamp()?;
                 latest = task.time_started;
@@ -0,0 +1,90 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+
+use std::time::Instant;
+
+use crate::scheduler::Scheduler;
+
+/// A source of time in seconds, the progress of a run can be measured in simulated time via
+/// SimClock or in wall-clock time via WallClock
+pub trait Clock {
+    fn now(&self, sched: &Scheduler) -> f32;
+}
+
+/// The simulated time of the Scheduler, i.e. Scheduler::now
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SimClock;
+
+impl Clock for SimClock {
+    fn now(&self, sched: &Scheduler) -> f32 {
+        sched.now
+    }
+}
+
+/// The wall-clock seconds since the WallClock was created
+#[derive(Debug, Clone, Copy)]
+pub struct WallClock {
+    start: Instant,
+}
+
+impl WallClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for WallClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for WallClock {
+    fn now(&self, _sched: &Scheduler) -> f32 {
+        self.start.elapsed().as_secs_f32()
+    }
+}
+
+/// Fires once every @every seconds of a Clock, e.g. to report the progress of a run
+pub struct Cadence {
+    pub clock: Box<dyn Clock>,
+    pub every: f32,
+    last: f32,
+}
+
+impl Cadence {
+    /// The first period starts at the current time of @clock
+    pub fn new(clock: Box<dyn Clock>, every: f32, sched: &Scheduler) -> Self {
+        let last = clock.now(sched);
+        Self { clock, every, last }
+    }
+
+    /// Returns true when more than @every seconds passed since the last time it returned true
+    pub fn due(&mut self, sched: &Scheduler) -> bool {
+        let now = self.clock.now(sched);
+
+        if now - self.last > self.every {
+            self.last = now;
+            true
+        } else {
+            false
+        }
+    }
+}
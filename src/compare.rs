@@ -0,0 +1,187 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+
+//! Runs the same workload under 2 scheduling policies and compares their key metrics, see
+//! compare_policies()
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use crate::job::Job;
+use crate::job_factory::JobCollection;
+use crate::job_factory::JobFactory;
+use crate::registry::NodeRegistry;
+use crate::scheduler::SchedulerBuilder;
+use crate::scheduler::SchedulingPolicy;
+use crate::summary::JobTimes;
+use crate::summary::RunSummary;
+
+/// The user and the times of a job that finished or failed
+pub type UserTimes = (Option<String>, JobTimes);
+
+/// The metrics of 1 of the policies of a PolicyComparison
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyMetrics {
+    pub policy: String,
+    pub summary: RunSummary,
+    /// Jain's fairness index of the mean bounded slowdown of each user, between 1/users and 1
+    /// which means that all users saw the same slowdown. Jobs without a user count as 1 user
+    pub fairness: f64,
+}
+
+/// The outcome of compare_policies(), the deltas in rows() are b - a
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyComparison {
+    pub a: PolicyMetrics,
+    pub b: PolicyMetrics,
+}
+
+impl PolicyComparison {
+    /// Returns the name of each metric with its value under policy a and under policy b
+    pub fn rows(&self) -> Vec<(&'static str, f64, f64)> {
+        let (a, b) = (&self.a.summary, &self.b.summary);
+        vec![
+            ("makespan", a.makespan, b.makespan),
+            ("mean wait", a.mean_wait, b.mean_wait),
+            ("p95 wait", a.p95_wait, b.p95_wait),
+            ("mean slowdown", a.mean_slowdown, b.mean_slowdown),
+            ("core utilization", a.core_utilization, b.core_utilization),
+            ("memory utilization", a.memory_utilization, b.memory_utilization),
+            ("fairness", self.a.fairness, self.b.fairness),
+            ("failed jobs", a.jobs_failed as f64, b.jobs_failed as f64),
+        ]
+    }
+}
+
+impl Display for PolicyComparison {
+    /// Prints 1 row per metric with the value of each policy and the difference b - a
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:<20} {:>14} {:>14} {:>14}", "metric", self.a.policy, self.b.policy,
+                 "difference")?;
+        for (metric, a, b) in self.rows() {
+            writeln!(f, "{:<20} {:>14.4} {:>14.4} {:>+14.4}", metric, a, b, b - a)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns Jain's fairness index of the mean bounded slowdown of each user in @jobs, 1 if there
+/// are no jobs
+pub fn jain_fairness(jobs: &[UserTimes], threshold: f32) -> f64 {
+    let mut per_user: BTreeMap<Option<&str>, (f64, usize)> = BTreeMap::new();
+    for (user, times) in jobs {
+        let entry = per_user.entry(user.as_deref()).or_default();
+        entry.0 += times.slowdown(threshold) as f64;
+        entry.1 += 1;
+    }
+
+    let means: Vec<f64> = per_user.values().map(|(sum, count)| sum / *count as f64).collect();
+    let sum: f64 = means.iter().sum();
+    let squares: f64 = means.iter().map(|mean| mean * mean).sum();
+    if squares == 0.0 {
+        1.0
+    } else {
+        sum * sum / (means.len() as f64 * squares)
+    }
+}
+
+/// Wraps a JobCollection and records the user and the times of each job that finishes or fails
+struct RecordUsers {
+    inner: JobCollection,
+    stopped: Rc<RefCell<Vec<UserTimes>>>,
+}
+
+impl RecordUsers {
+    fn record(&mut self, job: &Job, failed: bool) {
+        if let (Some(started), Some(done)) = (job.time_started, job.time_done) {
+            let times = JobTimes { created: job.time_created, started, done, failed };
+            self.stopped.borrow_mut().push((job.user.clone(), times));
+        }
+    }
+}
+
+impl JobFactory for RecordUsers {
+    fn job_peek(&self) -> Option<&Job> {
+        self.inner.job_peek()
+    }
+
+    fn job_get(&mut self) -> Job {
+        self.inner.job_get()
+    }
+
+    fn job_mark_done(&mut self, job: &Job) {
+        self.inner.job_mark_done(job);
+        self.record(job, false);
+    }
+
+    fn job_mark_failed(&mut self, job: &Job) {
+        self.inner.job_mark_failed(job);
+        self.record(job, true);
+    }
+
+    fn more_jobs(&self) -> bool {
+        self.inner.more_jobs()
+    }
+
+    fn jobs_done(&self) -> &Vec<usize> {
+        self.inner.jobs_done()
+    }
+
+    fn check_stream(&self) -> Result<()> {
+        self.inner.check_stream()
+    }
+}
+
+/// Simulates @jobs under the policies @a and @b, each a name and a policy, on the registries
+/// that @make_registry builds. Both runs use @seed and their own copy of the jobs so they are
+/// deterministic and only differ in their policy. Runs end once no waiting job can ever start
+pub fn compare_policies<F>(
+    make_registry: F,
+    jobs: &[Job],
+    a: (&str, Box<dyn SchedulingPolicy>),
+    b: (&str, Box<dyn SchedulingPolicy>),
+    seed: u64,
+) -> Result<PolicyComparison>
+    where F: Fn() -> Result<NodeRegistry>
+{
+    let run = |(name, policy): (&str, Box<dyn SchedulingPolicy>)| -> Result<PolicyMetrics> {
+        let stopped = Rc::new(RefCell::new(vec![]));
+        let jobs = RecordUsers {
+            inner: JobCollection::new(jobs.to_vec()),
+            stopped: stopped.clone(),
+        };
+        let mut sched = SchedulerBuilder::new(make_registry()?, Box::new(jobs))
+            .policy(policy)
+            .seed(seed)
+            .build();
+
+        while sched.tick() && !sched.has_unschedulable() {}
+
+        let fairness = jain_fairness(&stopped.borrow(), sched.slowdown_threshold);
+        Ok(PolicyMetrics { policy: name.to_owned(), summary: sched.summary(), fairness })
+    };
+
+    Ok(PolicyComparison { a: run(a)?, b: run(b)? })
+}
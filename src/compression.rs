@@ -0,0 +1,73 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+
+//! Opens input and output files, the ones with the .gz extension are (de)compressed on the fly
+
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+pub fn is_gzip(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+/// Returns the extension of @path ignoring a trailing .gz, e.g. "swf" for trace.swf.gz
+pub fn extension(path: &Path) -> Option<&str> {
+    let path = if is_gzip(path) { Path::new(path.file_stem()?) } else { path };
+    path.extension()?.to_str()
+}
+
+/// Opens @path for reading
+pub fn open(path: &Path) -> std::io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+
+    if is_gzip(path) {
+        // VV: Tools like pigz produce multiple gzip members, they decompress to 1 stream
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+/// Creates @path for writing, flushing a compressed file makes everything written so far
+/// readable even if the file is never closed properly
+pub fn create(path: &Path) -> std::io::Result<Box<dyn Write>> {
+    let file = File::create(path)?;
+
+    if is_gzip(path) {
+        Ok(Box::new(BufWriter::new(GzEncoder::new(file, Compression::default()))))
+    } else {
+        Ok(Box::new(BufWriter::new(file)))
+    }
+}
+
+pub fn read_to_string(path: &Path) -> std::io::Result<String> {
+    let mut content = String::new();
+    open(path)?.read_to_string(&mut content)?;
+    Ok(content)
+}
@@ -0,0 +1,132 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use crate::job::Job;
+use crate::job::JobId;
+use crate::profile;
+use crate::registry::NodeRegistry;
+use crate::scheduler::FirstFit;
+use crate::scheduler::Placement;
+use crate::scheduler::PolicyContext;
+use crate::scheduler::SchedulingPolicy;
+
+/// Dominant Resource Fairness: the share of a user for a resource is the fraction of the
+/// cluster capacity of that resource which the running jobs of the user hold, the dominant
+/// share is the largest of these shares across cores, memory, and the named resources. The next
+/// job comes from the user with the smallest dominant share. Jobs of the same user keep their
+/// order in the queue and jobs without a user belong to the anonymous user "".
+///
+/// Like FairShare, Drf considers all queued jobs every time.
+#[derive(Default)]
+pub struct Drf {}
+
+/// The resources that the jobs of a user hold, "cores" and "memory" plus the named resources
+type Allocation = BTreeMap<String, f64>;
+
+/// The Allocation of a user and its queued jobs along with their index in the queue
+type UserQueue<'a> = (Allocation, VecDeque<(usize, &'a Job)>);
+
+impl Drf {
+    fn charge(allocation: &mut Allocation, job: &Job) {
+        *allocation.entry("cores".to_owned()).or_default() += job.cores as f64;
+        *allocation.entry("memory".to_owned()).or_default() += job.memory as f64;
+        for (name, amount) in &job.resources {
+            *allocation.entry(name.clone()).or_default() += *amount as f64;
+        }
+    }
+
+    fn dominant_share(allocation: &Allocation, capacity: &Allocation) -> f64 {
+        allocation
+            .iter()
+            .filter_map(|(name, amount)| {
+                capacity.get(name).filter(|c| **c > 0.0).map(|c| amount / c)
+            })
+            .fold(0.0, f64::max)
+    }
+}
+
+impl SchedulingPolicy for Drf {
+    fn select(
+        &mut self,
+        queued: &[Job],
+        registry: &mut NodeRegistry,
+        context: &PolicyContext,
+    ) -> Vec<(JobId, Placement)> {
+        let mut capacity = Allocation::new();
+        for node in &registry.nodes {
+            *capacity.entry("cores".to_owned()).or_default() += node.cores.capacity as f64;
+            *capacity.entry("memory".to_owned()).or_default() += node.memory.capacity as f64;
+            for (name, resource) in &node.resources {
+                *capacity.entry(name.clone()).or_default() += resource.capacity as f64;
+            }
+        }
+
+        let mut users: HashMap<&str, UserQueue> = HashMap::new();
+
+        for job in context.jobs_running {
+            let user = job.user.as_deref().unwrap_or_default();
+            Self::charge(&mut users.entry(user).or_default().0, job);
+        }
+
+        for (idx, job) in queued.iter().enumerate() {
+            let user = job.user.as_deref().unwrap_or_default();
+            users.entry(user).or_default().1.push_back((idx, job));
+        }
+
+        let mut selected = vec![];
+        let (mut max_cores, mut max_memory) = registry.get_max_cores_memory();
+
+        loop {
+            // VV: Ties go to the user whose next job is earlier in the queue
+            let next = users
+                .iter()
+                .filter_map(|(user, (allocation, jobs))| {
+                    let share = Self::dominant_share(allocation, &capacity);
+                    jobs.front().map(|(idx, _)| (share, *idx, *user))
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+            let user = match next {
+                Some((_, _, user)) => user,
+                None => break,
+            };
+
+            let (allocation, jobs) = users.get_mut(user).unwrap();
+            let (_, job) = jobs.pop_front().unwrap();
+
+            if !job.gang && (job.cores > max_cores || job.memory > max_memory) {
+                continue;
+            }
+
+            profile::count_attempt();
+            if let Some(placement) = FirstFit::job_place(registry, job) {
+                placement.reserve(registry, job);
+                selected.push((job.uid, placement));
+                Self::charge(allocation, job);
+                (max_cores, max_memory) = registry.get_max_cores_memory();
+            }
+        }
+
+        selected
+    }
+}
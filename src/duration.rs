@@ -0,0 +1,41 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+
+//! Durations and points in simulated time as they appear in the input files. The simulator
+//! counts time in seconds, a number may carry a unit suffix to make the files easier to read
+
+use std::num::ParseFloatError;
+
+/// The suffixes that parse_time() understands and how many seconds each one stands for
+pub const UNITS: [(&str, f32); 4] = [("s", 1.0), ("m", 60.0), ("h", 3600.0), ("d", 86400.0)];
+
+/// Parses @text into seconds, e.g. "30s", "5m", "1.5h", or "2d". Numbers without a suffix are
+/// seconds, i.e. "7200" and "2h" are the same time
+pub fn parse_time(text: &str) -> Result<f32, ParseFloatError> {
+    let text = text.trim();
+
+    for (suffix, seconds) in UNITS {
+        if let Some(number) = text.strip_suffix(suffix) {
+            return number.trim_end().parse::<f32>().map(|value| value * seconds);
+        }
+    }
+
+    text.parse::<f32>()
+}
@@ -0,0 +1,564 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::num::ParseFloatError;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::bail;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::compression;
+use crate::duration::parse_time;
+use crate::job::Job;
+use crate::job::JobId;
+use crate::node::NodeId;
+use crate::registry::NodeRegistry;
+
+/// A node that fails at @time_fail and, optionally, recovers at @time_recover
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeFailure {
+    pub node: NodeId,
+    pub time_fail: f32,
+    pub time_recover: Option<f32>,
+}
+
+impl NodeFailure {
+    pub fn from_str(line: &str, registry: &NodeRegistry) -> Result<Self> {
+        // VV: format is <node:str>;<time_fail:f32>[;<time_recover:f32>]
+        let tokens: Vec<_> = line.split(';').map(|x| x.trim()).collect();
+
+        if tokens.len() < 2 || tokens.len() > 3 {
+            bail!("Expected <node>;<time_fail>[;<time_recover>] but found \"{}\"", line)
+        }
+
+        let node = match registry.registry.get(tokens[0]) {
+            Some(&uid) => uid,
+            None => bail!("Unknown node name {}", tokens[0]),
+        };
+
+        let time_fail: f32 = match parse_time(tokens[1]) {
+            Ok(t) => t,
+            Err(_) => bail!("Unable to parse {} into time_fail:f32", tokens[1]),
+        };
+
+        let time_recover = match tokens.get(2).filter(|t| !t.is_empty()) {
+            Some(token) => match parse_time(token) {
+                Ok(t) if t > time_fail => Some(t),
+                Ok(t) => bail!("Node {} cannot recover at {} before failing at {}",
+                    tokens[0], t, time_fail),
+                Err(_) => bail!("Unable to parse {} into time_recover:f32", token),
+            },
+            None => None,
+        };
+
+        Ok(Self { node, time_fail, time_recover })
+    }
+}
+
+/// Planned maintenance of a node: it drains at @time_drain, i.e. it accepts no new jobs but lets
+/// its running jobs finish, it is offline from @time_start until @time_end, and then returns.
+/// Unlike a NodeFailure, maintenance never kills jobs, jobs that are still running at @time_start
+/// keep the node busy until they finish
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeMaintenance {
+    pub node: NodeId,
+    pub time_drain: f32,
+    pub time_start: f32,
+    pub time_end: f32,
+}
+
+impl NodeMaintenance {
+    pub fn from_str(line: &str, registry: &NodeRegistry) -> Result<Self> {
+        // VV: format is <node:str>;<time_drain:f32>;<time_start:f32>;<time_end:f32>
+        let tokens: Vec<_> = line.split(';').map(|x| x.trim()).collect();
+
+        if tokens.len() != 4 {
+            bail!("Expected <node>;<time_drain>;<time_start>;<time_end> but found \"{}\"", line)
+        }
+
+        let node = match registry.registry.get(tokens[0]) {
+            Some(&uid) => uid,
+            None => bail!("Unknown node name {}", tokens[0]),
+        };
+
+        let time = |idx: usize, name: &str| -> Result<f32> {
+            match parse_time(tokens[idx]) {
+                Ok(t) => Ok(t),
+                Err(_) => bail!("Unable to parse {} into {}:f32", tokens[idx], name),
+            }
+        };
+
+        let time_drain = time(1, "time_drain")?;
+        let time_start = time(2, "time_start")?;
+        let time_end = time(3, "time_end")?;
+
+        if !(time_drain <= time_start && time_start < time_end) {
+            bail!("The maintenance of node {} must drain ({}) no later than it starts ({}) and \
+                start before it ends ({})", tokens[0], time_drain, time_start, time_end)
+        }
+
+        Ok(Self { node, time_drain, time_start, time_end })
+    }
+}
+
+/// Advance reservation: from @time_start until @time_end only the jobs of the user @owner may use
+/// @cores and @memory of the node. The scheduler does not preempt jobs to honour a reservation, it
+/// keeps the resources that running jobs free away from other users until the reservation has
+/// all of them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reservation {
+    pub node: NodeId,
+    pub time_start: f32,
+    pub time_end: f32,
+    pub cores: f32,
+    pub memory: f32,
+    pub owner: String,
+}
+
+impl Reservation {
+    pub fn from_str(line: &str, registry: &NodeRegistry) -> Result<Self> {
+        // VV: format is <node:str>;<time_start:f32>;<time_end:f32>;<cores:f32>;<memory:f32>;<owner:str>
+        let tokens: Vec<_> = line.split(';').map(|x| x.trim()).collect();
+
+        if tokens.len() != 6 {
+            bail!("Expected <node>;<time_start>;<time_end>;<cores>;<memory>;<owner> but found \
+                \"{}\"", line)
+        }
+
+        let node = match registry.registry.get(tokens[0]) {
+            Some(&uid) => uid,
+            None => bail!("Unknown node name {}", tokens[0]),
+        };
+
+        let number = |idx: usize, name: &str, parse: fn(&str) -> Result<f32, ParseFloatError>|
+                      -> Result<f32> {
+            match parse(tokens[idx]) {
+                Ok(x) if x >= 0.0 && x.is_finite() => Ok(x),
+                _ => bail!("Unable to parse {} into {}:f32", tokens[idx], name),
+            }
+        };
+
+        let time_start = number(1, "time_start", parse_time)?;
+        let time_end = number(2, "time_end", parse_time)?;
+        let cores = number(3, "cores", str::parse)?;
+        let memory = number(4, "memory", str::parse)?;
+
+        if time_start >= time_end {
+            bail!("The reservation of node {} must start ({}) before it ends ({})",
+                tokens[0], time_start, time_end)
+        }
+
+        let capacity = &registry.nodes[node];
+        if cores > capacity.cores.capacity || memory > capacity.memory.capacity {
+            bail!("The reservation of {} cores and {} memory does not fit on node {}",
+                cores, memory, tokens[0])
+        }
+
+        if tokens[5].is_empty() {
+            bail!("The reservation of node {} at {} has no owner", tokens[0], time_start)
+        }
+
+        Ok(Self { node, time_start, time_end, cores, memory, owner: tokens[5].to_owned() })
+    }
+
+    /// Returns true if the reservation holds resources at @now
+    pub fn is_active(&self, now: f32) -> bool {
+        self.time_start <= now && now < self.time_end
+    }
+}
+
+/// The limits of a partition (see Node::partitions) that the jobs which target it must respect.
+/// Jobs that request more than @max_cores or a walltime_limit longer than @max_time are rejected
+/// when they arrive, jobs without a walltime_limit get @max_time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartitionLimits {
+    pub name: String,
+    pub max_time: Option<f32>,
+    pub max_cores: Option<f32>,
+}
+
+impl PartitionLimits {
+    pub fn from_str(line: &str, registry: &NodeRegistry) -> Result<Self> {
+        // VV: format is <partition:str>;<max_time:f32 or ->;<max_cores:f32 or ->
+        let tokens: Vec<_> = line.split(';').map(|x| x.trim()).collect();
+
+        if tokens.len() != 3 {
+            bail!("Expected <partition>;<max_time>;<max_cores> but found \"{}\"", line)
+        }
+
+        let name = tokens[0];
+        if !registry.nodes.iter().any(|node| node.partitions.contains(name)) {
+            bail!("No node belongs to the partition \"{}\"", name)
+        }
+
+        let limit = |idx: usize, what: &str, parse: fn(&str) -> Result<f32, ParseFloatError>|
+                     -> Result<Option<f32>> {
+            match tokens[idx] {
+                "-" => Ok(None),
+                value => match parse(value) {
+                    Ok(x) if x > 0.0 && x.is_finite() => Ok(Some(x)),
+                    _ => bail!("Unable to parse {} into {}:f32 or - in \"{}\"", value, what, line),
+                },
+            }
+        };
+
+        Ok(Self {
+            name: name.to_owned(),
+            max_time: limit(1, "max_time", parse_time)?,
+            max_cores: limit(2, "max_cores", str::parse)?,
+        })
+    }
+
+    /// Returns an error if @job breaks the limits of the partition
+    pub fn check(&self, job: &Job) -> Result<()> {
+        if let Some(max_cores) = self.max_cores.filter(|max_cores| job.cores > *max_cores) {
+            bail!("Job {} requests {} cores but the partition {} allows {}", job.uid, job.cores,
+                self.name, max_cores)
+        }
+
+        let walltime = job.walltime_limit.zip(self.max_time)
+            .filter(|(walltime, max_time)| walltime > max_time);
+        if let Some((walltime, max_time)) = walltime {
+            bail!("Job {} requests a walltime_limit of {} but the partition {} allows {}", job.uid,
+                walltime, self.name, max_time)
+        }
+
+        Ok(())
+    }
+}
+
+/// Which jobs a ConcurrencyLimit covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LimitScope {
+    /// The jobs whose user is the name of the limit
+    User,
+    /// The jobs that target the partition with the name of the limit
+    Partition,
+}
+
+/// A cap on the cores that the running jobs of a user or a partition may use at the same time.
+/// Queued jobs that would take their user or partition past @max_cores wait even if there are
+/// free nodes, jobs that request more than @max_cores are rejected when they arrive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConcurrencyLimit {
+    pub scope: LimitScope,
+    pub name: String,
+    pub max_cores: f32,
+}
+
+impl ConcurrencyLimit {
+    pub fn from_str(line: &str, registry: &NodeRegistry) -> Result<Self> {
+        // VV: format is <user or partition>;<name:str>;<max_cores:f32>
+        let tokens: Vec<_> = line.split(';').map(|x| x.trim()).collect();
+
+        if tokens.len() != 3 {
+            bail!("Expected <user or partition>;<name>;<max_cores> but found \"{}\"", line)
+        }
+
+        let scope = match tokens[0] {
+            "user" => LimitScope::User,
+            "partition" => LimitScope::Partition,
+            scope => bail!("Expected user or partition but found {} in \"{}\"", scope, line),
+        };
+
+        let name = tokens[1];
+        if name.is_empty() {
+            bail!("The limit has no name in \"{}\"", line)
+        }
+        if scope == LimitScope::Partition
+            && !registry.nodes.iter().any(|node| node.partitions.contains(name)) {
+            bail!("No node belongs to the partition \"{}\"", name)
+        }
+
+        let max_cores = match tokens[2].parse::<f32>() {
+            Ok(x) if x > 0.0 && x.is_finite() => x,
+            _ => bail!("Unable to parse {} into max_cores:f32 in \"{}\"", tokens[2], line),
+        };
+
+        Ok(Self { scope, name: name.to_owned(), max_cores })
+    }
+
+    /// Returns true if the limit covers @job
+    pub fn covers(&self, job: &Job) -> bool {
+        let name = match self.scope {
+            LimitScope::User => &job.user,
+            LimitScope::Partition => &job.partition,
+        };
+        name.as_deref() == Some(self.name.as_str())
+    }
+}
+
+/// Operator intervention: at @time the job with the UID @job is put on hold (it does not start
+/// until it is released) or released. Holding a job that has not arrived yet holds it as soon as
+/// it arrives, holding a running job only takes effect if it goes back to the queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoldEvent {
+    pub time: f32,
+    pub job: JobId,
+    pub release: bool,
+}
+
+impl FromStr for HoldEvent {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        // VV: format is <time:f32>;hold|release;<job uid:usize>
+        let tokens: Vec<_> = line.split(';').map(|x| x.trim()).collect();
+
+        if tokens.len() != 3 {
+            bail!("Expected <time>;hold|release;<job> but found \"{}\"", line)
+        }
+
+        let time: f32 = match parse_time(tokens[0]) {
+            Ok(t) if t >= 0.0 => t,
+            _ => bail!("Unable to parse {} into time:f32", tokens[0]),
+        };
+
+        let release = match tokens[1] {
+            "hold" => false,
+            "release" => true,
+            _ => bail!("Expected hold or release but found \"{}\" in \"{}\"", tokens[1], line),
+        };
+
+        let job = match tokens[2].parse() {
+            Ok(uid) => uid,
+            Err(_) => bail!("Unable to parse {} into job:usize", tokens[2]),
+        };
+
+        Ok(Self { time, job, release })
+    }
+}
+
+/// What happens to the running jobs of a node that leaves the cluster
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RemoveMode {
+    /// The node accepts no new jobs and leaves once its running jobs finish
+    Drain,
+    /// The running jobs of the node go back to the queue and lose their progress
+    Kill,
+}
+
+/// A change to the nodes of the cluster, see ScalingEvent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ScalingAction {
+    /// A new node joins, it has the <name>;<cores>;<memory>[;<key>=<value>]* definition of
+    /// NodeRegistry::new_node_from_str() and no connections to other nodes
+    Add(String),
+    /// A node that an earlier ScalingAction::Remove took away joins again
+    Return(String),
+    Remove(String, RemoveMode),
+}
+
+/// Autoscaling: a node joins or leaves the cluster at @time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScalingEvent {
+    pub time: f32,
+    pub action: ScalingAction,
+}
+
+impl FromStr for ScalingEvent {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        // VV: format is one of
+        // - <time:f32>;add;<node definition> for new nodes
+        // - <time:f32>;add;<node:str> for nodes that were removed earlier
+        // - <time:f32>;remove;<node:str>[;drain|kill], without a mode the node drains
+        let tokens: Vec<_> = line.splitn(3, ';').map(|x| x.trim()).collect();
+
+        if tokens.len() != 3 {
+            bail!("Expected <time>;add|remove;<node> but found \"{}\"", line)
+        }
+
+        let time: f32 = match parse_time(tokens[0]) {
+            Ok(t) if t >= 0.0 => t,
+            _ => bail!("Unable to parse {} into time:f32", tokens[0]),
+        };
+
+        let action = match tokens[1] {
+            "add" if tokens[2].contains(';') => ScalingAction::Add(tokens[2].to_owned()),
+            "add" => ScalingAction::Return(tokens[2].to_owned()),
+            "remove" => {
+                let (name, mode) = match tokens[2].split_once(';') {
+                    Some((name, mode)) => (name.trim(), mode.trim()),
+                    None => (tokens[2], "drain"),
+                };
+
+                let mode = match mode {
+                    "drain" => RemoveMode::Drain,
+                    "kill" => RemoveMode::Kill,
+                    _ => bail!("Nodes may only drain or kill but found \"{}\" in \"{}\"",
+                        mode, line),
+                };
+                ScalingAction::Remove(name.to_owned(), mode)
+            }
+            _ => bail!("Expected add or remove but found \"{}\" in \"{}\"", tokens[1], line),
+        };
+
+        Ok(Self { time, action })
+    }
+}
+
+impl ScalingEvent {
+    /// The name of the node that joins or leaves
+    pub fn node(&self) -> &str {
+        match &self.action {
+            ScalingAction::Add(definition) => definition.split(';').next().unwrap().trim(),
+            ScalingAction::Return(name) | ScalingAction::Remove(name, _) => name,
+        }
+    }
+}
+
+/// Returns the ScalingEvents in @path sorted by time, they must make sense for the nodes of
+/// @registry i.e. new nodes have unique names and only nodes in the cluster can leave it, see
+/// check_scaling()
+pub fn load_scaling(path: &Path, registry: &NodeRegistry) -> Result<Vec<ScalingEvent>> {
+    let mut events = load_lines(path, "scaling", ScalingEvent::from_str)?;
+    events.sort_by(|a, b| a.time.total_cmp(&b.time));
+    check_scaling(&events, registry)?;
+
+    Ok(events)
+}
+
+/// Returns an error if the @events, in the order that the Scheduler applies them (by time and
+/// then by index), do not make sense for the nodes of @registry: new nodes need unique names and
+/// valid definitions, returning nodes must have left before, and leaving nodes must be in the
+/// cluster. This way the simulation never stumbles on a broken event halfway through
+pub fn check_scaling(events: &[ScalingEvent], registry: &NodeRegistry) -> Result<()> {
+    let mut order: Vec<&ScalingEvent> = events.iter().collect();
+    order.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    let mut present: HashMap<String, bool> =
+        registry.registry.keys().map(|name| (name.clone(), true)).collect();
+
+    // VV: The definitions of new nodes are checked against a scratch registry with the names,
+    // racks, and zones of the nodes so far, e.g. a rack sits in exactly 1 zone
+    let mut scratch = NodeRegistry::new();
+    for node in &registry.nodes {
+        let rack = node.rack.as_ref().map_or(String::new(), |rack| format!(";rack={}", rack));
+        let zone = node.zone.as_ref().map_or(String::new(), |zone| format!(";zone={}", zone));
+        scratch.new_node_from_str(&format!("{};0;0{}{}", node.name, rack, zone))?;
+    }
+
+    for event in order {
+        let name = event.node().to_owned();
+        match (&event.action, present.get(&name).copied()) {
+            (ScalingAction::Add(definition), None) => {
+                if let Err(err) = scratch.new_node_from_str(definition) {
+                    bail!("Cannot add node {} at {} because {}", name, event.time, err)
+                }
+                present.insert(name, true)
+            }
+            (ScalingAction::Add(_), Some(_)) => bail!("Cannot add node {} at {} because it \
+                already exists, use <time>;add;<name> to return a removed node", name, event.time),
+            (ScalingAction::Return(_), Some(false)) => present.insert(name, true),
+            (ScalingAction::Return(_), _) => bail!("Cannot return node {} at {} because it is \
+                not a removed node", name, event.time),
+            (ScalingAction::Remove(..), Some(true)) => present.insert(name, false),
+            (ScalingAction::Remove(..), _) => bail!("Cannot remove node {} at {} because it is \
+                not in the cluster", name, event.time),
+        };
+    }
+
+    Ok(())
+}
+
+/// Parses every line of @path which is not empty or a #comment with @parse
+fn load_lines<T>(
+    path: &Path,
+    what: &str,
+    parse: impl Fn(&str) -> Result<T>,
+) -> Result<Vec<T>> {
+    let file = compression::open(path);
+
+    if let Err(x) = file {
+        bail!("Unable to open {} file {} because of {:?}", what, path.display(), x)
+    }
+
+    let br = file.unwrap();
+    let mut entries = vec![];
+
+    for (i, x) in br.lines().enumerate() {
+        if let Err(err) = x {
+            bail!("Unable to read line {} because of {}", i, err)
+        }
+
+        let line = x.unwrap();
+        let line = line.trim();
+
+        if !line.is_empty() && !line.starts_with('#') {
+            entries.push(parse(line)?);
+        }
+    }
+
+    Ok(entries)
+}
+
+pub fn load_failures(path: &Path, registry: &NodeRegistry) -> Result<Vec<NodeFailure>> {
+    load_lines(path, "node_failures", |line| NodeFailure::from_str(line, registry))
+}
+
+pub fn load_maintenance(path: &Path, registry: &NodeRegistry) -> Result<Vec<NodeMaintenance>> {
+    load_lines(path, "maintenance", |line| NodeMaintenance::from_str(line, registry))
+}
+
+pub fn load_reservations(path: &Path, registry: &NodeRegistry) -> Result<Vec<Reservation>> {
+    load_lines(path, "reservations", |line| Reservation::from_str(line, registry))
+}
+
+pub fn load_partitions(path: &Path, registry: &NodeRegistry) -> Result<Vec<PartitionLimits>> {
+    let limits = load_lines(path, "partitions", |line| PartitionLimits::from_str(line, registry))?;
+
+    for (i, partition) in limits.iter().enumerate() {
+        if limits[..i].iter().any(|other| other.name == partition.name) {
+            bail!("The limits of the partition {} are defined twice", partition.name)
+        }
+    }
+
+    Ok(limits)
+}
+
+pub fn load_concurrency_limits(path: &Path, registry: &NodeRegistry)
+                               -> Result<Vec<ConcurrencyLimit>> {
+    let limits = load_lines(path, "limits", |line| ConcurrencyLimit::from_str(line, registry))?;
+
+    for (i, limit) in limits.iter().enumerate() {
+        if limits[..i].iter().any(|other| other.scope == limit.scope && other.name == limit.name) {
+            let scope = match limit.scope {
+                LimitScope::User => "user",
+                LimitScope::Partition => "partition",
+            };
+            bail!("The limit of the {} {} is defined twice", scope, limit.name)
+        }
+    }
+
+    Ok(limits)
+}
+
+/// Returns the HoldEvents in @path sorted by time
+pub fn load_holds(path: &Path) -> Result<Vec<HoldEvent>> {
+    let mut events = load_lines(path, "holds", HoldEvent::from_str)?;
+    events.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(events)
+}
@@ -0,0 +1,103 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use crate::job::Job;
+use crate::job::JobId;
+use crate::profile;
+use crate::registry::NodeRegistry;
+use crate::scheduler::FirstFit;
+use crate::scheduler::Placement;
+use crate::scheduler::PolicyContext;
+use crate::scheduler::SchedulingPolicy;
+
+/// The usage of a user divided by its weight, the weight, and the queued jobs of the user along
+/// with their index in the queue
+type UserQueue<'a> = (f64, f64, VecDeque<(usize, &'a Job)>);
+
+/// Fair-share: the next job comes from the user who consumed the fewest (decayed) core-seconds
+/// per unit of weight (see PolicyContext::user_weights), a user with weight 2 gets twice the
+/// core-seconds of a user with weight 1. Jobs of the same user keep their order in the queue.
+/// Every job that starts charges its user with cores * estimate so that users also take turns
+/// among the jobs that start at the same time. Jobs without a user belong to the anonymous
+/// user "".
+///
+/// Reordering the queue means that the skip hint of PolicyContext does not apply, FairShare
+/// considers all queued jobs every time.
+#[derive(Default)]
+pub struct FairShare {}
+
+impl SchedulingPolicy for FairShare {
+    fn select(
+        &mut self,
+        queued: &[Job],
+        registry: &mut NodeRegistry,
+        context: &PolicyContext,
+    ) -> Vec<(JobId, Placement)> {
+        let mut users: HashMap<&str, UserQueue> = HashMap::new();
+
+        for (idx, job) in queued.iter().enumerate() {
+            let user = job.user.as_deref().unwrap_or_default();
+            let usage = context.user_usage.get(user).copied().unwrap_or(0.0);
+            let weight = context.user_weights.get(user).copied().unwrap_or(1.0);
+            users
+                .entry(user)
+                .or_insert_with(|| (usage / weight, weight, VecDeque::new()))
+                .2
+                .push_back((idx, job));
+        }
+
+        let mut selected = vec![];
+        let (mut max_cores, mut max_memory) = registry.get_max_cores_memory();
+
+        loop {
+            // VV: Ties go to the user whose next job is earlier in the queue
+            let next = users
+                .iter()
+                .filter_map(|(user, (usage, _, jobs))| {
+                    jobs.front().map(|(idx, _)| (*usage, *idx, *user))
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+            let user = match next {
+                Some((_, _, user)) => user,
+                None => break,
+            };
+
+            let (usage, weight, jobs) = users.get_mut(user).unwrap();
+            let (_, job) = jobs.pop_front().unwrap();
+
+            if !job.gang && (job.cores > max_cores || job.memory > max_memory) {
+                continue;
+            }
+
+            profile::count_attempt();
+            if let Some(placement) = FirstFit::job_place(registry, job) {
+                placement.reserve(registry, job);
+                selected.push((job.uid, placement));
+                *usage += (job.cores * job.estimate) as f64 / *weight;
+                (max_cores, max_memory) = registry.get_max_cores_memory();
+            }
+        }
+
+        selected
+    }
+}
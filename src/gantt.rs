@@ -0,0 +1,148 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::job::JobId;
+use crate::node::NodeId;
+use crate::registry::NodeRegistry;
+
+/// The time that (a piece of) a Job spent on the node that provided its cores. Gang jobs have 1
+/// span per node, jobs that get preempted have 1 span for each time they ran
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobSpan {
+    pub uid: JobId,
+    pub user: Option<String>,
+    pub node: NodeId,
+    pub start: f32,
+    pub end: f32,
+}
+
+/// Renders JobSpans as an SVG Gantt chart with 1 row per node and simulated time on the X axis.
+/// Bars of jobs with a user get the color of their user, the others a color per job
+#[derive(Debug, Clone, Default)]
+pub struct GanttChart {
+    /// Only draw the jobs that started first
+    pub max_jobs: Option<usize>,
+    /// Only draw the part of the timeline between these 2 points in time
+    pub window: Option<(f32, f32)>,
+}
+
+const WIDTH: f32 = 1200.0;
+const LABEL_WIDTH: f32 = 120.0;
+const ROW_HEIGHT: f32 = 14.0;
+const AXIS_HEIGHT: f32 = 30.0;
+const TICKS: usize = 10;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Returns a hue in [0, 360) that is stable for the same @key
+fn hue(key: &str) -> u64 {
+    // VV: FNV-1a, the hashers of std are not guaranteed to be stable across releases
+    let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    hash % 360
+}
+
+impl GanttChart {
+    /// Returns an SVG document with the @spans of jobs that ran on the nodes of @registry
+    pub fn render(&self, spans: &[JobSpan], registry: &NodeRegistry) -> String {
+        let mut spans: Vec<&JobSpan> = spans.iter().collect();
+        spans.sort_by(|a, b| a.start.total_cmp(&b.start).then(a.uid.cmp(&b.uid)));
+
+        if let Some(max_jobs) = self.max_jobs {
+            let mut jobs = HashSet::new();
+            spans.retain(|span| jobs.contains(&span.uid)
+                || (jobs.len() < max_jobs && jobs.insert(span.uid)));
+        }
+
+        let (from, to) = match self.window {
+            Some(window) => window,
+            None => (
+                spans.iter().map(|span| span.start).fold(f32::INFINITY, f32::min).min(0.0),
+                spans.iter().map(|span| span.end).fold(0.0, f32::max),
+            ),
+        };
+        spans.retain(|span| span.end > from && span.start < to);
+
+        let plot = WIDTH - LABEL_WIDTH;
+        let scale = if to > from { plot / (to - from) } else { 0.0 };
+        let x = |time: f32| LABEL_WIDTH + (time.clamp(from, to) - from) * scale;
+        let height = registry.nodes.len() as f32 * ROW_HEIGHT + AXIS_HEIGHT;
+
+        let mut svg = String::new();
+        writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+            font-family=\"sans-serif\" font-size=\"10\">", WIDTH, height).unwrap();
+
+        for node in &registry.nodes {
+            let y = node.uid as f32 * ROW_HEIGHT;
+            writeln!(svg, "<text x=\"2\" y=\"{}\">{}</text>", y + ROW_HEIGHT - 3.0,
+                     escape(&node.name)).unwrap();
+            writeln!(svg, "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#ddd\"/>",
+                     LABEL_WIDTH, y + ROW_HEIGHT, WIDTH, y + ROW_HEIGHT).unwrap();
+        }
+
+        for span in spans {
+            let (color, who) = match &span.user {
+                Some(user) => (hue(user), format!(" ({})", escape(user))),
+                None => (hue(&span.uid.to_string()), String::new()),
+            };
+            let left = x(span.start);
+            writeln!(svg, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" \
+                fill=\"hsl({}, 70%, 60%)\" stroke=\"#333\" stroke-width=\"0.5\"><title>Job {}{} \
+                from {} to {}</title></rect>", left, span.node as f32 * ROW_HEIGHT + 1.0,
+                     x(span.end) - left, ROW_HEIGHT - 2.0, color, span.uid, who, span.start,
+                     span.end).unwrap();
+        }
+
+        let axis = registry.nodes.len() as f32 * ROW_HEIGHT;
+        writeln!(svg, "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#000\"/>",
+                 LABEL_WIDTH, axis, WIDTH, axis).unwrap();
+        for tick in 0..=TICKS {
+            let time = from + (to - from) * tick as f32 / TICKS as f32;
+            writeln!(svg, "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                     x(time), axis + AXIS_HEIGHT / 2.0, time).unwrap();
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Renders the chart into the file at @path
+    pub fn write(&self, path: &Path, spans: &[JobSpan], registry: &NodeRegistry) -> Result<()> {
+        if let Some((from, to)) = self.window.filter(|(from, to)| from >= to) {
+            bail!("The window of the Gantt chart must end after it starts but it is {}:{}",
+                from, to)
+        }
+
+        std::fs::write(path, self.render(spans, registry))
+            .context(format!("Unable to write Gantt chart to {}", path.display()))
+    }
+}
@@ -0,0 +1,175 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+//! Golden-trace testing: compares the output trace of a run with the output trace of an
+//! earlier run of the same inputs, e.g. one that an older version of the crate produced. Both
+//! runs must have made the same placement decisions, i.e. every job started and finished at the
+//! same times on the same nodes
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::path::Path;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+
+use crate::compression;
+use crate::job::JobId;
+use crate::job_factory::TraceFormat;
+
+/// The placement decision of 1 finished job in an output trace
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceDecision {
+    pub uid: JobId,
+    /// When and where the job ran: the start;finish;node;memory tokens of Text traces or the
+    /// start, finish, node, node_memory, and gang_cores of JsonLines traces
+    pub placement: String,
+}
+
+impl TraceDecision {
+    /// Parses 1 line of an output trace in @format, returns None for the header of Text traces
+    pub fn from_line(line: &str, format: TraceFormat) -> Result<Option<Self>> {
+        match format {
+            TraceFormat::Text => {
+                if line.starts_with('#') || line.trim().is_empty() {
+                    return Ok(None);
+                }
+
+                let tokens: Vec<&str> = line.split(';').map(|s| s.trim()).collect();
+                if tokens.len() < 9 {
+                    bail!("Expected at least 9 tokens in the trace line \"{}\"", line)
+                }
+                let uid = tokens[0].parse()
+                    .context(format!("Invalid uid in the trace line \"{}\"", line))?;
+                Ok(Some(Self { uid, placement: tokens[6..].join(";") }))
+            }
+            TraceFormat::JsonLines => {
+                let job: serde_json::Value = serde_json::from_str(line)
+                    .context(format!("Invalid JSON in the trace line \"{}\"", line))?;
+                let uid = match job["id"].as_u64() {
+                    Some(uid) => uid as JobId,
+                    None => bail!("The trace line \"{}\" has no id", line),
+                };
+                let placement = serde_json::json!({
+                    "start": job["start"],
+                    "finish": job["finish"],
+                    "node": job["node"],
+                    "node_memory": job["node_memory"],
+                    "gang_cores": job["gang_cores"],
+                });
+                Ok(Some(Self { uid, placement: placement.to_string() }))
+            }
+        }
+    }
+}
+
+/// Reads the decisions of the output trace at @path in the order the jobs finished, the
+/// extension of @path picks the TraceFormat
+pub fn read_trace(path: &Path) -> Result<Vec<TraceDecision>> {
+    let content = compression::read_to_string(path)
+        .context(format!("Unable to read trace {}", path.display()))?;
+    let format = TraceFormat::from_path(path);
+
+    let mut decisions = vec![];
+    for line in content.lines() {
+        if let Some(decision) = TraceDecision::from_line(line, format)? {
+            decisions.push(decision);
+        }
+    }
+    Ok(decisions)
+}
+
+/// The first job whose placement differs between 2 traces, a missing placement means that the
+/// job did not finish in that trace
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceDivergence {
+    pub uid: JobId,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+impl Display for TraceDivergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let show = |placement: &Option<String>| match placement {
+            Some(placement) => placement.clone(),
+            None => "no such job".to_string(),
+        };
+        write!(f, "Job {} diverges, expected {} but found {}", self.uid, show(&self.expected),
+               show(&self.actual))
+    }
+}
+
+/// Returns the first job of @expected, in the order of @expected, whose placement differs in
+/// @actual. Jobs that only @actual contains come after all jobs of @expected
+pub fn first_divergence(
+    expected: &[TraceDecision],
+    actual: &[TraceDecision],
+) -> Option<TraceDivergence> {
+    let placements: HashMap<JobId, &String> = actual
+        .iter()
+        .map(|decision| (decision.uid, &decision.placement))
+        .collect();
+
+    for decision in expected {
+        let found = placements.get(&decision.uid).copied();
+        if found != Some(&decision.placement) {
+            return Some(TraceDivergence {
+                uid: decision.uid,
+                expected: Some(decision.placement.clone()),
+                actual: found.cloned(),
+            });
+        }
+    }
+
+    if expected.len() == actual.len() {
+        // VV: Every job of actual matched a job of expected
+        return None;
+    }
+
+    let known: HashSet<JobId> = expected.iter().map(|decision| decision.uid).collect();
+    actual
+        .iter()
+        .find(|decision| !known.contains(&decision.uid))
+        .map(|decision| TraceDivergence {
+            uid: decision.uid,
+            expected: None,
+            actual: Some(decision.placement.clone()),
+        })
+}
+
+/// Returns an error with the first TraceDivergence if the output trace at @actual does not
+/// repeat the placement decisions of the golden trace at @golden. Both traces must have the
+/// same TraceFormat
+pub fn verify_trace(golden: &Path, actual: &Path) -> Result<()> {
+    if TraceFormat::from_path(golden) != TraceFormat::from_path(actual) {
+        bail!("The golden trace {} and the output trace {} have different formats",
+            golden.display(), actual.display())
+    }
+
+    let expected = read_trace(golden)?;
+    let found = read_trace(actual)?;
+    if let Some(divergence) = first_divergence(&expected, &found) {
+        bail!("The output trace {} differs from the golden trace {}: {}", actual.display(),
+            golden.display(), divergence)
+    }
+    Ok(())
+}
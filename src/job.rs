@@ -17,39 +17,201 @@ specific language governing permissions and limitations
 under the License.
 */
 
+use std::cell::Cell;
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::str::FromStr;
-use std::sync::atomic::AtomicUsize;
-use std::sync::atomic::Ordering;
 
-static NEXT_JOB_UID: AtomicUsize = AtomicUsize::new(0);
-static mut LAST_TIME_CREATED: f32 = 0.0;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::duration::parse_time;
+
+pub type JobId = usize;
+
+// VV: Every thread has its own job metadata so that independent simulations can run in parallel
+thread_local! {
+    static NEXT_JOB_UID: Cell<usize> = const { Cell::new(0) };
+    static LAST_TIME_CREATED: Cell<f32> = const { Cell::new(0.0) };
+}
 
 pub fn reset_job_metadata() {
-    unsafe {
-        LAST_TIME_CREATED = 0.0;
+    LAST_TIME_CREATED.set(0.0);
+    NEXT_JOB_UID.set(0);
+}
+
+/// Why a waiting Job did not start at the last scheduling cycle, see
+/// Scheduler::block_reasons()
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum BlockReason {
+    /// The nodes that match the selector of the job are unavailable
+    Label,
+    /// The nodes of the partition of the job are unavailable
+    Partition,
+    /// The nodes that the job may use all run jobs of its anti-affinity group
+    AntiAffinity,
+    /// The job is exclusive and none of the nodes that it may use is idle
+    Exclusive,
+    /// No node that the job may use has enough free cores
+    Cores,
+    /// The nodes with enough free cores lack free units of this named resource
+    Resource(String),
+    /// The nodes with enough free cores lack free memory, including what they may borrow
+    Memory,
+    /// The running jobs of the user or the partition of the job use up its ConcurrencyLimit
+    Limit,
+    /// The job fits but the other jobs of its bundle are not ready or do not fit on its node too
+    Bundle,
+    /// The job waits for the jobs it depends on
+    Dependency,
+    /// The job is on hold
+    Held,
+    /// The job fits on the free resources but the policy did not start it, e.g. it waits behind
+    /// jobs with a higher priority or for a reservation
+    Policy,
+}
+
+impl Display for BlockReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockReason::Label => write!(f, "no node with matching labels is available"),
+            BlockReason::Partition => write!(f, "no node of the partition is available"),
+            BlockReason::AntiAffinity => write!(f, "anti-affinity with the jobs on every node"),
+            BlockReason::Exclusive => write!(f, "exclusive but no node is idle"),
+            BlockReason::Cores => write!(f, "insufficient cores"),
+            BlockReason::Resource(name) => write!(f, "insufficient {}", name),
+            BlockReason::Memory => write!(f, "insufficient memory"),
+            BlockReason::Bundle => write!(f, "the rest of its bundle is not ready or does not fit"),
+            BlockReason::Limit => write!(f, "the limit of its user or partition is reached"),
+            BlockReason::Dependency => write!(f, "blocked by a dependency"),
+            BlockReason::Held => write!(f, "held"),
+            BlockReason::Policy => write!(f, "fits but the policy did not start it"),
+        }
+    }
+}
+
+/// Why a waiting Job can never start, see Scheduler::deadlocked()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Deadlock {
+    /// The job is on hold and no pending HoldEvent releases it
+    Held,
+    /// The job depends on this job which failed or was rejected
+    Lost(JobId),
+    /// The job depends on these jobs which can never finish either, e.g. because they are
+    /// deadlocked or depend on each other
+    Waits(Vec<JobId>),
+}
+
+impl Display for Deadlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Deadlock::Held => write!(f, "held and never released"),
+            Deadlock::Lost(uid) => write!(f, "depends on Job {} which failed or was rejected", uid),
+            Deadlock::Waits(uids) => write!(f, "depends on the deadlocked jobs {:?}", uids),
+        }
     }
-    NEXT_JOB_UID.store(0, Ordering::SeqCst);
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Job {
     pub uid: usize,
     pub cores: f32,
     pub memory: f32,
+    // VV: Requests for resources other than cores and memory (e.g. "gpus"), keyed by their name.
+    // These resources live on the node(s) that provide the cores of the job
+    pub resources: BTreeMap<String, f32>,
+    // VV: The named resources (e.g. "scratch") that stay allocated on the nodes of the job after
+    // it finishes, until one of the jobs that depend on it finishes with the uid of this job in
+    // release. Jobs that do not finish release what they keep right away
+    pub keep: Vec<String>,
+    pub release: Vec<JobId>,
+    // VV: The labels that the node which provides the cores of the job must have, see
+    // Node::matches()
+    pub selector: BTreeMap<String, String>,
+    // VV: The job only runs on the nodes of this partition, see Node::partitions
+    pub partition: Option<String>,
     pub can_borrow: bool,
     pub duration: f32,
+    // VV: The runtime that the user expects the job to have, policies like Backfill use this
+    // instead of the actual duration to plan ahead
+    pub estimate: f32,
+    // VV: The scheduler considers jobs with a higher priority first
+    pub priority: i32,
+    // VV: The job cannot start before all of these jobs are done
+    pub depends_on: Vec<JobId>,
+    // VV: A gang job may spread its cores over multiple nodes, all pieces start at the same time
+    pub gang: bool,
+    // VV: An exclusive job only starts on an idle node and no other job may use that node until
+    // it stops, no matter how few resources it requests
+    pub exclusive: bool,
+    // VV: The jobs of a bundle start together on a single node once all of them fit on it, see
+    // Scheduler::start_bundles()
+    pub bundle: Option<String>,
+    // VV: The user that submitted the job, fair-share policies use this
+    pub user: Option<String>,
+    // VV: Jobs of the same anti-affinity group avoid running on the same node, colocated is set
+    // when the job had to start on a node that already ran a job of its group
+    pub anti_affinity: Option<String>,
+    pub colocated: bool,
+    // VV: Jobs of the same affinity group run faster on the nodes that recently finished a job of
+    // the group, see Scheduler::warm_decay
+    pub affinity: Option<String>,
+    // VV: This is the speculative copy of a straggler, see Scheduler::speculation
+    pub speculative: bool,
+    // VV: Jobs that would run for longer than this are killed at the limit and marked as failed
+    pub walltime_limit: Option<f32>,
+    // VV: The time by which the job must finish to be useful, see Scheduler::cancel_late
+    pub deadline: Option<f32>,
+    // VV: The job gives up and is rejected if it waits for longer than this after it arrives
+    pub max_queue_time: Option<f32>,
+    // VV: The scheduler places jobs by the memory they request but they may actually use up to
+    // memory_limit. memory_usage is the memory that the job actually uses, when it is unset the
+    // Scheduler draws it between memory and memory_limit. oom_kills counts how many times the
+    // Scheduler killed the job because its nodes ran out of memory, see Scheduler::oom_kill()
+    pub memory_limit: Option<f32>,
+    pub memory_usage: Option<f32>,
+    pub oom_kills: usize,
+    // VV: The memory that the job actually uses from each fraction of its runtime on, the
+    // fractions start at 0 and ascend. The scheduler reserves memory, which is at least the peak
+    // of the steps, and tracks the step the job is at, see Job::memory_used()
+    pub memory_profile: Vec<(f32, f32)>,
+    // VV: Each run of a flaky job fails with failure_probability when it reaches its end, the
+    // Scheduler runs it again up to max_retries times. attempts counts the runs that reached their
+    // end and failed is set when the last of them failed, see Scheduler::jobs_failed
+    pub failure_probability: f32,
+    pub max_retries: usize,
+    pub attempts: usize,
+    pub failed: bool,
+    // VV: Why the job did not start at the last scheduling cycle that it waited through
+    pub last_block_reason: Option<BlockReason>,
     pub time_created: f32,
     pub time_started: Option<f32>,
     pub time_done: Option<f32>,
     // VV: uid(s) of nodes
     pub node_cores: Option<usize>,
     pub node_memory: Vec<(usize, f32)>,
+    // VV: For gang jobs that span multiple nodes, the cores that the job uses on each node
+    pub gang_cores: Vec<(usize, f32)>,
+    // VV: The total hops between the node that provides the cores and the other nodes the job
+    // uses, None if they are not all connected (see Placement::hop_distance())
+    pub hops: Option<usize>,
+    // VV: For jobs of an array job, the uid of the first job in the array and the index of this
+    // job in the array, see job_factory::expand_array()
+    pub array: Option<(JobId, usize)>,
+    // VV: The speed of the nodes that the Job runs on, it progresses through its duration at this
+    // rate (see Placement::speed())
+    pub speed: f32,
+    // VV: The size of the input data, in the units of memory, that must reach the nodes of the
+    // Job before it can run
+    pub input_data: f32,
+    // VV: The seconds that the Job spent staging its input data after time_started, it runs from
+    // time_started + staging until time_done (see Placement::staging())
+    pub staging: f32,
 }
 
 impl Job {
-    #[allow(dead_code)]
     pub fn new(
         cores: f32,
         memory: f32,
@@ -57,7 +219,7 @@ impl Job {
         can_borrow: bool,
         time_created: f32,
     ) -> Self {
-        let uid = NEXT_JOB_UID.fetch_or(0, Ordering::SeqCst);
+        let uid = NEXT_JOB_UID.get();
 
         Self::new_with_uid(uid, cores, memory, duration, can_borrow, time_created)
     }
@@ -70,10 +232,10 @@ impl Job {
         can_borrow: bool,
         time_created: f32,
     ) -> Self {
-        let cur_uid = NEXT_JOB_UID.fetch_add(1, Ordering::SeqCst);
+        let cur_uid = NEXT_JOB_UID.replace(NEXT_JOB_UID.get() + 1);
 
         if cur_uid != uid {
-            println!(
+            warn!(
                 "Should not create Job with UID {}, \
             first create the Job with UID {}",
                 uid, cur_uid
@@ -84,50 +246,399 @@ impl Job {
         // Basically, I need to create a *different* Object which keeps track of metadata
         // for creating jobs such as next uid, and last_time_created.
         // Currently, you will get these prints periodically when running `cargo test`
-        unsafe {
-            if LAST_TIME_CREATED > time_created {
-                println!(
-                    "Should not create Job with UID {} with time_create {} because a job with \
-                a future time_create ({}) exists.",
-                    uid, time_created, LAST_TIME_CREATED
-                );
-            }
-
-            LAST_TIME_CREATED = time_created;
+        let last_time_created = LAST_TIME_CREATED.replace(time_created);
+        if last_time_created > time_created {
+            warn!(
+                "Should not create Job with UID {} with time_create {} because a job with \
+            a future time_create ({}) exists.",
+                uid, time_created, last_time_created
+            );
         }
 
         Self {
             uid,
             cores,
             memory,
+            resources: BTreeMap::new(),
+            keep: vec![],
+            release: vec![],
+            selector: BTreeMap::new(),
+            partition: None,
             can_borrow,
             duration,
+            estimate: duration,
+            priority: 0,
+            depends_on: vec![],
+            gang: false,
+            exclusive: false,
+            bundle: None,
+            user: None,
+            anti_affinity: None,
+            colocated: false,
+            affinity: None,
+            speculative: false,
+            walltime_limit: None,
+            deadline: None,
+            max_queue_time: None,
+            memory_limit: None,
+            memory_usage: None,
+            oom_kills: 0,
+            memory_profile: vec![],
+            failure_probability: 0.0,
+            max_retries: 0,
+            attempts: 0,
+            failed: false,
+            last_block_reason: None,
             time_created,
             time_started: None,
             time_done: None,
             node_cores: None,
             node_memory: vec![],
+            gang_cores: vec![],
+            hops: None,
+            array: None,
+            speed: 1.0,
+            input_data: 0.0,
+            staging: 0.0,
         }
     }
+
+    /// Returns an error that names the first field of the Job which no real job could have,
+    /// e.g. zero cores or a negative duration. The resources of the cluster are not checked here,
+    /// see NodeRegistry::check_fits()
+    pub fn validate(&self) -> Result<(), String> {
+        let invalid = |field: &str, value: f32, rule: &str| -> Result<(), String> {
+            Err(format!("Job {} has {} {} but it must be {}", self.uid, field, value, rule))
+        };
+
+        if !(self.cores > 0.0 && self.cores.is_finite()) {
+            return invalid("cores", self.cores, "positive");
+        }
+
+        for (field, value) in [("memory", self.memory), ("duration", self.duration),
+            ("estimate", self.estimate), ("time_created", self.time_created),
+            ("input_data", self.input_data)] {
+            if !(value >= 0.0 && value.is_finite()) {
+                return invalid(field, value, "non-negative");
+            }
+        }
+
+        if let Some(limit) = self.walltime_limit {
+            if !(limit > 0.0 && limit.is_finite()) {
+                return invalid("walltime_limit", limit, "positive");
+            }
+        }
+
+        if let Some(max) = self.max_queue_time {
+            if !(max > 0.0 && max.is_finite()) {
+                return invalid("max_queue_time", max, "positive");
+            }
+        }
+
+        if !(0.0..=1.0).contains(&self.failure_probability) {
+            return invalid("failure_probability", self.failure_probability, "between 0 and 1");
+        }
+
+        if let Some(limit) = self.memory_limit {
+            if !(limit >= self.memory && limit.is_finite()) {
+                return invalid("memory_limit", limit, "at least the requested memory");
+            }
+        }
+
+        if let Some(usage) = self.memory_usage {
+            let limit = self.memory_limit.unwrap_or(self.memory);
+            if !(usage >= 0.0 && usage <= limit) {
+                return invalid("memory_usage", usage, "between 0 and the memory_limit");
+            }
+        }
+
+        if !self.memory_profile.is_empty() {
+            if self.memory_limit.is_some() || self.memory_usage.is_some() {
+                return Err(format!("Job {} has a memory_profile so it cannot also have a \
+                    memory_limit or memory_usage", self.uid));
+            }
+
+            if self.memory_profile[0].0 != 0.0 {
+                return invalid("memory_profile starting at", self.memory_profile[0].0, "0");
+            }
+
+            for (idx, (fraction, memory)) in self.memory_profile.iter().enumerate() {
+                if idx > 0 && !(*fraction > self.memory_profile[idx - 1].0 && *fraction < 1.0) {
+                    return invalid("memory_profile step at", *fraction,
+                                   "after the previous step and before 1");
+                }
+                if !(*memory >= 0.0 && *memory <= self.memory) {
+                    return invalid("memory_profile step of", *memory,
+                                   "between 0 and the requested memory");
+                }
+            }
+        }
+
+        for (name, amount) in &self.resources {
+            if !(*amount >= 0.0 && amount.is_finite()) {
+                return invalid(name, *amount, "non-negative");
+            }
+        }
+
+        if let Some(name) = self.keep.iter().find(|name| !self.resources.contains_key(*name)) {
+            return Err(format!("Job {} keeps {} but it does not request any", self.uid, name));
+        }
+
+        if let Some(uid) = self.release.iter().find(|uid| !self.depends_on.contains(uid)) {
+            return Err(format!("Job {} releases what Job {} keeps but it does not depend on it",
+                               self.uid, uid));
+        }
+
+        if self.gang && self.bundle.is_some() {
+            return Err(format!("Job {} is a gang job but the jobs of a bundle share 1 node",
+                               self.uid));
+        }
+
+        if self.exclusive && self.bundle.is_some() {
+            return Err(format!("Job {} is exclusive but the jobs of a bundle share 1 node",
+                               self.uid));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the wall-clock time the Job needs to finish on nodes with its speed
+    pub fn effective_duration(&self) -> f32 {
+        self.duration / self.speed
+    }
+
+    /// Returns true if the Job runs for longer than its walltime limit
+    pub fn exceeds_walltime(&self) -> bool {
+        self.walltime_limit.is_some_and(|limit| self.effective_duration() > limit)
+    }
+
+    /// Returns how long the Job runs for once it starts, i.e. until it finishes or reaches its
+    /// walltime limit
+    pub fn runtime(&self) -> f32 {
+        match self.walltime_limit {
+            Some(limit) => self.effective_duration().min(limit),
+            None => self.effective_duration(),
+        }
+    }
+
+    /// Returns the memory that the Job actually uses at @now: the step of memory_profile that it
+    /// reached or, without a profile, its memory_usage which defaults to the requested memory
+    pub fn memory_used(&self, now: f32) -> f32 {
+        match self.memory_steps().iter().rev().find(|(time, _)| *time <= now) {
+            Some((_, memory)) => *memory,
+            None => self.memory_profile.first().map_or(
+                self.memory_usage.unwrap_or(self.memory), |(_, memory)| *memory),
+        }
+    }
+
+    /// Returns when the running Job reaches each step of its memory_profile and the memory it
+    /// uses from then on. The first step starts with the Job, the others at their fraction of
+    /// its effective_duration() after the input data is staged
+    pub fn memory_steps(&self) -> Vec<(f32, f32)> {
+        let started = match self.time_started {
+            Some(started) => started,
+            None => return vec![],
+        };
+        let run_start = started + self.staging;
+
+        self.memory_profile
+            .iter()
+            .map(|(fraction, memory)| if *fraction > 0.0 {
+                (run_start + fraction * self.effective_duration(), *memory)
+            } else {
+                (started, *memory)
+            })
+            .collect()
+    }
+
+    /// Returns the memory-seconds of the Job between @from and @to: the requested memory for the
+    /// entire time or, with a memory_profile, the memory that each step uses while it lasts
+    pub fn memory_seconds(&self, from: f32, to: f32) -> f64 {
+        let steps = self.memory_steps();
+        if steps.is_empty() {
+            return self.memory as f64 * (to - from).max(0.0) as f64;
+        }
+
+        let ends = steps.iter().skip(1).map(|(time, _)| *time).chain([f32::INFINITY]);
+        steps.iter()
+            .zip(ends)
+            .map(|((start, memory), end)| {
+                *memory as f64 * (end.min(to) - start.max(from)).max(0.0) as f64
+            })
+            .sum()
+    }
+
+    /// Returns the memory that the Job actually uses on each node at @now, the requested memory
+    /// on each node scaled by memory_used()
+    pub fn memory_usage_per_node(&self, now: f32) -> Vec<(usize, f32)> {
+        let scale = if self.memory > 0.0 { self.memory_used(now) / self.memory } else { 1.0 };
+        self.node_memory.iter().map(|(uid, memory)| (*uid, memory * scale)).collect()
+    }
+
+    /// Forgets where and when the job ran so that it can go back to the queue
+    pub fn reset_run(&mut self) {
+        self.speculative = false;
+        self.time_started = None;
+        self.time_done = None;
+        self.node_cores = None;
+        self.node_memory.clear();
+        self.gang_cores.clear();
+        self.hops = None;
+        self.staging = 0.0;
+        self.colocated = false;
+    }
+
+    /// Returns the cores that the Job uses on each node
+    pub fn cores_per_node(&self) -> Vec<(usize, f32)> {
+        if self.gang_cores.is_empty() {
+            match self.node_cores {
+                Some(node) => vec![(node, self.cores)],
+                None => vec![],
+            }
+        } else {
+            self.gang_cores.clone()
+        }
+    }
+
+    /// Returns the fraction of the named resources of the Job that each node provides,
+    /// pieces of gang jobs get a share that is proportional to their cores
+    pub fn resource_shares(&self) -> Vec<(usize, f32)> {
+        if self.gang_cores.is_empty() {
+            self.cores_per_node().into_iter().map(|(node, _)| (node, 1.0)).collect()
+        } else {
+            self.gang_cores
+                .iter()
+                .map(|(node, cores)| (*node, cores / self.cores))
+                .collect()
+        }
+    }
+
+    /// Parses a line of a Standard Workload Format (SWF) trace, a whitespace separated list of
+    /// 18 fields: https://www.cs.huji.ac.il/labs/parallel/workload/swf.html
+    ///
+    /// The Job gets the next available UID (i.e. the SWF job number is ignored) and:
+    /// - time_created: submit time (field 2), defaults to 0
+    /// - cores: requested processors (field 8), falls back to allocated processors (field 5)
+    ///   and then to 1
+    /// - memory: requested memory (field 10) which is in KB per processor, falls back to used
+    ///   memory (field 7) and then to 0. We convert it to GB for the entire job
+    /// - duration: run time (field 4), defaults to 0 i.e. the job finishes as soon as it starts
+    /// - estimate: requested time (field 9), defaults to duration
+    /// - can_borrow: y, SWF has no notion of memory borrowing
+    ///
+    /// Fields that are -1 (unknown) or cannot be parsed get the defaults above. The remaining
+    /// fields (e.g. status, user, preceding job) do not affect the simulation.
+    pub fn from_swf(line: &str) -> Result<Self, String> {
+        const KB_PER_GB: f32 = 1024. * 1024.;
+
+        let tokens: Vec<_> = line.split_whitespace().collect();
+
+        if tokens.len() < 18 {
+            return Err(format!(
+                "Expected 18 SWF fields separated by whitespace but found \"{:?}\"",
+                tokens
+            ));
+        }
+
+        // VV: SWF uses -1 for unknown values, none of the fields we use can be negative
+        let field = |idx: usize| -> Option<f32> {
+            tokens[idx - 1].parse::<f32>().ok().filter(|v| *v >= 0.0)
+        };
+
+        let time_created = field(2).unwrap_or(0.0);
+        let cores = field(8).or_else(|| field(5)).filter(|c| *c > 0.0).unwrap_or(1.0);
+        let memory = field(10).or_else(|| field(7)).unwrap_or(0.0) * cores / KB_PER_GB;
+        let duration = field(4).unwrap_or(0.0);
+
+        let mut job = Job::new(cores, memory, duration, true, time_created);
+        if let Some(estimate) = field(9) {
+            job.estimate = estimate;
+        }
+
+        Ok(job)
+    }
+}
+
+/// The fields that an output trace may contain: the keys of Job::to_json() plus wait, the time
+/// between the submission and the start of the job, and slowdown, its (wait + runtime) / runtime
+pub const TRACE_FIELDS: &[&str] = &[
+    "id", "submit", "start", "finish", "node", "cores", "memory", "node_memory", "gang_cores",
+    "bundle", "user", "anti_affinity", "colocated", "affinity", "partition", "deadline",
+    "memory_usage", "oom_kills", "attempts", "status", "speculative", "hops", "resources", "keep",
+    "array_id", "array_index", "speed", "run_start", "runtime", "wait", "slowdown",
+];
+
+impl Job {
+    /// Returns the values of the trace @fields of the Job in the order of @fields, the names
+    /// must be in TRACE_FIELDS
+    pub fn trace_fields(&self, fields: &[String]) -> Vec<serde_json::Value> {
+        let mut json = self.to_json();
+        let wait = self.time_started.map(|start| start - self.time_created);
+        json["wait"] = serde_json::json!(wait);
+        json["slowdown"] = serde_json::json!(self.time_started.zip(self.time_done)
+            .filter(|(start, done)| done > start)
+            .map(|(start, done)| (done - self.time_created) / (done - start)));
+
+        fields.iter().map(|name| json[name.as_str()].take()).collect()
+    }
+
+    /// Returns a JSON object describing the Job, used for the JSON-lines output traces
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.uid,
+            "submit": self.time_created,
+            "start": self.time_started,
+            "finish": self.time_done,
+            "node": self.node_cores,
+            "cores": self.cores,
+            "memory": self.memory,
+            "node_memory": self.node_memory,
+            "gang_cores": self.gang_cores,
+            "bundle": self.bundle,
+            "user": self.user,
+            "anti_affinity": self.anti_affinity,
+            "colocated": self.colocated,
+            "affinity": self.affinity,
+            "partition": self.partition,
+            "deadline": self.deadline,
+            "memory_usage": self.memory_usage,
+            "oom_kills": self.oom_kills,
+            "attempts": self.attempts,
+            "status": match (self.failed, self.time_done) {
+                (true, _) => Some("failed"),
+                (false, Some(_)) => Some("completed"),
+                (false, None) => None,
+            },
+            "speculative": self.speculative,
+            "hops": self.hops,
+            "resources": self.resources,
+            "keep": self.keep,
+            "array_id": self.array.map(|(id, _)| id),
+            "array_index": self.array.map(|(_, index)| index),
+            "speed": self.speed,
+            "run_start": self.time_started.map(|start| start + self.staging),
+            "runtime": self.time_started.zip(self.time_done).map(|(start, done)| done - start),
+        })
+    }
 }
 
 impl Display for Job {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        if self.time_done.is_some() {
+        if let Some(time_done) = self.time_done {
             write!(f, "{};{};{};{};{};{};{};{};{}",
                    self.uid, self.cores, self.memory, self.duration,
                    if self.can_borrow { 'y' } else { 'n' }, self.time_created,
-                   self.time_started.unwrap(), self.time_done.unwrap(), self.node_cores.unwrap())?;
+                   self.time_started.unwrap(), time_done, self.node_cores.unwrap())?;
 
             for (node, mem) in &self.node_memory {
                 write!(f, ";{};{}", node, mem)?;
             }
             std::fmt::Result::Ok(())
-        } else if self.time_started.is_some() {
+        } else if let Some(time_started) = self.time_started {
             write!(f, "{};{};{};{};{};{};{};null;{}",
                    self.uid, self.cores, self.memory, self.duration,
                    if self.can_borrow { 'y' } else { 'n' }, self.time_created,
-                   self.time_started.unwrap(), self.node_cores.unwrap())?;
+                   time_started, self.node_cores.unwrap())?;
             for (node, mem) in &self.node_memory {
                 write!(f, ";{};{}", node, mem)?;
             }
@@ -142,24 +653,69 @@ impl Display for Job {
 
 impl FromStr for Job {
     type Err = String;
-    /// Format is <uid:usize or '?' to use next available UID>;<cores:f32>;<memory:f32>;<duration:f32>;<borrow:y/n>;<time_created:f32>
+    /// Format is <uid:usize or '?' to use next available UID>;<cores:f32>;<memory:f32>;<duration:time>;<borrow:y/n>;<time_created:time>[;<key>=<value>]*
     /// First job must have uid 0, subsequent jobs must increase uid by 1 and Jobs
-    /// cannot skip UID values.
+    /// cannot skip UID values. Times are seconds and may have a unit suffix such as 5m or 2h, see
+    /// duration::parse_time().
     ///
     /// Just use '?' for the parser to pick the appropriate UID.
+    ///
+    /// The optional key=value tokens are:
+    /// - estimate=<time>: the runtime the user expects the job to have, defaults to duration
+    /// - priority=<i32>: jobs with higher priority are considered first, defaults to 0
+    /// - depends_on=[<uid:usize>,...]: the job may only start after these jobs are done
+    /// - gang=<y/n>: the job may spread its cores over multiple nodes, defaults to n
+    /// - exclusive=<y/n>: the job only starts on an idle node and keeps other jobs off the nodes
+    ///   that provide its cores until it stops, defaults to n
+    /// - bundle=<str>: the job starts together with the other waiting jobs of this bundle on a
+    ///   single node, defaults to none
+    /// - user=<str>: the user that submitted the job, defaults to none
+    /// - anti_affinity=<str>: the job avoids the nodes that run other jobs with the same group,
+    ///   defaults to none
+    /// - affinity=<str>: the job prefers, and runs faster on, the nodes that recently finished
+    ///   other jobs of the same group, defaults to none
+    /// - partition=<str>: the job only runs on the nodes of this partition, defaults to none
+    ///   i.e. any node
+    /// - walltime_limit=<time>: the job fails if its duration exceeds this, defaults to no limit
+    /// - deadline=<time>: the simulated time by which the job must finish, defaults to none
+    /// - max_queue_time=<time>: the job gives up if it waits for longer than this after it
+    ///   arrives, defaults to no limit
+    /// - memory_limit=<f32>: the job may use up to this much memory although the scheduler only
+    ///   reserves what it requests, defaults to the requested memory
+    /// - memory_usage=<f32>: the memory that the job actually uses, no more than its
+    ///   memory_limit, defaults to a random amount between the request and the limit
+    /// - memory_profile=[<fraction>:<f32>,...]: the memory that the job actually uses from each
+    ///   fraction of its runtime on, e.g. [0:1,0.8:4] uses 1 until 80% of the runtime and then 4.
+    ///   The first step must be at 0, the scheduler reserves the larger of the memory column and
+    ///   the peak of the steps. Defaults to none i.e. the job uses memory_usage throughout
+    /// - failure_probability=<f32>: the chance, between 0 and 1, that a run of the job fails when
+    ///   it reaches its end, defaults to 0
+    /// - max_retries=<usize>: how many times the job runs again after a failed run, defaults to 0
+    /// - input_data=<f32>: data that is staged to the nodes of the job before it runs, in the
+    ///   units of memory, defaults to 0
+    /// - keep=[<resource>,...]: these named resources stay allocated on the nodes of the job
+    ///   after it finishes, e.g. scratch disk with data for later jobs, defaults to none
+    /// - release=[<uid:usize>,...]: the job frees what these jobs, which it must depend on, keep
+    ///   once it finishes
+    /// - label:<key>=<value>: the job only runs on nodes that have this label (e.g.
+    ///   label:zone=us-east), may repeat
+    /// - <resource>=<f32>: any other key is the amount of a named resource (e.g. gpus=1) that
+    ///   the job needs, nodes that provide this resource must define it too
+    ///
+    /// Lines of array jobs (array=<count>) must go through job_factory::expand_array() first
     fn from_str(line: &str) -> Result<Self, <Self as FromStr>::Err> {
         let tokens: Vec<_> = line.split(';').map(|s| s.trim()).collect();
 
-        if tokens.len() != 6 {
+        if tokens.len() < 6 {
             return Err(format!(
-                "Expected 6 tokens partitioned by ';' in string but found \"{:?}\"",
+                "Expected at least 6 tokens partitioned by ';' in string but found \"{:?}\"",
                 tokens
             ));
         }
 
         let uid: usize;
         if tokens[0] == "?" {
-            uid = NEXT_JOB_UID.fetch_or(0, Ordering::SeqCst);
+            uid = NEXT_JOB_UID.get();
         } else if let Ok(c) = tokens[0].parse() {
             uid = c;
         } else {
@@ -181,7 +737,7 @@ impl FromStr for Job {
         }
 
         let duration: f32;
-        if let Ok(c) = tokens[3].parse() {
+        if let Ok(c) = parse_time(tokens[3]) {
             duration = c;
         } else {
             return Err(format!("Invalid duration \"{}\"", tokens[3]));
@@ -200,19 +756,196 @@ impl FromStr for Job {
         }
 
         let time_created: f32;
-        if let Ok(c) = tokens[5].parse() {
+        if let Ok(c) = parse_time(tokens[5]) {
             time_created = c;
         } else {
             return Err(format!("Invalid time_created \"{}\"", tokens[5]));
         }
 
-        Ok(Self::new_with_uid(
+        let mut estimate: Option<f32> = None;
+        let mut priority: Option<i32> = None;
+        let mut depends_on: Vec<JobId> = vec![];
+        let mut keep: Vec<String> = vec![];
+        let mut release: Vec<JobId> = vec![];
+        let mut gang = false;
+        let mut exclusive = false;
+        let mut bundle: Option<String> = None;
+        let mut user: Option<String> = None;
+        let mut anti_affinity: Option<String> = None;
+        let mut affinity: Option<String> = None;
+        let mut partition: Option<String> = None;
+        let mut walltime_limit: Option<f32> = None;
+        let mut deadline: Option<f32> = None;
+        let mut max_queue_time: Option<f32> = None;
+        let mut memory_limit: Option<f32> = None;
+        let mut memory_usage: Option<f32> = None;
+        let mut memory_profile: Vec<(f32, f32)> = vec![];
+        let mut failure_probability: f32 = 0.0;
+        let mut max_retries: usize = 0;
+        let mut input_data: f32 = 0.0;
+        let mut resources: BTreeMap<String, f32> = BTreeMap::new();
+        let mut selector: BTreeMap<String, String> = BTreeMap::new();
+
+        for token in tokens.iter().skip(6).filter(|t| !t.is_empty()) {
+            let (key, value) = match token.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(format!("Expected <key>=<value> but found \"{}\"", token)),
+            };
+
+            if let Some(label) = key.strip_prefix("label:") {
+                if label.is_empty() {
+                    return Err(format!("Expected label:<key>=<value> but found \"{}\"", token));
+                }
+                selector.insert(label.to_owned(), value.to_owned());
+                continue;
+            }
+
+            match key {
+                "estimate" => match parse_time(value) {
+                    Ok(c) => estimate = Some(c),
+                    Err(_) => return Err(format!("Invalid estimate \"{}\"", value)),
+                },
+                "priority" => match value.parse() {
+                    Ok(c) => priority = Some(c),
+                    Err(_) => return Err(format!("Invalid priority \"{}\"", value)),
+                },
+                "gang" => match value {
+                    "y" => gang = true,
+                    "n" => gang = false,
+                    _ => return Err(format!("gang may only be y or n but found \"{}\"", value)),
+                },
+                "exclusive" => match value {
+                    "y" => exclusive = true,
+                    "n" => exclusive = false,
+                    _ => return Err(format!("exclusive may only be y or n but found \"{}\"",
+                                            value)),
+                },
+                "walltime_limit" => match parse_time(value) {
+                    Ok(c) => walltime_limit = Some(c),
+                    Err(_) => return Err(format!("Invalid walltime_limit \"{}\"", value)),
+                },
+                "deadline" => match parse_time(value) {
+                    Ok(c) => deadline = Some(c),
+                    Err(_) => return Err(format!("Invalid deadline \"{}\"", value)),
+                },
+                "max_queue_time" => match parse_time(value) {
+                    Ok(c) => max_queue_time = Some(c),
+                    Err(_) => return Err(format!("Invalid max_queue_time \"{}\"", value)),
+                },
+                "memory_limit" => match value.parse() {
+                    Ok(c) => memory_limit = Some(c),
+                    Err(_) => return Err(format!("Invalid memory_limit \"{}\"", value)),
+                },
+                "memory_usage" => match value.parse() {
+                    Ok(c) => memory_usage = Some(c),
+                    Err(_) => return Err(format!("Invalid memory_usage \"{}\"", value)),
+                },
+                "memory_profile" => {
+                    let value = value.trim_start_matches('[').trim_end_matches(']');
+                    for step in value.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                        let parsed = step.split_once(':')
+                            .and_then(|(f, m)| f.trim().parse().ok().zip(m.trim().parse().ok()));
+                        match parsed {
+                            Some(step) => memory_profile.push(step),
+                            None => return Err(format!("Invalid memory_profile step \"{}\", \
+                                expected <fraction>:<memory>", step)),
+                        }
+                    }
+                }
+                "failure_probability" => match value.parse() {
+                    Ok(c) => failure_probability = c,
+                    Err(_) => return Err(format!("Invalid failure_probability \"{}\"", value)),
+                },
+                "max_retries" => match value.parse() {
+                    Ok(c) => max_retries = c,
+                    Err(_) => return Err(format!("Invalid max_retries \"{}\"", value)),
+                },
+                "input_data" => match value.parse() {
+                    Ok(c) => input_data = c,
+                    Err(_) => return Err(format!("Invalid input_data \"{}\"", value)),
+                },
+                "user" => user = Some(value.to_owned()).filter(|u| !u.is_empty()),
+                "bundle" => bundle = Some(value.to_owned()).filter(|b| !b.is_empty()),
+                "anti_affinity" => {
+                    anti_affinity = Some(value.to_owned()).filter(|g| !g.is_empty())
+                }
+                "affinity" => affinity = Some(value.to_owned()).filter(|g| !g.is_empty()),
+                "partition" => partition = Some(value.to_owned()).filter(|p| !p.is_empty()),
+                "depends_on" => {
+                    let value = value.trim_start_matches('[').trim_end_matches(']');
+                    for uid in value.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                        match uid.parse() {
+                            Ok(c) => depends_on.push(c),
+                            Err(_) => return Err(format!("Invalid depends_on uid \"{}\"", uid)),
+                        }
+                    }
+                }
+                "keep" => {
+                    let value = value.trim_start_matches('[').trim_end_matches(']');
+                    keep.extend(value.split(',').map(|s| s.trim()).filter(|s| !s.is_empty())
+                        .map(|s| s.to_owned()));
+                }
+                "release" => {
+                    let value = value.trim_start_matches('[').trim_end_matches(']');
+                    for uid in value.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                        match uid.parse() {
+                            Ok(c) => release.push(c),
+                            Err(_) => return Err(format!("Invalid release uid \"{}\"", uid)),
+                        }
+                    }
+                }
+                "cores" | "memory" => {
+                    return Err(format!("Use the {} column instead of \"{}\"", key, token))
+                }
+                _ => match value.parse() {
+                    Ok(c) => {
+                        resources.insert(key.to_owned(), c);
+                    }
+                    Err(_) => return Err(format!("Invalid amount of {} \"{}\"", key, value)),
+                },
+            }
+        }
+
+        let peak = memory_profile.iter().map(|(_, m)| *m).fold(memory, f32::max);
+        let mut job = Self::new_with_uid(
             uid,
             cores,
-            memory,
+            peak,
             duration,
             borrow,
             time_created,
-        ))
+        );
+
+        if let Some(estimate) = estimate {
+            job.estimate = estimate;
+        }
+
+        if let Some(priority) = priority {
+            job.priority = priority;
+        }
+
+        job.depends_on = depends_on;
+        job.gang = gang;
+        job.exclusive = exclusive;
+        job.bundle = bundle;
+        job.user = user;
+        job.anti_affinity = anti_affinity;
+        job.affinity = affinity;
+        job.partition = partition;
+        job.walltime_limit = walltime_limit;
+        job.deadline = deadline;
+        job.max_queue_time = max_queue_time;
+        job.memory_limit = memory_limit;
+        job.memory_usage = memory_usage;
+        job.memory_profile = memory_profile;
+        job.failure_probability = failure_probability;
+        job.max_retries = max_retries;
+        job.input_data = input_data;
+        job.resources = resources;
+        job.keep = keep;
+        job.release = release;
+        job.selector = selector;
+
+        Ok(job)
     }
 }
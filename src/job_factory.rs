@@ -17,38 +17,187 @@ specific language governing permissions and limitations
 under the License.
 */
 
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
-use std::fs::File;
 use std::io::BufRead;
-use std::io::BufReader;
-use std::io::BufWriter;
 use std::io::Cursor;
 use std::io::Write;
 use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
 
 use anyhow::bail;
+use anyhow::Context;
 use anyhow::Result;
+use rand::SeedableRng;
+use rand_distr::Distribution;
+use rand_distr::Exp;
+use tracing::warn;
 
+use crate::compression;
+use crate::duration::parse_time;
 use crate::job::Job;
+use crate::job::JobId;
+use crate::job::TRACE_FIELDS;
 use crate::job::reset_job_metadata;
+use crate::kube;
+use crate::registry::NodeRegistry;
+use crate::scheduler::SimRng;
 
 pub trait JobFactory {
     fn job_peek(&self) -> Option<&Job>;
     fn job_get(&mut self) -> Job;
     fn job_mark_done(&mut self, _job: &Job) {}
+    /// Called for jobs that the Scheduler killed, e.g. because they exceeded their walltime limit
+    fn job_mark_failed(&mut self, _job: &Job) {}
     fn more_jobs(&self) -> bool;
     fn jobs_done(&self) -> &Vec<usize>;
+    /// Returns the error that ended the jobs early, e.g. the first invalid job of a JobStreaming
+    /// in Validation::Strict
+    fn check_stream(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
 pub struct JobStreaming {
     pub reader: Box<dyn BufRead>,
     jobs_done: Vec<usize>,
     next_job: Option<Job>,
+    // VV: The remaining jobs of the last array job that the stream defined
+    array_jobs: VecDeque<Job>,
+    validation: Validation,
+    line_number: usize,
+    // VV: The jobs that Validation::Lenient skipped, jobs that depend on them are skipped too
+    skipped: HashSet<JobId>,
+    // VV: Streams that can only be read once (e.g. stdin) must be sorted by submit time
+    require_sorted: bool,
+    // VV: The UID and submit time of the last job that the stream defined
+    last_submitted: Option<(JobId, f32)>,
+    // VV: The first invalid job of Validation::Strict, the stream ends there
+    error: Option<String>,
+}
+
+/// How JobStreaming handles jobs that are malformed or physically impossible (see Job::validate())
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Validation {
+    /// Invalid jobs are errors, use check_jobs() to find them before the simulation starts. A
+    /// JobStreaming ends at its first invalid job and reports it with JobFactory::check_stream()
+    #[default]
+    Strict,
+    /// Invalid jobs, and the jobs that depend on them, are skipped with a warning
+    Lenient,
+}
+
+impl FromStr for Validation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "strict" => Ok(Validation::Strict),
+            "lenient" => Ok(Validation::Lenient),
+            _ => bail!("validation may only be strict or lenient but found \"{}\"", s),
+        }
+    }
+}
+
+/// Streams Jobs out of a trace in the Standard Workload Format (SWF), see Job::from_swf()
+pub struct JobStreamingSwf {
+    pub reader: Box<dyn BufRead>,
+    jobs_done: Vec<usize>,
+    next_job: Option<Job>,
+}
+
+/// The format of the output trace that JobStreamingWithOutput produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// The ';' separated format of Job::fmt() with a header line
+    Text,
+    /// One JSON object per completed Job, see Job::to_json()
+    JsonLines,
+}
+
+impl TraceFormat {
+    /// Picks JsonLines for paths with the .jsonl extension and Text for everything else
+    pub fn from_path(path: &Path) -> Self {
+        if compression::extension(path) == Some("jsonl") {
+            TraceFormat::JsonLines
+        } else {
+            TraceFormat::Text
+        }
+    }
 }
 
+/// Writes every Job that finishes to an output trace. The trace is flushed once every
+/// @flush_every jobs (by default after each job) so that it is safe to tail it and a crash loses
+/// at most the last flush_every - 1 jobs
 pub struct JobStreamingWithOutput {
-    pub inner: JobStreaming,
+    pub inner: Box<dyn JobFactory>,
     pub writer: Box<dyn Write>,
+    pub format: TraceFormat,
+    pub flush_every: usize,
+    /// The fields of each job in the trace, see job::TRACE_FIELDS. None keeps the fields of
+    /// Job::fmt() in Text traces and of Job::to_json() in JsonLines ones
+    pub fields: Option<Vec<String>>,
+    // VV: Jobs that are in the buffer of writer but not in the file yet
+    unflushed: usize,
+}
+
+/// Fails unless @fields is a non-empty list of the names in job::TRACE_FIELDS without duplicates
+pub fn check_trace_fields(fields: &[String]) -> Result<()> {
+    if fields.is_empty() {
+        bail!("The list of trace fields is empty")
+    }
+
+    for (idx, name) in fields.iter().enumerate() {
+        if !TRACE_FIELDS.contains(&name.as_str()) {
+            bail!("Unknown trace field \"{}\", expected one of {}", name, TRACE_FIELDS.join(", "))
+        }
+        if fields[..idx].contains(name) {
+            bail!("The trace field \"{}\" appears more than once", name)
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the submit times of the jobs of @inner with the arrivals of a Poisson process with
+/// @rate jobs per second, the jobs keep their order. This turns 1 workload into workloads of any
+/// load intensity. The gaps between arrivals come from a SimRng seeded with @seed that does not
+/// share its stream with the rng of the Scheduler
+pub struct PoissonArrivals {
+    pub inner: Box<dyn JobFactory>,
+    pub rate: f32,
+    rng: SimRng,
+    gaps: Exp<f32>,
+    // VV: The submit time of the last job that arrived
+    now: f32,
+    next_job: Option<Job>,
+}
+
+impl PoissonArrivals {
+    /// Panics if @rate is not positive
+    pub fn new(inner: Box<dyn JobFactory>, rate: f32, seed: u64) -> Self {
+        assert!(rate > 0.0 && rate.is_finite(), "arrival rate {} must be positive", rate);
+
+        let mut rng = SimRng::seed_from_u64(seed);
+        rng.set_stream(1);
+        let gaps = Exp::new(rate).unwrap();
+        let mut arrivals = Self { inner, rate, rng, gaps, now: 0.0, next_job: None };
+        arrivals.advance();
+        arrivals
+    }
+
+    /// Takes the next job of inner and draws its submit time
+    fn advance(&mut self) {
+        self.next_job = if self.inner.job_peek().is_some() {
+            let mut job = self.inner.job_get();
+            self.now += self.gaps.sample(&mut self.rng);
+            job.time_created = self.now;
+            Some(job)
+        } else {
+            None
+        };
+    }
 }
 
 pub struct JobCollection {
@@ -58,7 +207,7 @@ pub struct JobCollection {
 
 impl JobFactory for JobCollection {
     fn job_peek(&self) -> Option<&Job> {
-        self.jobs.get(0)
+        self.jobs.front()
     }
 
     fn job_get(&mut self) -> Job {
@@ -81,7 +230,6 @@ impl JobFactory for JobCollection {
 }
 
 impl JobCollection {
-    #[allow(dead_code)]
     pub fn new(jobs: Vec<Job>) -> Self {
         let jobs = VecDeque::from(jobs);
         Self {
@@ -89,31 +237,273 @@ impl JobCollection {
             jobs_done: vec![],
         }
     }
+
+    /// Same as JobCollection::new() but fails if the dependencies of @jobs are invalid
+    pub fn try_new(jobs: Vec<Job>) -> Result<Self> {
+        check_dependencies(&jobs)?;
+        Ok(Self::new(jobs))
+    }
+
+    /// Creates the jobs out of the Kubernetes Pod manifests in @path, see kube::jobs_from_str()
+    pub fn from_yaml(path: &Path) -> Result<Self> {
+        let content = compression::read_to_string(path)
+            .context(format!("Unable to read jobs file {}", path.display()))?;
+        Ok(Self::new(kube::jobs_from_str(&content)?))
+    }
+
+    /// Merges the JobStreaming files at @paths into 1 stream ordered by time_created, the files
+    /// need not be sorted. Jobs with the '?' UID continue the numbering of the previous file and
+    /// a UID that more than 1 file defines is an error. With @registry and Validation::Strict,
+    /// jobs must also fit in the cluster like in check_jobs()
+    pub fn from_paths(
+        paths: &[PathBuf],
+        validation: Validation,
+        registry: Option<&NodeRegistry>,
+    ) -> Result<Self> {
+        let capacity = registry.map(|registry| registry.capacity_resources());
+        let mut defined: HashMap<JobId, &Path> = HashMap::new();
+        let mut skipped: HashSet<JobId> = HashSet::new();
+        let mut jobs = vec![];
+
+        reset_job_metadata();
+
+        for path in paths {
+            let reader = match compression::open(path) {
+                Ok(reader) => reader,
+                Err(x) => bail!("Unable to open file \"{}\" because: {:?}", path.display(), x),
+            };
+
+            for (i, line) in reader.lines().enumerate() {
+                let line = line?;
+                let line = line.trim();
+                if line.starts_with('#') || line.is_empty() {
+                    continue;
+                }
+
+                let entries = parse_entry(line).unwrap_or_else(|err| vec![Err((None, err))]);
+
+                for entry in entries {
+                    let checked = entry.and_then(|job| {
+                        let fits = match (registry, &capacity, validation) {
+                            (Some(registry), Some(capacity), Validation::Strict) => registry
+                                .check_resources(&job)
+                                .and_then(|_| registry.check_fits(&job, capacity)),
+                            _ => Ok(()),
+                        };
+
+                        match job.depends_on.iter().find(|uid| skipped.contains(uid)) {
+                            Some(uid) => Err((Some(job.uid),
+                                format!("Job {} depends on the skipped Job {}", job.uid, uid))),
+                            None => fits.map(|_| job)
+                                .map_err(|err| (None, err.to_string())),
+                        }
+                    });
+
+                    let job = match (checked, validation) {
+                        (Ok(job), _) => job,
+                        (Err((_, err)), Validation::Strict) => {
+                            bail!("Invalid job on line {} of {}: {}", i + 1, path.display(), err)
+                        }
+                        (Err((uid, err)), Validation::Lenient) => {
+                            warn!(line = i + 1, "Skipping invalid job on line {} of {}: {}",
+                                i + 1, path.display(), err);
+                            skipped.extend(uid);
+                            continue;
+                        }
+                    };
+
+                    if let Some(other) = defined.insert(job.uid, path) {
+                        bail!("Job {} of {} is already defined in {}", job.uid, path.display(),
+                            other.display())
+                    }
+                    jobs.push(job);
+                }
+            }
+        }
+
+        // VV: Jobs that arrive at the same time keep the order of their UIDs
+        jobs.sort_by(|a, b| a.time_created.total_cmp(&b.time_created).then(a.uid.cmp(&b.uid)));
+        Self::try_new(jobs)
+    }
+}
+
+/// Returns an error if a Job depends on a Job that is not in @jobs or if the dependencies
+/// contain a cycle (which would otherwise leave the Jobs in the cycle waiting forever)
+pub fn check_dependencies(jobs: &[Job]) -> Result<()> {
+    let index: HashMap<JobId, usize> = jobs.iter().enumerate().map(|(i, j)| (j.uid, i)).collect();
+    let mut dependents: Vec<Vec<usize>> = vec![vec![]; jobs.len()];
+    let mut pending: Vec<usize> = vec![0; jobs.len()];
+
+    for (i, job) in jobs.iter().enumerate() {
+        for uid in &job.depends_on {
+            match index.get(uid) {
+                Some(&dep) => dependents[dep].push(i),
+                None => bail!("Job {} depends on unknown Job {}", job.uid, uid),
+            }
+            pending[i] += 1;
+        }
+    }
+
+    // VV: Kahn's algorithm, any Job that never runs out of pending dependencies is in a cycle
+    let mut ready: Vec<usize> = (0..jobs.len()).filter(|i| pending[*i] == 0).collect();
+    let mut visited = 0;
+
+    while let Some(i) = ready.pop() {
+        visited += 1;
+        for &dependent in &dependents[i] {
+            pending[dependent] -= 1;
+            if pending[dependent] == 0 {
+                ready.push(dependent);
+            }
+        }
+    }
+
+    if visited != jobs.len() {
+        let cycle: Vec<JobId> = (0..jobs.len())
+            .filter(|i| pending[*i] > 0)
+            .map(|i| jobs[i].uid)
+            .collect();
+        bail!("Jobs {:?} have cyclic dependencies", cycle)
+    }
+
+    Ok(())
+}
+
+/// Expands the definition of an array job into the lines of its individual jobs.
+///
+/// An array job is a job line (see Job::from_str()) with the extra tokens:
+/// - array=<count:usize>: the number of jobs in the array
+/// - array_stride=<time>: the i-th job is created i * stride seconds after the first, defaults to 0
+/// - <key>[<index:usize>]=<value>: overrides <key> for the job with this index, <key> can be
+///   cores, memory, duration, borrow, or any of the key=value tokens of a job line
+///
+/// The jobs of an array get consecutive uids starting from the uid of the line. Returns None for
+/// lines without an array token.
+pub fn expand_array(line: &str) -> Result<Option<Vec<String>>> {
+    let tokens: Vec<_> = line.split(';').map(|s| s.trim()).collect();
+
+    if tokens.len() < 6 {
+        return Ok(None);
+    }
+
+    let mut count: Option<usize> = None;
+    let mut stride = 0.0f32;
+    let mut overrides: Vec<(usize, &str, &str)> = vec![];
+    let mut extra: Vec<(&str, &str)> = vec![];
+
+    for token in tokens.iter().skip(6).filter(|t| !t.is_empty()) {
+        let (key, value) = match token.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            // VV: Job::from_str() reports the error
+            None => return Ok(None),
+        };
+
+        match key {
+            "array" => match value.parse() {
+                Ok(c) if c > 0 => count = Some(c),
+                _ => bail!("Invalid array size \"{}\"", value),
+            },
+            "array_stride" => match parse_time(value) {
+                Ok(c) => stride = c,
+                Err(_) => bail!("Invalid array_stride \"{}\"", value),
+            },
+            _ => match key.strip_suffix(']').and_then(|k| k.split_once('[')) {
+                Some((key, index)) => match index.trim().parse() {
+                    Ok(index) => overrides.push((index, key.trim(), value)),
+                    Err(_) => bail!("Invalid array index in \"{}\"", token),
+                },
+                None => extra.push((key, value)),
+            },
+        }
+    }
+
+    let count = match count {
+        Some(count) => count,
+        None if stride != 0.0 || !overrides.is_empty() => {
+            bail!("Only array jobs may use array_stride and overrides but found \"{}\"", line)
+        }
+        None => return Ok(None),
+    };
+
+    if let Some((index, key, _)) = overrides.iter().find(|(index, _, _)| *index >= count) {
+        bail!("Cannot override {} of job {} in an array of {} jobs", key, index, count)
+    }
+
+    let time_created: f32 = match parse_time(tokens[5]) {
+        Ok(c) => c,
+        Err(_) => bail!("Invalid time_created \"{}\"", tokens[5]),
+    };
+
+    let mut lines = Vec::with_capacity(count);
+
+    for i in 0..count {
+        let mut columns: Vec<String> = tokens[..6].iter().map(|s| s.to_string()).collect();
+        let mut extra = extra.clone();
+
+        if i > 0 {
+            columns[0] = "?".to_owned();
+            columns[5] = (time_created + i as f32 * stride).to_string();
+        }
+
+        for (_, key, value) in overrides.iter().filter(|(index, _, _)| *index == i) {
+            let column = match *key {
+                "cores" => 1,
+                "memory" => 2,
+                "duration" => 3,
+                "borrow" => 4,
+                _ => {
+                    match extra.iter_mut().find(|(k, _)| k == key) {
+                        Some(token) => token.1 = value,
+                        None => extra.push((key, value)),
+                    }
+                    continue;
+                }
+            };
+            columns[column] = value.to_string();
+        }
+
+        columns.extend(extra.iter().map(|(key, value)| format!("{}={}", key, value)));
+        lines.push(columns.join(";"));
+    }
+
+    Ok(Some(lines))
 }
 
 impl JobStreaming {
     pub fn from_path(path: &Path) -> Result<Self> {
-        let file = File::open(path);
-        if let Err(x) = file {
-            bail!("Unable to open file \"{}\" because: {:?}", path.display(), x)
-        }
+        Self::from_path_with_validation(path, Validation::Strict)
+    }
 
-        let reader = Box::new(BufReader::new(file.unwrap())) as Box<dyn BufRead>;
+    pub fn from_path_with_validation(path: &Path, validation: Validation) -> Result<Self> {
+        let reader = match compression::open(path) {
+            Ok(reader) => reader,
+            Err(x) => bail!("Unable to open file \"{}\" because: {:?}", path.display(), x),
+        };
 
-        Ok(Self::from_reader(reader))
+        Ok(Self::from_reader_with_validation(reader, validation))
     }
 
-    #[allow(dead_code)]
     pub fn from_string(content: String) -> Result<Self> {
         let reader = Box::new(Cursor::new(content));
         Ok(Self::from_reader(reader))
     }
 
     pub fn from_reader(reader: Box<dyn BufRead>) -> Self {
+        Self::from_reader_with_validation(reader, Validation::Strict)
+    }
+
+    pub fn from_reader_with_validation(reader: Box<dyn BufRead>, validation: Validation) -> Self {
         let mut me = Self {
             reader,
             jobs_done: vec![],
             next_job: None,
+            array_jobs: VecDeque::new(),
+            validation,
+            line_number: 0,
+            skipped: HashSet::new(),
+            require_sorted: false,
+            last_submitted: None,
+            error: None,
         };
         reset_job_metadata();
 
@@ -122,13 +512,37 @@ impl JobStreaming {
         me
     }
 
+    /// Streams the jobs of the standard input, see with_sorted()
+    pub fn from_stdin(validation: Validation) -> Self {
+        Self::from_reader_with_validation(Box::new(std::io::stdin().lock()), validation)
+            .with_sorted()
+    }
+
+    /// Treats jobs that are submitted before the job preceding them in the stream as invalid.
+    /// Files can be sorted before the simulation starts (see JobCollection::from_paths()) but
+    /// sorting a stream that can only be read once means buffering all of it
+    pub fn with_sorted(mut self) -> Self {
+        self.require_sorted = true;
+        self
+    }
+
     fn may_read_line(&mut self) {
+        if self.error.is_some() {
+            return;
+        }
+
+        if let Some(job) = self.array_jobs.pop_front() {
+            self.next_job = Some(job);
+            return;
+        }
+
         let mut line: String = String::new();
         loop {
             let read = self.reader.read_line(&mut line);
             match read {
                 Ok(0) => break,
                 Ok(_) => {
+                    self.line_number += 1;
                     // VV: Skip empty lines, and lines starting with a "#"
                     line = line.trim().to_owned();
 
@@ -137,7 +551,237 @@ impl JobStreaming {
                         continue;
                     }
 
-                    self.next_job = Some(line.parse().unwrap());
+                    let jobs = match parse_entry(&line) {
+                        Ok(jobs) => jobs,
+                        Err(err) => vec![Err((None, err))],
+                    };
+
+                    for job in jobs {
+                        let checked = job.and_then(|job| {
+                            match self.check_skipped(&job).and_then(|_| self.check_sorted(&job)) {
+                                Ok(()) => Ok(job),
+                                Err(err) => Err((Some(job.uid), err)),
+                            }
+                        });
+
+                        match checked {
+                            Ok(job) => {
+                                self.last_submitted = Some((job.uid, job.time_created));
+                                self.array_jobs.push_back(job)
+                            }
+                            Err((uid, err)) => self.invalid_entry(uid, &err),
+                        }
+                    }
+
+                    if self.error.is_some() {
+                        self.array_jobs.clear();
+                        return;
+                    }
+
+                    if self.array_jobs.is_empty() {
+                        line.clear();
+                        continue;
+                    }
+
+                    self.next_job = self.array_jobs.pop_front();
+                    break;
+                }
+                Err(x) => panic!("Could not read next line due to {}", x),
+            }
+        }
+    }
+
+    fn check_skipped(&self, job: &Job) -> Result<(), String> {
+        match job.depends_on.iter().find(|uid| self.skipped.contains(uid)) {
+            Some(uid) => Err(format!("Job {} depends on the skipped Job {}", job.uid, uid)),
+            None => Ok(()),
+        }
+    }
+
+    fn check_sorted(&self, job: &Job) -> Result<(), String> {
+        match self.last_submitted {
+            Some((uid, time)) if self.require_sorted && job.time_created < time => Err(format!(
+                "Job {} is submitted at {} before Job {} at {}, the jobs of a stream that can \
+                only be read once must be sorted by their submit time",
+                job.uid, job.time_created, uid, time)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Ends the stream with @err in Strict mode, in Lenient mode it logs @err and remembers the
+    /// skipped @uid
+    fn invalid_entry(&mut self, uid: Option<JobId>, err: &str) {
+        match self.validation {
+            Validation::Strict => {
+                if self.error.is_none() {
+                    self.error = Some(format!("Invalid job on line {}: {}", self.line_number, err));
+                }
+            }
+            Validation::Lenient => {
+                warn!(line = self.line_number, "Skipping invalid job on line {}: {}",
+                    self.line_number, err);
+                if let Some(uid) = uid {
+                    self.skipped.insert(uid);
+                }
+            }
+        }
+    }
+}
+
+/// A job of a JobStreaming entry or the reason it is not valid along with its UID, if known
+type EntryJob = Result<Job, (Option<JobId>, String)>;
+
+/// Parses the jobs of 1 line of a JobStreaming file, entries of array jobs define many jobs.
+/// Returns an error for lines that do not parse, and 1 error per job that is not valid
+fn parse_entry(line: &str) -> Result<Vec<EntryJob>, String> {
+    let (lines, is_array) = match expand_array(line).map_err(|err| err.to_string())? {
+        Some(lines) => (lines, true),
+        None => (vec![line.to_owned()], false),
+    };
+    let mut array_id = None;
+    let mut jobs = vec![];
+
+    for (index, line) in lines.iter().enumerate() {
+        let mut job: Job = line.parse()?;
+
+        if is_array {
+            let array_id = *array_id.get_or_insert(job.uid);
+            job.array = Some((array_id, index));
+        }
+
+        // VV: Jobs may only depend on Jobs that appear earlier in the stream, this
+        // way there can be no cyclic dependencies
+        let checked = match job.depends_on.iter().find(|uid| **uid >= job.uid) {
+            Some(uid) => Err(format!("Job {} cannot depend on Job {} which is not defined \
+                before it", job.uid, uid)),
+            None => job.validate(),
+        };
+
+        jobs.push(match checked {
+            Ok(()) => Ok(job),
+            Err(err) => Err((Some(job.uid), err)),
+        });
+    }
+
+    Ok(jobs)
+}
+
+/// Returns whether @path is "-" which stands for the standard input
+pub fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Returns the job files that @pattern refers to, it is a comma separated list of paths and glob
+/// patterns (e.g. jobs/2024-*.jobs). Every pattern must match at least 1 file, the matches of a
+/// pattern are in alphabetical order
+pub fn expand_paths(pattern: &Path) -> Result<Vec<PathBuf>> {
+    let pattern = pattern.to_string_lossy();
+    let mut paths = vec![];
+
+    for pattern in pattern.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        if !pattern.contains(['*', '?', '[']) {
+            paths.push(PathBuf::from(pattern));
+            continue;
+        }
+
+        let mut matches = glob::glob(pattern)
+            .context(format!("Invalid pattern of job files \"{}\"", pattern))?
+            .collect::<Result<Vec<_>, _>>()?;
+        if matches.is_empty() {
+            bail!("No job files match \"{}\"", pattern)
+        }
+        matches.sort();
+        paths.extend(matches);
+    }
+
+    Ok(paths)
+}
+
+/// Reads the entire JobStreaming file at @path and returns the number of jobs it defines or an
+/// error for the first line with a job that is not valid. With a @registry, jobs must also fit
+/// in the cluster (see NodeRegistry::check_fits()). This is the load-time check of
+/// Validation::Strict, JobStreaming itself only reaches invalid jobs in the middle of a simulation
+/// and then ends early (see JobFactory::check_stream())
+pub fn check_jobs(path: &Path, registry: Option<&NodeRegistry>) -> Result<usize> {
+    let reader = match compression::open(path) {
+        Ok(reader) => reader,
+        Err(x) => bail!("Unable to open file \"{}\" because: {:?}", path.display(), x),
+    };
+    let capacity = registry.map(|registry| registry.capacity_resources());
+
+    reset_job_metadata();
+    let mut count = 0;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+
+        let error = |err: &str| anyhow::anyhow!("Invalid job on line {} of {}: {}", i + 1,
+            path.display(), err);
+
+        for job in parse_entry(line).map_err(|err| error(&err))? {
+            let job = job.map_err(|(_, err)| error(&err))?;
+
+            if let (Some(registry), Some(capacity)) = (registry, &capacity) {
+                registry.check_resources(&job)
+                    .and_then(|_| registry.check_fits(&job, capacity))
+                    .map_err(|err| error(&err.to_string()))?;
+            }
+            count += 1;
+        }
+    }
+
+    reset_job_metadata();
+    Ok(count)
+}
+
+impl JobStreamingSwf {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let reader = match compression::open(path) {
+            Ok(reader) => reader,
+            Err(x) => bail!("Unable to open file \"{}\" because: {:?}", path.display(), x),
+        };
+
+        Ok(Self::from_reader(reader))
+    }
+
+    pub fn from_string(content: String) -> Result<Self> {
+        let reader = Box::new(Cursor::new(content));
+        Ok(Self::from_reader(reader))
+    }
+
+    pub fn from_reader(reader: Box<dyn BufRead>) -> Self {
+        let mut me = Self {
+            reader,
+            jobs_done: vec![],
+            next_job: None,
+        };
+        reset_job_metadata();
+
+        me.may_read_line();
+
+        me
+    }
+
+    fn may_read_line(&mut self) {
+        let mut line: String = String::new();
+        loop {
+            let read = self.reader.read_line(&mut line);
+            match read {
+                Ok(0) => break,
+                Ok(_) => {
+                    // VV: Skip empty lines, and the SWF header comments which start with a ";"
+                    line = line.trim().to_owned();
+
+                    if line.starts_with(';') || line.is_empty() {
+                        line.clear();
+                        continue;
+                    }
+
+                    self.next_job = Some(Job::from_swf(&line).unwrap());
                     break;
                 }
                 Err(x) => panic!("Could not read next line due to {}", x),
@@ -147,42 +791,114 @@ impl JobStreaming {
 }
 
 impl JobStreamingWithOutput {
-    fn make_writer(path: &Path) -> Result<Box<dyn Write>> {
-        let file = File::create(path);
-        if let Err(x) = file {
-            bail!("Unable to create file \"{}\" because: {:?}", path.display(), x)
+    /// Flushes the trace every flush_every jobs
+    fn count_written(&mut self) {
+        self.unflushed += 1;
+        if self.unflushed >= self.flush_every {
+            self.writer.flush().unwrap();
+            self.unflushed = 0;
         }
+    }
 
-        let mut writer = Box::new(BufWriter::new(file.unwrap())) as Box<dyn Write>;
-        if let Err(x) = writeln!(writer, "#uid:usize;cores:f32;memory:f32;duration:f32;\
-            can_borrow:y/n;time_created:f32;time_started:f32;time_done:f32;uid_node_cores:usize;\
-            [uid_node_memory:usize;memory_alloc:f32]+") {
+    fn make_writer(
+        path: &Path,
+        format: TraceFormat,
+        fields: Option<&[String]>,
+    ) -> Result<Box<dyn Write>> {
+        let mut writer = match compression::create(path) {
+            Ok(writer) => writer,
+            Err(x) => bail!("Unable to create file \"{}\" because: {:?}", path.display(), x),
+        };
+        if format == TraceFormat::JsonLines {
+            return Ok(writer);
+        }
+
+        let written = match fields {
+            Some(fields) => writeln!(writer, "#{}", fields.join(";")),
+            None => writeln!(writer, "#uid:usize;cores:f32;memory:f32;duration:f32;\
+                can_borrow:y/n;time_created:f32;time_started:f32;time_done:f32;\
+                uid_node_cores:usize;[uid_node_memory:usize;memory_alloc:f32]+"),
+        };
+        if let Err(x) = written {
             bail!("Unable to write header to path {} because of {:?}", path.display(), x)
         }
         Ok(writer)
     }
 
-    pub fn from_path_to_path(path: &Path, output_path: &Path) -> Result<Self> {
-        let file = File::open(path);
-        if let Err(x) = file {
-            bail!("Unable to open file \"{}\" because: {:?}", path.display(), x)
-        }
+    /// Writes 1 line with the fields of @job
+    fn write_job(&mut self, job: &Job) {
+        let line = match (&self.fields, self.format) {
+            (None, TraceFormat::Text) => job.to_string(),
+            (None, TraceFormat::JsonLines) => job.to_json().to_string(),
+            (Some(fields), TraceFormat::Text) => job.trace_fields(fields)
+                .into_iter()
+                // VV: Strings go in as they are, everything else as JSON e.g. null or [1,2]
+                .map(|value| match value {
+                    serde_json::Value::String(value) => value,
+                    value => value.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(";"),
+            (Some(fields), TraceFormat::JsonLines) => {
+                let object: serde_json::Map<String, serde_json::Value> = fields.iter()
+                    .cloned()
+                    .zip(job.trace_fields(fields))
+                    .collect();
+                serde_json::Value::Object(object).to_string()
+            }
+        };
+        writeln!(self.writer, "{}", line).unwrap();
+    }
 
-        let reader = Box::new(BufReader::new(file.unwrap())) as Box<dyn BufRead>;
+    pub fn from_path_to_path(path: &Path, output_path: &Path) -> Result<Self> {
+        let reader = match compression::open(path) {
+            Ok(reader) => reader,
+            Err(x) => bail!("Unable to open file \"{}\" because: {:?}", path.display(), x),
+        };
 
         Self::from_reader_to_path(reader, output_path)
     }
 
-    #[allow(dead_code)]
     pub fn from_string_to_path(content: String, output_path: &Path) -> Result<Self> {
         let reader = Box::new(Cursor::new(content));
         Self::from_reader_to_path(reader, output_path)
     }
 
     pub fn from_reader_to_path(reader: Box<dyn BufRead>, output_path: &Path) -> Result<Self> {
-        let inner = JobStreaming::from_reader(reader);
-        let writer = JobStreamingWithOutput::make_writer(output_path)?;
-        Ok(Self { inner, writer })
+        let inner = Box::new(JobStreaming::from_reader(reader));
+        Self::from_factory_to_path(inner, output_path, TraceFormat::Text)
+    }
+
+    /// Records the Jobs of any @inner JobFactory (e.g. JobStreamingSwf) as they complete
+    pub fn from_factory_to_path(
+        inner: Box<dyn JobFactory>,
+        output_path: &Path,
+        format: TraceFormat,
+    ) -> Result<Self> {
+        Self::from_factory_to_path_with_fields(inner, output_path, format, None)
+    }
+
+    /// Like from_factory_to_path() but the trace only contains the @fields of each job instead of
+    /// the default ones, Text traces keep the order of @fields. See check_trace_fields()
+    pub fn from_factory_to_path_with_fields(
+        inner: Box<dyn JobFactory>,
+        output_path: &Path,
+        format: TraceFormat,
+        fields: Option<Vec<String>>,
+    ) -> Result<Self> {
+        if let Some(fields) = &fields {
+            check_trace_fields(fields)?;
+        }
+
+        let writer = JobStreamingWithOutput::make_writer(output_path, format, fields.as_deref())?;
+        Ok(Self { inner, writer, format, flush_every: 1, fields, unflushed: 0 })
+    }
+
+    /// Flushes the trace once every @jobs jobs instead of after each one, larger values buffer
+    /// more of the trace in memory in exchange for fewer writes
+    pub fn with_flush_every(mut self, jobs: usize) -> Self {
+        self.flush_every = jobs.max(1);
+        self
     }
 }
 
@@ -196,7 +912,40 @@ impl JobFactory for JobStreaming {
 
     /// Consumes job and also reads the next available job definition from the stream
     fn job_get(&mut self) -> Job {
-        let cur_job = std::mem::replace(&mut self.next_job, None).unwrap();
+        let cur_job = self.next_job.take().unwrap();
+        self.may_read_line();
+
+        cur_job
+    }
+
+    fn job_mark_done(&mut self, job: &Job) {
+        self.jobs_done.push(job.uid)
+    }
+
+    fn more_jobs(&self) -> bool {
+        self.next_job.is_some()
+    }
+
+    fn jobs_done(&self) -> &Vec<usize> {
+        &self.jobs_done
+    }
+
+    fn check_stream(&self) -> Result<()> {
+        match &self.error {
+            Some(err) => bail!("{}", err),
+            None => Ok(()),
+        }
+    }
+}
+
+impl JobFactory for JobStreamingSwf {
+    fn job_peek(&self) -> Option<&Job> {
+        self.next_job.as_ref()
+    }
+
+    /// Consumes job and also reads the next available job definition from the stream
+    fn job_get(&mut self) -> Job {
+        let cur_job = self.next_job.take().unwrap();
         self.may_read_line();
 
         cur_job
@@ -215,6 +964,37 @@ impl JobFactory for JobStreaming {
     }
 }
 
+impl JobFactory for PoissonArrivals {
+    fn job_peek(&self) -> Option<&Job> {
+        self.next_job.as_ref()
+    }
+
+    fn job_get(&mut self) -> Job {
+        let job = self.next_job.take().unwrap();
+        self.advance();
+        job
+    }
+
+    fn job_mark_done(&mut self, job: &Job) {
+        self.inner.job_mark_done(job);
+    }
+
+    fn job_mark_failed(&mut self, job: &Job) {
+        self.inner.job_mark_failed(job);
+    }
+
+    fn more_jobs(&self) -> bool {
+        self.next_job.is_some()
+    }
+
+    fn jobs_done(&self) -> &Vec<usize> {
+        self.inner.jobs_done()
+    }
+
+    fn check_stream(&self) -> Result<()> {
+        self.inner.check_stream()
+    }
+}
 
 impl JobFactory for JobStreamingWithOutput {
     fn job_peek(&self) -> Option<&Job> {
@@ -226,9 +1006,19 @@ impl JobFactory for JobStreamingWithOutput {
     }
 
     fn job_mark_done(&mut self, job: &Job) {
-        self.inner.jobs_done.push(job.uid);
-        writeln!(self.writer, "{}", job).unwrap();
-        self.writer.flush().unwrap();
+        self.inner.job_mark_done(job);
+        self.write_job(job);
+        self.count_written();
+    }
+
+    /// Only JSON lines traces contain the jobs that failed, the text format has no column for
+    /// the status of a job
+    fn job_mark_failed(&mut self, job: &Job) {
+        self.inner.job_mark_failed(job);
+        if self.format == TraceFormat::JsonLines {
+            self.write_job(job);
+            self.count_written();
+        }
     }
 
     fn more_jobs(&self) -> bool {
@@ -236,6 +1026,10 @@ impl JobFactory for JobStreamingWithOutput {
     }
 
     fn jobs_done(&self) -> &Vec<usize> {
-        &self.inner.jobs_done
+        self.inner.jobs_done()
+    }
+
+    fn check_stream(&self) -> Result<()> {
+        self.inner.check_stream()
     }
 }
\ No newline at end of file
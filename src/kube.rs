@@ -0,0 +1,316 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+
+//! Reads nodes and jobs from simplified Kubernetes manifests. Every YAML document is either a
+//! Node, a Pod, or a List whose items are Nodes or Pods. Cores are CPUs and 1 unit of memory is
+//! 1 GiB, e.g. a Pod that requests "500m" cpu and "512Mi" memory becomes a Job with 0.5 cores
+//! and 0.5 memory
+
+use std::collections::BTreeMap;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use serde::Deserialize;
+use serde_yaml::Value;
+
+use crate::job::Job;
+
+/// The annotations of a Pod that carry what Kubernetes does not describe
+pub const ANNOTATION_DURATION: &str = "dismem/duration";
+pub const ANNOTATION_CREATED: &str = "dismem/created";
+pub const ANNOTATION_BORROW: &str = "dismem/borrow";
+
+/// The cores, memory, extended resources (e.g. "nvidia.com/gpu"), and labels of a Node manifest
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeSpec {
+    pub name: String,
+    pub cores: f32,
+    pub memory: f32,
+    pub resources: BTreeMap<String, f32>,
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Metadata {
+    name: String,
+    namespace: Option<String>,
+    annotations: BTreeMap<String, String>,
+    labels: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct NodeStatus {
+    capacity: BTreeMap<String, Value>,
+    allocatable: BTreeMap<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct NodeManifest {
+    #[serde(default)]
+    metadata: Metadata,
+    #[serde(default)]
+    status: NodeStatus,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Resources {
+    requests: BTreeMap<String, Value>,
+    limits: BTreeMap<String, Value>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Container {
+    resources: Resources,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct PodSpec {
+    containers: Vec<Container>,
+    node_selector: BTreeMap<String, String>,
+    priority: Option<i32>,
+    active_deadline_seconds: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct PodManifest {
+    #[serde(default)]
+    metadata: Metadata,
+    #[serde(default)]
+    spec: PodSpec,
+}
+
+/// A Pod along with the time it gets created, which decides the order of the Jobs
+struct PodJob {
+    name: String,
+    created: f32,
+    manifest: PodManifest,
+}
+
+/// Returns the text of a quantity, YAML may have already turned quantities like "2" into numbers
+fn quantity_text(value: &Value) -> Result<String> {
+    match value {
+        Value::String(text) => Ok(text.trim().to_owned()),
+        Value::Number(number) => Ok(number.to_string()),
+        _ => bail!("Expected a quantity but found {:?}", value),
+    }
+}
+
+fn parse_number(text: &str, quantity: &str) -> Result<f64> {
+    match text.parse::<f64>() {
+        Ok(number) if number >= 0.0 && number.is_finite() => Ok(number),
+        _ => bail!("Unable to parse quantity \"{}\"", quantity),
+    }
+}
+
+/// Parses a cpu quantity like "2", "1.5", or "500m" into cores
+pub fn parse_cpu(quantity: &str) -> Result<f32> {
+    let quantity = quantity.trim();
+    let cores = match quantity.strip_suffix('m') {
+        Some(millicores) => parse_number(millicores, quantity)? / 1000.0,
+        None => parse_number(quantity, quantity)?,
+    };
+    Ok(cores as f32)
+}
+
+/// Parses a memory quantity like "512Mi", "2Gi", "1G", or "1048576" (bytes) into GiB
+pub fn parse_memory(quantity: &str) -> Result<f32> {
+    let quantity = quantity.trim();
+    let units: [(&str, f64); 12] = [
+        ("Ki", 1024f64), ("Mi", 1024f64.powi(2)), ("Gi", 1024f64.powi(3)),
+        ("Ti", 1024f64.powi(4)), ("Pi", 1024f64.powi(5)), ("Ei", 1024f64.powi(6)),
+        ("k", 1e3), ("M", 1e6), ("G", 1e9), ("T", 1e12), ("P", 1e15), ("E", 1e18),
+    ];
+
+    // VV: The binary suffixes come first so that "Mi" does not match as "M"
+    let (number, bytes) = units
+        .iter()
+        .find_map(|(suffix, bytes)| quantity.strip_suffix(suffix).map(|n| (n, *bytes)))
+        .unwrap_or((quantity, 1.0));
+
+    Ok((parse_number(number, quantity)? * bytes / 1024f64.powi(3)) as f32)
+}
+
+/// Parses the count of an extended resource like "nvidia.com/gpu"
+fn parse_count(quantity: &str) -> Result<f32> {
+    Ok(parse_number(quantity.trim(), quantity)? as f32)
+}
+
+/// Kubernetes only lets domain-prefixed names (e.g. nvidia.com/gpu) be extended resources
+fn is_extended_resource(name: &str) -> bool {
+    name.contains('/')
+}
+
+/// Returns the manifests in every document of @content, Lists contribute their items
+fn manifests(content: &str) -> Result<Vec<Value>> {
+    let mut manifests = vec![];
+
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let value = Value::deserialize(document).context("Invalid YAML document")?;
+        if value.is_null() {
+            continue;
+        }
+
+        match value.get("items") {
+            Some(Value::Sequence(items)) => manifests.extend(items.iter().cloned()),
+            Some(_) => bail!("The items of a List must be a sequence"),
+            None => manifests.push(value),
+        }
+    }
+
+    Ok(manifests)
+}
+
+fn kind(manifest: &Value) -> &str {
+    manifest.get("kind").and_then(Value::as_str).unwrap_or("")
+}
+
+/// Parses the Node manifests in @content
+pub fn nodes_from_str(content: &str) -> Result<Vec<NodeSpec>> {
+    let mut nodes = vec![];
+
+    for manifest in manifests(content)? {
+        if kind(&manifest) != "Node" {
+            bail!("Expected a Node manifest but found kind \"{}\"", kind(&manifest))
+        }
+
+        let node: NodeManifest = serde_yaml::from_value(manifest)?;
+        let name = node.metadata.name;
+        if name.is_empty() {
+            bail!("Node manifest without a metadata.name")
+        }
+
+        // VV: Pods may only use the allocatable part of the capacity
+        let mut resources = node.status.capacity;
+        resources.extend(node.status.allocatable);
+
+        let mut spec = NodeSpec {
+            name,
+            cores: 0.0,
+            memory: 0.0,
+            resources: BTreeMap::new(),
+            labels: node.metadata.labels,
+        };
+        for (resource, value) in &resources {
+            let quantity = quantity_text(value)?;
+            let parsed = match resource.as_str() {
+                "cpu" => parse_cpu(&quantity).map(|cores| spec.cores = cores),
+                "memory" => parse_memory(&quantity).map(|memory| spec.memory = memory),
+                name if is_extended_resource(name) => parse_count(&quantity)
+                    .map(|count| _ = spec.resources.insert(name.to_owned(), count)),
+                _ => Ok(()),
+            };
+            parsed.context(format!("Invalid {} of Node {}", resource, spec.name))?;
+        }
+
+        nodes.push(spec);
+    }
+
+    Ok(nodes)
+}
+
+impl PodJob {
+    fn new(manifest: PodManifest) -> Result<Self> {
+        let name = manifest.metadata.name.clone();
+
+        let created = match manifest.metadata.annotations.get(ANNOTATION_CREATED) {
+            Some(created) => parse_number(created, created)
+                .context(format!("Invalid {} of Pod {}", ANNOTATION_CREATED, name))? as f32,
+            None => 0.0,
+        };
+
+        Ok(Self { name, created, manifest })
+    }
+
+    /// The requests of all containers, a container that only sets a limit requests the limit
+    fn requests(&self) -> Result<(f32, f32, BTreeMap<String, f32>)> {
+        let (mut cores, mut memory, mut resources) = (0.0, 0.0, BTreeMap::new());
+
+        for container in &self.manifest.spec.containers {
+            let mut requested = container.resources.limits.clone();
+            requested.extend(container.resources.requests.clone());
+
+            for (resource, value) in &requested {
+                let quantity = quantity_text(value)?;
+                let parsed = match resource.as_str() {
+                    "cpu" => parse_cpu(&quantity).map(|c| cores += c),
+                    "memory" => parse_memory(&quantity).map(|m| memory += m),
+                    name if is_extended_resource(name) => parse_count(&quantity)
+                        .map(|count| *resources.entry(name.to_owned()).or_insert(0.0) += count),
+                    _ => Ok(()),
+                };
+                parsed.context(format!("Invalid {} of Pod {}", resource, self.name))?;
+            }
+        }
+
+        Ok((cores, memory, resources))
+    }
+
+    fn into_job(self) -> Result<Job> {
+        let annotations = &self.manifest.metadata.annotations;
+
+        let duration = match annotations.get(ANNOTATION_DURATION) {
+            Some(duration) => parse_number(duration, duration)
+                .context(format!("Invalid {} of Pod {}", ANNOTATION_DURATION, self.name))?,
+            None => bail!("Pod {} has no {} annotation", self.name, ANNOTATION_DURATION),
+        };
+
+        let can_borrow = match annotations.get(ANNOTATION_BORROW).map(|x| x.as_str()) {
+            Some("true") | Some("y") => true,
+            Some("false") | Some("n") | None => false,
+            Some(other) => bail!("Invalid {} \"{}\" of Pod {}", ANNOTATION_BORROW, other,
+                self.name),
+        };
+
+        let (cores, memory, resources) = self.requests()?;
+        let mut job = Job::new(cores, memory, duration as f32, can_borrow, self.created);
+        job.resources = resources;
+        job.selector = self.manifest.spec.node_selector;
+        job.user = self.manifest.metadata.namespace;
+        job.priority = self.manifest.spec.priority.unwrap_or(0);
+        job.walltime_limit = self.manifest.spec.active_deadline_seconds;
+
+        if let Err(err) = job.validate() {
+            bail!("Pod {}: {}", self.name, err)
+        }
+
+        Ok(job)
+    }
+}
+
+/// Parses the Pod manifests in @content, the Jobs are in the order that the Pods get created
+pub fn jobs_from_str(content: &str) -> Result<Vec<Job>> {
+    let mut pods = vec![];
+
+    for manifest in manifests(content)? {
+        if kind(&manifest) != "Pod" {
+            bail!("Expected a Pod manifest but found kind \"{}\"", kind(&manifest))
+        }
+        pods.push(PodJob::new(serde_yaml::from_value(manifest)?)?);
+    }
+
+    pods.sort_by(|a, b| a.created.partial_cmp(&b.created).unwrap());
+    pods.into_iter().map(PodJob::into_job).collect()
+}
@@ -17,9 +17,45 @@ specific language governing permissions and limitations
 under the License.
 */
 
+//! Simulator for scheduling Jobs on a cluster with hardware-composability features such as
+//! memory borrowing. The `dismem` binary is a thin front-end over this library.
+
+pub mod backfill;
+pub mod best_fit;
+pub mod clock;
+pub mod compare;
+pub mod compression;
+pub mod drf;
+pub mod duration;
+pub mod failures;
+pub mod fairshare;
+pub mod gantt;
+pub mod golden;
 pub mod job;
 pub mod job_factory;
+pub mod kube;
+pub mod metrics;
 pub mod node;
+pub mod objective;
+pub mod profile;
 pub mod registry;
 pub mod resource;
+pub mod runtime;
 pub mod scheduler;
+pub mod snapshot;
+pub mod summary;
+pub mod sweep;
+pub mod topology;
+
+pub use job::Job;
+pub use job::JobId;
+pub use job_factory::JobFactory;
+pub use node::Node;
+pub use node::NodeId;
+pub use registry::NodeRegistry;
+pub use resource::NamedResources;
+pub use resource::Resource;
+pub use scheduler::Scheduler;
+pub use scheduler::SchedulerBuilder;
+pub use scheduler::SchedulingPolicy;
+pub use scheduler::SimRng;
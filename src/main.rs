@@ -17,69 +17,1295 @@ specific language governing permissions and limitations
 under the License.
 */
 
-use std::env::args;
+use std::collections::HashMap;
+use std::io::Write;
 use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::time::SystemTime;
 
 use anyhow::bail;
+use anyhow::Context;
 use anyhow::Result;
+use clap::App;
+use clap::Arg;
+use serde::Deserialize;
+use tracing::info;
+use tracing::warn;
+use tracing::Level;
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::EnvFilter;
 
-mod job;
-mod job_factory;
+use dismem::best_fit;
+use dismem::clock::Cadence;
+use dismem::clock::SimClock;
+use dismem::clock::WallClock;
+use dismem::compression;
+use dismem::drf;
+use dismem::failures;
+use dismem::gantt::GanttChart;
+use dismem::golden;
+use dismem::job_factory;
+use dismem::objective::OBJECTIVES;
+use dismem::objective::ObjectivePolicy;
+use dismem::objective::objective_policy;
+use dismem::registry;
+use dismem::runtime::RuntimeModel;
+use dismem::scheduler;
+use dismem::snapshot;
+use dismem::snapshot::SnapshotWriter;
+use dismem::summary::ThroughputSample;
+use dismem::summary::WaitHistogram;
+use dismem::sweep;
+use dismem::topology;
 
-mod node;
-mod registry;
-mod resource;
+struct Arguments {
+    json_logs: bool,
+    path_nodes: PathBuf,
+    // VV: None when the nodes file is a .toml topology which also holds the connections
+    path_connections: Option<PathBuf>,
+    path_jobs: PathBuf,
+    path_sweep: Option<PathBuf>,
+    validation: job_factory::Validation,
+    path_output_trace: Option<PathBuf>,
+    flush_every: usize,
+    trace_fields: Option<Vec<String>>,
+    report_utilization: bool,
+    report_occupancy: bool,
+    backfill: bool,
+    seed: Option<u64>,
+    path_failures: Option<PathBuf>,
+    path_maintenance: Option<PathBuf>,
+    path_reservations: Option<PathBuf>,
+    path_holds: Option<PathBuf>,
+    path_partitions: Option<PathBuf>,
+    path_limits: Option<PathBuf>,
+    path_scaling: Option<PathBuf>,
+    fair_share: Option<f32>,
+    user_weights: HashMap<String, f64>,
+    path_summary: Option<PathBuf>,
+    path_summary_json: Option<PathBuf>,
+    drf: bool,
+    topology: bool,
+    best_fit: Option<String>,
+    tick_granularity: Option<f32>,
+    schedule_interval: Option<f32>,
+    decision_latency: Option<f32>,
+    slowdown_threshold: Option<f32>,
+    warm_cache: Option<(f32, f32)>,
+    speculation: Option<f32>,
+    max_queue_depth: Option<usize>,
+    arrival_rate: Option<f32>,
+    cancel_late: bool,
+    cascade: scheduler::CascadePolicy,
+    fragmentation: bool,
+    profile: bool,
+    runtime_model: Option<RuntimeModel>,
+    path_gantt: Option<PathBuf>,
+    gantt: GanttChart,
+    path_throughput: Option<PathBuf>,
+    path_snapshots: Option<PathBuf>,
+    snapshot_every: f32,
+    stats_window: Option<(f32, f32)>,
+    max_jobs: Option<usize>,
+    until: Option<f32>,
+    path_checkpoint: Option<PathBuf>,
+    checkpoint_every: f32,
+    report_every: Option<f32>,
+    // VV: When set, main() prints the state after every tick() and waits for the user
+    step: bool,
+    wait_histogram: Option<Vec<f32>>,
+    path_restore: Option<PathBuf>,
+    // VV: The output trace of an earlier run that this run must repeat, see golden::verify_trace()
+    path_verify_trace: Option<PathBuf>,
+    #[cfg(feature = "metrics")]
+    metrics_address: Option<String>,
+}
 
-mod scheduler;
+/// The contents of a --config TOML file, each key matches the command-line option with the
+/// same name. Relative paths are relative to the directory containing the config file
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct RunConfig {
+    /// Either text (default) or json
+    log_format: Option<String>,
+    nodes: Option<PathBuf>,
+    /// Leave out when nodes is a .toml topology file
+    connections: Option<PathBuf>,
+    jobs: Option<PathBuf>,
+    output: Option<PathBuf>,
+    /// Flush the output trace once every this many finished jobs, defaults to 1
+    flush_every: Option<usize>,
+    /// The fields of each job in the output trace, e.g. ["id", "node", "user", "slowdown"]
+    trace_fields: Option<Vec<String>>,
+    /// Either strict or lenient (default)
+    validation: Option<String>,
+    /// One of first_fit (default), backfill, fair_share, drf, topology, spread, pack
+    policy: Option<String>,
+    /// The time window of the fair_share policy
+    fair_share_window: Option<f32>,
+    /// One of makespan, utilization, fairness, wait-time, picks the policy instead of policy
+    objective: Option<String>,
+    /// The share of each user under fair_share, e.g. { alice = 2.0, bob = 1.0 }. Users without
+    /// a weight have weight 1
+    user_weights: Option<HashMap<String, f64>>,
+    seed: Option<u64>,
+    failures: Option<PathBuf>,
+    maintenance: Option<PathBuf>,
+    reservations: Option<PathBuf>,
+    holds: Option<PathBuf>,
+    /// Limits of partitions, each line is <partition>;<max time>;<max cores>
+    partitions: Option<PathBuf>,
+    /// Caps on the cores that the running jobs of users and partitions use, each line is
+    /// <user or partition>;<name>;<max cores>
+    limits: Option<PathBuf>,
+    scaling: Option<PathBuf>,
+    summary: Option<PathBuf>,
+    /// Path to a JSON file with the summary of the run, written even if the run fails
+    summary_json: Option<PathBuf>,
+    tick_granularity: Option<f32>,
+    /// Jobs only start at multiples of this much simulated time
+    schedule_interval: Option<f32>,
+    /// The simulated time that starting a job keeps the scheduler busy for
+    decision_latency: Option<f32>,
+    /// The runtime threshold of the bounded slowdown, defaults to 10
+    slowdown_threshold: Option<f32>,
+    /// How long a node stays warm for the affinity group of a job that finished on it
+    warm_decay: Option<f32>,
+    /// How many times faster jobs run on nodes that are warm for them, defaults to 1
+    warm_speedup: Option<f32>,
+    /// Jobs of arrays that run this many times longer than the median of their finished peers
+    /// get a speculative copy
+    speculation: Option<f32>,
+    /// Reject the jobs that arrive while this many jobs are queuing
+    max_queue_depth: Option<usize>,
+    /// Jobs arrive at this Poisson rate per second instead of at their submit times
+    arrival_rate: Option<f32>,
+    /// Reject the jobs that would finish after their deadline instead of starting them
+    cancel_late: bool,
+    /// Either hold (default) or fail-cascade, what happens to the dependents of failed jobs
+    cascade: Option<String>,
+    utilization: bool,
+    /// Print what each node runs and how much of it is free at the end of the simulation
+    occupancy: bool,
+    fragmentation: bool,
+    /// Count the placement attempts, feasibility checks, and placements of each tick
+    profile: bool,
+    /// Perturbs the declared duration of jobs, there is no command-line option for this
+    runtime: Option<RuntimeModel>,
+    gantt: Option<PathBuf>,
+    gantt_max_jobs: Option<usize>,
+    /// The start and end of the timeline in the Gantt chart, e.g. [0.0, 3600.0]
+    gantt_window: Option<(f32, f32)>,
+    /// CSV file with the cumulative number of finished jobs after every completion
+    throughput: Option<PathBuf>,
+    /// Newline-delimited JSON file with the occupancy of every node over time
+    snapshots: Option<PathBuf>,
+    /// Simulated time between 2 snapshots, defaults to 60
+    snapshot_every: Option<f32>,
+    /// The part of the simulation that the summary covers, e.g. [3600.0, 86400.0]
+    stats_window: Option<(f32, f32)>,
+    /// Stop the simulation once this many jobs are done
+    max_jobs: Option<usize>,
+    /// Stop the simulation at this simulated time
+    until: Option<f32>,
+    /// Simulated time between 2 progress reports, defaults to every 5 wall-clock seconds
+    report_every: Option<f32>,
+    /// The upper bounds of the buckets of the histogram of waits, e.g. [60.0, 600.0, 3600.0]
+    wait_histogram: Option<Vec<f32>>,
+}
 
-fn main() -> Result<()> {
-    let arguments: Vec<_> = args().collect();
+impl RunConfig {
+    fn from_path(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Unable to read config file {}", path.display()))?;
+        let mut config: RunConfig = toml::from_str(&content)
+            .context(format!("Invalid config file {}", path.display()))?;
+
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        for path in [&mut config.nodes, &mut config.connections, &mut config.jobs,
+            &mut config.output, &mut config.failures, &mut config.maintenance, &mut config.summary,
+            &mut config.scaling, &mut config.gantt, &mut config.reservations,
+            &mut config.holds, &mut config.partitions, &mut config.snapshots,
+            &mut config.throughput, &mut config.limits, &mut config.summary_json]
+            .into_iter().flatten() {
+            if path.is_relative() && !job_factory::is_stdin(path) {
+                *path = base.join(&path);
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn parse_arguments() -> Result<Arguments> {
+    let app = App::new("dismem")
+        .version("0.1.0")
+        .author("Vassilis Vassiliadis")
+        .about("Simulates the scheduling of jobs on a cluster with memory borrowing")
+        .arg(Arg::new("nodes")
+            .required_unless_present("config")
+            .index(1)
+            .help("Path to node definition, use .yaml for Kubernetes Node manifests and .toml for \
+                a topology file that also lists the connections"))
+        .arg(Arg::new("connections")
+            .required_unless_present("config")
+            .index(2)
+            .help("Path to node connection definition, - when the nodes are in a .toml topology \
+                file"))
+        .arg(Arg::new("jobs")
+            .required_unless_present_any(["config", "sweep"])
+            .index(3)
+            .help("Path to job definition, use .swf for traces in the Standard Workload Format \
+                and .yaml for Kubernetes Pod manifests. A comma separated list of paths and glob \
+                patterns of job files merges them into 1 stream ordered by submit time, - reads \
+                jobs sorted by submit time from the standard input"))
+        .arg(Arg::new("output")
+            .index(4)
+            .help("Path to output file for output trace, use .jsonl for JSON lines"))
+        .arg(Arg::new("logFormat")
+            .long("logFormat")
+            .takes_value(true)
+            .possible_values(["text", "json"])
+            .help("Log plain text (default) or 1 JSON object per line, RUST_LOG picks what \
+                gets logged e.g. RUST_LOG=debug also logs every job that is submitted, placed, \
+                completed, or failed"))
+        .arg(Arg::new("flushEvery")
+            .long("flushEvery")
+            .takes_value(true)
+            .help("Flush the output trace once every this many finished jobs instead of after \
+                each one"))
+        .arg(Arg::new("traceFields")
+            .long("traceFields")
+            .takes_value(true)
+            .help("Comma separated fields of each job in the output trace instead of the default \
+                ones e.g. id,start,finish,node,user,partition,slowdown"))
+        .arg(Arg::new("sweep")
+            .long("sweep")
+            .takes_value(true)
+            .help("Simulate every job file in this directory in parallel instead of the jobs \
+                argument and print a table of their summaries, --summary also appends it to a CSV"))
+        .arg(Arg::new("config")
+            .short('c')
+            .long("config")
+            .takes_value(true)
+            .help("Path to a TOML file with the configuration of the run, command-line \
+                arguments override the values in the file"))
+        .arg(Arg::new("utilization")
+            .short('u')
+            .long("utilization")
+            .help("Print the least and most utilized nodes at the end of the simulation"))
+        .arg(Arg::new("occupancy")
+            .long("occupancy")
+            .help("Print the running jobs and the used and free resources of every node at the \
+                end of the simulation, e.g. the state of the cluster at --until"))
+        .arg(Arg::new("validation")
+            .long("validation")
+            .takes_value(true)
+            .help("Either strict or lenient (default). Strict aborts before the simulation starts \
+                if any job is malformed, physically impossible (e.g. 0 cores, negative duration), \
+                or too large for the cluster. Lenient skips malformed and impossible jobs with a \
+                warning and rejects the ones that are too large when they arrive"))
+        .arg(Arg::new("fragmentation")
+            .long("fragmentation")
+            .help("Track how many queued jobs cannot start only because the free resources \
+                are spread over many nodes and print a summary at the end of the simulation"))
+        .arg(Arg::new("profile")
+            .long("profile")
+            .help("Count the placement attempts, feasibility checks, and placements of the \
+                scheduler in each tick and print the totals and the busiest tick at the end of \
+                the simulation"))
+        .arg(Arg::new("backfill")
+            .short('b')
+            .long("backfill")
+            .help("Use EASY backfilling instead of the default FirstFit policy"))
+        .arg(Arg::new("seed")
+            .short('s')
+            .long("seed")
+            .takes_value(true)
+            .help("Seed for the random number generator, defaults to a random seed. \
+                Runs with the same seed and inputs produce identical output traces"))
+        .arg(Arg::new("failures")
+            .short('f')
+            .long("failures")
+            .takes_value(true)
+            .help("Path to node failure schedule, each line is \
+                <node name>;<time fail>[;<time recover>]"))
+        .arg(Arg::new("maintenance")
+            .long("maintenance")
+            .takes_value(true)
+            .help("Path to node maintenance schedule, each line is \
+                <node name>;<time drain>;<time start>;<time end>. Draining nodes accept no new \
+                jobs but let their running jobs finish, they return after <time end>"))
+        .arg(Arg::new("reservations")
+            .long("reservations")
+            .takes_value(true)
+            .help("Path to advance reservations, each line is <node name>;<time start>;\
+                <time end>;<cores>;<memory>;<user>. Only the jobs of <user> may use the reserved \
+                cores and memory of the node between <time start> and <time end>"))
+        .arg(Arg::new("holds")
+            .long("holds")
+            .takes_value(true)
+            .help("Path to the timeline of jobs that operators hold and release, each line is \
+                <time>;hold|release;<job uid>. Held jobs do not start until they are released"))
+        .arg(Arg::new("partitions")
+            .long("partitions")
+            .takes_value(true)
+            .help("Path to the limits of the partitions that jobs target with partition=<name>, \
+                each line is <partition>;<max time>;<max cores> and - means no limit. Jobs that \
+                break the limits are rejected, jobs without a walltime_limit get <max time>"))
+        .arg(Arg::new("limits")
+            .long("limits")
+            .takes_value(true)
+            .help("Path to caps on the cores that the running jobs of a user or a partition use \
+                at the same time, each line is <user or partition>;<name>;<max cores>. Jobs wait \
+                while they would take their user or partition past its cap"))
+        .arg(Arg::new("scaling")
+            .long("scaling")
+            .takes_value(true)
+            .help("Path to the autoscaling timeline, each line is either <time>;add;<node \
+                definition as in the nodes file>, <time>;add;<name of removed node>, or \
+                <time>;remove;<node name>[;drain|kill]. Drained nodes leave once their running \
+                jobs finish, killed jobs go back to the queue"))
+        .arg(Arg::new("fairShare")
+            .long("fairShare")
+            .takes_value(true)
+            .help("Use the fair-share policy, users who consumed fewer core-seconds go first. \
+                The value is the time window (in simulated seconds) over which usage decays"))
+        .arg(Arg::new("userWeights")
+            .long("userWeights")
+            .takes_value(true)
+            .help("Comma separated <user>=<weight> pairs, fair-share gives each user a share of \
+                the cluster that is proportional to its weight. Users without a weight have \
+                weight 1"))
+        .arg(Arg::new("summary")
+            .long("summary")
+            .takes_value(true)
+            .help("Path to a CSV file to append a summary of the run to"))
+        .arg(Arg::new("summaryJson")
+            .long("summaryJson")
+            .alias("summary-json")
+            .takes_value(true)
+            .help("Path to a JSON file to write the summary of the run to, including the jobs \
+                that were rejected or still waiting. It is written even when jobs are \
+                unschedulable"))
+        .arg(Arg::new("drf")
+            .long("drf")
+            .help("Use the Dominant Resource Fairness policy"))
+        .arg(Arg::new("topology")
+            .long("topology")
+            .help("Place the pieces of gang jobs on nodes that are close in the connection graph"))
+        .arg(Arg::new("bestFit")
+            .long("bestFit")
+            .takes_value(true)
+            .possible_values(["spread", "pack"])
+            .help("Place every job on the node with the most (spread) or the least (pack) free \
+                memory among the nodes that can host it"))
+        .arg(Arg::new("objective")
+            .long("objective")
+            .takes_value(true)
+            .possible_values(OBJECTIVES)
+            .help("Pick the built-in policy that suits the goal instead of naming a policy: \
+                makespan uses backfill, utilization --bestFit pack, fairness fair-share with a \
+                window of 1 day, and wait-time first_fit"))
+        .arg(Arg::new("tickGranularity")
+            .long("tickGranularity")
+            .takes_value(true)
+            .help("Handle events in steps of this much simulated time (same unit as the job \
+                definitions) instead of at their exact time"))
+        .arg(Arg::new("scheduleInterval")
+            .long("scheduleInterval")
+            .takes_value(true)
+            .help("Only start jobs once every this much simulated time (a scheduling cycle), \
+                jobs still arrive and finish in between"))
+        .arg(Arg::new("decisionLatency")
+            .long("decisionLatency")
+            .takes_value(true)
+            .help("The simulated time that starting a job takes, the scheduler starts at most 1 \
+                job every this much time"))
+        .arg(Arg::new("maxJobs")
+            .long("maxJobs")
+            .alias("max-jobs")
+            .takes_value(true)
+            .help("Stop the simulation once this many jobs are done, the summary then only \
+                covers these jobs and says that the run was truncated"))
+        .arg(Arg::new("until")
+            .long("until")
+            .takes_value(true)
+            .help("Stop the simulation at this simulated time and list the jobs that are still \
+                running along with how long they need to finish"))
+        .arg(Arg::new("statsWindow")
+            .long("statsWindow")
+            .takes_value(true)
+            .help("Only summarize the jobs that ran during <start>:<end> to leave out the \
+                warm-up and drain phases of the simulation"))
+        .arg(Arg::new("checkpoint")
+            .long("checkpoint")
+            .takes_value(true)
+            .help("Path to periodically save the state of the simulation to, see --restore"))
+        .arg(Arg::new("checkpointEvery")
+            .long("checkpointEvery")
+            .takes_value(true)
+            .help("Wall-clock seconds between 2 checkpoints, defaults to 600"))
+        .arg(Arg::new("reportEvery")
+            .long("reportEvery")
+            .takes_value(true)
+            .help("Report the progress of the run every this much simulated time instead of \
+                every 5 wall-clock seconds"))
+        .arg(Arg::new("step")
+            .long("step")
+            .help("Print the nodes and queues after every scheduling step and wait for enter, \
+                type c to run the rest without stopping or q to stop the simulation"))
+        .arg(Arg::new("slowdownThreshold")
+            .long("slowdownThreshold")
+            .takes_value(true)
+            .help("Jobs that run for less than this are treated as if they ran for this long \
+                when computing their bounded slowdown, defaults to 10"))
+        .arg(Arg::new("warmDecay")
+            .long("warmDecay")
+            .takes_value(true)
+            .help("Nodes stay warm for this long for the affinity group of each job that finishes \
+                on them, --bestFit then prefers warm nodes for the jobs of the group"))
+        .arg(Arg::new("warmSpeedup")
+            .long("warmSpeedup")
+            .takes_value(true)
+            .requires("warmDecay")
+            .help("Jobs that start on nodes which are warm for their affinity group run this many \
+                times faster, defaults to 1"))
+        .arg(Arg::new("speculation")
+            .long("speculation")
+            .takes_value(true)
+            .help("Jobs of an array that run for longer than this many times the median runtime \
+                of the finished jobs of their array get a speculative copy on another node, the \
+                attempt that finishes first wins"))
+        .arg(Arg::new("maxQueueDepth")
+            .long("maxQueueDepth")
+            .takes_value(true)
+            .help("Reject the jobs that arrive while this many jobs are queuing"))
+        .arg(Arg::new("arrivalRate")
+            .long("arrivalRate")
+            .takes_value(true)
+            .help("Ignore the submit times of the jobs, they arrive in the same order at this \
+                Poisson rate of jobs per second drawn with the seed of the run"))
+        .arg(Arg::new("cancelLate")
+            .long("cancelLate")
+            .help("Reject the jobs whose estimated finish is past their deadline when they are \
+                about to start instead of running them"))
+        .arg(Arg::new("cascade")
+            .long("cascade")
+            .takes_value(true)
+            .possible_values(["hold", "fail-cascade"])
+            .help("What happens to the jobs that depend on a job which fails: they stay blocked \
+                waiting for a successful retry (hold, default) or fail too (fail-cascade)"))
+        .arg(Arg::new("waitHistogram")
+            .long("waitHistogram")
+            .takes_value(true)
+            .help("Print a histogram of how long jobs waited before they started, the value is \
+                the comma separated upper bounds of the buckets e.g. 60,600,3600"))
+        .arg(Arg::new("verifyTrace")
+            .long("verifyTrace")
+            .takes_value(true)
+            .help("Path to the output trace of an earlier run with the same inputs, fail with \
+                the first job whose placement differs in the output trace of this run"))
+        .arg(Arg::new("restore")
+            .long("restore")
+            .takes_value(true)
+            .help("Resume the simulation from a checkpoint, the other arguments must describe \
+                the same run. The output trace then only contains the jobs that finish after \
+                the checkpoint"))
+        .arg(Arg::new("gantt")
+            .long("gantt")
+            .takes_value(true)
+            .help("Path to write an SVG Gantt chart of the jobs on each node to after the run"))
+        .arg(Arg::new("throughput")
+            .long("throughput")
+            .takes_value(true)
+            .help("Path to write a CSV with the cumulative number of finished jobs after every \
+                completion to after the run"))
+        .arg(Arg::new("ganttMaxJobs")
+            .long("ganttMaxJobs")
+            .takes_value(true)
+            .help("Only draw this many jobs, the ones that started first, in the Gantt chart"))
+        .arg(Arg::new("ganttWindow")
+            .long("ganttWindow")
+            .takes_value(true)
+            .help("Only draw the <start>:<end> part of the timeline in the Gantt chart"))
+        .arg(Arg::new("snapshots")
+            .long("snapshots")
+            .takes_value(true)
+            .help("Path to write the cores and memory that jobs use on each node, and the jobs \
+                that run there, to as 1 JSON frame per line, e.g. to animate the run"))
+        .arg(Arg::new("snapshotEvery")
+            .long("snapshotEvery")
+            .takes_value(true)
+            .requires("snapshots")
+            .help("Simulated time between 2 frames of --snapshots, defaults to 60"));
+
+    #[cfg(feature = "metrics")]
+    let app = app.arg(Arg::new("metrics")
+        .long("metrics")
+        .takes_value(true)
+        .help("Serve live metrics in the Prometheus text format at http://<address>/metrics, \
+            e.g. --metrics 127.0.0.1:9898"));
+
+    let args = app.get_matches();
+
+    let config = match args.value_of("config") {
+        Some(path) => RunConfig::from_path(Path::new(path))?,
+        None => RunConfig::default(),
+    };
+
+    let seed = match args.value_of("seed") {
+        Some(seed) => Some(seed.parse()
+            .context(format!("seed \"{}\" is not a valid u64", seed))?),
+        None => config.seed,
+    };
+
+    let fair_share = match args.value_of("fairShare") {
+        Some(window) => Some(window.parse()
+            .context(format!("fairShare \"{}\" is not a valid f32", window))?),
+        None => None,
+    };
+
+    let tick_granularity = match args.value_of("tickGranularity") {
+        Some(granularity) => Some(granularity.parse()
+            .context(format!("tickGranularity \"{}\" is not a valid f32", granularity))?),
+        None => config.tick_granularity,
+    };
+
+    if let Some(granularity) = tick_granularity.filter(|g: &f32| *g <= 0.0) {
+        bail!("tickGranularity {} must be positive", granularity)
+    }
+
+    let schedule_interval = match args.value_of("scheduleInterval") {
+        Some(interval) => Some(interval.parse()
+            .context(format!("scheduleInterval \"{}\" is not a valid f32", interval))?),
+        None => config.schedule_interval,
+    };
 
-    if arguments.len() < 1 + 3 || arguments.len() > 1 + 3 + 1 {
-        bail!("Expected arguments: \
-            <path to node definition> \
-            <path to node connection definition> \
-            <path to job definition> \
-            [<path to output file for output trace>]")
+    if let Some(interval) = schedule_interval.filter(|i: &f32| *i <= 0.0) {
+        bail!("scheduleInterval {} must be positive", interval)
     }
 
-    let path_nodes = Path::new(&arguments[1]);
-    let path_connections = Path::new(&arguments[2]);
-    let path_jobs = Path::new(&arguments[3]);
+    let decision_latency = match args.value_of("decisionLatency") {
+        Some(latency) => Some(latency.parse()
+            .context(format!("decisionLatency \"{}\" is not a valid f32", latency))?),
+        None => config.decision_latency,
+    };
+
+    if let Some(latency) = decision_latency.filter(|l: &f32| *l <= 0.0) {
+        bail!("decisionLatency {} must be positive", latency)
+    }
+
+    let slowdown_threshold = match args.value_of("slowdownThreshold") {
+        Some(threshold) => Some(threshold.parse()
+            .context(format!("slowdownThreshold \"{}\" is not a valid f32", threshold))?),
+        None => config.slowdown_threshold,
+    };
+
+    if let Some(threshold) = slowdown_threshold.filter(|t: &f32| *t <= 0.0) {
+        bail!("slowdownThreshold {} must be positive", threshold)
+    }
+
+    let warm_decay = match args.value_of("warmDecay") {
+        Some(decay) => Some(decay.parse()
+            .context(format!("warmDecay \"{}\" is not a valid f32", decay))?),
+        None => config.warm_decay,
+    };
+
+    let warm_speedup = match args.value_of("warmSpeedup") {
+        Some(speedup) => Some(speedup.parse()
+            .context(format!("warmSpeedup \"{}\" is not a valid f32", speedup))?),
+        None => config.warm_speedup,
+    };
+
+    let warm_cache = match (warm_decay, warm_speedup.unwrap_or(1.0)) {
+        (None, _) if warm_speedup.is_some() => bail!("warmSpeedup needs a warmDecay"),
+        (Some(decay), _) if decay <= 0.0 => bail!("warmDecay {} must be positive", decay),
+        (Some(_), speedup) if speedup <= 0.0 => bail!("warmSpeedup {} must be positive", speedup),
+        (decay, speedup) => decay.map(|decay| (decay, speedup)),
+    };
+
+    let speculation = match args.value_of("speculation") {
+        Some(threshold) => Some(threshold.parse()
+            .context(format!("speculation \"{}\" is not a valid f32", threshold))?),
+        None => config.speculation,
+    };
+
+    if let Some(threshold) = speculation.filter(|t: &f32| *t <= 0.0) {
+        bail!("speculation {} must be positive", threshold)
+    }
+
+    let gantt_max_jobs = match args.value_of("ganttMaxJobs") {
+        Some(jobs) => Some(jobs.parse()
+            .context(format!("ganttMaxJobs \"{}\" is not a valid usize", jobs))?),
+        None => config.gantt_max_jobs,
+    };
+
+    let window = |name: &str, from_config: Option<(f32, f32)>| -> Result<Option<(f32, f32)>> {
+        let window = match args.value_of(name) {
+            Some(window) => match window.split_once(':').map(|(a, b)| (a.parse(), b.parse())) {
+                Some((Ok(start), Ok(end))) => Some((start, end)),
+                _ => bail!("{} \"{}\" is not <start:f32>:<end:f32>", name, window),
+            },
+            None => from_config,
+        };
+
+        match window {
+            Some((start, end)) if start >= end => {
+                bail!("{} must end after it starts but it is {}:{}", name, start, end)
+            }
+            window => Ok(window),
+        }
+    };
+
+    let checkpoint_every = match args.value_of("checkpointEvery") {
+        Some(every) => every.parse()
+            .context(format!("checkpointEvery \"{}\" is not a valid f32", every))?,
+        None => 600.0,
+    };
+
+    let report_every = match args.value_of("reportEvery") {
+        Some(every) => match every.parse() {
+            Ok(every) if every > 0.0 => Some(every),
+            _ => bail!("reportEvery \"{}\" is not a positive f32", every),
+        },
+        None => config.report_every,
+    };
+
+    let snapshot_every = match args.value_of("snapshotEvery") {
+        Some(every) => match every.parse() {
+            Ok(every) if every > 0.0 => every,
+            _ => bail!("snapshotEvery \"{}\" is not a positive f32", every),
+        },
+        None => config.snapshot_every.unwrap_or(60.0),
+    };
+
+    if snapshot_every <= 0.0 {
+        bail!("snapshot_every {} must be positive", snapshot_every)
+    }
+
+    let flush_every = match args.value_of("flushEvery") {
+        Some(jobs) => match jobs.parse() {
+            Ok(jobs) if jobs > 0 => jobs,
+            _ => bail!("flushEvery \"{}\" is not a positive integer", jobs),
+        },
+        None => config.flush_every.unwrap_or(1),
+    };
+
+    let trace_fields = match args.value_of("traceFields") {
+        Some(fields) => Some(fields.split(',').map(|name| name.trim().to_owned()).collect()),
+        None => config.trace_fields,
+    };
+    if let Some(fields) = &trace_fields {
+        job_factory::check_trace_fields(fields)?;
+    }
+
+    let max_queue_depth = match args.value_of("maxQueueDepth") {
+        Some(depth) => match depth.parse() {
+            Ok(depth) if depth > 0 => Some(depth),
+            _ => bail!("maxQueueDepth \"{}\" is not a positive integer", depth),
+        },
+        None => config.max_queue_depth,
+    };
+
+    if max_queue_depth == Some(0) {
+        bail!("max_queue_depth must be a positive integer")
+    }
+
+    let arrival_rate = match args.value_of("arrivalRate") {
+        Some(rate) => Some(rate.parse()
+            .context(format!("arrivalRate \"{}\" is not a valid f32", rate))?),
+        None => config.arrival_rate,
+    };
+
+    if let Some(rate) = arrival_rate.filter(|r: &f32| !(*r > 0.0 && r.is_finite())) {
+        bail!("arrivalRate {} must be positive", rate)
+    }
+
+    let max_jobs = match args.value_of("maxJobs") {
+        Some(jobs) => match jobs.parse() {
+            Ok(jobs) if jobs > 0 => Some(jobs),
+            _ => bail!("maxJobs \"{}\" is not a positive integer", jobs),
+        },
+        None => config.max_jobs,
+    };
+
+    let until = match args.value_of("until") {
+        Some(time) => Some(time.parse()
+            .context(format!("until \"{}\" is not a valid f32", time))?),
+        None => config.until,
+    };
+
+    let user_weights = match args.value_of("userWeights") {
+        Some(weights) => weights.split(',')
+            .map(|pair| match pair.split_once('=') {
+                Some((user, weight)) => Ok((user.trim().to_owned(), weight.trim().parse::<f64>()
+                    .context(format!("userWeights weight \"{}\" is not a valid f64", weight))?)),
+                None => bail!("userWeights expects <user>=<weight> but found \"{}\"", pair),
+            })
+            .collect::<Result<HashMap<_, _>>>()?,
+        None => config.user_weights.unwrap_or_default(),
+    };
+    for (user, weight) in &user_weights {
+        if !(*weight > 0.0 && weight.is_finite()) {
+            bail!("The weight {} of user {} must be positive", weight, user)
+        }
+    }
+
+    let wait_histogram = match args.value_of("waitHistogram") {
+        Some(bounds) => Some(bounds.split(',')
+            .map(|bound| bound.trim().parse::<f32>()
+                .context(format!("waitHistogram bound \"{}\" is not a valid f32", bound)))
+            .collect::<Result<Vec<_>>>()?),
+        None => config.wait_histogram,
+    };
+    if let Some(bounds) = &wait_histogram {
+        WaitHistogram::new(bounds)?;
+    }
+
+    let cascade = match args.value_of("cascade").or(config.cascade.as_deref()) {
+        Some(cascade) => cascade.parse()?,
+        None => scheduler::CascadePolicy::Hold,
+    };
+
+    let validation = match args.value_of("validation").or(config.validation.as_deref()) {
+        Some(validation) => validation.parse()?,
+        None => job_factory::Validation::Lenient,
+    };
+
+    let gantt_window = window("ganttWindow", config.gantt_window)?;
+    let stats_window = window("statsWindow", config.stats_window)?;
+
+    let path = |name: &str, from_config: &Option<PathBuf>| -> Option<PathBuf> {
+        args.value_of(name).map(PathBuf::from).or_else(|| from_config.clone())
+    };
+
+    let required = |name: &str, from_config: &Option<PathBuf>| -> Result<PathBuf> {
+        match path(name, from_config) {
+            Some(path) => Ok(path),
+            None => bail!("The path to {} is missing from the config file", name),
+        }
+    };
 
-    println!("Instantiating node registry");
-    let registry = registry::NodeRegistry::from_paths(path_nodes, path_connections).unwrap();
+    let json_logs = match args.value_of("logFormat").or(config.log_format.as_deref()) {
+        Some("json") => true,
+        Some("text") | None => false,
+        Some(format) => bail!("logFormat must be text or json but it is \"{}\"", format),
+    };
 
-    println!("Instantiating job factory");
-    let jfactory: Box<dyn job_factory::JobFactory>;
-    if arguments.len() == 1 + 3 {
-        let jf = job_factory::JobStreaming::from_path(path_jobs).unwrap();
-        jfactory = Box::new(jf);
+    let mut arguments = Arguments {
+        json_logs,
+        path_nodes: required("nodes", &config.nodes)?,
+        path_connections: match args.value_of("connections") {
+            Some("-") => None,
+            _ => path("connections", &config.connections),
+        },
+        path_jobs: match args.value_of("sweep") {
+            Some(_) => PathBuf::new(),
+            None => required("jobs", &config.jobs)?,
+        },
+        path_sweep: args.value_of("sweep").map(PathBuf::from),
+        validation,
+        path_output_trace: path("output", &config.output),
+        flush_every,
+        trace_fields,
+        report_utilization: args.is_present("utilization") || config.utilization,
+        report_occupancy: args.is_present("occupancy") || config.occupancy,
+        backfill: args.is_present("backfill"),
+        seed,
+        path_failures: path("failures", &config.failures),
+        path_maintenance: path("maintenance", &config.maintenance),
+        path_reservations: path("reservations", &config.reservations),
+        path_holds: path("holds", &config.holds),
+        path_partitions: path("partitions", &config.partitions),
+        path_limits: path("limits", &config.limits),
+        path_scaling: path("scaling", &config.scaling),
+        fair_share,
+        user_weights,
+        path_summary: path("summary", &config.summary),
+        path_summary_json: path("summaryJson", &config.summary_json),
+        drf: args.is_present("drf"),
+        topology: args.is_present("topology"),
+        best_fit: args.value_of("bestFit").map(|scorer| scorer.to_owned()),
+        tick_granularity,
+        schedule_interval,
+        decision_latency,
+        slowdown_threshold,
+        warm_cache,
+        speculation,
+        max_queue_depth,
+        arrival_rate,
+        cancel_late: args.is_present("cancelLate") || config.cancel_late,
+        cascade,
+        fragmentation: args.is_present("fragmentation") || config.fragmentation,
+        profile: args.is_present("profile") || config.profile,
+        runtime_model: config.runtime,
+        path_gantt: path("gantt", &config.gantt),
+        path_snapshots: path("snapshots", &config.snapshots),
+        snapshot_every,
+        gantt: GanttChart { max_jobs: gantt_max_jobs, window: gantt_window },
+        path_throughput: path("throughput", &config.throughput),
+        stats_window,
+        max_jobs,
+        until,
+        path_checkpoint: args.value_of("checkpoint").map(PathBuf::from),
+        checkpoint_every,
+        report_every,
+        step: args.is_present("step"),
+        wait_histogram,
+        path_restore: args.value_of("restore").map(PathBuf::from),
+        path_verify_trace: args.value_of("verifyTrace").map(PathBuf::from),
+        #[cfg(feature = "metrics")]
+        metrics_address: args.value_of("metrics").map(|address| address.to_owned()),
+    };
+
+    if let Some(model) = &arguments.runtime_model {
+        model.validate()?;
+    }
+
+    if arguments.path_verify_trace.is_some() {
+        if arguments.path_output_trace.is_none() {
+            bail!("--verifyTrace compares the output trace with the golden one, the path to the \
+                output trace is missing")
+        }
+        if arguments.path_restore.is_some() {
+            bail!("--verifyTrace cannot check a run that resumes from a checkpoint")
+        }
+        if arguments.trace_fields.is_some() {
+            bail!("--verifyTrace needs the default fields of the output trace, it cannot be \
+                combined with --traceFields")
+        }
+    }
+
+    // VV: The policy of the config file only applies if the command-line does not pick one
+    let cli_policy = arguments.backfill || arguments.drf || arguments.topology
+        || arguments.fair_share.is_some() || arguments.best_fit.is_some();
+
+    if let Some(objective) = args.value_of("objective").or(config.objective.as_deref()) {
+        match objective_policy(objective, cli_policy || config.policy.is_some())? {
+            ObjectivePolicy::Backfill => arguments.backfill = true,
+            ObjectivePolicy::BestFitPack => arguments.best_fit = Some("pack".to_owned()),
+            ObjectivePolicy::FairShare(window) => arguments.fair_share = Some(window),
+            ObjectivePolicy::FirstFit => (),
+        }
+    }
+
+    match config.policy.as_deref().filter(|_| !cli_policy) {
+        None | Some("first_fit") => (),
+        Some("backfill") => arguments.backfill = true,
+        Some("drf") => arguments.drf = true,
+        Some("topology") => arguments.topology = true,
+        Some(scorer @ ("spread" | "pack")) => arguments.best_fit = Some(scorer.to_owned()),
+        Some("fair_share") => match config.fair_share_window {
+            Some(window) => arguments.fair_share = Some(window),
+            None => bail!("The fair_share policy needs a fair_share_window"),
+        },
+        Some(policy) => bail!("Unknown policy \"{}\"", policy),
+    }
+
+    Ok(arguments)
+}
+
+/// Returns a SchedulerBuilder for the simulation of @path_jobs, it records the jobs that finish
+/// in @path_output_trace
+fn scheduler_builder(
+    arguments: &Arguments,
+    path_jobs: &Path,
+    path_output_trace: Option<&Path>,
+    seed: u64,
+) -> Result<scheduler::SchedulerBuilder> {
+    // VV: Kubernetes manifests use the .yaml or .yml extension
+    let is_yaml = |path: &Path| matches!(compression::extension(path), Some("yaml" | "yml"));
+
+    let is_toml = matches!(compression::extension(&arguments.path_nodes), Some("toml"));
+
+    let registry = match &arguments.path_connections {
+        None if is_toml => registry::NodeRegistry::from_path(&arguments.path_nodes)?,
+        Some(_) if is_toml => bail!("The topology file {} already defines the connections, \
+            use - for the connections", arguments.path_nodes.display()),
+        None => bail!("The path to connections is missing from the config file"),
+        Some(path) if is_yaml(&arguments.path_nodes) => {
+            registry::NodeRegistry::from_yaml(&arguments.path_nodes, path)?
+        }
+        Some(path) => registry::NodeRegistry::from_paths(&arguments.path_nodes, path)?,
+    };
+
+    let failures = match &arguments.path_failures {
+        Some(path) => failures::load_failures(path, &registry)?,
+        None => vec![],
+    };
+
+    let maintenance = match &arguments.path_maintenance {
+        Some(path) => failures::load_maintenance(path, &registry)?,
+        None => vec![],
+    };
+
+    let scaling = match &arguments.path_scaling {
+        Some(path) => failures::load_scaling(path, &registry)?,
+        None => vec![],
+    };
+
+    let reservations = match &arguments.path_reservations {
+        Some(path) => failures::load_reservations(path, &registry)?,
+        None => vec![],
+    };
+
+    let holds = match &arguments.path_holds {
+        Some(path) => failures::load_holds(path)?,
+        None => vec![],
+    };
+
+    let partitions = match &arguments.path_partitions {
+        Some(path) => failures::load_partitions(path, &registry)?,
+        None => vec![],
+    };
+
+    let limits = match &arguments.path_limits {
+        Some(path) => failures::load_concurrency_limits(path, &registry)?,
+        None => vec![],
+    };
+
+    // VV: Traces in the Standard Workload Format use the .swf extension
+    let paths_jobs = job_factory::expand_paths(path_jobs)?;
+    let path_jobs = match paths_jobs.as_slice() {
+        [path] => path.as_path(),
+        _ => path_jobs,
+    };
+
+    if paths_jobs.len() > 1 && paths_jobs.iter().any(|path| job_factory::is_stdin(path)) {
+        bail!("The jobs of the standard input cannot be combined with other job files")
+    }
+
+    let jfactory: Box<dyn job_factory::JobFactory> =
+        if job_factory::is_stdin(path_jobs) {
+            // VV: The standard input can only be read once so its jobs are checked as they arrive
+            Box::new(job_factory::JobStreaming::from_stdin(arguments.validation))
+        } else if paths_jobs.len() > 1 {
+            Box::new(job_factory::JobCollection::from_paths(
+                &paths_jobs, arguments.validation, Some(&registry))?)
+        } else if compression::extension(path_jobs) == Some("swf") {
+            Box::new(job_factory::JobStreamingSwf::from_path(path_jobs)?)
+        } else if is_yaml(path_jobs) {
+            Box::new(job_factory::JobCollection::from_yaml(path_jobs)?)
+        } else {
+            if arguments.validation == job_factory::Validation::Strict {
+                job_factory::check_jobs(path_jobs, Some(&registry))?;
+            }
+            Box::new(job_factory::JobStreaming::from_path_with_validation(
+                path_jobs, arguments.validation)?)
+        };
+
+    let jfactory: Box<dyn job_factory::JobFactory> = match arguments.arrival_rate {
+        Some(rate) => Box::new(job_factory::PoissonArrivals::new(jfactory, rate, seed)),
+        None => jfactory,
+    };
+
+    let jfactory: Box<dyn job_factory::JobFactory> =
+        if let Some(path_output_trace) = path_output_trace {
+            // VV: Output traces with the .jsonl extension contain 1 JSON object per line
+            let format = job_factory::TraceFormat::from_path(path_output_trace);
+            let jf = job_factory::JobStreamingWithOutput::from_factory_to_path_with_fields(
+                jfactory, path_output_trace, format, arguments.trace_fields.clone())?
+                .with_flush_every(arguments.flush_every);
+            Box::new(jf)
+        } else {
+            jfactory
+        };
+
+    let mut builder = scheduler::SchedulerBuilder::new(registry, jfactory)
+        .backfill(arguments.backfill)
+        .track_fragmentation(arguments.fragmentation)
+        .profile(arguments.profile)
+        .cancel_late(arguments.cancel_late)
+        .cascade(arguments.cascade)
+        .record_spans(arguments.path_gantt.is_some())
+        .seed(seed)
+        .failures(failures)
+        .maintenance(maintenance)
+        .reservations(reservations)
+        .holds(holds)
+        .partitions(partitions)
+        .limits(limits)
+        .scaling(scaling)?;
+
+    if let Some(window) = arguments.fair_share {
+        builder = builder.fair_share(window);
+    }
+    builder = builder.user_weights(arguments.user_weights.clone());
+
+    if arguments.drf {
+        builder = builder.policy(Box::new(drf::Drf::default()));
+    }
+
+    if let Some((start, end)) = arguments.stats_window {
+        builder = builder.stats_window(start, end);
+    }
+
+    if let Some(jobs) = arguments.max_jobs {
+        builder = builder.max_jobs(jobs);
+    }
+
+    if let Some(time) = arguments.until {
+        builder = builder.until(time);
+    }
+
+    if let Some(granularity) = arguments.tick_granularity {
+        builder = builder.tick_granularity(granularity);
+    }
+
+    if let Some(interval) = arguments.schedule_interval {
+        builder = builder.schedule_interval(interval);
+    }
+
+    if let Some(latency) = arguments.decision_latency {
+        builder = builder.decision_latency(latency);
+    }
+
+    if let Some(threshold) = arguments.slowdown_threshold {
+        builder = builder.slowdown_threshold(threshold);
+    }
+
+    if let Some(model) = arguments.runtime_model {
+        builder = builder.runtime_model(model);
+    }
+
+    if arguments.topology {
+        builder = builder.policy(Box::new(topology::TopologyAware::default()));
+    }
+
+    if let Some((decay, speedup)) = arguments.warm_cache {
+        builder = builder.warm_cache(decay, speedup);
+    }
+
+    if let Some(threshold) = arguments.speculation {
+        builder = builder.speculation(threshold);
+    }
+
+    if let Some(depth) = arguments.max_queue_depth {
+        builder = builder.max_queue_depth(depth);
+    }
+
+    let best_fit = match arguments.best_fit.as_deref() {
+        Some("spread") => Some(best_fit::BestFit::spread()),
+        Some("pack") => Some(best_fit::BestFit::pack()),
+        _ => None,
+    };
+
+    if let Some(policy) = best_fit {
+        let policy = match arguments.warm_cache {
+            Some(_) => policy.prefer_warm(),
+            None => policy,
+        };
+        builder = builder.policy(Box::new(policy));
+    }
+
+    Ok(builder)
+}
+
+/// Simulates every job file in @dir in parallel and reports their summaries
+fn run_sweep(arguments: &Arguments, dir: &Path, seed: u64) -> Result<()> {
+    if arguments.path_output_trace.is_some() || arguments.path_restore.is_some()
+        || arguments.path_checkpoint.is_some() || arguments.path_gantt.is_some()
+        || arguments.path_throughput.is_some() || arguments.path_verify_trace.is_some()
+        || arguments.path_summary_json.is_some() {
+        bail!("--sweep does not support output traces, checkpoints, Gantt charts, throughput \
+            series, golden traces, or JSON summaries")
+    }
+
+    let paths = sweep::job_files(dir)?;
+    info!("Simulating {} job files in {}", paths.len(), dir.display());
+
+    let results = sweep::run_sweep(&paths, |path| {
+        Ok(scheduler_builder(arguments, path, None, seed)?.build())
+    });
+
+    info!("{}", sweep::SweepResult::csv_header());
+    for result in &results {
+        info!("{}", result.to_csv_row());
+    }
+
+    if let Some(path) = &arguments.path_summary {
+        sweep::append_csv(&results, path)?;
+    }
+
+    let failed = results.iter().filter(|result| result.summary.is_err()).count();
+    if failed > 0 {
+        bail!("{} of the {} simulations failed", failed, results.len())
+    }
+    Ok(())
+}
+
+/// Logs info and debug events to stdout and warnings and errors to stderr. RUST_LOG selects the
+/// events, it defaults to info. Plain text logs contain just the message and its fields
+fn init_logging(json: bool) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let writer = std::io::stderr.with_max_level(Level::WARN).or_else(std::io::stdout);
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_writer(writer);
+
+    if json {
+        builder.json().init();
     } else {
-        let path_output_trace = Path::new(&arguments[4]);
-        let jf = job_factory::JobStreamingWithOutput::from_path_to_path(
-            path_jobs, path_output_trace).unwrap();
-        jfactory = Box::new(jf);
+        builder.without_time().with_target(false).with_level(false).init();
+    }
+}
+
+/// What the user asks for at the prompt of --step
+enum Step {
+    Next,
+    Continue,
+    Quit,
+}
+
+/// Waits for the user to press enter, the end of stdin counts as c
+fn prompt_step() -> Result<Step> {
+    print!("[enter] step, [c] continue, [q] quit: ");
+    std::io::stdout().flush()?;
+
+    let mut line = String::new();
+    if std::io::stdin().read_line(&mut line)? == 0 {
+        return Ok(Step::Continue);
+    }
+
+    Ok(match line.trim() {
+        "c" => Step::Continue,
+        "q" => Step::Quit,
+        _ => Step::Next,
+    })
+}
+
+/// The exit status of runs that stopped early because of SIGINT, 128 + the number of SIGINT
+const EXIT_INTERRUPTED: i32 = 130;
+
+// VV: The SIGINT handler only raises this flag, the simulation checks it between ticks
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Makes SIGINT stop the simulation after the current tick, a second SIGINT exits right away
+fn handle_sigint() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+    }).context("Unable to install the SIGINT handler")
+}
+
+/// Prints every job that is still waiting to start
+fn warn_waiting(sched: &scheduler::Scheduler) {
+    for j in &sched.jobs_queuing {
+        match &j.last_block_reason {
+            Some(reason) => warn!("{} waits, {}", j, reason),
+            None => warn!("{}", j),
+        }
+    }
+
+    for j in &sched.jobs_blocked {
+        warn!("{} blocked by {:?}", j, j.depends_on);
+    }
+
+    for j in &sched.jobs_held {
+        warn!("{} on hold", j);
     }
+}
+
+fn main() -> Result<()> {
+    let arguments = parse_arguments()?;
+    init_logging(arguments.json_logs);
+
+    let seed = arguments.seed.unwrap_or_else(rand::random);
+    info!("Using seed {}", seed);
+
+    if let Some(dir) = &arguments.path_sweep {
+        return run_sweep(&arguments, dir, seed);
+    }
+
+    info!("Instantiating scheduler");
+    let builder = scheduler_builder(&arguments, &arguments.path_jobs,
+                                    arguments.path_output_trace.as_deref(), seed)?;
 
-    println!("Instantiating scheduler");
-    let mut sched = scheduler::Scheduler::new(registry, jfactory);
+    let mut sched = match &arguments.path_restore {
+        Some(path) => {
+            let sched = builder.restore(path)?;
+            info!("Resuming from {} at simulated time {}", path.display(), sched.now);
+            sched
+        }
+        None => builder.build(),
+    };
+
+    #[cfg(feature = "metrics")]
+    let metrics = match &arguments.metrics_address {
+        Some(address) => {
+            let server = dismem::metrics::MetricsServer::start(address)?;
+            info!("Serving metrics at http://{}/metrics", server.address());
+            Some(server)
+        }
+        None => None,
+    };
 
 
-    println!("Starting simulation");
-    let report_every_secs = 5.0;
+    info!("Starting simulation");
+    let mut report = match arguments.report_every {
+        Some(every) => Cadence::new(Box::new(SimClock), every, &sched),
+        None => Cadence::new(Box::new(WallClock::new()), 5.0, &sched),
+    };
+    let mut checkpoint = Cadence::new(Box::new(WallClock::new()), arguments.checkpoint_every,
+                                      &sched);
+    let mut snapshots = match &arguments.path_snapshots {
+        Some(path) => Some(SnapshotWriter::new(path, arguments.snapshot_every, &sched)?),
+        None => None,
+    };
     let mut last_report_time = SystemTime::now();
     let start = last_report_time;
 
     let mut throughput_last = 0;
     let mut throughput_delta = 0;
 
-    while sched.tick() {
-        let now = SystemTime::now();
-        let delta = now.duration_since(last_report_time).unwrap();
+    handle_sigint()?;
+
+    let mut stepping = arguments.step;
+    if stepping {
+        println!("Before the first step\n{}", snapshot::state_table(&sched));
+    }
+
+    let mut stream;
+    loop {
+        let time = sched.now;
+        let ticked = !INTERRUPTED.load(Ordering::SeqCst) && sched.tick();
+        // VV: A stream that is checked as it arrives (e.g. the standard input) ends at its first
+        // invalid job in the strict validation mode
+        stream = sched.job_factory.check_stream();
+        if !ticked || stream.is_err() {
+            break;
+        }
+
+        if stepping {
+            println!("Scheduled at {}, the next event is at {}\n{}", time, sched.now,
+                     snapshot::state_table(&sched));
+            match prompt_step()? {
+                Step::Next => (),
+                Step::Continue => stepping = false,
+                Step::Quit => break,
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(server) = &metrics {
+            server.update(&sched);
+        }
+
+        if let Some(snapshots) = &mut snapshots {
+            snapshots.update(&sched)?;
+        }
+
+        if let Some(path) = &arguments.path_checkpoint {
+            if checkpoint.due(&sched) {
+                sched.save_checkpoint(path)?;
+            }
+        }
 
-        if delta.as_secs_f32() > report_every_secs {
+        if report.due(&sched) {
+            let now = SystemTime::now();
+            let delta = now.duration_since(last_report_time).unwrap();
             let throughput = sched.jobs_running.len()
                 + sched.jobs_done.len()
                 + sched.jobs_queuing.len();
@@ -88,33 +1314,213 @@ fn main() -> Result<()> {
             last_report_time = now;
 
             let since_beg = now.duration_since(start).unwrap();
-            println!("{:#?}) At tick {}, finished: {} - running: {} - queueing: {}",
-                     since_beg, sched.now, sched.jobs_done.len(), sched.jobs_running.len(),
-                     sched.jobs_queuing.len());
+            // VV: Reports on the simulated clock leave out wall-clock measurements so that they
+            // are identical across machines
+            let status = format!("At tick {}, finished: {} - running: {} - queueing: {}",
+                                 sched.now, sched.jobs_done.len(), sched.jobs_running.len(),
+                                 sched.jobs_queuing.len());
+            if arguments.report_every.is_some() {
+                info!("{}", status);
+            } else {
+                info!("{:#?}) {}", since_beg, status);
+            }
             let (cores, memory) = sched.registry.get_max_cores_memory();
-            println!("  Max cores: {}, Max memory: {}", cores, memory);
-            println!("  Simulator throughput events: {}", throughput_delta);
-            println!("  Simulator throughput events/sec: {}",
-                     throughput_delta as f32 / (delta.as_secs_f32()));
+            info!("  Max cores: {}, Max memory: {}", cores, memory);
+            for name in &sched.registry.resource_names {
+                info!("  Max {}: {}", name, sched.registry.get_max_resource(name));
+            }
+            info!("  Simulator throughput events: {}", throughput_delta);
+            if arguments.report_every.is_none() {
+                info!("  Simulator throughput events/sec: {}",
+                      throughput_delta as f32 / (delta.as_secs_f32()));
+            }
             throughput_delta = 0;
         }
         if sched.has_unschedulable() {
             break
         }
     }
+
+    // VV: The JSON summary comes before every other output so that it exists even if the run
+    // failed or writing the other outputs fails
+    if let Some(path) = &arguments.path_summary_json {
+        sched.exit_summary().write_json(path)?;
+    }
+    stream?;
+
+    // VV: The last frame shows the cluster at the end of the simulation
+    if let Some(snapshots) = &mut snapshots {
+        snapshots.write(&sched)?;
+        snapshots.flush()?;
+    }
     let delta = SystemTime::now().duration_since(start).unwrap();
 
-    println!("{}) Scheduled {} jobs in simulated seconds {}",
-             delta.as_secs_f32(), sched.jobs_done.len(), sched.now);
+    info!("{}) Scheduled {} jobs in simulated seconds {}",
+          delta.as_secs_f32(), sched.jobs_done.len(), sched.now);
+
+    let summary = sched.summary();
+    let interrupted = INTERRUPTED.load(Ordering::SeqCst);
+    if interrupted {
+        warn!("INTERRUPTED: the simulation stopped at {} because of SIGINT, the statistics below \
+            only cover the {} jobs that finished so far", sched.now, sched.jobs_done.len());
+    } else if summary.truncated {
+        warn!("TRUNCATED: the simulation stopped once {} jobs were done, the statistics below \
+            only cover the jobs that finished so far", sched.jobs_done.len());
+    }
+
+    if interrupted || summary.truncated {
+        let in_progress = sched.in_progress();
+        if !in_progress.is_empty() {
+            info!("{} jobs were in progress at {}:", in_progress.len(), sched.now);
+        }
+        for (uid, remaining) in in_progress {
+            info!("  Job {} needs {} more to finish", uid, remaining);
+        }
+    }
+
+    // VV: has_unschedulable() runs report the jobs that wait at the end in more detail
+    if !sched.has_unschedulable() {
+        for (reason, count) in sched.block_reasons() {
+            info!("{} jobs were waiting at the end, {}", count, reason);
+        }
+    }
+
+    if summary.total_jobs > 0 {
+        info!("Makespan: the last job finished at {}", summary.makespan);
+        info!("Bounded slowdown: mean {:.4}, p95 {:.4}", summary.mean_slowdown,
+              summary.p95_slowdown);
+        info!("Utilization: cores {:.4}, memory {:.4}", summary.core_utilization,
+              summary.memory_utilization);
+    }
+
+    if sched.registry.nodes.iter().any(|node| node.max_watts > 0.0) {
+        info!("The nodes consumed {} kWh", summary.energy_kwh);
+    }
+
+    if sched.registry.nodes.iter().any(|node| node.cost_per_hour > 0.0) {
+        info!("The nodes cost {} in total, {} per completed job", summary.total_cost,
+              summary.cost_per_job);
+    }
+
+    if sched.oom_kills > 0 {
+        info!("Nodes ran out of memory and killed jobs {} times, the victims ran again",
+              sched.oom_kills);
+    }
+
+    if !sched.speculated.is_empty() {
+        let won = sched.speculated.values().filter(|copy_won| **copy_won).count();
+        info!("{} stragglers got a speculative copy, {} of the copies finished first",
+              sched.speculated.len(), won);
+    }
+
+    if sched.retries > 0 {
+        info!("Runs of flaky jobs failed and were retried {} times", sched.retries);
+    }
+
+    if !sched.jobs_failed.is_empty() {
+        info!("{} jobs failed because they exceeded their walltime limit or ran out of retries",
+              sched.jobs_failed.len());
+    }
+
+    if !sched.jobs_rejected.is_empty() {
+        info!("{} jobs were rejected", sched.jobs_rejected.len());
+        for (_, reason) in &sched.jobs_rejected {
+            info!("  {}", reason);
+        }
+    }
+
+    if let Some(path) = &arguments.path_gantt {
+        arguments.gantt.write(path, &sched.spans, &sched.registry)?;
+    }
+
+    if let Some(path) = &arguments.path_throughput {
+        ThroughputSample::write_csv(&sched.throughput(), path)?;
+    }
+
+    if let Some(path) = &arguments.path_summary {
+        summary.append_csv(path)?;
+    }
+
+    if arguments.fragmentation && !sched.fragmentation.is_empty() {
+        let samples = &sched.fragmentation;
+        let blocked: usize = samples.iter().map(|sample| sample.blocked()).sum();
+        let worst = samples.iter().max_by_key(|sample| sample.blocked()).unwrap();
+        info!("Jobs blocked by fragmentation: {:.4} per scheduling decision on average, \
+            at most {} at time {}", blocked as f64 / samples.len() as f64, worst.blocked(),
+              worst.time);
+    }
+
+    if let Some(profile) = &sched.placement_profile {
+        let per_tick = |count: u64| count as f64 / profile.ticks.max(1) as f64;
+        info!("Placement profile of {} ticks: {} attempts ({:.2} per tick), {} feasibility \
+            checks ({:.2} per tick), {} placements ({:.2} per tick)", profile.ticks,
+              profile.total.attempts, per_tick(profile.total.attempts), profile.total.checks,
+              per_tick(profile.total.checks), profile.total.placements,
+              per_tick(profile.total.placements));
+        info!("The busiest tick at time {} made {} attempts, {} feasibility checks, and {} \
+            placements", profile.busiest_at, profile.busiest.attempts, profile.busiest.checks,
+              profile.busiest.placements);
+    }
+
+    if let Some(bounds) = &arguments.wait_histogram {
+        info!("Time that jobs waited before they started:");
+        info!("{}", sched.wait_histogram(bounds)?.to_string().trim_end());
+    }
+
+    if arguments.report_utilization {
+        let mut utilization: Vec<_> = sched.node_utilization().into_iter().collect();
+        utilization.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+
+        let name = |uid: usize| &sched.registry.nodes[uid].name;
+        if let (Some(least), Some(most)) = (utilization.first(), utilization.last()) {
+            info!("Least utilized node: {} ({:.4})", name(least.0), least.1);
+            info!("Most utilized node: {} ({:.4})", name(most.0), most.1);
+        }
+    }
+
+    if arguments.report_occupancy {
+        info!("Occupancy of the nodes at {}:", sched.now);
+        info!("{}", snapshot::occupancy_table(&sched.occupancy_snapshot()).trim_end());
+    }
 
-    if sched.has_unschedulable() {
-        eprintln!("There were {} unschedulable jobs", sched.jobs_queuing.len());
+    if interrupted {
+        let waiting = sched.jobs_queuing.len() + sched.jobs_blocked.len() + sched.jobs_held.len();
+        warn!("There were {} jobs still waiting when the simulation was interrupted", waiting);
+        warn_waiting(&sched);
 
-        for j in &sched.jobs_queuing {
-            println!("{}", j);
+        // VV: The output trace is complete once its JobStreamingWithOutput is gone
+        drop(sched);
+        std::process::exit(EXIT_INTERRUPTED);
+    }
+
+    let stuck = sched.has_unschedulable();
+    if let (Some(golden), Some(output)) = (&arguments.path_verify_trace,
+                                           &arguments.path_output_trace) {
+        // VV: The output trace is complete once its JobStreamingWithOutput is gone
+        sched.job_factory = Box::new(job_factory::JobCollection::new(vec![]));
+        golden::verify_trace(golden, output)?;
+        info!("The output trace repeats the placement decisions of {}", golden.display());
+    }
+
+    if stuck {
+        let unschedulable =
+            sched.jobs_queuing.len() + sched.jobs_blocked.len() + sched.jobs_held.len();
+        warn!("There were {} jobs still waiting when the simulation ended", unschedulable);
+        for (reason, count) in sched.block_reasons() {
+            warn!("  {} jobs: {}", count, reason);
         }
 
-        bail!("Unable to schedule {} jobs", sched.jobs_queuing.len())
+        let deadlocked = sched.deadlocked();
+        if !deadlocked.is_empty() {
+            warn!("{} of them are deadlocked and can never start", deadlocked.len());
+            for (uid, reason) in &deadlocked {
+                warn!("  Job {}: {}", uid, reason);
+            }
+        }
+
+        warn_waiting(&sched);
+
+        bail!("Unable to schedule {} jobs", unschedulable)
     } else {
         Ok(())
     }
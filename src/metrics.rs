@@ -0,0 +1,182 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+//! Live metrics of a running simulation in the Prometheus text format. The HTTP server that
+//! exposes them is only available with the "metrics" feature
+
+use std::fmt::Write;
+
+use crate::scheduler::Scheduler;
+
+/// The state of a Scheduler at the end of a tick
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    pub now: f32,
+    pub jobs_running: usize,
+    pub jobs_queuing: usize,
+    pub jobs_blocked: usize,
+    pub jobs_done: usize,
+    pub jobs_failed: usize,
+    pub jobs_rejected: usize,
+    /// The fraction of the cores of the cluster that jobs currently use
+    pub cores_utilization: f64,
+    /// The fraction of the memory of the cluster that jobs currently use
+    pub memory_utilization: f64,
+}
+
+impl MetricsSnapshot {
+    pub fn from_scheduler(sched: &Scheduler) -> Self {
+        // VV: Failed nodes do not show up as utilized, their resources are not in use by jobs
+        let fraction = |used: f64, capacity: f64| if capacity > 0.0 { used / capacity } else { 0.0 };
+        let (mut cores, mut cores_used, mut memory, mut memory_used) = (0.0, 0.0, 0.0, 0.0);
+
+        for node in sched.registry.nodes.iter().filter(|node| !node.failed) {
+            cores += node.cores.capacity as f64;
+            cores_used += (node.cores.capacity - node.cores.current) as f64;
+            memory += node.memory.capacity as f64;
+            memory_used += (node.memory.capacity - node.memory.current) as f64;
+        }
+
+        Self {
+            now: sched.now,
+            jobs_running: sched.jobs_running.len(),
+            jobs_queuing: sched.jobs_queuing.len(),
+            jobs_blocked: sched.jobs_blocked.len(),
+            jobs_done: sched.jobs_done.len(),
+            jobs_failed: sched.jobs_failed.len(),
+            jobs_rejected: sched.jobs_rejected.len(),
+            cores_utilization: fraction(cores_used, cores),
+            memory_utilization: fraction(memory_used, memory),
+        }
+    }
+
+    /// Formats the snapshot in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let metrics: [(&str, &str, &str, f64); 9] = [
+            ("dismem_simulated_time", "gauge", "The current simulated time", self.now as f64),
+            ("dismem_jobs_running", "gauge", "Jobs that are running", self.jobs_running as f64),
+            ("dismem_jobs_queuing", "gauge", "Jobs that wait for resources",
+             self.jobs_queuing as f64),
+            ("dismem_jobs_blocked", "gauge", "Jobs that wait for their dependencies",
+             self.jobs_blocked as f64),
+            ("dismem_jobs_done_total", "counter", "Jobs that finished", self.jobs_done as f64),
+            ("dismem_jobs_failed_total", "counter",
+             "Jobs that reached their walltime limit or ran out of retries", self.jobs_failed as f64),
+            ("dismem_jobs_rejected_total", "counter", "Jobs that were rejected on arrival",
+             self.jobs_rejected as f64),
+            ("dismem_cores_utilization", "gauge", "Fraction of the cores that jobs use",
+             self.cores_utilization),
+            ("dismem_memory_utilization", "gauge", "Fraction of the memory that jobs use",
+             self.memory_utilization),
+        ];
+
+        let mut out = String::new();
+        for (name, kind, help, value) in metrics {
+            writeln!(out, "# HELP {} {}", name, help).unwrap();
+            writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+            writeln!(out, "{} {}", name, value).unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(feature = "metrics")]
+pub use server::MetricsServer;
+
+#[cfg(feature = "metrics")]
+mod server {
+    use std::io::BufRead;
+    use std::io::BufReader;
+    use std::io::Write;
+    use std::net::SocketAddr;
+    use std::net::TcpListener;
+    use std::net::TcpStream;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use anyhow::Context;
+    use anyhow::Result;
+
+    use super::MetricsSnapshot;
+    use crate::scheduler::Scheduler;
+
+    /// Serves the latest MetricsSnapshot at http://<address>/metrics from a background thread
+    pub struct MetricsServer {
+        snapshot: Arc<Mutex<MetricsSnapshot>>,
+        address: SocketAddr,
+    }
+
+    impl MetricsServer {
+        /// Binds to @address (e.g. "127.0.0.1:9898", port 0 picks a free port) and starts serving
+        pub fn start(address: &str) -> Result<Self> {
+            let listener = TcpListener::bind(address)
+                .context(format!("Unable to bind the metrics server to {}", address))?;
+            let address = listener.local_addr()?;
+            let snapshot = Arc::new(Mutex::new(MetricsSnapshot::default()));
+
+            let shared = snapshot.clone();
+            std::thread::Builder::new()
+                .name("metrics".to_owned())
+                .spawn(move || {
+                    // VV: A broken connection only affects the scrape that it belongs to
+                    for stream in listener.incoming().flatten() {
+                        let _ = Self::respond(stream, &shared);
+                    }
+                })?;
+
+            Ok(Self { snapshot, address })
+        }
+
+        /// The address that the server listens on
+        pub fn address(&self) -> SocketAddr {
+            self.address
+        }
+
+        /// Replaces the metrics that the server reports with the current state of @sched
+        pub fn update(&self, sched: &Scheduler) {
+            let snapshot = MetricsSnapshot::from_scheduler(sched);
+            *self.snapshot.lock().unwrap() = snapshot;
+        }
+
+        fn respond(stream: TcpStream, snapshot: &Mutex<MetricsSnapshot>) -> std::io::Result<()> {
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+            reader.read_line(&mut request)?;
+
+            // VV: Skip the headers, the request line is all we need
+            let mut header = String::new();
+            while reader.read_line(&mut header)? > 2 {
+                header.clear();
+            }
+
+            let mut stream = reader.into_inner();
+            let path = request.split_whitespace().nth(1).unwrap_or("");
+
+            if path == "/metrics" {
+                let body = snapshot.lock().unwrap().to_prometheus();
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+                    Content-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)?;
+            } else {
+                write!(stream, "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\
+                    Connection: close\r\n\r\n")?;
+            }
+            stream.flush()
+        }
+    }
+}
@@ -17,22 +17,81 @@ specific language governing permissions and limitations
 under the License.
 */
 
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::fmt::{Display, Formatter};
 
 use anyhow::bail;
 use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
 
+use crate::job::Job;
 use crate::resource;
 
 pub type NodeId = usize;
 
+/// The free resources that a draining node keeps away from new jobs, see Node::drain()
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Held {
+    pub cores: f32,
+    pub memory: f32,
+    pub resources: BTreeMap<String, f32>,
+}
+
 #[derive(Debug)]
 pub struct Node {
     pub cores: resource::Resource,
     pub memory: resource::Resource,
+    pub resources: resource::NamedResources,
     pub name: String,
     pub uid: NodeId,
     pub share_from: Vec<NodeId>,
+    // VV: Failed nodes have none of their resources available until they recover
+    pub failed: bool,
+    // VV: Draining nodes accept no new jobs but let their running jobs finish, held is what
+    // these jobs have freed up so far
+    pub draining: bool,
+    pub held: Held,
+    // VV: Nodes that left the cluster (see failures::ScalingEvent) stay drained until they return
+    pub removed: bool,
+    // VV: Jobs take duration / speed seconds to finish on this node, defaults to 1
+    pub speed: f32,
+    // VV: The bandwidth of the link that stages the input data of jobs to this node, in units of
+    // memory per second, defaults to infinite i.e. staging is instant
+    pub bandwidth: f32,
+    // VV: The node draws idle_watts when its cores are idle and max_watts when all of them are
+    // busy, both default to 0
+    pub idle_watts: f32,
+    pub max_watts: f32,
+    // VV: What the node costs per hour while it is part of the cluster, defaults to 0
+    pub cost_per_hour: f32,
+    // VV: Jobs with a selector only run on nodes whose labels contain all of its key=value pairs
+    pub labels: BTreeMap<String, String>,
+    // VV: Jobs that target a partition only run on the nodes of that partition
+    pub partitions: BTreeSet<String>,
+    // VV: The rack of the node and the zone of its rack, TopologyAware keeps the pieces of gang
+    // jobs in as few racks and zones as it can
+    pub rack: Option<String>,
+    pub zone: Option<String>,
+    // VV: How many of the jobs that use the cores of this node belong to each anti-affinity group
+    pub groups: BTreeMap<String, usize>,
+    // VV: The affinity groups of the jobs that recently finished on this node, and the time until
+    // which the node stays warm for each of them
+    pub warm: BTreeMap<String, f32>,
+    // VV: Jobs may allocate up to oversubscription times the physical_memory of the node (the
+    // capacity of memory), those that start while the memory is overcommitted run slower
+    pub physical_memory: f32,
+    pub oversubscription: f32,
+    // VV: After a job stops using the cores of the node, the node accepts no new jobs for
+    // cooldown seconds, i.e. until cool_until. While cooling its free resources go to held like
+    // those of draining nodes
+    pub cooldown: f32,
+    pub cooling: bool,
+    pub cool_until: f32,
+    // VV: The node runs an exclusive job (see Job::exclusive), its free resources go to held like
+    // those of draining nodes until the job stops
+    pub exclusive: bool,
 }
 
 impl Display for Node {
@@ -46,7 +105,13 @@ impl Display for Node {
             self.cores.capacity,
             self.memory.current,
             self.memory.capacity,
-        )
+        )?;
+
+        for (name, resource) in &self.resources {
+            write!(f, ", {}: {}/{}", name, resource.current, resource.capacity)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -76,21 +141,333 @@ impl Node {
             }
         };
 
+        let physical_memory = memory.capacity;
+
         Ok(Self {
             cores,
             memory,
+            resources: resource::NamedResources::new(),
             name,
             share_from: vec![],
             uid,
+            failed: false,
+            draining: false,
+            held: Held::default(),
+            removed: false,
+            speed: 1.0,
+            bandwidth: f32::INFINITY,
+            idle_watts: 0.0,
+            max_watts: 0.0,
+            cost_per_hour: 0.0,
+            labels: BTreeMap::new(),
+            partitions: BTreeSet::new(),
+            rack: None,
+            zone: None,
+            groups: BTreeMap::new(),
+            warm: BTreeMap::new(),
+            physical_memory,
+            oversubscription: 1.0,
+            cooldown: 0.0,
+            cooling: false,
+            exclusive: false,
+            cool_until: 0.0,
+        })
+    }
+
+    pub fn set_resource(&mut self, name: &str, capacity: f32) -> Result<()> {
+        match resource::Resource::new(capacity) {
+            Ok(resource) => self.resources.insert(name.to_owned(), resource),
+            Err(s) => bail!("{} definition of {} invalid because {}", name, self.name, s),
+        };
+        Ok(())
+    }
+
+    pub fn set_speed(&mut self, speed: f32) -> Result<()> {
+        if !(speed > 0.0 && speed.is_finite()) {
+            bail!("speed of {} must be positive but it is {}", self.name, speed)
+        }
+        self.speed = speed;
+        Ok(())
+    }
+
+    pub fn set_bandwidth(&mut self, bandwidth: f32) -> Result<()> {
+        if bandwidth.is_nan() || bandwidth <= 0.0 {
+            bail!("bandwidth of {} must be positive but it is {}", self.name, bandwidth)
+        }
+        self.bandwidth = bandwidth;
+        Ok(())
+    }
+
+    pub fn set_cooldown(&mut self, cooldown: f32) -> Result<()> {
+        if !(cooldown >= 0.0 && cooldown.is_finite()) {
+            bail!("cooldown of {} must be a non-negative f32 but it is {}", self.name, cooldown)
+        }
+        self.cooldown = cooldown;
+        Ok(())
+    }
+
+    pub fn set_power(&mut self, idle_watts: f32, max_watts: f32) -> Result<()> {
+        if !(idle_watts >= 0.0 && idle_watts <= max_watts && max_watts.is_finite()) {
+            bail!("power of {} must satisfy 0 <= idle_watts <= max_watts but it is {}..{}",
+                self.name, idle_watts, max_watts)
+        }
+        self.idle_watts = idle_watts;
+        self.max_watts = max_watts;
+        Ok(())
+    }
+
+    pub fn set_cost(&mut self, cost_per_hour: f32) -> Result<()> {
+        if !(cost_per_hour >= 0.0 && cost_per_hour.is_finite()) {
+            bail!("cost of {} must be a non-negative f32 but it is {}", self.name, cost_per_hour)
+        }
+        self.cost_per_hour = cost_per_hour;
+        Ok(())
+    }
+
+    /// Lets jobs allocate @ratio times the physical memory of the node, the node must not host
+    /// any jobs
+    pub fn set_oversubscription(&mut self, ratio: f32) -> Result<()> {
+        if !(ratio >= 1.0 && ratio.is_finite()) {
+            bail!("oversubscription of {} must be at least 1 but it is {}", self.name, ratio)
+        }
+        if self.memory.current != self.memory.capacity {
+            bail!("Cannot oversubscribe the memory of {} while it hosts jobs", self.name)
+        }
+
+        self.memory = match resource::Resource::new(self.physical_memory * ratio) {
+            Ok(memory) => memory,
+            Err(s) => bail!("memory definition of {} invalid because {}", self.name, s),
+        };
+        self.oversubscription = ratio;
+        Ok(())
+    }
+
+    /// Returns how many times slower jobs run because the node swaps: 1 plus the allocated
+    /// memory beyond the physical memory as a fraction of the physical memory
+    pub fn swap_slowdown(&self) -> f32 {
+        if self.oversubscription <= 1.0 || self.physical_memory <= 0.0 || self.failed {
+            return 1.0;
+        }
+
+        let used = self.memory.capacity - self.memory.current - self.held.memory;
+        1.0 + (used - self.physical_memory).max(0.0) / self.physical_memory
+    }
+
+    /// Returns the joules that the node consumes in @seconds while jobs use @core_seconds of its
+    /// cores. Power grows linearly from idle_watts to max_watts with the utilization of the cores,
+    /// nodes without cores always draw idle_watts
+    pub fn energy(&self, seconds: f64, core_seconds: f64) -> f64 {
+        let idle = self.idle_watts as f64 * seconds;
+
+        if self.cores.capacity > 0.0 {
+            let dynamic = (self.max_watts - self.idle_watts) as f64;
+            idle + dynamic * core_seconds / self.cores.capacity as f64
+        } else {
+            idle
+        }
+    }
+
+    /// Returns true if the node has enough free named resources for @share of the @requests
+    pub fn has_resources(&self, requests: &BTreeMap<String, f32>, share: f32) -> bool {
+        requests.iter().all(|(name, amount)| {
+            *amount <= 0.0 || self.resources
+                .get(name)
+                .is_some_and(|resource| resource.current >= amount * share)
         })
     }
 
-    #[allow(dead_code)]
+    /// Returns true if the node has every label of @selector with the same value
+    pub fn matches(&self, selector: &BTreeMap<String, String>) -> bool {
+        selector.iter().all(|(key, value)| self.labels.get(key) == Some(value))
+    }
+
+    /// Returns how far @other is from this node in the rack/zone hierarchy: 0 for nodes in the
+    /// same rack, 1 for nodes in the same zone but not the same rack, and 2 for the rest
+    /// including the nodes that are not in any rack or zone
+    pub fn locality(&self, other: &Node) -> usize {
+        if self.rack.is_some() && self.rack == other.rack {
+            0
+        } else if self.zone.is_some() && self.zone == other.zone {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Returns true if the node belongs to the partition of @job, jobs without a partition may
+    /// use any node
+    pub fn in_partition(&self, job: &Job) -> bool {
+        job.partition.as_ref().is_none_or(|partition| self.partitions.contains(partition))
+    }
+
+    /// Returns true if the cores of @job may go to this node, i.e. the node matches the selector
+    /// and partition of @job, runs no other job of its anti-affinity group, and is idle if @job
+    /// is exclusive
+    pub fn accepts(&self, job: &Job) -> bool {
+        self.matches(&job.selector)
+            && self.in_partition(job)
+            && job.anti_affinity.as_ref().is_none_or(|group| !self.groups.contains_key(group))
+            && (!job.exclusive || self.is_idle())
+    }
+
+    /// Returns true if nothing uses or holds any resource of the node
+    pub fn is_idle(&self) -> bool {
+        self.cores.current == self.cores.capacity
+            && self.memory.current == self.memory.capacity
+            && self.resources.values().all(|resource| resource.current == resource.capacity)
+    }
+
+    /// Records that a job of the anti-affinity @group starts or stops using the cores of this node
+    pub fn join_group(&mut self, group: &str) {
+        *self.groups.entry(group.to_owned()).or_insert(0) += 1;
+    }
+
+    pub fn leave_group(&mut self, group: &str) {
+        if let Some(count) = self.groups.get_mut(group) {
+            *count -= 1;
+            if *count == 0 {
+                self.groups.remove(group);
+            }
+        }
+    }
+
+    /// Keeps the node warm for the affinity @group until the time @until
+    pub fn warm_up(&mut self, group: &str, until: f32) {
+        let entry = self.warm.entry(group.to_owned()).or_insert(until);
+        *entry = entry.max(until);
+    }
+
+    /// Forgets the affinity groups that the node is no longer warm for at time @now
+    pub fn cool_down(&mut self, now: f32) {
+        self.warm.retain(|_, until| *until > now);
+    }
+
+    /// Returns true if @job belongs to an affinity group that the node is warm for
+    pub fn is_warm(&self, job: &Job) -> bool {
+        job.affinity.as_ref().is_some_and(|group| self.warm.contains_key(group))
+    }
+
+    /// Stops the node from accepting new jobs, the resources that running jobs free go to held
+    /// until undrain()
+    pub fn drain(&mut self) {
+        self.draining = true;
+        self.hold_free();
+    }
+
+    /// Makes the resources of a draining node available to new jobs again, unless it is cooling
+    pub fn undrain(&mut self) {
+        self.draining = false;
+        if !self.cooling && !self.exclusive {
+            self.release_held();
+        }
+    }
+
+    /// Keeps new jobs away from the node until the time @until, see Node::cooldown
+    pub fn start_cooldown(&mut self, until: f32) {
+        self.cooling = true;
+        self.cool_until = self.cool_until.max(until);
+        self.hold_free();
+    }
+
+    /// Lets new jobs use the node again if its cooldown is over at time @now. Returns true if
+    /// the cooldown ended
+    pub fn end_cooldown(&mut self, now: f32) -> bool {
+        if !self.cooling || self.cool_until > now {
+            return false;
+        }
+        self.cooling = false;
+        if !self.draining && !self.exclusive {
+            self.release_held();
+        }
+        true
+    }
+
+    /// Keeps new jobs away from the node while an exclusive job runs on it
+    pub fn start_exclusive(&mut self) {
+        self.exclusive = true;
+        self.hold_free();
+    }
+
+    /// Lets new jobs use the node again once its exclusive job stops, unless it is draining or
+    /// cooling
+    pub fn end_exclusive(&mut self) {
+        self.exclusive = false;
+        if !self.draining && !self.cooling {
+            self.release_held();
+        }
+    }
+
+    /// Moves all free resources of a draining, cooling, or exclusive node to held, there is at
+    /// most 1 allocation per resource for held so that Resource::free() can fully restore them
+    /// later
+    pub fn hold_free(&mut self) {
+        if !(self.draining || self.cooling || self.exclusive) || self.failed {
+            return;
+        }
+        self.release_held();
+
+        let hold = |resource: &mut resource::Resource| -> f32 {
+            let free = resource.current;
+            if free > 0.0 {
+                resource.allocate(free);
+            }
+            free
+        };
+
+        self.held.cores = hold(&mut self.cores);
+        self.held.memory = hold(&mut self.memory);
+        for (name, resource) in self.resources.iter_mut() {
+            self.held.resources.insert(name.clone(), hold(resource));
+        }
+    }
+
+    fn release_held(&mut self) {
+        let release = |resource: &mut resource::Resource, held: f32| {
+            if held > 0.0 {
+                resource.free(held);
+            }
+        };
+
+        let held = std::mem::take(&mut self.held);
+        release(&mut self.cores, held.cores);
+        release(&mut self.memory, held.memory);
+        for (name, amount) in held.resources {
+            release(self.resources.get_mut(&name).unwrap(), amount);
+        }
+    }
+
+    /// Makes all resources of the node unavailable, the node must not host any jobs
+    pub fn fail(&mut self) {
+        if self.failed {
+            return;
+        }
+        self.release_held();
+        self.failed = true;
+        self.cores.allocate(self.cores.capacity);
+        self.memory.allocate(self.memory.capacity);
+        for resource in self.resources.values_mut() {
+            resource.allocate(resource.capacity);
+        }
+    }
+
+    /// Makes all resources of a failed node available again
+    pub fn recover(&mut self) {
+        if !self.failed {
+            return;
+        }
+        self.failed = false;
+        self.cores.free(self.cores.capacity);
+        self.memory.free(self.memory.capacity);
+        for resource in self.resources.values_mut() {
+            resource.free(resource.capacity);
+        }
+        self.hold_free();
+    }
+
     pub fn can_host_job(&self, cores: f32, memory: f32) -> bool {
         self.cores.current >= cores && self.memory.current >= memory
     }
 
-    #[allow(dead_code)]
     pub fn allocate_job(&mut self, cores: f32, memory: f32) {
         self.allocate_cores(cores);
         self.allocate_memory(memory);
@@ -111,4 +488,16 @@ impl Node {
     pub fn free_cores(&mut self, cores: f32) {
         self.cores.free(cores)
     }
+
+    pub fn allocate_resources(&mut self, requests: &BTreeMap<String, f32>, share: f32) {
+        for (name, amount) in requests.iter().filter(|(_, amount)| **amount > 0.0) {
+            self.resources.get_mut(name).unwrap().allocate(amount * share)
+        }
+    }
+
+    pub fn free_resources(&mut self, requests: &BTreeMap<String, f32>, share: f32) {
+        for (name, amount) in requests.iter().filter(|(_, amount)| **amount > 0.0) {
+            self.resources.get_mut(name).unwrap().free(amount * share)
+        }
+    }
 }
@@ -0,0 +1,58 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+use anyhow::bail;
+use anyhow::Result;
+
+/// The goals that --objective accepts, each picks one of the built-in policies
+pub const OBJECTIVES: [&str; 4] = ["makespan", "utilization", "fairness", "wait-time"];
+
+/// The time window of the fair-share usage that the fairness objective uses, 1 day
+pub const FAIRNESS_WINDOW: f32 = 86400.0;
+
+/// The built-in policy that an objective picks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectivePolicy {
+    /// EASY Backfill, see SchedulerBuilder::backfill()
+    Backfill,
+    /// BestFit::pack()
+    BestFitPack,
+    /// FairShare with a usage window, see SchedulerBuilder::fair_share()
+    FairShare(f32),
+    /// The default FirstFit policy
+    FirstFit,
+}
+
+/// Returns the policy that @objective picks. @explicit_policy tells whether the command-line or
+/// the config file also picks a policy, which is an error because the objective picks it
+pub fn objective_policy(objective: &str, explicit_policy: bool) -> Result<ObjectivePolicy> {
+    let policy = match objective {
+        "makespan" => ObjectivePolicy::Backfill,
+        "utilization" => ObjectivePolicy::BestFitPack,
+        "fairness" => ObjectivePolicy::FairShare(FAIRNESS_WINDOW),
+        "wait-time" => ObjectivePolicy::FirstFit,
+        _ => bail!("Unknown objective \"{}\", expected one of {}", objective,
+                   OBJECTIVES.join(", ")),
+    };
+
+    if explicit_policy {
+        bail!("The objective {} picks the policy, it cannot be combined with a policy", objective)
+    }
+    Ok(policy)
+}
@@ -0,0 +1,115 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+//! Counters of the work that the placement hot path does. Counting is off unless enable() was
+//! called, then every count costs a relaxed atomic load and an increment of a thread-local
+//! counter so that the simulations of a sweep, which run on separate threads, do not mix
+
+use std::cell::Cell;
+use std::ops::Sub;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static COUNTERS: Cell<PlacementCounters> = const { Cell::new(PlacementCounters {
+        attempts: 0, checks: 0, placements: 0 }) };
+}
+
+/// @attempts counts the times a policy tried to place a job, @checks the nodes that it checked
+/// for enough free resources, and @placements the placements that it reserved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlacementCounters {
+    pub attempts: u64,
+    pub checks: u64,
+    pub placements: u64,
+}
+
+impl Sub for PlacementCounters {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            attempts: self.attempts - other.attempts,
+            checks: self.checks - other.checks,
+            placements: self.placements - other.placements,
+        }
+    }
+}
+
+/// The placement work of the ticks of a run, see SchedulerBuilder::profile()
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlacementProfile {
+    pub ticks: u64,
+    pub total: PlacementCounters,
+    /// The counters of the tick with the most feasibility checks and the time of that tick
+    pub busiest: PlacementCounters,
+    pub busiest_at: f32,
+}
+
+impl PlacementProfile {
+    /// Adds the counters of 1 tick at the simulated time @now
+    pub fn record(&mut self, now: f32, tick: PlacementCounters) {
+        self.ticks += 1;
+        self.total.attempts += tick.attempts;
+        self.total.checks += tick.checks;
+        self.total.placements += tick.placements;
+
+        if tick.checks > self.busiest.checks {
+            self.busiest = tick;
+            self.busiest_at = now;
+        }
+    }
+}
+
+/// Starts counting on every thread, there is no way to stop because other simulations may
+/// still rely on their counters
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Returns what the current thread counted so far
+pub fn counters() -> PlacementCounters {
+    COUNTERS.get()
+}
+
+fn count(update: fn(&mut PlacementCounters)) {
+    if is_enabled() {
+        let mut counters = COUNTERS.get();
+        update(&mut counters);
+        COUNTERS.set(counters);
+    }
+}
+
+pub fn count_attempt() {
+    count(|counters| counters.attempts += 1)
+}
+
+pub fn count_check() {
+    count(|counters| counters.checks += 1)
+}
+
+pub fn count_placement() {
+    count(|counters| counters.placements += 1)
+}
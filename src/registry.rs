@@ -17,20 +17,92 @@ specific language governing permissions and limitations
 under the License.
 */
 
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
-use std::fs::File;
+use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::io::BufRead;
-use std::io::BufReader;
+use std::ops::RangeInclusive;
 use std::path::Path;
 
 use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
-
+use rand::Rng;
+use rand::SeedableRng;
+use serde::Deserialize;
+
+use crate::compression;
+use crate::duration::parse_time;
+use crate::job::BlockReason;
+use crate::job::Job;
+use crate::kube;
 use crate::node::Node;
+use crate::node::NodeId;
+use crate::scheduler::SimRng;
 
 pub type UIDFactory = HashMap<String, usize>;
 
+/// A TOML document with both the nodes and their connections, see NodeRegistry::from_path()
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Topology {
+    #[serde(default)]
+    pub nodes: Vec<TopologyNode>,
+    #[serde(default)]
+    pub connections: Vec<TopologyConnection>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TopologyNode {
+    pub name: String,
+    pub cores: f32,
+    pub memory: f32,
+    /// The rack and zone of the node, same as the rack= and zone= tokens of the nodes file
+    #[serde(default)]
+    pub rack: Option<String>,
+    #[serde(default)]
+    pub zone: Option<String>,
+    /// The optional key=value tokens of the nodes file, e.g. "speed=0.5"
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TopologyConnection {
+    pub borrower: String,
+    /// Names of the lenders, "*" means every other node
+    #[serde(default)]
+    pub lenders: Vec<String>,
+}
+
+/// How the nodes of NodeRegistry::generate() connect to each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopologyPattern {
+    /// Every node may borrow memory from every other node, the number of connections grows
+    /// quadratically with the number of nodes
+    FullyConnected,
+    /// Every node may borrow memory from the 2 nodes next to it on a ring
+    Ring,
+    /// Leaf switches with @0 nodes each make up the racks and @0 racks make up a zone (a pod),
+    /// the nodes of a rack may borrow memory from each other
+    FatTree(usize),
+}
+
+/// The cluster that NodeRegistry::generate() creates. Every node gets a random whole number of
+/// cores, memory, and named @resources in the ranges, ranges like 4..=4 make identical nodes
+#[derive(Debug, Clone)]
+pub struct ClusterSpec {
+    pub nodes: usize,
+    pub cores: RangeInclusive<u32>,
+    pub memory: RangeInclusive<u32>,
+    pub resources: BTreeMap<String, RangeInclusive<u32>>,
+    pub topology: TopologyPattern,
+}
+
 pub struct NodeRegistry {
     pub registry: UIDFactory,
     pub nodes: Vec<Node>,
@@ -39,7 +111,19 @@ pub struct NodeRegistry {
     pub sorted_memory: Vec<usize>,
     pub connections: HashMap<usize, Vec<usize>>,
     pub connections_reverse: HashMap<usize, Vec<usize>>,
+    // VV: Names of the resources other than cores and memory that at least 1 node provides
+    pub resource_names: BTreeSet<String>,
+    // VV: When set, ensure_sorted() re-sorts the indices from scratch. Nodes that touch() lists
+    // move to their new place in the indices instead
     pub is_dirty: bool,
+    pub touched: Vec<NodeId>,
+    // VV: Whether nodes with the same free resources appear in node id order in the indices
+    pub in_id_order: bool,
+    // VV: The free cores and memory of each node at the time the indices last placed it
+    pub indexed: Vec<(f32, f32)>,
+    // VV: A segment tree with the largest free memory of the nodes in each range of sorted_cores,
+    // the leaves start at max_memory.len() / 2, see first_with_memory()
+    pub max_memory: Vec<f32>,
 }
 
 impl NodeRegistry {
@@ -53,18 +137,23 @@ impl NodeRegistry {
             sorted_memory: vec![],
             connections: HashMap::new(),
             connections_reverse: HashMap::new(),
+            resource_names: BTreeSet::new(),
             is_dirty: false,
+            touched: vec![],
+            in_id_order: true,
+            indexed: vec![],
+            max_memory: vec![],
         }
     }
 
     pub fn load_nodes(&mut self, path: &Path) -> Result<()> {
-        let file = File::open(path);
+        let file = compression::open(path);
 
         if let Err(x) = file {
             bail!("Unable to open node_definitions file {} because of {:?}", path.display(), x)
         }
 
-        let br = BufReader::new(file.unwrap());
+        let br = file.unwrap();
 
         for (i, x) in br.lines().enumerate() {
             if let Err(err) = x {
@@ -83,13 +172,13 @@ impl NodeRegistry {
     }
 
     pub fn load_connections(&mut self, path: &Path) -> Result<()> {
-        let file = File::open(path);
+        let file = compression::open(path);
 
         if let Err(x) = file {
             bail!("Unable to open node_connections file {} because of {:?}", path.display(), x)
         }
 
-        let br = BufReader::new(file.unwrap());
+        let br = file.unwrap();
 
         for (i, x) in br.lines().enumerate() {
             if let Err(err) = x {
@@ -116,10 +205,148 @@ impl NodeRegistry {
         Ok(reg)
     }
 
-    #[allow(dead_code)]
+    /// Loads the nodes and their connections from 1 TOML file, see NodeRegistry::from_toml_str()
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let content = compression::read_to_string(path)
+            .context(format!("Unable to read topology file {}", path.display()))?;
+        Self::from_toml_str(&content)
+            .context(format!("Invalid topology file {}", path.display()))
+    }
+
+    /// Builds the registry out of a Topology document, e.g.
+    ///
+    /// ```toml
+    /// [[nodes]]
+    /// name = "RAM_0"
+    /// cores = 0
+    /// memory = 16
+    ///
+    /// [[nodes]]
+    /// name = "CPU_0"
+    /// cores = 4
+    /// memory = 4
+    /// rack = "rack0"
+    /// zone = "east"
+    /// options = ["speed=0.5"]
+    ///
+    /// [[connections]]
+    /// borrower = "CPU_0"
+    /// lenders = ["RAM_0"]
+    /// ```
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        let topology: Topology = toml::from_str(content)?;
+        let mut reg = Self::new();
+
+        for node in &topology.nodes {
+            let mut line = format!("{};{};{}", node.name, node.cores, node.memory);
+            if let Some(rack) = &node.rack {
+                line.push_str(&format!(";rack={}", rack));
+            }
+            if let Some(zone) = &node.zone {
+                line.push_str(&format!(";zone={}", zone));
+            }
+            for option in &node.options {
+                line.push(';');
+                line.push_str(option);
+            }
+            reg.new_node_from_str(&line)?;
+        }
+
+        for (i, conn) in topology.connections.iter().enumerate() {
+            let known = |name: &String| reg.registry.contains_key(name);
+            if !known(&conn.borrower) {
+                bail!("Connection {} references the unknown borrower \"{}\"", i, conn.borrower)
+            }
+            if let Some(name) = conn.lenders.iter().find(|name| *name != "*" && !known(name)) {
+                bail!("Connection {} references the unknown lender \"{}\"", i, name)
+            }
+            if conn.lenders.len() > 1 && conn.lenders.iter().any(|name| name == "*") {
+                bail!("Connection {} mixes \"*\" with other lenders", i)
+            }
+
+            let mut line = conn.borrower.clone();
+            for lender in &conn.lenders {
+                line.push(';');
+                line.push_str(lender);
+            }
+            reg.new_connection_from_str(&line)?;
+        }
+
+        Ok(reg)
+    }
+
+    /// Loads the nodes from Kubernetes Node manifests instead of the ';' separated format,
+    /// see kube::nodes_from_str()
+    pub fn from_yaml(path_nodes: &Path, path_connections: &Path) -> Result<Self> {
+        let content = compression::read_to_string(path_nodes)
+            .context(format!("Unable to read nodes file {}", path_nodes.display()))?;
+        let mut reg = Self::new();
+
+        for node in kube::nodes_from_str(&content)? {
+            let uid = reg.new_node_with_resources(&node.name, node.cores, node.memory,
+                                                  node.resources)?.uid;
+            reg.nodes[uid].labels = node.labels;
+        }
+        reg.load_connections(path_connections)?;
+
+        Ok(reg)
+    }
+
+    /// Creates the nodes of @spec in memory instead of reading them from files, the nodes are
+    /// called node0, node1, and so on. The resources of the nodes come from a SimRng seeded with
+    /// @seed so the same @spec and @seed always produce the same cluster
+    pub fn generate(spec: &ClusterSpec, seed: u64) -> Result<Self> {
+        for (name, range) in [("cores", &spec.cores), ("memory", &spec.memory)]
+            .into_iter()
+            .chain(spec.resources.iter().map(|(name, range)| (name.as_str(), range))) {
+            if range.is_empty() {
+                bail!("The range {:?} of {} is empty", range, name)
+            }
+        }
+        if spec.topology == TopologyPattern::FatTree(0) {
+            bail!("The racks of a fat-tree need at least 1 node")
+        }
+
+        let mut rng = SimRng::seed_from_u64(seed);
+        let mut reg = Self::new();
+
+        for i in 0..spec.nodes {
+            let cores = rng.gen_range(spec.cores.clone()) as f32;
+            let memory = rng.gen_range(spec.memory.clone()) as f32;
+            let resources = spec.resources
+                .iter()
+                .map(|(name, range)| (name.clone(), rng.gen_range(range.clone()) as f32))
+                .collect();
+            let uid = reg.new_node_with_resources(&format!("node{}", i), cores, memory,
+                                                  resources)?.uid;
+
+            if let TopologyPattern::FatTree(arity) = spec.topology {
+                reg.nodes[uid].rack = Some(format!("rack{}", i / arity));
+                reg.nodes[uid].zone = Some(format!("zone{}", i / (arity * arity)));
+            }
+        }
+
+        let n = spec.nodes;
+        for uid in 0..n {
+            let mut lenders: Vec<NodeId> = match spec.topology {
+                TopologyPattern::FullyConnected => (0..n).filter(|other| *other != uid).collect(),
+                TopologyPattern::Ring => vec![(uid + n - 1) % n, (uid + 1) % n],
+                TopologyPattern::FatTree(arity) => {
+                    let rack = uid / arity * arity;
+                    (rack..(rack + arity).min(n)).filter(|other| *other != uid).collect()
+                }
+            };
+            // VV: Rings of 1 or 2 nodes would otherwise repeat lenders or lend to themselves
+            lenders.retain(|other| *other != uid);
+            lenders.dedup();
+            reg.new_connection(uid, lenders)?;
+        }
+
+        Ok(reg)
+    }
+
     pub fn nodes_mut(&mut self) -> &mut Vec<Node> { &mut self.nodes }
 
-    #[allow(dead_code)]
     pub fn nodes_immut(&self) -> &Vec<Node> { &self.nodes }
 
     fn register_node(&mut self, name: &str) -> Result<usize> {
@@ -168,7 +395,6 @@ impl NodeRegistry {
         self.sorted_memory.insert(idx, node.uid);
     }
 
-    #[allow(dead_code)]
     pub fn nodes_sorted_cores(&self, at_least: f32) -> impl Iterator<Item=&Node> {
         let cores_at_least = |n: &Node| -> bool { n.cores.current < at_least };
         let idx = self.index_bisect_right(&self.sorted_cores, cores_at_least, None, None);
@@ -179,23 +405,188 @@ impl NodeRegistry {
             .map(|idx| &self.nodes[*idx])
     }
 
+    /// Re-sorts the indices of nodes if any node has changed its free resources since the last sort
+    pub fn ensure_sorted(&mut self) {
+        // VV: Nodes joined the registry since the indices were last built
+        if self.indexed.len() != self.nodes.len() {
+            self.rebuild_index();
+        }
+
+        // VV: Moving nodes only works if the nodes with the same free resources are in node id
+        // order, insort_cores() and insort_memory() put new nodes before them instead
+        if self.is_dirty || (!self.touched.is_empty() && !self.in_id_order) {
+            self.resort_nodes_cores();
+            self.resort_nodes_memory();
+            self.touched.clear();
+            self.rebuild_index();
+            // VV: It's now safe to use the sorted indices
+            self.is_dirty = false;
+        } else if !self.touched.is_empty() {
+            let mut touched = std::mem::take(&mut self.touched);
+            touched.sort_unstable();
+            touched.dedup();
+            for uid in touched {
+                self.move_touched(uid);
+            }
+        }
+    }
+
+    /// Records that the free cores or memory of node @uid changed. Unlike is_dirty, which
+    /// re-sorts all nodes, ensure_sorted() then only moves the touched nodes in the indices
+    pub fn touch(&mut self, uid: NodeId) {
+        self.touched.push(uid);
+    }
+
+    /// Rebuilds indexed, in_id_order, and max_memory out of the sorted indices, which must match
+    /// the current free resources of the nodes
+    pub fn rebuild_index(&mut self) {
+        self.indexed = self.nodes
+            .iter()
+            .map(|node| (node.cores.current, node.memory.current))
+            .collect();
+
+        let indexed = &self.indexed;
+        let in_id_order = |order: &[NodeId], key: fn(&(f32, f32)) -> f32| {
+            order.windows(2).all(|pair| {
+                let (a, b) = (key(&indexed[pair[0]]), key(&indexed[pair[1]]));
+                a < b || (a == b && pair[0] < pair[1])
+            })
+        };
+        self.in_id_order = in_id_order(&self.sorted_cores, |(cores, _)| *cores)
+            && in_id_order(&self.sorted_memory, |(_, memory)| *memory);
+
+        let size = self.sorted_cores.len().next_power_of_two();
+        self.max_memory = vec![f32::NEG_INFINITY; 2 * size];
+        for (idx, uid) in self.sorted_cores.iter().enumerate() {
+            self.max_memory[size + idx] = self.nodes[*uid].memory.current;
+        }
+        for idx in (1..size).rev() {
+            self.max_memory[idx] = self.max_memory[2 * idx].max(self.max_memory[2 * idx + 1]);
+        }
+    }
+
+    /// Moves node @uid from the place of its indexed resources in the sorted indices to the
+    /// place of its current ones and updates max_memory for the nodes in between
+    fn move_touched(&mut self, uid: NodeId) {
+        let (old_cores, old_memory) = self.indexed[uid];
+        let node = &self.nodes[uid];
+        let (cores, memory) = (node.cores.current, node.memory.current);
+        self.indexed[uid] = (cores, memory);
+
+        let indexed = &self.indexed;
+        let from = Self::remove_sorted(&mut self.sorted_cores, uid, old_cores,
+                                       |other| indexed[other].0);
+        let to = Self::insert_sorted(&mut self.sorted_cores, uid, cores,
+                                     |other| indexed[other].0);
+        Self::remove_sorted(&mut self.sorted_memory, uid, old_memory, |other| indexed[other].1);
+        Self::insert_sorted(&mut self.sorted_memory, uid, memory, |other| indexed[other].1);
+
+        let size = self.max_memory.len() / 2;
+        let (mut lo, mut hi) = (size + from.min(to), size + from.max(to));
+        for idx in lo..=hi {
+            self.max_memory[idx] = self.nodes[self.sorted_cores[idx - size]].memory.current;
+        }
+        while lo > 1 {
+            (lo, hi) = (lo / 2, hi / 2);
+            for idx in lo..=hi {
+                self.max_memory[idx] = self.max_memory[2 * idx].max(self.max_memory[2 * idx + 1]);
+            }
+        }
+    }
+
+    /// Removes @uid from the @order which is sorted by @key and then by node id, @value is the
+    /// key of @uid. Returns the place that @uid had
+    fn remove_sorted<F>(order: &mut Vec<NodeId>, uid: NodeId, value: f32, key: F) -> usize
+        where
+            F: Fn(NodeId) -> f32,
+    {
+        let idx = order.partition_point(|other| {
+            key(*other) < value || (key(*other) == value && *other < uid)
+        });
+        // VV: Nodes that changed without touch() or is_dirty may be out of place
+        let idx = match order.get(idx) {
+            Some(other) if *other == uid => idx,
+            _ => order.iter().position(|other| *other == uid).unwrap(),
+        };
+        order.remove(idx);
+        idx
+    }
+
+    /// Inserts @uid with the key @value into the @order which is sorted by @key and then by node
+    /// id. Returns the place of @uid
+    fn insert_sorted<F>(order: &mut Vec<NodeId>, uid: NodeId, value: f32, key: F) -> usize
+        where
+            F: Fn(NodeId) -> f32,
+    {
+        let idx = order.partition_point(|other| {
+            key(*other) < value || (key(*other) == value && *other < uid)
+        });
+        order.insert(idx, uid);
+        idx
+    }
+
+    /// Returns the first place in sorted_cores from @start on whose node has at least @memory
+    /// free memory in O(log n), the indices must be up to date (see ensure_sorted())
+    pub fn first_with_memory(&self, start: usize, memory: f32) -> Option<usize> {
+        if start >= self.sorted_cores.len() {
+            return None;
+        }
+
+        let size = self.max_memory.len() / 2;
+        let mut idx = size + start;
+        // VV: Climb until the subtree of idx, which lies right of start, has enough memory
+        while self.max_memory[idx] < memory {
+            while idx % 2 == 1 {
+                idx /= 2;
+            }
+            if idx == 0 {
+                return None;
+            }
+            idx += 1;
+        }
+
+        while idx < size {
+            idx = if self.max_memory[2 * idx] >= memory { 2 * idx } else { 2 * idx + 1 };
+        }
+        Some(idx - size)
+    }
+
+    /// Returns the nodes with at least @cores free cores and @memory free memory in ascending
+    /// order of free cores (then node id). Finding each node takes O(log n) instead of a scan
+    /// over all nodes with enough cores
+    pub fn nodes_with_at_least(&mut self, cores: f32, memory: f32)
+        -> impl Iterator<Item=NodeId> + '_ {
+        self.ensure_sorted();
+        let registry: &Self = self;
+        let start = registry.idx_nodes_with_more_cores(cores);
+
+        std::iter::successors(registry.first_with_memory(start, memory),
+                              move |idx| registry.first_with_memory(idx + 1, memory))
+            .map(move |idx| registry.sorted_cores[idx])
+    }
+
+    /// Sorts the node indices by ascending free cores, nodes with the same free cores are in
+    /// ascending node id order
     pub fn resort_nodes_cores(&mut self) {
         self.sorted_cores.sort_by(|idx1: &usize, idx2: &usize| -> std::cmp::Ordering {
             self.nodes[*idx1].cores.current.partial_cmp(
                 &self.nodes[*idx2].cores.current)
                 .unwrap_or(std::cmp::Ordering::Equal)
+                .then(idx1.cmp(idx2))
         });
     }
 
+    /// Sorts the node indices by ascending free memory, nodes with the same free memory are in
+    /// ascending node id order
     pub fn resort_nodes_memory(&mut self) {
         self.sorted_memory.sort_by(|idx1: &usize, idx2: &usize| -> std::cmp::Ordering {
             self.nodes[*idx1].memory.current.partial_cmp(
                 &self.nodes[*idx2].memory.current)
                 .unwrap_or(std::cmp::Ordering::Equal)
+                .then(idx1.cmp(idx2))
         });
     }
 
-    #[allow(dead_code)]
     pub fn nodes_sorted_memory(&self, at_least: f32) -> impl Iterator<Item=&Node> {
         let memory_at_least = |n: &Node| -> bool { n.memory.current < at_least };
         let idx = self.index_bisect_right(&self.sorted_memory, memory_at_least, None, None);
@@ -206,7 +597,6 @@ impl NodeRegistry {
             .map(|idx| &self.nodes[*idx])
     }
 
-    #[allow(dead_code)]
     pub fn idx_sorted_cores(&self, node: &Node) -> usize {
         let cores_target = node.cores.current;
         let uid = node.uid;
@@ -241,7 +631,6 @@ impl NodeRegistry {
         self.sorted_cores.partition_point(pred)
     }
 
-    #[allow(dead_code)]
     pub fn idx_sorted_memory(&self, node: &Node) -> usize {
         let memory_target = node.memory.current;
         let uid = node.uid;
@@ -340,10 +729,27 @@ impl NodeRegistry {
     }
 
     pub fn new_node_from_str(&mut self, line: &str) -> Result<&Node> {
-        // VV: format is <name>;<cores>;<memory>
+        // VV: format is <name>;<cores>;<memory>[;<key>=<value>]*
+        // the optional key=value tokens are:
+        // - speed=<f32>: jobs on this node take duration / speed seconds, defaults to 1
+        // - bandwidth=<f32>: the link that stages the input data of jobs to this node moves this
+        //   much data (in units of memory) per second, defaults to infinite
+        // - idle_watts=<f32> and max_watts=<f32>: the power draw of the node when its cores are
+        //   idle and when they are all busy, they go together and default to 0
+        // - cost=<f32>: what the node costs per hour while it is part of the cluster, defaults
+        //   to 0
+        // - label:<key>=<value>: a label that the selectors of jobs match (e.g. label:gpu=true)
+        // - oversubscribe=<f32>: jobs may allocate this many times the memory of the node, the
+        //   jobs that start while it is overcommitted run slower, defaults to 1
+        // - partitions=<str>[,<str>]*: the partitions that the node belongs to, defaults to none
+        // - cooldown=<time>: the node accepts no new jobs for this long after a job stops using
+        //   its cores, defaults to 0
+        // - rack=<str> and zone=<str>: where the node sits in the hierarchy of the datacenter,
+        //   all nodes of a rack are in the same zone, default to none
+        // - <resource>=<f32>: capacity of a named resource (e.g. gpus=4)
         let tokens: Vec<_> = line.split(';').map(|s| s.trim()).collect();
 
-        if tokens.len() != 3 {
+        if tokens.len() < 3 {
             bail!("Expected that \"{}\" contained <name:str>;<cores:f32>;<memory:f32>, \
                 instead got {:?}", line, tokens)
         }
@@ -364,7 +770,135 @@ impl NodeRegistry {
             bail!("Unable to parse {} into memory:f32", tokens[2])
         }
 
-        self.new_node(name, cores, memory)
+        let mut resources: BTreeMap<String, f32> = BTreeMap::new();
+        let mut speed: Option<f32> = None;
+        let mut bandwidth: Option<f32> = None;
+        let mut idle_watts: Option<f32> = None;
+        let mut max_watts: Option<f32> = None;
+        let mut cost: Option<f32> = None;
+        let mut oversubscribe: Option<f32> = None;
+        let mut cooldown: Option<f32> = None;
+        let mut labels: BTreeMap<String, String> = BTreeMap::new();
+        let mut partitions: BTreeSet<String> = BTreeSet::new();
+        let mut rack: Option<String> = None;
+        let mut zone: Option<String> = None;
+
+        for token in tokens.iter().skip(3).filter(|t| !t.is_empty()) {
+            let (key, value) = match token.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => bail!("Expected <key>=<value> but found \"{}\" in \"{}\"", token, line),
+            };
+
+            if let Some(label) = key.strip_prefix("label:") {
+                if label.is_empty() {
+                    bail!("Expected label:<key>=<value> but found \"{}\" in \"{}\"", token, line)
+                }
+                labels.insert(label.to_owned(), value.to_owned());
+                continue;
+            }
+
+            match key {
+                "cores" | "memory" => bail!("Use the {} column instead of \"{}\"", key, token),
+                "speed" => match value.parse::<f32>() {
+                    Ok(c) if c > 0.0 && c.is_finite() => speed = Some(c),
+                    _ => bail!("speed must be a positive f32 but found \"{}\" in \"{}\"",
+                        value, line),
+                },
+                "bandwidth" => match value.parse::<f32>() {
+                    Ok(c) if c > 0.0 => bandwidth = Some(c),
+                    _ => bail!("bandwidth must be a positive f32 but found \"{}\" in \"{}\"",
+                        value, line),
+                },
+                "oversubscribe" => match value.parse::<f32>() {
+                    Ok(c) if c >= 1.0 && c.is_finite() => oversubscribe = Some(c),
+                    _ => bail!("oversubscribe must be an f32 no smaller than 1 but found \"{}\" \
+                        in \"{}\"", value, line),
+                },
+                "partitions" => {
+                    for partition in value.split(',').map(|p| p.trim()) {
+                        if partition.is_empty() {
+                            bail!("Empty partition name in \"{}\"", line)
+                        }
+                        partitions.insert(partition.to_owned());
+                    }
+                }
+                "rack" | "zone" if value.is_empty() => bail!("Empty {} name in \"{}\"", key, line),
+                "rack" => rack = Some(value.to_owned()),
+                "zone" => zone = Some(value.to_owned()),
+                "cooldown" => match parse_time(value) {
+                    Ok(c) => cooldown = Some(c),
+                    _ => bail!("cooldown must be a non-negative f32 but found \"{}\" in \"{}\"",
+                        value, line),
+                },
+                "cost" => match value.parse::<f32>() {
+                    Ok(c) if c >= 0.0 && c.is_finite() => cost = Some(c),
+                    _ => bail!("cost must be a non-negative f32 but found \"{}\" in \"{}\"",
+                        value, line),
+                },
+                "idle_watts" | "max_watts" => match value.parse::<f32>() {
+                    Ok(c) if c >= 0.0 && c.is_finite() => {
+                        if key == "idle_watts" { idle_watts = Some(c) } else { max_watts = Some(c) }
+                    }
+                    _ => bail!("{} must be a non-negative f32 but found \"{}\" in \"{}\"",
+                        key, value, line),
+                },
+                _ => match value.parse() {
+                    Ok(c) => {
+                        resources.insert(key.to_owned(), c);
+                    }
+                    Err(_) => bail!("Unable to parse {} into {}:f32", value, key),
+                },
+            }
+        }
+
+        // VV: A rack sits in exactly 1 zone
+        if let Some(rack) = &rack {
+            if let Some(other) = self.nodes.iter().find(|n| n.rack.as_ref() == Some(rack)) {
+                if other.zone != zone {
+                    bail!("Node {} puts rack {} in zone {:?} but node {} puts it in zone {:?}",
+                        name, rack, zone, other.name, other.zone)
+                }
+            }
+        }
+
+        let uid = self.new_node_with_resources(name, cores, memory, resources)?.uid;
+
+        if let Some(speed) = speed {
+            self.nodes[uid].set_speed(speed)?;
+        }
+
+        if let Some(bandwidth) = bandwidth {
+            self.nodes[uid].set_bandwidth(bandwidth)?;
+        }
+
+        if let Some(cooldown) = cooldown {
+            self.nodes[uid].set_cooldown(cooldown)?;
+        }
+
+        if let Some(cost) = cost {
+            self.nodes[uid].set_cost(cost)?;
+        }
+
+        match (idle_watts, max_watts) {
+            (Some(idle_watts), Some(max_watts)) => {
+                self.nodes[uid].set_power(idle_watts, max_watts)?
+            }
+            (None, None) => {}
+            _ => bail!("Expected both idle_watts and max_watts in \"{}\"", line),
+        }
+
+        if let Some(ratio) = oversubscribe {
+            self.nodes[uid].set_oversubscription(ratio)?;
+            self.memory_total[uid] = self.nodes[uid].memory.capacity;
+            self.is_dirty = true;
+        }
+
+        self.nodes[uid].labels = labels;
+        self.nodes[uid].partitions = partitions;
+        self.nodes[uid].rack = rack;
+        self.nodes[uid].zone = zone;
+
+        Ok(&self.nodes[uid])
     }
 
     pub fn new_node(
@@ -373,10 +907,25 @@ impl NodeRegistry {
         cores: f32,
         memory: f32,
         // memory_lendable: f32,
+    ) -> Result<&Node> {
+        self.new_node_with_resources(name, cores, memory, BTreeMap::new())
+    }
+
+    /// Creates a node which also provides the named @resources (e.g. "gpus")
+    pub fn new_node_with_resources(
+        &mut self,
+        name: &str,
+        cores: f32,
+        memory: f32,
+        resources: BTreeMap<String, f32>,
     ) -> Result<&Node> {
         let uid = self.register_node(name)?;
 
-        let node = Node::new(uid, name, cores, memory /*, memory_lendable*/)?;
+        let mut node = Node::new(uid, name, cores, memory /*, memory_lendable*/)?;
+        for (resource, capacity) in &resources {
+            node.set_resource(resource, *capacity)?;
+            self.resource_names.insert(resource.to_owned());
+        }
 
         self.insort_memory(&node);
         self.insort_cores(&node);
@@ -397,6 +946,52 @@ impl NodeRegistry {
         can_borrow + own_memory
     }
 
+    /// Returns the nodes that node @uid is connected to, in either direction
+    pub fn neighbours(&self, uid: NodeId) -> impl Iterator<Item=&NodeId> {
+        self.connections.get(&uid).into_iter().flatten()
+            .chain(self.connections_reverse.get(&uid).into_iter().flatten())
+    }
+
+    /// Returns the number of hops from node @from to every node in the connection graph, the
+    /// direction of connections does not matter. Unreachable nodes get None
+    pub fn hops_from(&self, from: NodeId) -> Vec<Option<usize>> {
+        let mut hops = vec![None; self.nodes.len()];
+        let mut pending = VecDeque::from([from]);
+        hops[from] = Some(0);
+
+        while let Some(uid) = pending.pop_front() {
+            let next = hops[uid].unwrap() + 1;
+            for &neighbour in self.neighbours(uid) {
+                if hops[neighbour].is_none() {
+                    hops[neighbour] = Some(next);
+                    pending.push_back(neighbour);
+                }
+            }
+        }
+
+        hops
+    }
+
+    /// Returns the largest free amount of the named @resource on any node
+    pub fn get_max_resource(&self, resource: &str) -> f32 {
+        self.nodes
+            .iter()
+            .filter_map(|n| n.resources.get(resource))
+            .map(|r| r.current)
+            .fold(0.0, f32::max)
+    }
+
+    /// Returns an error if @job requests a named resource that no node provides, such a job
+    /// could never run
+    pub fn check_resources(&self, job: &Job) -> Result<()> {
+        for (name, amount) in &job.resources {
+            if *amount > 0.0 && !self.resource_names.contains(name) {
+                bail!("Job {} requests {} {} but no node provides {}", job.uid, amount, name, name)
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_max_cores_memory(&self) -> (f32, f32) {
         let uid_max_cores = *self.sorted_cores.last().unwrap();
         let max_cores = self.nodes[uid_max_cores].cores.current;
@@ -413,4 +1008,228 @@ impl NodeRegistry {
 
         (max_cores, max_memory)
     }
+
+    /// Returns a copy of the free resources of each node
+    pub fn free_resources(&self) -> FreeResources {
+        FreeResources {
+            cores: self.nodes.iter().map(|n| n.cores.current).collect(),
+            memory: self.nodes.iter().map(|n| n.memory.current).collect(),
+            named: self.nodes
+                .iter()
+                .map(|n| n.resources.iter().map(|(k, r)| (k.clone(), r.current)).collect())
+                .collect(),
+        }
+    }
+
+    /// Returns the resources of each node when it hosts no jobs and has not failed
+    pub fn capacity_resources(&self) -> FreeResources {
+        FreeResources {
+            cores: self.nodes.iter().map(|n| n.cores.capacity).collect(),
+            memory: self.nodes.iter().map(|n| n.memory.capacity).collect(),
+            named: self.nodes
+                .iter()
+                .map(|n| n.resources.iter().map(|(k, r)| (k.clone(), r.capacity)).collect())
+                .collect(),
+        }
+    }
+
+    /// Returns an error that explains why @job could not run even if all nodes had the @capacity
+    /// of capacity_resources(), no amount of waiting will let such a job start
+    pub fn check_fits(&self, job: &Job, capacity: &FreeResources) -> Result<()> {
+        if self.fits_with(job, capacity).is_some() {
+            return Ok(());
+        }
+
+        if !self.nodes.iter().any(|node| node.matches(&job.selector)) {
+            let selector: Vec<String> = job.selector.iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            bail!("Job {} selects the labels {} but no node has all of them", job.uid,
+                selector.join(","))
+        }
+
+        if !self.nodes.iter().any(|node| node.in_partition(job)) {
+            bail!("Job {} targets the partition {} but no node belongs to it", job.uid,
+                job.partition.as_deref().unwrap_or_default())
+        }
+
+        let (cores, where_) = if job.gang {
+            (capacity.cores.iter().sum(), "the cluster")
+        } else {
+            (capacity.cores.iter().copied().fold(0.0, f32::max), "the largest node")
+        };
+
+        if job.cores > cores {
+            bail!("Job {} requests {} cores but {} has {}", job.uid, job.cores, where_, cores)
+        }
+
+        for (name, amount) in &job.resources {
+            let most = capacity.named.iter().filter_map(|n| n.get(name)).copied();
+            let most = if job.gang { most.sum() } else { most.fold(0.0, f32::max) };
+
+            if *amount > most {
+                bail!("Job {} requests {} {} but {} has {}", job.uid, amount, name, where_, most)
+            }
+        }
+
+        bail!("Job {} requests {} memory but no node can provide it along with {} cores",
+            job.uid, job.memory, job.cores)
+    }
+
+    /// Returns why @job cannot start if the nodes have the @free resources, BlockReason::Policy if
+    /// it fits. Label and Partition mean that the job would fit on nodes outside its selector or
+    /// partition
+    pub fn block_reason(&self, job: &Job, free: &FreeResources) -> BlockReason {
+        let has_cores = |uids: &[NodeId]| if job.gang {
+            uids.iter().map(|uid| free.cores[*uid]).sum::<f32>() >= job.cores
+        } else {
+            uids.iter().any(|uid| free.cores[*uid] >= job.cores)
+        };
+        let uids = |keep: &dyn Fn(&Node) -> bool| -> Vec<NodeId> {
+            self.nodes.iter().filter(|node| keep(node)).map(|node| node.uid).collect()
+        };
+
+        let eligible = uids(&|node| node.matches(&job.selector) && node.in_partition(job));
+        let accepting: Vec<NodeId> = eligible.iter()
+            .copied()
+            .filter(|uid| self.nodes[*uid].accepts(job))
+            .collect();
+
+        if !eligible.is_empty() && accepting.is_empty() {
+            return if job.exclusive { BlockReason::Exclusive } else { BlockReason::AntiAffinity };
+        }
+
+        if !has_cores(&accepting) {
+            if !job.selector.is_empty() && has_cores(&uids(&|node| node.in_partition(job))) {
+                return BlockReason::Label;
+            }
+            if job.partition.is_some() && has_cores(&uids(&|node| node.matches(&job.selector))) {
+                return BlockReason::Partition;
+            }
+            return BlockReason::Cores;
+        }
+
+        if !job.gang {
+            let with_cores: Vec<NodeId> = accepting.into_iter()
+                .filter(|uid| free.cores[*uid] >= job.cores)
+                .collect();
+            for (name, amount) in job.resources.iter().filter(|(_, amount)| **amount > 0.0) {
+                let has = |uid: &NodeId| free.named[*uid].get(name).is_some_and(|f| f >= amount);
+                if !with_cores.iter().any(has) {
+                    return BlockReason::Resource(name.clone());
+                }
+            }
+        }
+
+        match self.fits_with(job, free) {
+            Some(_) => BlockReason::Policy,
+            None => BlockReason::Memory,
+        }
+    }
+
+    /// Returns the nodes that @job would use if the nodes had the @free resources,
+    /// use this to reason about hypothetical states of the cluster
+    pub fn fits_with(&self, job: &Job, free: &FreeResources) -> Option<HashSet<NodeId>> {
+        if job.gang {
+            if let Some(nodes) = self.gang_fits(job, free) {
+                return Some(nodes);
+            }
+        }
+
+        for uid_cores in 0..self.nodes.len() {
+            if free.cores[uid_cores] < job.cores || !free.has_named(uid_cores, job, 1.0)
+                || !self.nodes[uid_cores].matches(&job.selector)
+                || !self.nodes[uid_cores].in_partition(job) {
+                continue;
+            }
+
+            if free.memory[uid_cores] >= job.memory {
+                return Some(HashSet::from([uid_cores]));
+            }
+
+            if !job.can_borrow {
+                continue;
+            }
+
+            let mut nodes = HashSet::from([uid_cores]);
+            let mut rem_mem = job.memory - free.memory[uid_cores];
+
+            for &uid_mem in self.connections.get(&uid_cores).unwrap() {
+                if free.memory[uid_mem] > 0.0 {
+                    nodes.insert(uid_mem);
+                    rem_mem -= rem_mem.min(free.memory[uid_mem]);
+                }
+
+                if rem_mem == 0.0 {
+                    return Some(nodes);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns the nodes that a gang @job would spread over if the nodes had the @free resources
+    fn gang_fits(&self, job: &Job, free: &FreeResources) -> Option<HashSet<NodeId>> {
+        let mut by_cores: Vec<NodeId> = (0..free.cores.len()).collect();
+        by_cores.sort_by(|a, b| free.cores[*b].partial_cmp(&free.cores[*a])
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.cmp(b)));
+
+        let mut nodes = HashSet::new();
+        let mut rem_cores = job.cores;
+
+        for uid in by_cores {
+            let cores = rem_cores.min(free.cores[uid]);
+            if cores <= 0.0 {
+                break;
+            }
+
+            let share = cores / job.cores;
+            if free.memory[uid] >= job.memory * share && free.has_named(uid, job, share)
+                && self.nodes[uid].matches(&job.selector) && self.nodes[uid].in_partition(job) {
+                nodes.insert(uid);
+                rem_cores -= cores;
+                if rem_cores == 0.0 {
+                    return Some(nodes);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// A copy of the free resources of each node, indexed by the uid of the node
+#[derive(Debug, Clone)]
+pub struct FreeResources {
+    pub cores: Vec<f32>,
+    pub memory: Vec<f32>,
+    pub named: Vec<BTreeMap<String, f32>>,
+}
+
+impl FreeResources {
+    /// Returns the resources that @job uses back to the nodes
+    pub fn release(&mut self, job: &Job) {
+        for (uid_cores, cores) in job.cores_per_node() {
+            self.cores[uid_cores] += cores;
+        }
+        for (uid_memory, memory) in &job.node_memory {
+            self.memory[*uid_memory] += memory;
+        }
+        for (uid, share) in job.resource_shares() {
+            for (name, amount) in &job.resources {
+                if let Some(free) = self.named[uid].get_mut(name) {
+                    *free += amount * share;
+                }
+            }
+        }
+    }
+
+    /// Returns true if node @uid has enough free named resources for @share of the requests of @job
+    pub fn has_named(&self, uid: NodeId, job: &Job, share: f32) -> bool {
+        job.resources.iter().all(|(name, amount)| {
+            *amount <= 0.0 || self.named[uid].get(name).is_some_and(|free| *free >= amount * share)
+        })
+    }
 }
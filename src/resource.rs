@@ -17,10 +17,32 @@ specific language governing permissions and limitations
 under the License.
 */
 
-#[derive(Debug)]
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Allocations are accounted in fixed-point units of 1/UNITS so that fractional requests, e.g.
+/// 0.1 cores or 100Mi of memory, add up exactly instead of accumulating f32 rounding errors. The
+/// resolution is 1/1,024,000 of a core or of a unit of memory, so millicores (like those of
+/// Kubernetes) and 1/1024 fractions of memory are both whole multiples of it
+pub const UNITS: f64 = 1_024_000.0;
+
+/// Returns @value in units of 1/UNITS
+pub fn to_units(value: f32) -> i64 {
+    (value as f64 * UNITS).round() as i64
+}
+
+/// Resources other than cores and memory (e.g. "gpus", "licenses") keyed by their name
+pub type NamedResources = BTreeMap<String, Resource>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Resource {
     pub capacity: f32,
+    // VV: What is left of capacity, it is derived from the fixed-point sum of the allocations in
+    // used (see UNITS)
     pub current: f32,
+    used: i64,
     num_allocate: u32,
     // pub lendable: f32,
 }
@@ -40,24 +62,35 @@ impl Resource {
             capacity,
             // lendable: lendable,
             current: capacity,
+            used: 0,
             num_allocate: 0,
         })
     }
     pub fn allocate(&mut self, value: f32) {
-        self.current -= value;
+        self.used += to_units(value);
         self.num_allocate += 1;
+        self.update_current();
 
-        assert!(self.current >= 0.)
+        // VV: Each allocation rounds to the closest unit, those of capacity may be off by 1/2 unit
+        let slack = self.num_allocate as f64;
+        assert!(self.used as f64 <= self.capacity as f64 * UNITS + slack)
     }
 
     pub fn free(&mut self, value: f32) {
         self.num_allocate -= 1;
         if self.num_allocate == 0 {
+            self.used = 0;
             self.current = self.capacity;
             return;
         }
-        self.current += value;
+        self.used -= to_units(value);
+        self.update_current();
+
+        assert!(self.used >= 0)
+    }
 
-        assert!(self.current <= self.capacity)
+    fn update_current(&mut self) {
+        let current = self.capacity as f64 - self.used as f64 / UNITS;
+        self.current = current.clamp(0.0, self.capacity as f64) as f32;
     }
 }
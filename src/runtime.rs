@@ -0,0 +1,89 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+use anyhow::bail;
+use anyhow::Result;
+use rand::Rng;
+use rand_distr::Distribution;
+use rand_distr::LogNormal;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::scheduler::SimRng;
+
+/// Perturbs the declared duration of Jobs into the duration they actually run for. The declared
+/// duration remains the estimate of the Job, so policies like Backfill still plan with it.
+///
+/// In a config file this is a table whose distribution key picks the variant, e.g.
+/// `{ distribution = "lognormal", sigma = 0.5 }`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "distribution", rename_all = "snake_case", deny_unknown_fields)]
+pub enum RuntimeModel {
+    /// The actual duration is the declared one times a lognormal factor whose median is @scale
+    /// (defaults to 1) and whose logarithm has the standard deviation @sigma
+    Lognormal {
+        sigma: f32,
+        #[serde(default = "default_scale")]
+        scale: f32,
+    },
+    /// The actual duration is the declared one times a factor in [@low, @high)
+    Uniform { low: f32, high: f32 },
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+impl RuntimeModel {
+    /// Returns an error if the parameters do not describe a distribution of positive factors
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            RuntimeModel::Lognormal { sigma, scale } => {
+                if !(sigma >= 0.0 && sigma.is_finite()) {
+                    bail!("The sigma of the lognormal runtime model must be finite and not \
+                        negative but it is {}", sigma)
+                }
+                if !(scale > 0.0 && scale.is_finite()) {
+                    bail!("The scale of the lognormal runtime model must be positive but it is {}",
+                        scale)
+                }
+            }
+            RuntimeModel::Uniform { low, high } => {
+                if !(low > 0.0 && low < high && high.is_finite()) {
+                    bail!("The uniform runtime model needs 0 < low < high but found low={} and \
+                        high={}", low, high)
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draws the actual duration of a Job whose declared duration is @declared
+    pub fn sample(&self, declared: f32, rng: &mut SimRng) -> f32 {
+        let factor = match *self {
+            RuntimeModel::Lognormal { sigma, scale } => LogNormal::new(scale.ln(), sigma)
+                .expect("Invalid lognormal runtime model")
+                .sample(rng),
+            RuntimeModel::Uniform { low, high } => rng.gen_range(low..high),
+        };
+
+        declared * factor
+    }
+}
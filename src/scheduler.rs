@@ -16,44 +16,2216 @@ KIND, either express or implied.  See the License for the
 specific language governing permissions and limitations
 under the License.
 */
+use std::cmp::Ordering;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::path::Path;
+use std::str::FromStr;
 
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::debug;
+use tracing::info;
+
+use crate::backfill::Backfill;
+use crate::failures::ConcurrencyLimit;
+use crate::failures::HoldEvent;
+use crate::failures::NodeFailure;
+use crate::failures::NodeMaintenance;
+use crate::failures::PartitionLimits;
+use crate::failures::RemoveMode;
+use crate::failures::Reservation;
+use crate::failures::ScalingAction;
+use crate::failures::ScalingEvent;
+use crate::failures::check_scaling;
+use crate::fairshare::FairShare;
+use crate::gantt::JobSpan;
+use crate::runtime::RuntimeModel;
+use crate::snapshot::ClusterSnapshot;
+use crate::summary::ExitSummary;
+use crate::summary::FragmentationSample;
+use crate::summary::JobTimes;
+use crate::summary::RunSummary;
+use crate::summary::RunningJobStatus;
+use crate::summary::ThroughputSample;
+use crate::summary::WaitHistogram;
+use crate::job::BlockReason;
+use crate::job::Deadlock;
 use crate::job::Job;
+use crate::job::JobId;
 use crate::job_factory::JobFactory;
+use crate::node::Held;
+use crate::node::NodeId;
+use crate::profile;
+use crate::profile::PlacementProfile;
+use crate::registry::FreeResources;
 use crate::registry::NodeRegistry;
+use crate::resource::NamedResources;
+use crate::resource::Resource;
+
+/// The nodes that a Job uses, @node_cores provides the cores and @node_memory lists the
+/// nodes that the Job gets its memory from along with the amount of memory on each node.
+/// Gang jobs which span multiple nodes also list the cores they use on each node in @gang_cores
+/// (@node_cores is then the first of those nodes).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    pub node_cores: NodeId,
+    pub node_memory: Vec<(NodeId, f32)>,
+    pub gang_cores: Vec<(NodeId, f32)>,
+}
+
+impl Placement {
+    /// Returns the fraction of the named resources of @job that each node of the Placement provides
+    pub fn resource_shares(&self, job: &Job) -> Vec<(NodeId, f32)> {
+        if self.gang_cores.is_empty() {
+            vec![(self.node_cores, 1.0)]
+        } else {
+            self.gang_cores
+                .iter()
+                .map(|(uid, cores)| (*uid, cores / job.cores))
+                .collect()
+        }
+    }
+
+    /// Returns the sum of the hops from node_cores to every other node of the Placement, or None
+    /// if some of the nodes are not connected to node_cores
+    pub fn hop_distance(&self, registry: &NodeRegistry) -> Option<usize> {
+        let mut others: Vec<NodeId> = self.gang_cores
+            .iter()
+            .chain(self.node_memory.iter())
+            .map(|(uid, _)| *uid)
+            .filter(|uid| *uid != self.node_cores)
+            .collect();
+        others.sort_unstable();
+        others.dedup();
+
+        // VV: Lenders are always 1 hop away, only look further for the pieces of gang jobs
+        let direct: HashSet<NodeId> = registry.neighbours(self.node_cores).copied().collect();
+        if others.iter().all(|uid| direct.contains(uid)) {
+            return Some(others.len());
+        }
+
+        let hops = registry.hops_from(self.node_cores);
+        others.iter().map(|uid| hops[*uid]).sum()
+    }
+
+    /// Returns the seconds it takes to stage @input_data to the nodes that provide cores to the
+    /// Placement, every piece of a gang job receives all of the data over its own link
+    pub fn staging(&self, registry: &NodeRegistry, input_data: f32) -> f32 {
+        if input_data <= 0.0 {
+            return 0.0;
+        }
+
+        let bandwidth = self.gang_cores
+            .iter()
+            .map(|(uid, _)| *uid)
+            .chain(std::iter::once(self.node_cores))
+            .map(|uid| registry.nodes[uid].bandwidth)
+            .fold(f32::INFINITY, f32::min);
+
+        input_data / bandwidth
+    }
+
+    /// Returns the speed of the slowest node that provides cores to the Placement, all pieces
+    /// of a gang job progress at the pace of the slowest one. Jobs also slow down by the
+    /// Node::swap_slowdown() of the node providing memory which swaps the most
+    pub fn speed(&self, registry: &NodeRegistry) -> f32 {
+        let speed = self.gang_cores
+            .iter()
+            .map(|(uid, _)| *uid)
+            .chain(std::iter::once(self.node_cores))
+            .map(|uid| registry.nodes[uid].speed)
+            .fold(f32::INFINITY, f32::min);
+
+        let swap = self.node_memory
+            .iter()
+            .map(|(uid, _)| registry.nodes[*uid].swap_slowdown())
+            .fold(1.0, f32::max);
+
+        speed / swap
+    }
+
+    /// Returns true if every node that the Placement takes cores from is warm for @job, see
+    /// Node::is_warm()
+    pub fn is_warm(&self, registry: &NodeRegistry, job: &Job) -> bool {
+        self.gang_cores
+            .iter()
+            .map(|(uid, _)| *uid)
+            .chain(std::iter::once(self.node_cores))
+            .all(|uid| registry.nodes[uid].is_warm(job))
+    }
+
+    /// Returns true if the Placement takes cores or memory from node @uid
+    pub fn uses(&self, uid: NodeId) -> bool {
+        self.node_cores == uid
+            || self.node_memory.iter().any(|(uid_memory, _)| *uid_memory == uid)
+            || self.gang_cores.iter().any(|(uid_cores, _)| *uid_cores == uid)
+    }
+
+    /// Allocates the resources of @job on the nodes of the Placement
+    pub fn reserve(&self, registry: &mut NodeRegistry, job: &Job) {
+        profile::count_placement();
+        if self.gang_cores.is_empty() {
+            registry.nodes[self.node_cores].allocate_cores(job.cores);
+        } else {
+            for (uid_cores, cores) in &self.gang_cores {
+                registry.nodes[*uid_cores].allocate_cores(*cores);
+            }
+        }
+
+        for (uid_mem, allocated) in &self.node_memory {
+            registry.nodes[*uid_mem].allocate_memory(*allocated);
+        }
+
+        for (uid, share) in self.resource_shares(job) {
+            registry.nodes[uid].allocate_resources(&job.resources, share);
+            if let Some(group) = &job.anti_affinity {
+                registry.nodes[uid].join_group(group);
+            }
+            if job.exclusive {
+                registry.nodes[uid].start_exclusive();
+            }
+        }
+
+        // VV: It's not safe to use the sorted indices until they move the nodes of the Placement
+        for (uid, _) in self.gang_cores.iter().chain(&self.node_memory) {
+            registry.touch(*uid);
+        }
+        registry.touch(self.node_cores);
+    }
+}
+
+/// Places @job with @place while keeping it away from the nodes that run jobs of its
+/// anti-affinity group (see Node::accepts()). When that is impossible the job may share a node
+/// with its group instead of waiting
+pub fn place_apart<F>(registry: &mut NodeRegistry, job: &Job, place: F) -> Option<Placement>
+    where
+        F: Fn(&mut NodeRegistry, &Job) -> Option<Placement>,
+{
+    let placement = place(registry, job);
+    if placement.is_some() || job.anti_affinity.is_none() {
+        return placement;
+    }
+
+    let mut colocated = job.clone();
+    colocated.anti_affinity = None;
+    place(registry, &colocated)
+}
+
+/// Read-only view of the Scheduler that policies may consult when making decisions
+pub struct PolicyContext<'a> {
+    pub now: f32,
+    pub jobs_running: &'a VecDeque<Job>,
+    /// The first @unchanged jobs in the queue were already offered to the policy and no job has
+    /// finished since, policies may skip trying to place them again
+    pub unchanged: usize,
+    /// The decayed core-seconds that the jobs of each user consumed, empty unless the
+    /// Scheduler tracks usage (see SchedulerBuilder::fair_share())
+    pub user_usage: &'a HashMap<String, f64>,
+    /// The share of each user that has one, see SchedulerBuilder::user_weights()
+    pub user_weights: &'a HashMap<String, f64>,
+}
+
+pub trait SchedulingPolicy {
+    /// Decides which of the @queued jobs should start running right now and where.
+    ///
+    /// The policy must reserve the resources of every Placement it returns (see
+    /// Placement::reserve()) so that its subsequent decisions, and the Scheduler, see the
+    /// remaining free resources of @registry.
+    ///
+    /// The Scheduler assumes that a job which the policy skipped cannot start until some
+    /// running job finishes. Until then, it only asks the policy again when new jobs get queued
+    /// and uses PolicyContext::unchanged to point out which jobs the policy has already seen.
+    fn select(
+        &mut self,
+        queued: &[Job],
+        registry: &mut NodeRegistry,
+        context: &PolicyContext,
+    ) -> Vec<(JobId, Placement)>;
+}
+
+/// Visits queued jobs in order and starts every job that fits on the cluster. A job first
+/// tries to fit on a single node, if that is not possible and the job can borrow memory it
+/// may use the memory of the lenders of the node that provides its cores.
+#[derive(Default)]
+pub struct FirstFit {}
+
+impl FirstFit {
+    fn try_allocate_on_many_nodes(
+        registry: &NodeRegistry,
+        idx_cores: usize,
+        job: &Job,
+    ) -> Option<Placement> {
+        let uid_cores = registry.sorted_cores[idx_cores];
+        let lenders = registry.connections.get(&uid_cores).unwrap();
+
+
+        let mut rem_mem = job.memory;
+        let mut mem_alloc: Vec<(usize, f32)> = Vec::new();
+        let node_cores = &registry.nodes[uid_cores];
+
+        profile::count_check();
+        if !node_cores.has_resources(&job.resources, 1.0) || !node_cores.accepts(job) {
+            return None;
+        }
+
+        if node_cores.memory.current > 0.0 {
+            let alloc = rem_mem.min(node_cores.memory.current);
+            mem_alloc.push((uid_cores, alloc));
+
+            rem_mem -= alloc;
+        }
+
+        for uid_mem in lenders {
+            if uid_mem != &uid_cores {
+                let node_mem = &registry.nodes[*uid_mem];
+                let alloc = rem_mem.min(node_mem.memory.current);
+                mem_alloc.push((*uid_mem, alloc));
+
+                rem_mem -= alloc;
+
+                if rem_mem == 0.0 {
+                    break;
+                }
+            }
+        }
+
+        if rem_mem == 0.0 {
+            Some(Placement { node_cores: uid_cores, node_memory: mem_alloc, gang_cores: vec![] })
+        } else {
+            None
+        }
+    }
+
+    fn job_try_allocate(
+        registry: &NodeRegistry,
+        job: &Job,
+        cores_start: usize,
+        cores_end: usize,
+    ) -> Option<Placement> {
+        // VV: Walk the segment tree of NodeRegistry::first_with_memory() to visit only the nodes
+        // with enough cores (those in cores_start..cores_end) that also have enough memory
+        let mut start = cores_start;
+        while let Some(idx) = registry.first_with_memory(start, job.memory)
+            .filter(|idx| *idx < cores_end) {
+            let uid_cores = registry.sorted_cores[idx];
+            let node = &registry.nodes[uid_cores];
+            profile::count_check();
+            if node.memory.current >= job.memory && node.has_resources(&job.resources, 1.0)
+                && node.accepts(job) {
+                return Some(Placement {
+                    node_cores: uid_cores,
+                    node_memory: vec![(uid_cores, job.memory)],
+                    gang_cores: vec![],
+                });
+            }
+            start = idx + 1;
+        }
+
+        None
+    }
+
+    /// Splits the cores of a gang @job over as few nodes as possible, each piece gets a share of
+    /// the memory that is proportional to its cores and must get it from the node that hosts it
+    pub fn gang_try_allocate(registry: &NodeRegistry, job: &Job) -> Option<Placement> {
+        let mut rem_cores = job.cores;
+        let mut rem_mem = job.memory;
+        let mut cores_alloc: Vec<(usize, f32)> = Vec::new();
+        let mut mem_alloc: Vec<(usize, f32)> = Vec::new();
+
+        for &uid in registry.sorted_cores.iter().rev() {
+            let node = &registry.nodes[uid];
+            let cores = rem_cores.min(node.cores.current);
+
+            if cores <= 0.0 {
+                break;
+            }
+
+            let memory = if cores == rem_cores { rem_mem } else { job.memory * cores / job.cores };
+
+            profile::count_check();
+            if node.memory.current < memory || !node.has_resources(&job.resources, cores / job.cores)
+                || !node.accepts(job) {
+                continue;
+            }
+
+            cores_alloc.push((uid, cores));
+            mem_alloc.push((uid, memory));
+            rem_cores -= cores;
+            rem_mem -= memory;
+
+            if rem_cores == 0.0 {
+                return Some(Placement {
+                    node_cores: cores_alloc[0].0,
+                    node_memory: mem_alloc,
+                    gang_cores: cores_alloc,
+                });
+            }
+        }
+
+        None
+    }
+
+    /// Finds where @job could run using the currently free resources of @registry
+    pub fn job_place(registry: &mut NodeRegistry, job: &Job) -> Option<Placement> {
+        place_apart(registry, job, Self::job_place_once)
+    }
+
+    fn job_place_once(registry: &mut NodeRegistry, job: &Job) -> Option<Placement> {
+        registry.ensure_sorted();
+
+        let cores_start = registry.idx_nodes_with_more_cores(job.cores);
+        if cores_start == registry.sorted_cores.len() {
+            if job.gang {
+                return Self::gang_try_allocate(registry, job);
+            }
+            return None;
+        }
+
+        // VV: First ty to fit job on any single node
+        let mut ret = Self::job_try_allocate(registry, job, cores_start,
+                                             registry.sorted_cores.len());
+
+        // VV: If that's not possible, and the job can borrow resources, then try scheduling it
+        // using multiple nodes
+        if ret.is_none() && job.can_borrow {
+            for idx_cores in cores_start..registry.sorted_cores.len() {
+                ret = Self::try_allocate_on_many_nodes(registry, idx_cores, job);
+                if ret.is_some() {
+                    break
+                }
+            }
+        }
+
+        ret
+    }
+}
+
+impl SchedulingPolicy for FirstFit {
+    fn select(
+        &mut self,
+        queued: &[Job],
+        registry: &mut NodeRegistry,
+        context: &PolicyContext,
+    ) -> Vec<(JobId, Placement)> {
+        let mut selected = vec![];
+        let (mut max_cores, mut max_memory) = registry.get_max_cores_memory();
+
+        // println!("Cores: {}, Memory: {}", max_cores, max_memory);
+
+        for job in queued.iter().skip(context.unchanged) {
+            if !job.gang && (job.cores > max_cores || job.memory > max_memory) {
+                continue;
+            }
+
+            profile::count_attempt();
+            if let Some(placement) = Self::job_place(registry, job) {
+                placement.reserve(registry, job);
+                // println!("Scheduling {}x{} on {:?}", job.cores, job.memory, placement);
+                selected.push((job.uid, placement));
+                (max_cores, max_memory) = registry.get_max_cores_memory();
+            }
+        }
+
+        selected
+    }
+}
+
+/// A point in simulated time at which the state of the Scheduler changes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    /// The next Job of the JobFactory is created
+    Arrival(f32),
+    /// A running Job finishes
+    Completion(f32, JobId),
+    /// A node fails, its running jobs go back to the queue
+    NodeFailure(f32, NodeId),
+    /// A failed node becomes available again
+    NodeRecovery(f32, NodeId),
+    /// A node stops accepting new jobs ahead of its maintenance, see NodeMaintenance
+    NodeDrain(f32, NodeId),
+    /// The maintenance of a node ends and it accepts new jobs again
+    NodeReturn(f32, NodeId),
+    /// A node joins or leaves the cluster, the index points to Scheduler::scaling
+    NodeScaling(f32, usize),
+    /// A reservation starts or ends, the index points to Scheduler::reservations
+    ReservationStart(f32, usize),
+    ReservationEnd(f32, usize),
+    /// The scheduler places jobs, see Scheduler::schedule_interval and Scheduler::decision_latency
+    SchedulingCycle(f32),
+    /// A job is put on hold or released, the index points to Scheduler::holds
+    JobHold(f32, usize),
+    /// A running job may become a straggler, see Scheduler::speculation
+    Speculation(f32),
+    /// The cooldown of a node may be over, see Node::cooldown
+    NodeCooled(f32, NodeId),
+    /// A job gives up if it is still waiting, see Job::max_queue_time
+    QueueTimeout(f32, JobId),
+    /// A running job reaches the next step of its Job::memory_profile
+    MemoryStep(f32, JobId),
+}
+
+impl Event {
+    pub fn time(&self) -> f32 {
+        match self {
+            Event::Arrival(time) => *time,
+            Event::Completion(time, _) => *time,
+            Event::NodeFailure(time, _) => *time,
+            Event::NodeRecovery(time, _) => *time,
+            Event::NodeDrain(time, _) => *time,
+            Event::NodeReturn(time, _) => *time,
+            Event::NodeScaling(time, _) => *time,
+            Event::ReservationStart(time, _) => *time,
+            Event::ReservationEnd(time, _) => *time,
+            Event::SchedulingCycle(time) => *time,
+            Event::JobHold(time, _) => *time,
+            Event::Speculation(time) => *time,
+            Event::NodeCooled(time, _) => *time,
+            Event::QueueTimeout(time, _) => *time,
+            Event::MemoryStep(time, _) => *time,
+        }
+    }
+}
+
+impl Eq for Event {}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Event {
+    /// Events are ordered by their time, then arrivals, completions, failures, recoveries,
+    /// drains, returns, scaling, reservations, scheduling cycles, holds, speculation, cooldowns,
+    /// queue timeouts, and memory steps
+    fn cmp(&self, other: &Self) -> Ordering {
+        let rank = |event: &Event| match event {
+            Event::Arrival(_) => (0, 0),
+            Event::Completion(_, uid) => (1, *uid),
+            Event::NodeFailure(_, uid) => (2, *uid),
+            Event::NodeRecovery(_, uid) => (3, *uid),
+            Event::NodeDrain(_, uid) => (4, *uid),
+            Event::NodeReturn(_, uid) => (5, *uid),
+            Event::NodeScaling(_, idx) => (6, *idx),
+            Event::ReservationStart(_, idx) => (7, *idx),
+            Event::ReservationEnd(_, idx) => (8, *idx),
+            Event::SchedulingCycle(_) => (9, 0),
+            Event::JobHold(_, idx) => (10, *idx),
+            Event::Speculation(_) => (11, 0),
+            Event::NodeCooled(_, uid) => (12, *uid),
+            Event::QueueTimeout(_, uid) => (13, *uid),
+            Event::MemoryStep(_, uid) => (14, *uid),
+        };
+
+        self.time().total_cmp(&other.time()).then_with(|| rank(self).cmp(&rank(other)))
+    }
+}
+
+/// What happens to the jobs that depend on a job which fails for good, i.e. after its last retry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CascadePolicy {
+    /// The dependents stay in jobs_blocked waiting for the job to succeed, see
+    /// Scheduler::deadlocked() for the ones that wait forever
+    #[default]
+    Hold,
+    /// The dependents, and the jobs that depend on them, fail too without ever running
+    FailCascade,
+}
+
+impl FromStr for CascadePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "hold" => Ok(CascadePolicy::Hold),
+            "fail-cascade" => Ok(CascadePolicy::FailCascade),
+            _ => bail!("cascade may only be hold or fail-cascade but found \"{}\"", s),
+        }
+    }
+}
+
+/// The reason for the rejection of @job which waited past its max_queue_time at @now
+fn queue_timeout_reason(job: &Job, now: f32) -> String {
+    format!("Job {} exceeded max queue time {} at {}", job.uid,
+            job.max_queue_time.unwrap_or_default(), now)
+}
+
+/// Returns true if starting @job takes the cores that the running jobs use, @usage, past one of
+/// the @limits that cover it
+fn exceeds_limits(limits: &[ConcurrencyLimit], usage: &[f32], job: &Job) -> bool {
+    limits.iter()
+        .zip(usage)
+        .any(|(limit, used)| limit.covers(job) && used + job.cores > limit.max_cores)
+}
+
+/// The named resources that a finished job keeps on each of its nodes, see Job::keep
+pub type KeptResources = Vec<(NodeId, BTreeMap<String, f32>)>;
+
+/// The random number generator of the simulation. Unlike StdRng, ChaCha8Rng produces the same
+/// stream for a given seed on every platform and version of rand, so runs can be replayed
+pub type SimRng = ChaCha8Rng;
 
 pub struct Scheduler
 {
     pub registry: NodeRegistry,
     pub job_factory: Box<dyn JobFactory>,
+    pub policy: Box<dyn SchedulingPolicy>,
     pub now: f32,
 
     pub jobs_queuing: VecDeque<Job>,
     pub jobs_running: VecDeque<Job>,
     pub jobs_done: HashSet<usize>,
+    // VV: Jobs that the scheduler killed because they reached their walltime limit, and the
+    // jobs that depend on them if cascade is CascadePolicy::FailCascade
+    pub jobs_failed: HashSet<usize>,
+    pub cascade: CascadePolicy,
+    // VV: Jobs that have been submitted but depend on jobs which are not done yet
+    pub jobs_blocked: Vec<Job>,
+    // VV: Jobs that an operator put on hold, they do not start until they are released. held
+    // contains the UIDs of all held jobs including those that have not arrived yet, are blocked,
+    // or are running. queue_changed tells tick() that the policy must look at the entire queue
+    pub jobs_held: Vec<Job>,
+    pub held: HashSet<JobId>,
+    queue_changed: bool,
+    // VV: Jobs that could never run on this cluster, arrived at a full queue (see
+    // max_queue_depth), or would miss their deadline (see cancel_late) along with the reason,
+    // the scheduler drops them
+    pub jobs_rejected: Vec<(Job, String)>,
+    // VV: The resources of the idle cluster, see NodeRegistry::check_fits()
+    capacity: FreeResources,
+
+    // VV: When set, queued jobs that do not fit may evict running jobs with a lower priority
+    pub preemption: bool,
+
+    // VV: The future events, tick() jumps straight to the earliest one
+    pub events: BinaryHeap<Reverse<Event>>,
+    // VV: The creation time of the next Job of the JobFactory if it is already in events
+    next_arrival: Option<f32>,
+    // VV: How many jobs the scheduler has taken out of the JobFactory
+    jobs_arrived: usize,
+
+    // VV: The core-seconds and memory-seconds that jobs consumed on each node, indexed by NodeId.
+    // Jobs contribute to these when they finish or get preempted
+    pub node_core_seconds: Vec<f64>,
+    pub node_memory_seconds: Vec<f64>,
+
+    // VV: The times of every job that finished or failed
+    pub job_times: Vec<JobTimes>,
+    // VV: When set, summary() only considers the jobs that ran between these 2 points in time and
+    // window_core_seconds (window_memory_seconds) holds the core-seconds (memory-seconds) that
+    // jobs consumed in between and window_node_core_seconds breaks them down per node
+    pub stats_window: Option<(f32, f32)>,
+    // VV: When set, tick() returns false as soon as this many jobs are done or once now would
+    // move past until, the Events after until never happen
+    pub max_jobs: Option<usize>,
+    pub until: Option<f32>,
+    window_core_seconds: f64,
+    window_memory_seconds: f64,
+    window_node_core_seconds: Vec<f64>,
+    // VV: The runtime below which summary() treats jobs as if they ran for this long when it
+    // computes their bounded slowdown
+    pub slowdown_threshold: f32,
+    // VV: When set to (decay, speedup), the nodes that finish a job of an affinity group stay warm
+    // for the group during decay, jobs of the group that start on warm nodes run speedup times
+    // faster
+    pub warm_cache: Option<(f32, f32)>,
+    // VV: When set, a job of an array that runs for longer than speculation times the median
+    // runtime of the finished jobs of its array gets a speculative copy on another node. The
+    // attempt that finishes first wins and kills the other. speculated holds the jobs that got a
+    // copy and whether the copy won
+    pub speculation: Option<f32>,
+    pub speculated: BTreeMap<JobId, bool>,
+    array_runtimes: HashMap<JobId, Vec<f32>>,
+    next_speculation: Option<f32>,
+    // VV: When set, jobs that arrive while jobs_queuing holds this many jobs are rejected
+    pub max_queue_depth: Option<usize>,
+    // VV: When set, jobs whose estimated finish at placement time is past their deadline are
+    // rejected instead of started
+    pub cancel_late: bool,
+    // VV: How many times the scheduler killed a job because its nodes ran out of memory
+    pub oom_kills: usize,
+    // VV: How many times the scheduler ran a flaky job again after one of its runs failed
+    pub retries: usize,
+    // VV: The named resources that finished jobs keep allocated on each of their nodes until a
+    // job that depends on them releases them, see Job::keep
+    pub kept: BTreeMap<JobId, KeptResources>,
+
+    // VV: When set, the duration of every arriving job is drawn from this model and its
+    // declared duration only remains as the estimate
+    pub runtime_model: Option<RuntimeModel>,
+
+    // VV: When set, every job that stops running adds its JobSpans to spans
+    pub record_spans: bool,
+    pub spans: Vec<JobSpan>,
+
+    // VV: When set, every scheduling decision adds a sample to fragmentation
+    pub track_fragmentation: bool,
+    pub fragmentation: Vec<FragmentationSample>,
+    // VV: The work of the placement hot path in each tick, see SchedulerBuilder::profile()
+    pub placement_profile: Option<PlacementProfile>,
+
+    // VV: Seeds the stochastic features of the simulation so that runs are reproducible,
+    // they must draw their random numbers from rng
+    pub seed: u64,
+    pub rng: SimRng,
+    // VV: When set, now only takes values which are multiples of tick_granularity. Events that
+    // happen in between are handled at the next multiple
+    pub tick_granularity: Option<f32>,
+    // VV: When set, jobs only start at multiples of schedule_interval (a scheduling cycle) while
+    // jobs keep arriving and finishing in between. next_cycle is the time of the next cycle,
+    // cycle_event records whether there is an Event for it, and decided whether the last tick()
+    // was a cycle
+    pub schedule_interval: Option<f32>,
+    next_cycle: f32,
+    cycle_event: bool,
+    decided: bool,
+    // VV: When set, each job that starts keeps the scheduler busy for decision_latency, it makes
+    // no decisions before busy_until and the policy places at most 1 job per decision
+    pub decision_latency: Option<f32>,
+    busy_until: f32,
+
+    // VV: The core-seconds that the running jobs of each user consumed, usage decays
+    // exponentially with a time constant of usage_window. No tracking if usage_window is None
+    pub user_usage: HashMap<String, f64>,
+    pub usage_window: Option<f32>,
+    usage_updated: f32,
+    // VV: Fair-share divides the usage of each user by its weight, users without one have
+    // weight 1
+    pub user_weights: HashMap<String, f64>,
+
+    // VV: The nodes that join or leave the cluster, nodes_added holds the definitions of the
+    // nodes that joined so far so that a Checkpoint can add them to the registry again
+    pub scaling: Vec<ScalingEvent>,
+    nodes_added: Vec<String>,
+    // VV: The times at which nodes joined (true) or left (false) the cluster, the summary charges
+    // each node its cost_per_hour while it is part of the cluster
+    power_changes: Vec<(f32, NodeId, bool)>,
+
+    // VV: Resources of nodes that only the jobs of a single user may use for a while
+    pub reservations: Vec<Reservation>,
+
+    // VV: The timeline of jobs that are put on hold and released
+    pub holds: Vec<HoldEvent>,
+
+    // VV: The limits of the partitions that jobs may target, partitions without an entry have
+    // no limits
+    pub partitions: Vec<PartitionLimits>,
+
+    // VV: Caps on the cores that the running jobs of a user or a partition use at the same time
+    pub limits: Vec<ConcurrencyLimit>,
 }
 
-impl Scheduler
-{
+/// Configures and creates a Scheduler, options that are not set keep their defaults:
+/// FirstFit policy, no preemption, seed 0, and ticks at the exact time of each event
+pub struct SchedulerBuilder {
+    registry: NodeRegistry,
+    job_factory: Box<dyn JobFactory>,
+    policy: Option<Box<dyn SchedulingPolicy>>,
+    preemption: bool,
+    seed: u64,
+    tick_granularity: Option<f32>,
+    schedule_interval: Option<f32>,
+    decision_latency: Option<f32>,
+    failures: Vec<NodeFailure>,
+    maintenance: Vec<NodeMaintenance>,
+    scaling: Vec<ScalingEvent>,
+    reservations: Vec<Reservation>,
+    holds: Vec<HoldEvent>,
+    partitions: Vec<PartitionLimits>,
+    limits: Vec<ConcurrencyLimit>,
+    usage_window: Option<f32>,
+    user_weights: HashMap<String, f64>,
+    track_fragmentation: bool,
+    profile: bool,
+    runtime_model: Option<RuntimeModel>,
+    record_spans: bool,
+    stats_window: Option<(f32, f32)>,
+    max_jobs: Option<usize>,
+    until: Option<f32>,
+    slowdown_threshold: f32,
+    warm_cache: Option<(f32, f32)>,
+    speculation: Option<f32>,
+    max_queue_depth: Option<usize>,
+    cancel_late: bool,
+    cascade: CascadePolicy,
+}
+
+impl SchedulerBuilder {
     pub fn new(registry: NodeRegistry, job_factory: Box<dyn JobFactory>) -> Self {
         Self {
             registry,
             job_factory,
-            now: 0.0,
-            jobs_done: HashSet::new(),
-            jobs_queuing: VecDeque::new(),
-            jobs_running: VecDeque::new(),
+            policy: None,
+            preemption: false,
+            seed: 0,
+            tick_granularity: None,
+            schedule_interval: None,
+            decision_latency: None,
+            failures: vec![],
+            maintenance: vec![],
+            scaling: vec![],
+            reservations: vec![],
+            holds: vec![],
+            partitions: vec![],
+            limits: vec![],
+            usage_window: None,
+            user_weights: HashMap::new(),
+            track_fragmentation: false,
+            profile: false,
+            runtime_model: None,
+            record_spans: false,
+            stats_window: None,
+            max_jobs: None,
+            until: None,
+            slowdown_threshold: 10.0,
+            warm_cache: None,
+            speculation: None,
+            max_queue_depth: None,
+            cancel_late: false,
+            cascade: CascadePolicy::Hold,
+        }
+    }
+
+    pub fn policy(mut self, policy: Box<dyn SchedulingPolicy>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Switches between the EASY Backfill policy and FirstFit
+    pub fn backfill(self, enabled: bool) -> Self {
+        if enabled {
+            self.policy(Box::new(Backfill::default()))
+        } else {
+            self.policy(Box::new(FirstFit::default()))
+        }
+    }
+
+    pub fn preemption(mut self, enabled: bool) -> Self {
+        self.preemption = enabled;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Makes the Scheduler observe time in steps of @granularity, in the same unit as the
+    /// time_created and duration of jobs (e.g. 0.001 for millisecond steps, 60.0 for minutes).
+    ///
+    /// The Scheduler remains event-driven: tick() does not visit steps in which nothing happens,
+    /// it jumps to the first multiple of @granularity at or after the next event and handles all
+    /// events up to that point together. Coarse steps batch more events per tick() which speeds
+    /// up the simulation but jobs start and finish later than they would with exact event
+    /// times. Without a granularity, tick() moves to the exact time of the next event.
+    ///
+    /// Panics if @granularity is not positive
+    pub fn tick_granularity(mut self, granularity: f32) -> Self {
+        assert!(granularity > 0.0, "tick granularity {} must be positive", granularity);
+        self.tick_granularity = Some(granularity);
+        self
+    }
+
+    /// Batches scheduling decisions: jobs only start at multiples of @interval, a job that
+    /// arrives on an idle cluster waits for the next cycle. The first cycle is at @interval.
+    ///
+    /// Panics if @interval is not positive
+    pub fn schedule_interval(mut self, interval: f32) -> Self {
+        assert!(interval > 0.0, "schedule interval {} must be positive", interval);
+        self.schedule_interval = Some(interval);
+        self
+    }
+
+    /// Models the overhead of a real scheduler: starting a job takes @latency of simulated time
+    /// during which the scheduler cannot start other jobs. The policy places at most 1 job per
+    /// decision, so at most 1 job starts every @latency even on an idle cluster. Jobs that start
+    /// together, i.e. bundles and the jobs of reservations, keep the scheduler busy for @latency
+    /// each.
+    ///
+    /// Panics if @latency is not positive
+    pub fn decision_latency(mut self, latency: f32) -> Self {
+        assert!(latency > 0.0, "decision latency {} must be positive", latency);
+        self.decision_latency = Some(latency);
+        self
+    }
+
+    /// Nodes fail, and optionally recover, based on the @failures schedule
+    pub fn failures(mut self, failures: Vec<NodeFailure>) -> Self {
+        self.failures = failures;
+        self
+    }
+
+    /// Nodes drain and go offline for maintenance based on the @maintenance schedule
+    pub fn maintenance(mut self, maintenance: Vec<NodeMaintenance>) -> Self {
+        self.maintenance = maintenance;
+        self
+    }
+
+    /// Nodes join and leave the cluster based on the @scaling timeline. Jobs that arrive early
+    /// are rejected if they would only fit on nodes which have not joined yet. Returns an error
+    /// if the timeline does not make sense for the registry, see failures::check_scaling()
+    pub fn scaling(mut self, scaling: Vec<ScalingEvent>) -> Result<Self> {
+        check_scaling(&scaling, &self.registry)?;
+        self.scaling = scaling;
+        Ok(self)
+    }
+
+    /// Keeps the resources of the @reservations away from the jobs of other users, see Reservation
+    pub fn reservations(mut self, reservations: Vec<Reservation>) -> Self {
+        self.reservations = reservations;
+        self
+    }
+
+    /// Rejects the jobs that break the @partitions limits of their partition, see PartitionLimits
+    pub fn partitions(mut self, partitions: Vec<PartitionLimits>) -> Self {
+        self.partitions = partitions;
+        self
+    }
+
+    /// Keeps queued jobs waiting while they would take the running jobs of their user or
+    /// partition past one of the @limits, see ConcurrencyLimit
+    pub fn limits(mut self, limits: Vec<ConcurrencyLimit>) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Puts jobs on hold and releases them based on the @holds timeline, see HoldEvent
+    pub fn holds(mut self, holds: Vec<HoldEvent>) -> Self {
+        self.holds = holds;
+        self
+    }
+
+    /// Uses the FairShare policy and tracks the usage of each user, which decays with a time
+    /// constant of @window simulated seconds. Panics if @window is not positive
+    pub fn fair_share(mut self, window: f32) -> Self {
+        assert!(window > 0.0, "fair-share window {} must be positive", window);
+        self.usage_window = Some(window);
+        self.policy(Box::new(FairShare::default()))
+    }
+
+    /// Gives each user of @weights a share of the cluster that is proportional to its weight,
+    /// fair-share divides the usage of a user by its weight. Users without a weight have weight
+    /// 1. Panics if a weight is not positive
+    pub fn user_weights(mut self, weights: HashMap<String, f64>) -> Self {
+        for (user, weight) in &weights {
+            assert!(*weight > 0.0 && weight.is_finite(), "weight {} of user {} must be positive",
+                    weight, user);
+        }
+        self.user_weights = weights;
+        self
+    }
+
+    /// Jobs run for a duration that @model draws around the declared one using the seeded rng,
+    /// the declared duration becomes the estimate unless the job already has one
+    pub fn runtime_model(mut self, model: RuntimeModel) -> Self {
+        self.runtime_model = Some(model);
+        self
+    }
+
+    /// Excludes the warm-up before @start and the drain after @end from Scheduler::summary(),
+    /// it then only considers jobs that ran in between and prorates their core-seconds
+    pub fn stats_window(mut self, start: f32, end: f32) -> Self {
+        self.stats_window = Some((start, end));
+        self
+    }
+
+    /// Stops the simulation once @jobs jobs are done, the RunSummary is then truncated
+    pub fn max_jobs(mut self, jobs: usize) -> Self {
+        self.max_jobs = Some(jobs);
+        self
+    }
+
+    /// Stops the simulation at simulated time @time, the RunSummary is then truncated.
+    /// Scheduler::in_progress() lists the jobs that are still running at that point
+    pub fn until(mut self, time: f32) -> Self {
+        self.until = Some(time);
+        self
+    }
+
+    /// Sets the threshold of the bounded slowdown in Scheduler::summary(), defaults to 10
+    pub fn slowdown_threshold(mut self, threshold: f32) -> Self {
+        self.slowdown_threshold = threshold;
+        self
+    }
+
+    /// Keeps the nodes that finish a job of an affinity group (see Job::affinity) warm for the
+    /// group during @decay, the jobs of the group that start on warm nodes run @speedup times
+    /// faster. Only the BestFit policy with the WarmCache scorer prefers warm nodes.
+    ///
+    /// Panics if @decay or @speedup are not positive
+    pub fn warm_cache(mut self, decay: f32, speedup: f32) -> Self {
+        assert!(decay > 0.0, "warm cache decay {} must be positive", decay);
+        assert!(speedup > 0.0, "warm cache speedup {} must be positive", speedup);
+        self.warm_cache = Some((decay, speedup));
+        self
+    }
+
+    /// Launches a speculative copy of every job of an array that runs for longer than @threshold
+    /// times the median runtime of the finished jobs of its array. The copy runs on a different
+    /// node and the attempt that finishes first wins, each job gets at most 1 copy. Gang jobs
+    /// never get a copy.
+    ///
+    /// Panics if @threshold is not positive
+    pub fn speculation(mut self, threshold: f32) -> Self {
+        assert!(threshold > 0.0, "speculation threshold {} must be positive", threshold);
+        self.speculation = Some(threshold);
+        self
+    }
+
+    /// Rejects the jobs that arrive while @depth jobs are queuing, e.g. to study backpressure.
+    /// Jobs that go back to the queue, e.g. because they got preempted, are never rejected.
+    ///
+    /// Panics if @depth is 0
+    pub fn max_queue_depth(mut self, depth: usize) -> Self {
+        assert!(depth > 0, "max queue depth must be positive");
+        self.max_queue_depth = Some(depth);
+        self
+    }
+
+    /// Rejects the jobs that declare a deadline if, at the time the scheduler places them, they
+    /// would finish after it. The finish is estimated from the estimate of the job on the nodes
+    /// it would run on, the job's actual duration stays unknown to the scheduler
+    pub fn cancel_late(mut self, enabled: bool) -> Self {
+        self.cancel_late = enabled;
+        self
+    }
+
+    /// Picks what happens to the jobs that depend on a job which fails, see CascadePolicy
+    pub fn cascade(mut self, policy: CascadePolicy) -> Self {
+        self.cascade = policy;
+        self
+    }
+
+    /// Records when and where each job ran in Scheduler::spans, e.g. to draw a GanttChart
+    pub fn record_spans(mut self, enabled: bool) -> Self {
+        self.record_spans = enabled;
+        self
+    }
+
+    /// Records how many queued jobs could start if the free resources were not fragmented over
+    /// many nodes after every scheduling decision, see Scheduler::fragmentation
+    pub fn track_fragmentation(mut self, enabled: bool) -> Self {
+        self.track_fragmentation = enabled;
+        self
+    }
+
+    /// Counts the placement attempts, feasibility checks, and placements of every tick in
+    /// Scheduler::placement_profile. Enabling it turns on the counters of the profile module for
+    /// the whole process, a run that resumes from a checkpoint only counts its own ticks
+    pub fn profile(mut self, enabled: bool) -> Self {
+        if enabled {
+            profile::enable();
+        }
+        self.profile = enabled;
+        self
+    }
+
+    pub fn build(self) -> Scheduler {
+        let capacity = self.registry.capacity_resources();
+        let mut scheduler = Scheduler {
+            registry: self.registry,
+            job_factory: self.job_factory,
+            policy: self.policy.unwrap_or_else(|| Box::new(FirstFit::default())),
+            now: 0.0,
+            jobs_done: HashSet::new(),
+            jobs_failed: HashSet::new(),
+            cascade: self.cascade,
+            jobs_queuing: VecDeque::new(),
+            jobs_running: VecDeque::new(),
+            jobs_blocked: vec![],
+            jobs_held: vec![],
+            held: HashSet::new(),
+            queue_changed: false,
+            jobs_rejected: vec![],
+            capacity,
+            preemption: self.preemption,
+            events: BinaryHeap::new(),
+            next_arrival: None,
+            jobs_arrived: 0,
+            node_core_seconds: vec![],
+            node_memory_seconds: vec![],
+            job_times: vec![],
+            stats_window: self.stats_window,
+            max_jobs: self.max_jobs,
+            until: self.until,
+            window_core_seconds: 0.0,
+            window_memory_seconds: 0.0,
+            window_node_core_seconds: vec![],
+            slowdown_threshold: self.slowdown_threshold,
+            warm_cache: self.warm_cache,
+            speculation: self.speculation,
+            speculated: BTreeMap::new(),
+            array_runtimes: HashMap::new(),
+            next_speculation: None,
+            max_queue_depth: self.max_queue_depth,
+            cancel_late: self.cancel_late,
+            oom_kills: 0,
+            retries: 0,
+            kept: BTreeMap::new(),
+            runtime_model: self.runtime_model,
+            record_spans: self.record_spans,
+            spans: vec![],
+            track_fragmentation: self.track_fragmentation,
+            fragmentation: vec![],
+            placement_profile: self.profile.then(PlacementProfile::default),
+            seed: self.seed,
+            rng: SimRng::seed_from_u64(self.seed),
+            tick_granularity: self.tick_granularity,
+            schedule_interval: self.schedule_interval,
+            next_cycle: self.schedule_interval.unwrap_or(0.0),
+            cycle_event: false,
+            decided: false,
+            decision_latency: self.decision_latency,
+            busy_until: 0.0,
+            user_usage: HashMap::new(),
+            user_weights: self.user_weights,
+            usage_window: self.usage_window,
+            usage_updated: 0.0,
+            scaling: vec![],
+            nodes_added: vec![],
+            power_changes: vec![],
+            reservations: vec![],
+            holds: vec![],
+            partitions: self.partitions,
+            limits: self.limits,
+        };
+
+        for failure in &self.failures {
+            scheduler.schedule_failure(failure);
+        }
+
+        for maintenance in &self.maintenance {
+            scheduler.schedule_maintenance(maintenance);
+        }
+
+        for (idx, scaling) in self.scaling.iter().enumerate() {
+            scheduler.events.push(Reverse(Event::NodeScaling(scaling.time, idx)));
+        }
+        scheduler.scaling = self.scaling;
+
+        for (idx, reservation) in self.reservations.iter().enumerate() {
+            scheduler.events.push(Reverse(Event::ReservationStart(reservation.time_start, idx)));
+            scheduler.events.push(Reverse(Event::ReservationEnd(reservation.time_end, idx)));
+        }
+        scheduler.reservations = self.reservations;
+
+        for (idx, hold) in self.holds.iter().enumerate() {
+            scheduler.events.push(Reverse(Event::JobHold(hold.time, idx)));
+        }
+        scheduler.holds = self.holds;
+
+        scheduler
+    }
+
+    /// Resumes the simulation that Scheduler::save_checkpoint() stored in @path. The registry,
+    /// JobFactory and policy of the builder must be equivalent to the ones of the original run,
+    /// e.g. load the same files. Everything else (including the failures and options like
+    /// seed()) comes from the checkpoint.
+    pub fn restore(self, path: &Path) -> Result<Scheduler> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Unable to read checkpoint {}", path.display()))?;
+        let checkpoint: Checkpoint = serde_json::from_str(&content)
+            .context(format!("Invalid checkpoint {}", path.display()))?;
+
+        let mut registry = self.registry;
+        let mut job_factory = self.job_factory;
+
+        for definition in &checkpoint.nodes_added {
+            registry.new_node_from_str(definition)?;
+        }
+
+        if registry.nodes.len() != checkpoint.nodes.len() {
+            bail!("The checkpoint {} has {} nodes but the registry has {}", path.display(),
+                checkpoint.nodes.len(), registry.nodes.len())
+        }
+
+        for (node, state) in registry.nodes.iter_mut().zip(checkpoint.nodes) {
+            node.cores = state.cores;
+            node.memory = state.memory;
+            node.resources = state.resources;
+            node.failed = state.failed;
+            node.draining = state.draining;
+            node.held = state.held;
+            node.removed = state.removed;
+            node.warm = state.warm;
+            node.cooling = state.cooling;
+            node.cool_until = state.cool_until;
+        }
+        // VV: The anti-affinity groups and exclusive nodes follow from the running jobs, held
+        // already has the free resources of the exclusive nodes
+        for job in &checkpoint.jobs_running {
+            for (uid, _) in job.resource_shares() {
+                if let Some(group) = &job.anti_affinity {
+                    registry.nodes[uid].join_group(group);
+                }
+                registry.nodes[uid].exclusive |= job.exclusive;
+            }
+        }
+
+        // VV: The order of nodes with the same free resources depends on the earlier sorts
+        registry.sorted_cores = checkpoint.sorted_cores;
+        registry.sorted_memory = checkpoint.sorted_memory;
+        registry.rebuild_index();
+        registry.is_dirty = checkpoint.registry_dirty;
+
+        // VV: The jobs that arrived before the checkpoint are already part of its state
+        for _ in 0..checkpoint.jobs_arrived {
+            if !job_factory.more_jobs() {
+                bail!("The JobFactory has fewer than the {} jobs of the checkpoint {}",
+                    checkpoint.jobs_arrived, path.display())
+            }
+            job_factory.job_get();
+        }
+
+        let capacity = registry.capacity_resources();
+
+        Ok(Scheduler {
+            registry,
+            job_factory,
+            policy: self.policy.unwrap_or_else(|| Box::new(FirstFit::default())),
+            now: checkpoint.now,
+            jobs_queuing: checkpoint.jobs_queuing,
+            jobs_running: checkpoint.jobs_running,
+            jobs_done: checkpoint.jobs_done,
+            jobs_failed: checkpoint.jobs_failed,
+            cascade: checkpoint.cascade,
+            jobs_blocked: checkpoint.jobs_blocked,
+            jobs_held: checkpoint.jobs_held,
+            held: checkpoint.held,
+            queue_changed: false,
+            jobs_rejected: checkpoint.jobs_rejected,
+            capacity,
+            preemption: checkpoint.preemption,
+            events: checkpoint.events.into_iter().map(Reverse).collect(),
+            next_arrival: checkpoint.next_arrival,
+            jobs_arrived: checkpoint.jobs_arrived,
+            node_core_seconds: checkpoint.node_core_seconds,
+            node_memory_seconds: checkpoint.node_memory_seconds,
+            job_times: checkpoint.job_times,
+            stats_window: checkpoint.stats_window,
+            max_jobs: checkpoint.max_jobs,
+            until: checkpoint.until,
+            window_core_seconds: checkpoint.window_core_seconds,
+            window_memory_seconds: checkpoint.window_memory_seconds,
+            window_node_core_seconds: checkpoint.window_node_core_seconds,
+            slowdown_threshold: checkpoint.slowdown_threshold,
+            warm_cache: checkpoint.warm_cache,
+            speculation: checkpoint.speculation,
+            speculated: checkpoint.speculated,
+            array_runtimes: checkpoint.array_runtimes,
+            next_speculation: checkpoint.next_speculation,
+            max_queue_depth: checkpoint.max_queue_depth,
+            cancel_late: checkpoint.cancel_late,
+            oom_kills: checkpoint.oom_kills,
+            retries: checkpoint.retries,
+            kept: checkpoint.kept,
+            runtime_model: checkpoint.runtime_model,
+            record_spans: checkpoint.record_spans,
+            spans: checkpoint.spans,
+            track_fragmentation: checkpoint.track_fragmentation,
+            fragmentation: checkpoint.fragmentation,
+            placement_profile: self.profile.then(PlacementProfile::default),
+            seed: checkpoint.seed,
+            rng: checkpoint.rng,
+            tick_granularity: checkpoint.tick_granularity,
+            schedule_interval: checkpoint.schedule_interval,
+            next_cycle: checkpoint.next_cycle,
+            cycle_event: checkpoint.cycle_event,
+            decided: false,
+            decision_latency: checkpoint.decision_latency,
+            busy_until: checkpoint.busy_until,
+            user_usage: checkpoint.user_usage,
+            user_weights: checkpoint.user_weights,
+            usage_window: checkpoint.usage_window,
+            usage_updated: checkpoint.usage_updated,
+            scaling: checkpoint.scaling,
+            nodes_added: checkpoint.nodes_added,
+            power_changes: checkpoint.power_changes,
+            reservations: checkpoint.reservations,
+            holds: checkpoint.holds,
+            partitions: checkpoint.partitions,
+            limits: checkpoint.limits,
+        })
+    }
+}
+
+/// The free resources of a Node, see Checkpoint
+#[derive(Serialize, Deserialize)]
+struct NodeState {
+    cores: Resource,
+    memory: Resource,
+    resources: NamedResources,
+    failed: bool,
+    draining: bool,
+    held: Held,
+    removed: bool,
+    warm: BTreeMap<String, f32>,
+    cooling: bool,
+    cool_until: f32,
+}
+
+/// Everything that Scheduler::tick() depends on except for the registry topology, the
+/// JobFactory, and the policy which are not serializable
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    now: f32,
+    jobs_queuing: VecDeque<Job>,
+    jobs_running: VecDeque<Job>,
+    jobs_done: HashSet<usize>,
+    jobs_failed: HashSet<usize>,
+    cascade: CascadePolicy,
+    jobs_blocked: Vec<Job>,
+    jobs_held: Vec<Job>,
+    held: HashSet<JobId>,
+    jobs_rejected: Vec<(Job, String)>,
+    preemption: bool,
+    events: Vec<Event>,
+    next_arrival: Option<f32>,
+    jobs_arrived: usize,
+    node_core_seconds: Vec<f64>,
+    node_memory_seconds: Vec<f64>,
+    job_times: Vec<JobTimes>,
+    stats_window: Option<(f32, f32)>,
+    max_jobs: Option<usize>,
+    until: Option<f32>,
+    window_core_seconds: f64,
+    window_memory_seconds: f64,
+    window_node_core_seconds: Vec<f64>,
+    slowdown_threshold: f32,
+    warm_cache: Option<(f32, f32)>,
+    speculation: Option<f32>,
+    speculated: BTreeMap<JobId, bool>,
+    array_runtimes: HashMap<JobId, Vec<f32>>,
+    next_speculation: Option<f32>,
+    max_queue_depth: Option<usize>,
+    cancel_late: bool,
+    oom_kills: usize,
+    retries: usize,
+    kept: BTreeMap<JobId, KeptResources>,
+    runtime_model: Option<RuntimeModel>,
+    record_spans: bool,
+    spans: Vec<JobSpan>,
+    track_fragmentation: bool,
+    fragmentation: Vec<FragmentationSample>,
+    seed: u64,
+    rng: SimRng,
+    tick_granularity: Option<f32>,
+    schedule_interval: Option<f32>,
+    next_cycle: f32,
+    cycle_event: bool,
+    decision_latency: Option<f32>,
+    busy_until: f32,
+    user_usage: HashMap<String, f64>,
+    user_weights: HashMap<String, f64>,
+    usage_window: Option<f32>,
+    usage_updated: f32,
+    scaling: Vec<ScalingEvent>,
+    nodes_added: Vec<String>,
+    power_changes: Vec<(f32, NodeId, bool)>,
+    reservations: Vec<Reservation>,
+    holds: Vec<HoldEvent>,
+    partitions: Vec<PartitionLimits>,
+    limits: Vec<ConcurrencyLimit>,
+    nodes: Vec<NodeState>,
+    sorted_cores: Vec<usize>,
+    sorted_memory: Vec<usize>,
+    registry_dirty: bool,
+}
+
+impl Scheduler
+{
+    pub fn new(
+        registry: NodeRegistry,
+        job_factory: Box<dyn JobFactory>,
+        policy: Box<dyn SchedulingPolicy>,
+    ) -> Self {
+        SchedulerBuilder::new(registry, job_factory).policy(policy).build()
+    }
+
+    /// Stores the state of the simulation in @path as JSON, call this in between 2 ticks.
+    /// Resuming with Scheduler::load_checkpoint() produces the same trace as not stopping
+    pub fn save_checkpoint(&self, path: &Path) -> Result<()> {
+        let checkpoint = Checkpoint {
+            now: self.now,
+            jobs_queuing: self.jobs_queuing.clone(),
+            jobs_running: self.jobs_running.clone(),
+            jobs_done: self.jobs_done.clone(),
+            jobs_failed: self.jobs_failed.clone(),
+            cascade: self.cascade,
+            jobs_blocked: self.jobs_blocked.clone(),
+            jobs_held: self.jobs_held.clone(),
+            held: self.held.clone(),
+            jobs_rejected: self.jobs_rejected.clone(),
+            preemption: self.preemption,
+            events: self.events.clone().into_sorted_vec().into_iter().map(|e| e.0).collect(),
+            next_arrival: self.next_arrival,
+            jobs_arrived: self.jobs_arrived,
+            node_core_seconds: self.node_core_seconds.clone(),
+            node_memory_seconds: self.node_memory_seconds.clone(),
+            job_times: self.job_times.clone(),
+            stats_window: self.stats_window,
+            max_jobs: self.max_jobs,
+            until: self.until,
+            window_core_seconds: self.window_core_seconds,
+            window_memory_seconds: self.window_memory_seconds,
+            window_node_core_seconds: self.window_node_core_seconds.clone(),
+            slowdown_threshold: self.slowdown_threshold,
+            warm_cache: self.warm_cache,
+            speculation: self.speculation,
+            speculated: self.speculated.clone(),
+            array_runtimes: self.array_runtimes.clone(),
+            next_speculation: self.next_speculation,
+            max_queue_depth: self.max_queue_depth,
+            cancel_late: self.cancel_late,
+            oom_kills: self.oom_kills,
+            retries: self.retries,
+            kept: self.kept.clone(),
+            runtime_model: self.runtime_model,
+            record_spans: self.record_spans,
+            spans: self.spans.clone(),
+            track_fragmentation: self.track_fragmentation,
+            fragmentation: self.fragmentation.clone(),
+            seed: self.seed,
+            rng: self.rng.clone(),
+            tick_granularity: self.tick_granularity,
+            schedule_interval: self.schedule_interval,
+            next_cycle: self.next_cycle,
+            cycle_event: self.cycle_event,
+            decision_latency: self.decision_latency,
+            busy_until: self.busy_until,
+            user_usage: self.user_usage.clone(),
+            user_weights: self.user_weights.clone(),
+            usage_window: self.usage_window,
+            usage_updated: self.usage_updated,
+            scaling: self.scaling.clone(),
+            nodes_added: self.nodes_added.clone(),
+            power_changes: self.power_changes.clone(),
+            reservations: self.reservations.clone(),
+            holds: self.holds.clone(),
+            partitions: self.partitions.clone(),
+            limits: self.limits.clone(),
+            nodes: self.registry.nodes
+                .iter()
+                .map(|node| NodeState {
+                    cores: node.cores.clone(),
+                    memory: node.memory.clone(),
+                    resources: node.resources.clone(),
+                    failed: node.failed,
+                    draining: node.draining,
+                    held: node.held.clone(),
+                    removed: node.removed,
+                    warm: node.warm.clone(),
+                    cooling: node.cooling,
+                    cool_until: node.cool_until,
+                })
+                .collect(),
+            sorted_cores: self.registry.sorted_cores.clone(),
+            sorted_memory: self.registry.sorted_memory.clone(),
+            registry_dirty: self.registry.is_dirty || !self.registry.touched.is_empty(),
+        };
+
+        // VV: Write to a temporary file first so that a crash cannot leave a broken checkpoint
+        let partial = path.with_extension("partial");
+        let content = serde_json::to_string(&checkpoint)?;
+        std::fs::write(&partial, content)
+            .context(format!("Unable to write checkpoint {}", partial.display()))?;
+        std::fs::rename(&partial, path)
+            .context(format!("Unable to move checkpoint to {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Resumes the simulation in the checkpoint @path, this is a shorthand for
+    /// SchedulerBuilder::restore()
+    pub fn load_checkpoint(
+        path: &Path,
+        registry: NodeRegistry,
+        job_factory: Box<dyn JobFactory>,
+        policy: Box<dyn SchedulingPolicy>,
+    ) -> Result<Self> {
+        SchedulerBuilder::new(registry, job_factory).policy(policy).restore(path)
+    }
+
+    /// Returns the fraction of the simulated time that each node was busy, i.e. the larger of
+    /// its core and memory utilization. Nodes that never received a job report 0.0
+    pub fn node_utilization(&self) -> HashMap<NodeId, f64> {
+        let now = self.now as f64;
+
+        self.registry.nodes
+            .iter()
+            .map(|node| {
+                let usage = |seconds: &Vec<f64>, capacity: f32| -> f64 {
+                    match seconds.get(node.uid) {
+                        Some(seconds) if capacity > 0.0 && now > 0.0 => {
+                            seconds / (capacity as f64 * now)
+                        }
+                        _ => 0.0,
+                    }
+                };
+
+                let cores = usage(&self.node_core_seconds, node.cores.capacity);
+                let memory = usage(&self.node_memory_seconds, node.memory.capacity);
+                (node.uid, cores.max(memory))
+            })
+            .collect()
+    }
+
+    /// Decays the usage of all users and charges the running jobs for the core-seconds they
+    /// consumed since the last update
+    fn update_user_usage(&mut self) {
+        let window = match self.usage_window {
+            Some(window) => window as f64,
+            None => return,
+        };
+
+        let elapsed = (self.now - self.usage_updated) as f64;
+        if elapsed <= 0.0 {
+            return;
+        }
+        self.usage_updated = self.now;
+
+        let decay = (-elapsed / window).exp();
+        for usage in self.user_usage.values_mut() {
+            *usage *= decay;
+        }
+
+        for job in &self.jobs_running {
+            let user = job.user.clone().unwrap_or_default();
+            *self.user_usage.entry(user).or_default() += job.cores as f64 * elapsed;
+        }
+    }
+
+    /// Records the core-seconds and memory-seconds that @job consumed until now
+    fn account_usage(&mut self, job: &Job) {
+        let (started, done) = match (job.time_started, job.time_done) {
+            (Some(started), Some(done)) => (started, done.min(self.now)),
+            _ => return,
+        };
+        let elapsed = (done - started) as f64;
+
+        if self.node_core_seconds.len() < self.registry.nodes.len() {
+            self.node_core_seconds.resize(self.registry.nodes.len(), 0.0);
+            self.node_memory_seconds.resize(self.registry.nodes.len(), 0.0);
+        }
+
+        if let Some((start, end)) = self.stats_window {
+            let overlap = (done.min(end) - started.max(start)).max(0.0) as f64;
+            self.window_core_seconds += job.cores as f64 * overlap;
+            self.window_memory_seconds += job.memory_seconds(started.max(start), done.min(end));
+
+            self.window_node_core_seconds.resize(self.registry.nodes.len(), 0.0);
+            for (uid_cores, cores) in job.cores_per_node() {
+                self.window_node_core_seconds[uid_cores] += cores as f64 * overlap;
+            }
+        }
+
+        for (uid_cores, cores) in job.cores_per_node() {
+            self.node_core_seconds[uid_cores] += cores as f64 * elapsed;
+        }
+
+        // VV: Jobs with a memory profile spread their memory-seconds like their memory
+        let profiled = !job.memory_profile.is_empty() && job.memory > 0.0;
+        let memory_seconds = job.memory_seconds(started, done);
+        for (uid_memory, memory) in &job.node_memory {
+            self.node_memory_seconds[*uid_memory] += if profiled {
+                (*memory / job.memory) as f64 * memory_seconds
+            } else {
+                *memory as f64 * elapsed
+            };
+        }
+    }
+
+    /// Returns the time of the earliest future event, if there is one
+    pub fn next_event_time(&self) -> Option<f32> {
+        self.events.peek().map(|Reverse(event)| event.time())
+    }
+
+    /// Returns true if the simulation stopped at max_jobs or until before all jobs were done
+    pub fn is_truncated(&self) -> bool {
+        (self.max_jobs.is_some_and(|max| self.jobs_done.len() >= max)
+            || self.until.is_some_and(|until| self.now >= until))
+            && (self.jobs_queuing.len() + self.jobs_running.len() + self.jobs_blocked.len()
+                + self.jobs_held.len() > 0 || self.job_factory.more_jobs())
+    }
+
+    /// Counts the waiting jobs by the reason they did not start at the last scheduling cycle,
+    /// jobs that have not been through a scheduling cycle yet are left out
+    pub fn block_reasons(&self) -> BTreeMap<BlockReason, usize> {
+        let mut reasons = BTreeMap::new();
+        let waiting = self.jobs_queuing.iter().chain(&self.jobs_blocked).chain(&self.jobs_held);
+
+        for reason in waiting.filter_map(|job| job.last_block_reason.clone()) {
+            *reasons.entry(reason).or_insert(0) += 1;
+        }
+        reasons
+    }
+
+    /// Records why each queued job did not start at this scheduling cycle
+    fn update_block_reasons(&mut self) {
+        let free = self.registry.free_resources();
+        let usage = self.limits_usage();
+        for job in self.jobs_queuing.iter_mut() {
+            job.last_block_reason = Some(match exceeds_limits(&self.limits, &usage, job) {
+                true => BlockReason::Limit,
+                false => match self.registry.block_reason(job, &free) {
+                    BlockReason::Policy if job.bundle.is_some() => BlockReason::Bundle,
+                    reason => reason,
+                },
+            });
+        }
+    }
+
+    /// Returns the UID of every running job along with the simulated time it needs to finish
+    pub fn in_progress(&self) -> Vec<(JobId, f32)> {
+        self.running_jobs_status()
+            .into_iter()
+            .map(|status| (status.uid, status.remaining))
+            .collect()
+    }
+
+    /// Returns where, since when, and until when each running job runs in the order that the jobs
+    /// finish. Does not advance the simulation
+    pub fn running_jobs_status(&self) -> Vec<RunningJobStatus> {
+        self.jobs_running
+            .iter()
+            .map(|job| {
+                let done = job.time_done.unwrap();
+                RunningJobStatus {
+                    uid: job.uid,
+                    node: job.node_cores.unwrap(),
+                    started: job.time_started.unwrap(),
+                    done,
+                    remaining: (done - self.now).max(0.0),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns true if jobs wait and nothing can ever change that, i.e. no job is running and no
+    /// pending Event can give the waiting jobs resources or release them. Waiting for busy
+    /// resources is not unschedulable, running jobs eventually free their resources
+    pub fn has_unschedulable(&self) -> bool {
+        // VV: Jobs that arrived after the last scheduling cycle have not had their chance yet
+        !self.is_truncated()
+            && (self.decided || (self.schedule_interval.is_none()
+                && self.decision_latency.is_none()))
+            && self.jobs_running.is_empty()
+            && (!self.jobs_queuing.is_empty() || !self.jobs_blocked.is_empty()
+                || !self.jobs_held.is_empty())
+            && !self.job_factory.more_jobs()
+            && !self.events.iter().any(|Reverse(event)| self.may_unblock(event))
+    }
+
+    /// Returns the waiting jobs that can never start no matter which resources free up, i.e.
+    /// the jobs on hold that no pending HoldEvent releases and the blocked jobs whose dependencies
+    /// failed, were rejected, or can never finish themselves. Jobs that wait for resources are
+    /// not deadlocked, see has_unschedulable() for those
+    pub fn deadlocked(&self) -> BTreeMap<JobId, Deadlock> {
+        let mut deadlocked = BTreeMap::new();
+        if self.jobs_blocked.is_empty() && self.jobs_held.is_empty() {
+            return deadlocked;
+        }
+
+        let released: HashSet<JobId> = self.events
+            .iter()
+            .filter_map(|Reverse(event)| match event {
+                Event::JobHold(_, idx) if self.holds[*idx].release => Some(self.holds[*idx].job),
+                _ => None,
+            })
+            .collect();
+        let held_forever = |uid: &JobId| self.held.contains(uid) && !released.contains(uid);
+
+        for job in self.jobs_held.iter().chain(&self.jobs_blocked) {
+            if held_forever(&job.uid) {
+                deadlocked.insert(job.uid, Deadlock::Held);
+            }
+        }
+
+        // VV: The jobs that may still finish, jobs that have not arrived yet may too
+        let mut alive: HashSet<JobId> = self.jobs_done.iter().copied()
+            .chain(self.jobs_running.iter().map(|job| job.uid))
+            .chain(self.jobs_queuing.iter().map(|job| job.uid))
+            .chain(self.jobs_held.iter().map(|job| job.uid).filter(|uid| !held_forever(uid)))
+            .collect();
+        let known: HashSet<JobId> = alive.iter().copied()
+            .chain(self.jobs_held.iter().chain(&self.jobs_blocked).map(|job| job.uid))
+            .chain(self.jobs_failed.iter().copied())
+            .chain(self.jobs_rejected.iter().map(|(job, _)| job.uid))
+            .collect();
+        let more_jobs = self.job_factory.more_jobs();
+        let may_finish = |uid: &JobId, alive: &HashSet<JobId>| {
+            alive.contains(uid) || (more_jobs && !known.contains(uid))
+        };
+
+        loop {
+            let unblocked: Vec<JobId> = self.jobs_blocked
+                .iter()
+                .filter(|job| !alive.contains(&job.uid) && !held_forever(&job.uid))
+                .filter(|job| job.depends_on.iter().all(|uid| may_finish(uid, &alive)))
+                .map(|job| job.uid)
+                .collect();
+            if unblocked.is_empty() {
+                break;
+            }
+            alive.extend(unblocked);
+        }
+
+        let lost = |uid: &JobId| self.jobs_failed.contains(uid)
+            || self.jobs_rejected.iter().any(|(job, _)| job.uid == *uid);
+        for job in &self.jobs_blocked {
+            if alive.contains(&job.uid) || deadlocked.contains_key(&job.uid) {
+                continue;
+            }
+
+            let reason = match job.depends_on.iter().find(|uid| lost(uid)) {
+                Some(uid) => Deadlock::Lost(*uid),
+                None => Deadlock::Waits(job.depends_on
+                    .iter()
+                    .filter(|uid| !may_finish(uid, &alive))
+                    .copied()
+                    .collect()),
+            };
+            deadlocked.insert(job.uid, reason);
+        }
+
+        deadlocked
+    }
+
+    /// Returns true if @event may let waiting jobs start, i.e. it brings back a node, adds a
+    /// node, frees reserved resources, releases a held job, or removes a job from the queue
+    fn may_unblock(&self, event: &Event) -> bool {
+        match event {
+            Event::NodeRecovery(..) | Event::ReservationEnd(..) | Event::NodeCooled(..)
+            | Event::QueueTimeout(..) => true,
+            Event::NodeReturn(_, uid) => !self.registry.nodes[*uid].removed,
+            Event::NodeScaling(_, idx) => matches!(self.scaling[*idx].action,
+                ScalingAction::Add(..) | ScalingAction::Return(..)),
+            Event::JobHold(_, idx) => self.holds[*idx].release,
+            _ => false,
+        }
+    }
+
+    /// Returns an error if @job requests a named resource that no node provides, does not fit on
+    /// the idle cluster, or depends on a rejected job
+    fn check_can_run(&self, job: &Job) -> Result<()> {
+        self.registry.check_resources(job)?;
+        self.registry.check_fits(job, &self.capacity)?;
+
+        if let Some(limits) = self.partition_limits(job) {
+            limits.check(job)?;
+        }
+
+        for limit in self.limits.iter().filter(|limit| limit.covers(job)) {
+            if job.cores > limit.max_cores {
+                bail!("Job {} requests {} cores but the limit of {} is {}", job.uid, job.cores,
+                    limit.name, limit.max_cores)
+            }
+        }
+
+        for uid in &job.depends_on {
+            if self.jobs_rejected.iter().any(|(rejected, _)| rejected.uid == *uid) {
+                bail!("Job {} depends on the rejected Job {}", job.uid, uid)
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the cores that the running jobs which each of the limits covers use
+    fn limits_usage(&self) -> Vec<f32> {
+        self.limits
+            .iter()
+            .map(|limit| self.jobs_running
+                .iter()
+                .filter(|job| limit.covers(job))
+                .map(|job| job.cores)
+                .sum())
+            .collect()
+    }
+
+    /// Moves the queued jobs that would take the running jobs of their user or partition past
+    /// one of the limits out of jobs_queuing, the policy does not see them
+    fn take_limited(&mut self) -> Vec<Job> {
+        if self.limits.is_empty() {
+            return vec![];
+        }
+
+        let usage = self.limits_usage();
+        let (limited, queuing): (VecDeque<Job>, VecDeque<Job>) =
+            std::mem::take(&mut self.jobs_queuing)
+                .into_iter()
+                .partition(|job| exceeds_limits(&self.limits, &usage, job));
+        self.jobs_queuing = queuing;
+
+        let mut limited = Vec::from(limited);
+        for job in &mut limited {
+            job.last_block_reason = Some(BlockReason::Limit);
+        }
+        limited
+    }
+
+    /// Moves the queued jobs that belong to a bundle out of jobs_queuing, the policy does not see
+    /// them and start_bundles() places them instead
+    fn take_bundled(&mut self) -> Vec<Job> {
+        if !self.jobs_queuing.iter().any(|job| job.bundle.is_some()) {
+            return vec![];
+        }
+
+        let (bundled, queuing): (VecDeque<Job>, VecDeque<Job>) =
+            std::mem::take(&mut self.jobs_queuing)
+                .into_iter()
+                .partition(|job| job.bundle.is_some());
+        self.jobs_queuing = queuing;
+        Vec::from(bundled)
+    }
+
+    /// Starts the bundles of @bundled whose jobs all fit on a single node at once, in the order of
+    /// their first job. A bundle waits while some of its jobs are blocked or on hold, or when its
+    /// jobs together would take their user or partition past a limit. The jobs of the bundles that
+    /// start leave @bundled. Returns the number of jobs that started
+    fn start_bundles(&mut self, bundled: &mut Vec<Job>) -> usize {
+        let mut names: Vec<String> = vec![];
+        for name in bundled.iter().filter_map(|job| job.bundle.as_ref()) {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+
+        let mut started = 0;
+        for name in names {
+            let (members, others): (Vec<Job>, Vec<Job>) = std::mem::take(bundled)
+                .into_iter()
+                .partition(|job| job.bundle.as_ref() == Some(&name));
+            *bundled = others;
+
+            let waits = self.jobs_blocked
+                .iter()
+                .chain(&self.jobs_held)
+                .any(|job| job.bundle.as_ref() == Some(&name));
+            if waits || self.bundle_exceeds_limits(&members) {
+                bundled.extend(members);
+                continue;
+            }
+
+            let held = self.hold_reservations(None);
+            profile::count_attempt();
+            let node = self.bundle_node(&members);
+            let placements: Vec<Placement> = match node {
+                Some(uid) => members
+                    .iter()
+                    .map(|job| {
+                        let placement = Placement {
+                            node_cores: uid,
+                            node_memory: vec![(uid, job.memory)],
+                            gang_cores: vec![],
+                        };
+                        placement.reserve(&mut self.registry, job);
+                        placement
+                    })
+                    .collect(),
+                None => vec![],
+            };
+            self.release_reservations(held);
+
+            if placements.is_empty() {
+                bundled.extend(members);
+                continue;
+            }
+
+            for (job, placement) in members.into_iter().zip(placements) {
+                self.start_job(job, placement);
+                started += 1;
+            }
+        }
+
+        started
+    }
+
+    /// Returns the first node with enough free resources to run all of @members at the same
+    /// time that also accepts each of them, see Node::accepts()
+    fn bundle_node(&self, members: &[Job]) -> Option<NodeId> {
+        let cores: f32 = members.iter().map(|job| job.cores).sum();
+        let memory: f32 = members.iter().map(|job| job.memory).sum();
+        let mut resources: BTreeMap<String, f32> = BTreeMap::new();
+        for (name, amount) in members.iter().flat_map(|job| &job.resources) {
+            *resources.entry(name.clone()).or_default() += amount;
+        }
+
+        self.registry.nodes
+            .iter()
+            .inspect(|_| profile::count_check())
+            .find(|node| node.can_host_job(cores, memory)
+                && node.has_resources(&resources, 1.0)
+                && members.iter().all(|job| node.accepts(job)))
+            .map(|node| node.uid)
+    }
+
+    /// Returns true if starting all of @members would take the running jobs of a user or
+    /// partition past one of the limits
+    fn bundle_exceeds_limits(&self, members: &[Job]) -> bool {
+        if self.limits.is_empty() {
+            return false;
+        }
+
+        let mut usage = self.limits_usage();
+        for job in members {
+            if exceeds_limits(&self.limits, &usage, job) {
+                return true;
+            }
+
+            for (limit, used) in self.limits.iter().zip(usage.iter_mut()) {
+                if limit.covers(job) {
+                    *used += job.cores;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Returns the limits of the partition that @job targets, if any
+    fn partition_limits(&self, job: &Job) -> Option<&PartitionLimits> {
+        let partition = job.partition.as_ref()?;
+        self.partitions.iter().find(|limits| &limits.name == partition)
+    }
+
+    fn dependencies_done(&self, job: &Job) -> bool {
+        job.depends_on.iter().all(|uid| self.jobs_done.contains(uid))
+    }
+
+    /// Moves the blocked jobs whose dependencies are all done to jobs_queuing
+    fn unblock_jobs(&mut self) {
+        let blocked = std::mem::take(&mut self.jobs_blocked);
+
+        for job in blocked {
+            if self.dependencies_done(&job) {
+                self.queue_job(job);
+            } else {
+                self.jobs_blocked.push(job);
+            }
+        }
+    }
+
+    /// Releases the resources of @job
+    fn job_release(&mut self, job: &Job) {
+        self.account_usage(job);
+
+        if self.record_spans {
+            if let (Some(start), Some(done)) = (job.time_started, job.time_done) {
+                for (node, _) in job.cores_per_node() {
+                    let user = job.user.clone();
+                    let end = done.min(self.now);
+                    self.spans.push(JobSpan { uid: job.uid, user, node, start, end });
+                }
+            }
+        }
+
+        self.free_placement(job);
+
+        // VV: Nodes with a cooldown stay idle for a while after they run a job
+        for (uid, _) in job.cores_per_node() {
+            let node = &mut self.registry.nodes[uid];
+            if node.cooldown > 0.0 {
+                let until = self.now + node.cooldown;
+                node.start_cooldown(until);
+                self.events.push(Reverse(Event::NodeCooled(until, uid)));
+            }
+        }
+    }
+
+    /// Frees the resources that the placement of @job holds and forgets the placement
+    fn release_placement(&mut self, job: &mut Job) {
+        self.free_placement(job);
+        job.node_cores = None;
+        job.node_memory.clear();
+        job.gang_cores.clear();
+    }
+
+    /// Frees the cores, memory, and named resources that @job holds on its nodes
+    fn free_placement(&mut self, job: &Job) {
+        for (uid_cores, cores) in job.cores_per_node() {
+            self.registry.nodes[uid_cores].free_cores(cores);
+        }
+
+        for (uid_memory, memory) in &job.node_memory {
+            let node = &mut self.registry.nodes[*uid_memory];
+            node.free_memory(*memory);
+        }
+
+        for (uid, share) in job.resource_shares() {
+            self.registry.nodes[uid].free_resources(&job.resources, share);
+            if let Some(group) = &job.anti_affinity {
+                self.registry.nodes[uid].leave_group(group);
+            }
+            if job.exclusive {
+                self.registry.nodes[uid].end_exclusive();
+            }
+        }
+
+        // VV: Draining nodes keep what the job freed away from new jobs
+        let touched = job.cores_per_node().into_iter().map(|(uid, _)| uid)
+            .chain(job.node_memory.iter().map(|(uid, _)| *uid))
+            .chain(job.resource_shares().into_iter().map(|(uid, _)| uid));
+        for uid in touched {
+            self.registry.nodes[uid].hold_free();
+            self.registry.touch(uid);
+        }
+    }
+
+    /// Finds the first queued job which could run if some running jobs with a lower priority
+    /// stopped. It evicts the lowest priority jobs (most recently started first) that are enough
+    /// to make room for the queued job and puts them back in jobs_queuing. Evicted jobs lose
+    /// their progress. Returns the number of evicted jobs.
+    fn preempt(&mut self) -> usize {
+        let lowest_running = match self.jobs_running.iter().map(|job| job.priority).min() {
+            Some(priority) => priority,
+            None => return 0,
+        };
+
+        let mut evict: Vec<usize> = vec![];
+
+        for job in &self.jobs_queuing {
+            if job.priority <= lowest_running {
+                // VV: jobs_queuing is sorted by descending priority
+                break;
+            }
+
+            let mut victims: Vec<usize> = (0..self.jobs_running.len())
+                .filter(|idx| self.jobs_running[*idx].priority < job.priority)
+                .collect();
+            victims.sort_by(|a, b| {
+                let a = &self.jobs_running[*a];
+                let b = &self.jobs_running[*b];
+                // VV: Lowest priority first, then the most recently started, then the job id
+                a.priority.cmp(&b.priority)
+                    .then(b.time_started.partial_cmp(&a.time_started)
+                        .unwrap_or(std::cmp::Ordering::Equal))
+                    .then(a.uid.cmp(&b.uid))
+            });
+
+            let mut free = self.registry.free_resources();
+
+            for (i, idx) in victims.iter().enumerate() {
+                free.release(&self.jobs_running[*idx]);
+
+                if let Some(nodes) = self.registry.fits_with(job, &free) {
+                    // VV: Only evict the victims that free resources on the nodes @job needs
+                    evict = victims[..=i]
+                        .iter()
+                        .copied()
+                        .filter(|idx| {
+                            let victim = &self.jobs_running[*idx];
+                            victim.node_memory.iter().any(|(uid, _)| nodes.contains(uid))
+                                || victim.cores_per_node().iter().any(|(uid, _)| nodes.contains(uid))
+                        })
+                        .collect();
+                    break;
+                }
+            }
+
+            if !evict.is_empty() {
+                break;
+            }
+        }
+
+        self.requeue_running(evict)
+    }
+
+    /// Moves the running jobs at the @indices of jobs_running back to jobs_queuing, they lose
+    /// their progress. Returns the number of requeued jobs.
+    fn requeue_running(&mut self, mut indices: Vec<usize>) -> usize {
+        indices.sort_unstable();
+
+        let requeued: HashSet<JobId> = indices
+            .iter()
+            .map(|idx| self.jobs_running[*idx].uid)
+            .collect();
+        self.events.retain(|Reverse(event)| match event {
+            Event::Completion(_, uid) => !requeued.contains(uid),
+            _ => true,
+        });
+
+        for idx in indices.iter().rev() {
+            let mut job = self.jobs_running.remove(*idx).unwrap();
+            self.job_release(&job);
+
+            // VV: The job keeps running if its other speculative attempt survives
+            if self.other_attempt(job.uid).is_some() {
+                continue;
+            }
+
+            job.reset_run();
+            self.queue_job(job);
+        }
+
+        for job in self.jobs_running.iter().filter(|job| requeued.contains(&job.uid)) {
+            self.events.push(Reverse(Event::Completion(job.time_done.unwrap(), job.uid)));
+        }
+
+        indices.len()
+    }
+
+    /// Kills the running jobs of the nodes where running jobs actually use more memory than the
+    /// node has, which happens when jobs use more memory than they request, see
+    /// Job::memory_limit. Jobs with a Job::memory_profile count the memory of their current step.
+    /// On each such node the job that uses the most memory beyond its request dies first until
+    /// the rest fit. The victims go back to jobs_queuing and request the memory that they use
+    /// from now on so that they do not overcommit their next node. Returns the number of killed
+    /// jobs.
+    fn oom_kill(&mut self) -> usize {
+        let now = self.now;
+        let excess = |job: &Job| -> f32 { job.memory_used(now) - job.memory };
+        let mut killed = 0;
+
+        while self.jobs_running.iter().any(|job| excess(job) > 0.0) {
+            let mut usage = vec![0.0f64; self.registry.nodes.len()];
+            for job in &self.jobs_running {
+                for (uid, used) in job.memory_usage_per_node(now) {
+                    usage[uid] += used as f64;
+                }
+            }
+
+            // VV: Nodes whose jobs requested all the memory they use have nothing to kill
+            let found = (0..usage.len())
+                .filter(|uid| usage[*uid] > self.registry.nodes[*uid].memory.capacity as f64)
+                .find_map(|node| {
+                    self.jobs_running
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, job)| job.node_memory.iter().any(|(uid, _)| *uid == node))
+                        .filter(|(_, job)| excess(job) > 0.0)
+                        .max_by(|(_, a), (_, b)| excess(a).total_cmp(&excess(b)))
+                        .map(|(idx, _)| (node, idx))
+                });
+            let (node, victim) = match found {
+                Some(found) => found,
+                None => break,
+            };
+
+            let job = &mut self.jobs_running[victim];
+            info!(time = self.now, job = job.uid, node = %self.registry.nodes[node].name,
+                usage = job.memory_usage, requested = job.memory, "Job killed, out of memory");
+            job.oom_kills += 1;
+            job.memory = job.memory_usage.unwrap_or(job.memory);
+            self.oom_kills += 1;
+            killed += self.requeue_running(vec![victim]);
+        }
+
+        killed
+    }
+
+    /// Schedules the drain and the return of a node, the node stays drained while it is offline
+    /// so there is no event for the start of the maintenance window
+    pub fn schedule_maintenance(&mut self, maintenance: &NodeMaintenance) {
+        self.events.push(Reverse(Event::NodeDrain(maintenance.time_drain, maintenance.node)));
+        self.events.push(Reverse(Event::NodeReturn(maintenance.time_end, maintenance.node)));
+    }
+
+    /// Schedules the failure, and optional recovery, of a node
+    pub fn schedule_failure(&mut self, failure: &NodeFailure) {
+        self.events.push(Reverse(Event::NodeFailure(failure.time_fail, failure.node)));
+        if let Some(time_recover) = failure.time_recover {
+            self.events.push(Reverse(Event::NodeRecovery(time_recover, failure.node)));
+        }
+    }
+
+    /// Requeues the running jobs that use node @uid for their cores or memory and then makes
+    /// the resources of the node unavailable. The entire job is requeued, even if it is a gang
+    /// job that only has some of its cores on the node. Returns the number of requeued jobs.
+    fn fail_node(&mut self, uid: NodeId) -> usize {
+        let requeued = self.requeue_running(self.running_on(uid));
+        self.registry.nodes[uid].fail();
+        self.registry.is_dirty = true;
+        info!(time = self.now, node = %self.registry.nodes[uid].name, requeued,
+            "Node failed");
+
+        requeued
+    }
+
+    /// Returns the indices of the running jobs which use node @uid
+    fn running_on(&self, uid: NodeId) -> Vec<usize> {
+        (0..self.jobs_running.len())
+            .filter(|idx| {
+                let job = &self.jobs_running[*idx];
+                job.cores_per_node().iter().any(|(node, _)| *node == uid)
+                    || job.node_memory.iter().any(|(node, _)| *node == uid)
+            })
+            .collect()
+    }
+
+    /// Adds or removes the node of the @idx-th ScalingEvent, returns the number of running jobs
+    /// that went back to the queue and whether there are new free resources. The node names and
+    /// definitions are valid, see SchedulerBuilder::scaling()
+    fn apply_scaling(&mut self, idx: usize) -> (usize, bool) {
+        let event = self.scaling[idx].clone();
+        let uid = |registry: &NodeRegistry| -> NodeId { registry.registry[event.node()] };
+        self.registry.is_dirty = true;
+
+        match &event.action {
+            ScalingAction::Add(definition) => {
+                self.registry.new_node_from_str(definition)
+                    .expect("SchedulerBuilder::scaling() checks the definitions of new nodes");
+                self.nodes_added.push(definition.clone());
+                self.power_changes.push((self.now, self.registry.nodes.len() - 1, true));
+                self.capacity = self.registry.capacity_resources();
+                (0, true)
+            }
+            ScalingAction::Return(_) => {
+                let uid = uid(&self.registry);
+                self.power_changes.push((self.now, uid, true));
+                let node = &mut self.registry.nodes[uid];
+                node.removed = false;
+                node.undrain();
+                (0, true)
+            }
+            ScalingAction::Remove(_, mode) => {
+                let uid = uid(&self.registry);
+                let requeued = match mode {
+                    RemoveMode::Drain => 0,
+                    RemoveMode::Kill => self.requeue_running(self.running_on(uid)),
+                };
+                self.power_changes.push((self.now, uid, false));
+                let node = &mut self.registry.nodes[uid];
+                node.removed = true;
+                node.drain();
+                (requeued, false)
+            }
+        }
+    }
+
+    /// Removes the events that are due (i.e. happen at or before now) from events and returns
+    /// the node failures, recoveries, drains, and returns among them
+    fn take_due_node_events(&mut self) -> Vec<Event> {
+        let mut due = vec![];
+
+        while let Some(Reverse(event)) = self.events.peek() {
+            if event.time() > self.now {
+                break;
+            }
+
+            let event = self.events.pop().unwrap().0;
+            if matches!(event, Event::NodeFailure(..) | Event::NodeRecovery(..)
+                | Event::NodeDrain(..) | Event::NodeReturn(..) | Event::NodeScaling(..)
+                | Event::ReservationStart(..) | Event::ReservationEnd(..) | Event::JobHold(..)
+                | Event::NodeCooled(..) | Event::QueueTimeout(..) | Event::MemoryStep(..)) {
+                due.push(event);
+            }
         }
-    }
 
-    pub fn has_unschedulable(&self) -> bool {
-        self.jobs_running.is_empty()
-            && !self.jobs_queuing.is_empty()
-            && !self.job_factory.more_jobs()
+        due
     }
 
-    fn job_free(&mut self, job: Job) {
+    fn job_free(&mut self, mut job: Job) {
         // println!(
         //     "Freeing {}x{} from cores {} and memory {:?}",
         //     job.cores,
@@ -62,153 +2234,703 @@ impl Scheduler
         //     job.node_memory
         // );
 
+        debug!(time = self.now, job = job.uid, "Job completed");
+        // VV: The first attempt of a speculatively re-executed job to finish kills the other one,
+        // the job counts as started when its first attempt did
+        let started = match self.take_other_attempt(job.uid) {
+            Some(other) => {
+                self.job_release(&other);
+                self.speculated.insert(job.uid, job.speculative);
+                job.time_started.zip(other.time_started).map(|(a, b)| a.min(b))
+            }
+            None => job.time_started,
+        };
+        self.job_release_keeping(&job);
+        for uid in &job.release {
+            self.release_kept(*uid);
+        }
+
+        job.time_started = started;
+        self.record_job_times(&job, false);
         self.jobs_done.insert(job.uid);
         self.job_factory.job_mark_done(&job);
 
-        let uid_cores = job.node_cores.unwrap();
-        self.registry.nodes[uid_cores].free_cores(job.cores);
+        if let (Some(_), Some((array, _))) = (self.speculation, job.array) {
+            let runtime = job.time_done.unwrap() - job.time_started.unwrap();
+            self.array_runtimes.entry(array).or_default().push(runtime);
+        }
 
-        for (uid_memory, memory) in &job.node_memory {
-            let node = &mut self.registry.nodes[*uid_memory];
-            node.free_memory(*memory);
+        if let (Some((decay, _)), Some(group)) = (self.warm_cache, &job.affinity) {
+            for (uid, _) in job.cores_per_node() {
+                self.registry.nodes[uid].warm_up(group, self.now + decay);
+            }
         }
-        // VV: It's not safe to use the sorted indices any more
-        self.registry.is_dirty = true;
     }
 
-    fn try_allocate_on_many_nodes(
-        registry: &NodeRegistry,
-        idx_cores: usize,
-        job: &Job,
-    ) -> Option<(usize, Vec<(usize, f32)>)> {
-        let uid_cores = registry.sorted_cores[idx_cores];
-        let lenders = registry.connections.get(&uid_cores).unwrap();
-
+    /// Counts the queued jobs which would fit, in queue order, in the free cores and memory of
+    /// the cluster if they were all on the same node
+    fn sample_fragmentation(&mut self, started: usize) {
+        let mut cores: f32 = self.registry.nodes.iter().map(|n| n.cores.current).sum();
+        let mut memory: f32 = self.registry.nodes.iter().map(|n| n.memory.current).sum();
+        let mut could_start = started;
 
-        let mut rem_mem = job.memory;
-        let mut mem_alloc: Vec<(usize, f32)> = Vec::new();
-        let node_cores = &registry.nodes[uid_cores];
+        for job in &self.jobs_queuing {
+            if job.cores <= cores && job.memory <= memory {
+                cores -= job.cores;
+                memory -= job.memory;
+                could_start += 1;
+            }
+        }
 
-        if node_cores.memory.current > 0.0 {
-            let alloc = rem_mem.min(node_cores.memory.current);
-            mem_alloc.push((uid_cores, alloc));
+        self.fragmentation.push(FragmentationSample { time: self.now, started, could_start });
+    }
 
-            // registry.nodes[uid_cores].allocate_memory(alloc);
-            rem_mem -= alloc;
+    fn record_job_times(&mut self, job: &Job, failed: bool) {
+        if let (Some(started), Some(done)) = (job.time_started, job.time_done) {
+            let created = job.time_created;
+            self.job_times.push(JobTimes { created, started, done, failed });
         }
+    }
 
-        for uid_mem in lenders {
-            if uid_mem != &uid_cores {
-                let node_mem = &registry.nodes[*uid_mem];
-                let alloc = rem_mem.min(node_mem.memory.current);
-                mem_alloc.push((*uid_mem, alloc));
+    /// Returns what each node runs right now and its used and free resources, e.g. to inspect
+    /// the cluster when a run stops early, see snapshot::occupancy_table()
+    pub fn occupancy_snapshot(&self) -> ClusterSnapshot {
+        ClusterSnapshot::from_scheduler(self)
+    }
 
-                rem_mem -= alloc;
+    /// Summarizes the simulation so far, see SchedulerBuilder::stats_window()
+    pub fn summary(&self) -> RunSummary {
+        let times: Vec<&JobTimes> = self.job_times
+            .iter()
+            .filter(|times| self.stats_window.is_none_or(|(start, end)| times.overlaps(start, end)))
+            .collect();
+        let mut waits: Vec<f32> = times.iter().map(|times| times.wait()).collect();
+        waits.sort_by(|a, b| a.total_cmp(b));
+        let mut slowdowns: Vec<f32> = times.iter()
+            .map(|times| times.slowdown(self.slowdown_threshold))
+            .collect();
+        slowdowns.sort_by(|a, b| a.total_cmp(b));
 
-                if rem_mem == 0.0 {
-                    break;
+        let mean = |values: &mut dyn Iterator<Item=f32>| -> f64 {
+            let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v as f64, count + 1));
+            if count > 0 { sum / count as f64 } else { 0.0 }
+        };
+
+        let total_cores: f64 = self.registry.nodes.iter().map(|n| n.cores.capacity as f64).sum();
+        let total_memory: f64 = self.registry.nodes.iter().map(|n| n.memory.capacity as f64).sum();
+        let makespan = self.job_times.iter().map(|times| times.done).fold(0.0, f32::max) as f64;
+        let (core_seconds, memory_seconds, node_core_seconds, duration) = match self.stats_window {
+            Some((start, end)) => (self.window_core_seconds, self.window_memory_seconds,
+                                   &self.window_node_core_seconds,
+                                   (end.min(self.now) - start).max(0.0) as f64),
+            None => (self.node_core_seconds.iter().sum(), self.node_memory_seconds.iter().sum(),
+                     &self.node_core_seconds, self.now as f64),
+        };
+        let utilization = |seconds: f64, capacity: f64| -> f64 {
+            if capacity > 0.0 && duration > 0.0 { seconds / (capacity * duration) } else { 0.0 }
+        };
+
+        // VV: Nodes draw their idle power for the entire duration even if they never run a job
+        let joules: f64 = self.registry.nodes
+            .iter()
+            .map(|node| {
+                let core_seconds = node_core_seconds.get(node.uid).copied().unwrap_or(0.0);
+                node.energy(duration, core_seconds)
+            })
+            .sum();
+
+        let (start, end) = match self.stats_window {
+            Some((start, end)) => (start, end.min(self.now)),
+            None => (0.0, self.now),
+        };
+        let total_cost: f64 = self.registry.nodes
+            .iter()
+            .filter(|node| node.cost_per_hour > 0.0)
+            .fold(0.0, |cost, node| {
+                cost + node.cost_per_hour as f64 * self.seconds_in_cluster(node.uid, start, end)
+                    / 3600.0
+            });
+        let completed = times.iter().filter(|times| !times.failed).count();
+
+        RunSummary {
+            total_jobs: times.len(),
+            jobs_failed: times.iter().filter(|times| times.failed).count(),
+            makespan,
+            mean_wait: mean(&mut waits.iter().copied()),
+            median_wait: RunSummary::percentile(&waits, 50.0),
+            p95_wait: RunSummary::percentile(&waits, 95.0),
+            mean_turnaround: mean(&mut times.iter().map(|times| times.turnaround())),
+            mean_slowdown: mean(&mut slowdowns.iter().copied()),
+            p95_slowdown: RunSummary::percentile(&slowdowns, 95.0),
+            core_utilization: utilization(core_seconds, total_cores),
+            memory_utilization: utilization(memory_seconds, total_memory),
+            energy_kwh: joules / 3.6e6,
+            total_cost,
+            cost_per_job: if completed > 0 { total_cost / completed as f64 } else { 0.0 },
+            truncated: self.is_truncated(),
+        }
+    }
+
+    /// Returns for how many seconds between @start and @end node @uid was part of the cluster.
+    /// Nodes that a ScalingEvent added join when it happens, all other nodes are there from 0
+    fn seconds_in_cluster(&self, uid: NodeId, start: f32, end: f32) -> f64 {
+        let overlap = |from: f32, to: f32| (to.min(end) - from.max(start)).max(0.0) as f64;
+        let initial = self.registry.nodes.len() - self.nodes_added.len();
+
+        let mut since = if uid < initial { Some(0.0) } else { None };
+        let mut seconds = 0.0;
+        for (time, _, joined) in self.power_changes.iter().filter(|(_, node, _)| *node == uid) {
+            match (since, joined) {
+                (None, true) => since = Some(*time),
+                (Some(from), false) => {
+                    seconds += overlap(from, *time);
+                    since = None;
                 }
+                _ => {}
             }
         }
 
-        if rem_mem == 0.0 {
-            Some((uid_cores, mem_alloc))
-        } else {
-            None
+        seconds + since.map_or(0.0, |from| overlap(from, self.now))
+    }
+
+    /// Summarizes the simulation so far along with the jobs that did not finish, e.g. to tell
+    /// apart runs that ended with unschedulable jobs
+    pub fn exit_summary(&self) -> ExitSummary {
+        ExitSummary {
+            summary: self.summary(),
+            jobs_done: self.jobs_done.len(),
+            jobs_rejected: self.jobs_rejected.len(),
+            jobs_waiting: self.jobs_queuing.len() + self.jobs_blocked.len() + self.jobs_held.len(),
+            jobs_deadlocked: self.deadlocked().len(),
+            oom_kills: self.oom_kills,
+            unschedulable: self.has_unschedulable(),
         }
     }
 
-    fn job_try_allocate(
-        registry: &NodeRegistry,
-        job: &Job,
-        idx_memory: usize,
-        cores_start: usize,
-        cores_end: usize,
-    ) -> Option<(usize, Vec<(usize, f32)>)> {
-        if idx_memory < registry.sorted_memory.len() {
-            // VV: There's a chance one of the nodes with enough cores (all_cores) has enough
-            // memory too (all_memory)
+    /// Returns the cumulative number of jobs that finished after every completion event, in
+    /// ascending order of time. Jobs that failed do not count, jobs that finish at the same time
+    /// share 1 sample
+    pub fn throughput(&self) -> Vec<ThroughputSample> {
+        let mut done: Vec<f32> = self.job_times
+            .iter()
+            .filter(|times| !times.failed)
+            .map(|times| times.done)
+            .collect();
+        done.sort_by(|a, b| a.total_cmp(b));
+
+        let mut samples: Vec<ThroughputSample> = vec![];
+        for (idx, time) in done.into_iter().enumerate() {
+            match samples.last_mut() {
+                Some(last) if last.time == time => last.done = idx + 1,
+                _ => samples.push(ThroughputSample { time, done: idx + 1 }),
+            }
+        }
+        samples
+    }
 
-            let all_cores = &registry.sorted_cores[cores_start..cores_end];
+    /// Sorts the waits of every job that arrived so far into a WaitHistogram with the given
+    /// @bounds. Unlike summary() this ignores the stats window and also counts the jobs that are
+    /// running or still waiting
+    pub fn wait_histogram(&self, bounds: &[f32]) -> Result<WaitHistogram> {
+        let mut histogram = WaitHistogram::new(bounds)?;
 
-            for &uid_cores in all_cores {
-                let node = &registry.nodes[uid_cores];
-                if node.memory.current >= job.memory {
-                    return Some((uid_cores, vec![(uid_cores, job.memory)]));
-                }
+        for times in &self.job_times {
+            histogram.add(times.wait());
+        }
+
+        for job in &self.jobs_running {
+            histogram.add(job.time_started.unwrap() - job.time_created);
+        }
+
+        histogram.never_started =
+            self.jobs_queuing.len() + self.jobs_blocked.len() + self.jobs_held.len();
+        Ok(histogram)
+    }
+
+    /// Releases the resources of the finished @job except for the named resources that it keeps,
+    /// which stay allocated until release_kept()
+    fn job_release_keeping(&mut self, job: &Job) {
+        if job.keep.is_empty() {
+            self.job_release(job);
+            return;
+        }
+
+        let mut released = job.clone();
+        released.resources.retain(|name, _| !job.keep.contains(name));
+        self.job_release(&released);
+
+        let kept = job.resource_shares()
+            .into_iter()
+            .map(|(uid, share)| {
+                let amounts = job.resources
+                    .iter()
+                    .filter(|(name, _)| job.keep.contains(name))
+                    .map(|(name, amount)| (name.clone(), amount * share))
+                    .collect();
+                (uid, amounts)
+            })
+            .collect();
+        debug!(time = self.now, job = job.uid, keep = ?job.keep, "Job keeps resources");
+        self.kept.insert(job.uid, kept);
+    }
+
+    /// Frees the named resources that the finished job @uid keeps, if any
+    fn release_kept(&mut self, uid: JobId) {
+        for (node, amounts) in self.kept.remove(&uid).unwrap_or_default() {
+            let node = &mut self.registry.nodes[node];
+            node.free_resources(&amounts, 1.0);
+            node.hold_free();
+            self.registry.is_dirty = true;
+        }
+    }
+
+    /// Returns true if the run of the flaky @job that just reached its end failed, see
+    /// Job::failure_probability
+    fn run_fails(&mut self, job: &Job) -> bool {
+        job.failure_probability > 0.0 && self.rng.gen::<f32>() < job.failure_probability
+    }
+
+    /// Frees the resources of the flaky @job whose run failed and queues it for another run
+    fn retry_job(&mut self, mut job: Job) {
+        debug!(time = self.now, job = job.uid, attempts = job.attempts, "Job retried");
+        self.job_release(&job);
+        job.reset_run();
+        self.retries += 1;
+        self.queue_job(job);
+    }
+
+    fn job_fail(&mut self, mut job: Job) {
+        debug!(time = self.now, job = job.uid, walltime_limit = ?job.walltime_limit,
+            attempts = job.attempts, "Job failed");
+        job.failed = true;
+        self.record_job_times(&job, true);
+        self.jobs_failed.insert(job.uid);
+        self.job_factory.job_mark_failed(&job);
+        self.job_release(&job);
+
+        if self.cascade == CascadePolicy::FailCascade {
+            self.fail_dependents();
+        }
+    }
+
+    /// Fails the blocked jobs that depend on a failed job, and then the ones that depend on
+    /// them, see CascadePolicy::FailCascade
+    fn fail_dependents(&mut self) {
+        loop {
+            let (failed, blocked): (Vec<Job>, Vec<Job>) = std::mem::take(&mut self.jobs_blocked)
+                .into_iter()
+                .partition(|job| job.depends_on.iter().any(|uid| self.jobs_failed.contains(uid)));
+            self.jobs_blocked = blocked;
+            if failed.is_empty() {
+                break;
+            }
+
+            for mut job in failed {
+                debug!(time = self.now, job = job.uid, "Job failed because a dependency failed");
+                job.failed = true;
+                self.jobs_failed.insert(job.uid);
+                self.job_factory.job_mark_failed(&job);
             }
         }
+    }
 
-        None
+    /// Inserts @job in jobs_queuing after all jobs with a higher priority and returns its index.
+    /// Jobs with the same priority are ordered by their UID, i.e. the order they were submitted.
+    fn queue_job(&mut self, mut job: Job) -> usize {
+        if self.held.contains(&job.uid) {
+            job.last_block_reason = Some(BlockReason::Held);
+            self.jobs_held.push(job);
+            return self.jobs_queuing.len();
+        }
+
+        let idx = self.jobs_queuing.partition_point(|queued| {
+            queued.priority > job.priority
+                || (queued.priority == job.priority && queued.uid < job.uid)
+        });
+        self.jobs_queuing.insert(idx, job);
+        idx
     }
 
-    fn job_allocate(registry: &mut NodeRegistry, job: &mut Job) -> bool {
-        if registry.is_dirty {
-            registry.resort_nodes_cores();
-            registry.resort_nodes_memory();
-            // VV: It's now safe to use the sorted indices
-            registry.is_dirty = false;
+    /// Puts the job @uid on hold, it waits in jobs_held instead of jobs_queuing until
+    /// release_job(). Returns true if the job was in jobs_queuing
+    pub fn hold_job(&mut self, uid: JobId) -> bool {
+        self.held.insert(uid);
+
+        match self.jobs_queuing.iter().position(|job| job.uid == uid) {
+            Some(idx) => {
+                let mut job = self.jobs_queuing.remove(idx).unwrap();
+                job.last_block_reason = Some(BlockReason::Held);
+                self.jobs_held.push(job);
+                self.queue_changed = true;
+                true
+            }
+            None => false,
         }
+    }
 
-        let cores_start = registry.idx_nodes_with_more_cores(job.cores);
-        if cores_start == registry.sorted_cores.len() {
+    /// Moves the job @uid to jobs_rejected if it is still waiting in jobs_queuing, jobs_blocked,
+    /// or jobs_held because it waited for longer than its max_queue_time. Returns true if the
+    /// job gave up
+    fn abandon_job(&mut self, uid: JobId) -> bool {
+        let job = if let Some(idx) = self.jobs_queuing.iter().position(|job| job.uid == uid) {
+            self.jobs_queuing.remove(idx).unwrap()
+        } else if let Some(idx) = self.jobs_blocked.iter().position(|job| job.uid == uid) {
+            self.jobs_blocked.remove(idx)
+        } else if let Some(idx) = self.jobs_held.iter().position(|job| job.uid == uid) {
+            self.jobs_held.remove(idx)
+        } else {
             return false;
+        };
+
+        let reason = queue_timeout_reason(&job, self.now);
+        debug!(time = self.now, job = uid, %reason, "Job rejected");
+        self.jobs_rejected.push((job, reason));
+        self.queue_changed = true;
+        true
+    }
+
+    /// Releases the job @uid, returns true if the job was in jobs_held and went back to
+    /// jobs_queuing
+    pub fn release_job(&mut self, uid: JobId) -> bool {
+        self.held.remove(&uid);
+
+        match self.jobs_held.iter().position(|job| job.uid == uid) {
+            Some(idx) => {
+                let job = self.jobs_held.remove(idx);
+                self.queue_job(job);
+                self.queue_changed = true;
+                true
+            }
+            None => false,
         }
+    }
 
-        let idx_memory = registry.idx_nodes_with_more_memory(job.memory);
+    /// Returns the other running attempt of the job @uid if it has a speculative copy
+    fn other_attempt(&self, uid: JobId) -> Option<&Job> {
+        if !self.speculated.contains_key(&uid) {
+            return None;
+        }
+        self.jobs_running.iter().find(|job| job.uid == uid)
+    }
 
-        // VV: First ty to fit job on any single node
-        let mut ret = Self::job_try_allocate(registry, job, idx_memory,
-                                             cores_start, registry.sorted_cores.len());
+    /// Removes the other running attempt of the job @uid, if any, along with its Completion
+    fn take_other_attempt(&mut self, uid: JobId) -> Option<Job> {
+        self.other_attempt(uid)?;
+        let idx = self.jobs_running.iter().position(|job| job.uid == uid)?;
+        let now = self.now;
+        self.events.retain(|Reverse(event)| match event {
+            Event::Completion(time, id) => *id != uid || *time <= now,
+            _ => true,
+        });
+        self.jobs_running.remove(idx)
+    }
 
-        // VV: If that's not possible, and the job can borrow resources, then try scheduling it
-        // using multiple nodes
-        if ret.is_none() && job.can_borrow {
-            for idx_cores in cores_start..registry.sorted_cores.len() {
-                ret = Self::try_allocate_on_many_nodes(registry, idx_cores, job);
-                if ret.is_some() {
-                    break
+    /// Starts a speculative copy of each straggler, see SchedulerBuilder::speculation(). The copy
+    /// goes to the first node, in node id order, that fits the job on its own and is not one of
+    /// the nodes of the straggler. Stragglers that do not fit anywhere try again in later ticks.
+    /// Schedules an Event::Speculation for the earliest time that another job becomes a straggler
+    fn speculate(&mut self) {
+        let threshold = match self.speculation {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        let mut stragglers = vec![];
+        let mut next: Option<f32> = None;
+
+        for job in &self.jobs_running {
+            let array = match job.array {
+                Some((array, _)) if !job.gang && !self.speculated.contains_key(&job.uid) => array,
+                _ => continue,
+            };
+            let mut runtimes = match self.array_runtimes.get(&array) {
+                Some(runtimes) => runtimes.clone(),
+                None => continue,
+            };
+            runtimes.sort_by(|a, b| a.total_cmp(b));
+
+            let median = RunSummary::percentile(&runtimes, 50.0) as f32;
+            let straggles_at = job.time_started.unwrap() + threshold * median;
+
+            if straggles_at <= self.now {
+                stragglers.push(job.clone());
+            } else {
+                next = Some(next.map_or(straggles_at, |next| next.min(straggles_at)));
+            }
+        }
+
+        for mut copy in stragglers {
+            let original = Placement {
+                node_cores: copy.node_cores.unwrap(),
+                node_memory: copy.node_memory.clone(),
+                gang_cores: vec![],
+            };
+            profile::count_attempt();
+            let node = self.registry.nodes.iter().find(|node| {
+                profile::count_check();
+                !original.uses(node.uid) && node.cores.current >= copy.cores
+                    && node.memory.current >= copy.memory
+                    && node.has_resources(&copy.resources, 1.0) && node.accepts(&copy)
+            });
+
+            if let Some(node) = node {
+                debug!(time = self.now, job = copy.uid, node = %node.name, "Job speculated");
+                let placement = Placement {
+                    node_cores: node.uid,
+                    node_memory: vec![(node.uid, copy.memory)],
+                    gang_cores: vec![],
+                };
+
+                copy.speculative = true;
+                placement.reserve(&mut self.registry, &copy);
+                self.speculated.insert(copy.uid, false);
+                self.start_job(copy, placement);
+            }
+        }
+
+        if let Some(next) = next.filter(|next| self.next_speculation != Some(*next)) {
+            self.next_speculation = Some(next);
+            self.events.push(Reverse(Event::Speculation(next)));
+        }
+    }
+
+    /// Starts @job on the resources of @placement, which the registry has already allocated
+    fn start_job(&mut self, mut job: Job, placement: Placement) {
+        // VV: Placed jobs no longer give up, their timeout must not keep the simulation going
+        if job.max_queue_time.is_some() {
+            let uid = job.uid;
+            self.events.retain(|Reverse(event)| !matches!(event,
+                Event::QueueTimeout(_, timeout) if *timeout == uid));
+        }
+
+        job.speed = placement.speed(&self.registry);
+        if let Some((_, speedup)) = self.warm_cache {
+            if placement.is_warm(&self.registry, &job) {
+                job.speed *= speedup;
+            }
+        }
+        job.staging = placement.staging(&self.registry, job.input_data);
+        job.last_block_reason = None;
+        let done = self.now + job.staging + job.runtime();
+        job.time_started = Some(self.now);
+        job.time_done = Some(done);
+        job.hops = placement.hop_distance(&self.registry);
+        job.node_cores = Some(placement.node_cores);
+        job.node_memory = placement.node_memory;
+        job.gang_cores = placement.gang_cores;
+
+        if let Some(deadline) = job.deadline.filter(|_| self.cancel_late && !job.speculative) {
+            let estimate = job.estimate / job.speed;
+            let estimate = job.walltime_limit.map_or(estimate, |limit| estimate.min(limit));
+            let finish = self.now + job.staging + estimate;
+            if finish > deadline {
+                self.release_placement(&mut job);
+
+                let reason = format!("Job {} would finish at {} after its deadline {}",
+                                     job.uid, finish, deadline);
+                debug!(time = self.now, job = job.uid, %reason, "Job rejected");
+                job.time_started = None;
+                job.time_done = None;
+                self.jobs_rejected.push((job, reason));
+                return;
+            }
+        }
+
+        debug!(time = self.now, job = job.uid, node = %self.registry.nodes[placement.node_cores].name,
+            done, "Job placed");
+        // VV: The Placement already counts the job itself in the groups
+        job.colocated = job.anti_affinity.as_ref().is_some_and(|group| {
+            job.resource_shares().iter()
+                .any(|(uid, _)| self.registry.nodes[*uid].groups[group] > 1)
+        });
+        self.events.push(Reverse(Event::Completion(done, job.uid)));
+        // VV: The memory of the nodes may run out when the job reaches a step of its profile
+        for (time, _) in job.memory_steps().into_iter().skip(1).filter(|(time, _)| *time < done) {
+            self.events.push(Reverse(Event::MemoryStep(time, job.uid)));
+        }
+
+        let predicate = |job: &Job| -> bool {
+            job.time_done.unwrap() < done
+        };
+        let idx = self.jobs_running.partition_point(predicate);
+        self.jobs_running.insert(idx, job);
+    }
+
+    /// Allocates the free resources that the active reservations keep for their owners, except
+    /// for the reservations of @owner. The resources that the running jobs of an owner use on a
+    /// reserved node count towards its reservation. Returns the cores and memory that each
+    /// reservation holds so that release_reservations() can free them
+    fn hold_reservations(&mut self, owner: Option<&str>) -> Vec<(NodeId, f32, f32)> {
+        let mut held = vec![];
+
+        for reservation in &self.reservations {
+            if !reservation.is_active(self.now) || owner == Some(reservation.owner.as_str()) {
+                continue;
+            }
+
+            let (mut cores, mut memory) = (reservation.cores, reservation.memory);
+            for job in self.jobs_running
+                .iter()
+                .filter(|job| job.user.as_deref() == Some(reservation.owner.as_str()))
+            {
+                for (uid, used) in job.cores_per_node() {
+                    if uid == reservation.node {
+                        cores -= used;
+                    }
+                }
+                for (uid, used) in &job.node_memory {
+                    if *uid == reservation.node {
+                        memory -= used;
+                    }
                 }
             }
+
+            // VV: Failed and draining nodes have no free resources to hold
+            let node = &mut self.registry.nodes[reservation.node];
+            let cores = cores.clamp(0.0, node.cores.current);
+            let memory = memory.clamp(0.0, node.memory.current);
+            if cores > 0.0 {
+                node.allocate_cores(cores);
+            }
+            if memory > 0.0 {
+                node.allocate_memory(memory);
+            }
+            held.push((reservation.node, cores, memory));
+        }
+
+        if !held.is_empty() {
+            for (uid, _, _) in &held {
+                self.registry.touch(*uid);
+            }
+            self.registry.ensure_sorted();
         }
 
-        match ret {
-            Some((uid_cores, mut all_memory)) => {
-                registry.nodes[uid_cores].allocate_cores(job.cores);
+        held
+    }
+
+    fn release_reservations(&mut self, held: Vec<(NodeId, f32, f32)>) {
+        for (uid, cores, memory) in held {
+            let node = &mut self.registry.nodes[uid];
+            if cores > 0.0 {
+                node.free_cores(cores);
+            }
+            if memory > 0.0 {
+                node.free_memory(memory);
+            }
+            self.registry.touch(uid);
+        }
+    }
 
-                let node_cores = &registry.nodes[uid_cores];
-                job.node_cores = Some(node_cores.uid);
+    /// Starts the queued jobs whose user owns an active reservation, in the order they were
+    /// queued, if FirstFit places them on a node that the user reserved. The other reservations
+    /// are unavailable to these jobs. Returns the number of jobs that started
+    fn start_reserved(&mut self) -> usize {
+        let mut started = 0;
+        let mut idx = 0;
 
-                for (uid_mem, allocated) in &all_memory {
-                    let node_mem = &mut registry.nodes[*uid_mem];
-                    node_mem.allocate_memory(*allocated);
+        while idx < self.jobs_queuing.len() {
+            let owner = match self.jobs_queuing[idx].user.clone() {
+                Some(user) => user,
+                None => {
+                    idx += 1;
+                    continue;
                 }
-                job.node_memory.append(&mut all_memory);
-                // println!("Scheduling {}x{} on Cores:{:?}, Memory:{:?}",
-                // job.cores, job.memory, job.node_cores, job.node_memory);
+            };
 
-                // VV: It's not safe to use the sorted indices any more
-                registry.is_dirty = true;
-                true
+            let reserved: Vec<NodeId> = self.reservations
+                .iter()
+                .filter(|r| r.is_active(self.now) && r.owner == owner)
+                .map(|r| r.node)
+                .collect();
+
+            if reserved.is_empty() {
+                idx += 1;
+                continue;
+            }
+
+            if !self.limits.is_empty()
+                && exceeds_limits(&self.limits, &self.limits_usage(), &self.jobs_queuing[idx]) {
+                idx += 1;
+                continue;
+            }
+
+            let held = self.hold_reservations(Some(&owner));
+            let job = &self.jobs_queuing[idx];
+            profile::count_attempt();
+            let placement = FirstFit::job_place(&mut self.registry, job)
+                .filter(|placement| reserved.iter().any(|uid| placement.uses(*uid)));
+
+            if let Some(placement) = &placement {
+                placement.reserve(&mut self.registry, job);
+            }
+            self.release_reservations(held);
+
+            match placement {
+                Some(placement) => {
+                    let job = self.jobs_queuing.remove(idx).unwrap();
+                    self.start_job(job, placement);
+                    started += 1;
+                }
+                None => idx += 1,
             }
-            None => false
         }
+
+        started
     }
 
+    /// Handles all events up to now and then advances now to the time of the next event. Idle
+    /// periods without events pass in a single tick but still count towards the duration of the run
     pub fn tick(&mut self) -> bool {
-        let mut next_tick: Option<f32> = None;
-        let mut run_now: Vec<usize> = vec![];
+        let counted = self.placement_profile.map(|_| profile::counters());
+        // VV: Running jobs that went back to the queue, e.g. preempted jobs or jobs of failed nodes
+        let mut new_preempted = 0;
+        // VV: Something other than finished or preempted jobs changed what the policy can place,
+        // e.g. nodes that came back or jobs that arrived since the last scheduling cycle, so the
+        // policy must look at the entire queue again
+        let mut state_changed = false;
+        // VV: Jobs that miss their deadline free their placement just like jobs that finish
+        let mut new_cancelled = 0;
+        let mut node_events = self.take_due_node_events();
+        self.update_user_usage();
+
+        // VV: The scheduler is still busy starting the jobs of its last decision
+        let busy = self.decision_latency.is_some() && self.now < self.busy_until;
+
+        self.decided = match self.schedule_interval {
+            _ if busy => false,
+            Some(interval) => {
+                // VV: There are no Events for the cycles that pass without queued jobs, the jobs
+                // that arrive now wait for the first cycle from now on
+                if !self.cycle_event && self.next_cycle < self.now {
+                    self.next_cycle = (self.now / interval).ceil() * interval;
+                }
+
+                let decide = self.now >= self.next_cycle;
+                if decide {
+                    self.next_cycle = ((self.now / interval).floor() + 1.0) * interval;
+                    self.cycle_event = false;
+                    // VV: The policy has not seen the jobs that arrived since the last cycle
+                    state_changed = true;
+                }
+                decide
+            }
+            None => true,
+        };
+
+        if self.warm_cache.is_some() {
+            for node in &mut self.registry.nodes {
+                node.cool_down(self.now);
+            }
+        }
         // println!("Now is {}", self.now);
 
         loop {
             let mut new_queueing = 0;
-            let new_running;
             let mut new_done = 0;
 
             while !self.jobs_running.is_empty() {
                 let job = &self.jobs_running[0];
                 if job.time_done.unwrap() <= self.now {
-                    let job = self.jobs_running.pop_front().unwrap();
+                    let mut job = self.jobs_running.pop_front().unwrap();
                     // println!(
                     //     "  Job {} that started on {} with duration {} finished",
                     //     job.uid,
@@ -216,101 +2938,337 @@ impl Scheduler
                     //     job.duration
                     // );
 
-                    self.job_free(job);
+                    // VV: Jobs only fail once the last of their attempts reaches the walltime limit
+                    let failed = job.exceeds_walltime() || self.run_fails(&job);
+                    if failed && self.other_attempt(job.uid).is_some() {
+                        self.job_release(&job);
+                    } else if failed && !job.exceeds_walltime() && job.attempts < job.max_retries {
+                        job.attempts += 1;
+                        self.retry_job(job);
+                    } else if failed {
+                        job.attempts += 1;
+                        self.job_fail(job);
+                    } else {
+                        job.attempts += 1;
+                        self.job_free(job);
+                    }
                     new_done += 1;
                 } else {
-                    // println!("  NextRunning {}", job.time_done.unwrap());
-                    next_tick = match next_tick {
-                        Some(x) => Some(x.min(job.time_done.unwrap())),
-                        None => job.time_done,
-                    };
-
                     break;
                 }
             }
 
-            let orig_queueing = self.jobs_queuing.len();
+            // VV: Jobs that finish at the time their node fails still get to finish
+            let nodes_changed = !node_events.is_empty();
+            for event in std::mem::take(&mut node_events) {
+                match event {
+                    Event::NodeFailure(_, uid) => new_preempted += self.fail_node(uid),
+                    Event::NodeRecovery(_, uid) => {
+                        self.registry.nodes[uid].recover();
+                        self.registry.is_dirty = true;
+                        state_changed = true;
+                    }
+                    Event::NodeDrain(_, uid) => {
+                        self.registry.nodes[uid].drain();
+                        self.registry.is_dirty = true;
+                    }
+                    // VV: Nodes that left the cluster stay drained after their maintenance
+                    Event::NodeReturn(_, uid) if !self.registry.nodes[uid].removed => {
+                        self.registry.nodes[uid].undrain();
+                        self.registry.is_dirty = true;
+                        state_changed = true;
+                    }
+                    Event::NodeScaling(_, idx) => {
+                        let (requeued, freed) = self.apply_scaling(idx);
+                        new_preempted += requeued;
+                        state_changed |= freed;
+                    }
+                    Event::NodeCooled(time, uid)
+                        if self.registry.nodes[uid].end_cooldown(time) => {
+                        self.registry.is_dirty = true;
+                        state_changed = true;
+                    }
+                    // VV: The reserved resources are free for everyone again
+                    Event::ReservationEnd(..) => state_changed = true,
+                    Event::JobHold(_, idx) => {
+                        let hold = &self.holds[idx];
+                        let uid = hold.job;
+                        if hold.release {
+                            self.release_job(uid);
+                        } else {
+                            self.hold_job(uid);
+                        }
+                    }
+                    Event::QueueTimeout(_, uid) => {
+                        self.abandon_job(uid);
+                    }
+                    _ => (),
+                }
+            }
+
+            if std::mem::take(&mut self.queue_changed) {
+                state_changed = true;
+            }
+
+            if new_done > 0 && !self.jobs_blocked.is_empty() {
+                self.unblock_jobs();
+            }
+
+            // VV: Index of the first job in jobs_queuing that the policy has not seen yet
+            let mut first_new = self.jobs_queuing.len();
 
             while let Some(job) = self.job_factory.job_peek() {
                 if job.time_created <= self.now {
-                    let job = self.job_factory.job_get();
-                    self.jobs_queuing.push_back(job);
+                    let mut job = self.job_factory.job_get();
+                    self.jobs_arrived += 1;
+
+                    debug!(time = self.now, job = job.uid, cores = job.cores, memory = job.memory,
+                        "Job submitted");
+
+                    if let Err(reason) = self.check_can_run(&job) {
+                        debug!(time = self.now, job = job.uid, %reason, "Job rejected");
+                        self.jobs_rejected.push((job, reason.to_string()));
+                        continue;
+                    }
+
+                    if let Some(depth) = self.max_queue_depth {
+                        if self.jobs_queuing.len() >= depth {
+                            let reason = format!("Job {} arrived at {} while {} jobs were queuing",
+                                                 job.uid, self.now, self.jobs_queuing.len());
+                            debug!(time = self.now, job = job.uid, %reason, "Job rejected");
+                            self.jobs_rejected.push((job, reason));
+                            continue;
+                        }
+                    }
+
+                    if let Some(model) = &self.runtime_model {
+                        job.duration = model.sample(job.duration, &mut self.rng);
+                    }
+
+                    if let (Some(limit), None) = (job.memory_limit, job.memory_usage) {
+                        job.memory_usage = Some(if limit > job.memory {
+                            self.rng.gen_range(job.memory..=limit)
+                        } else {
+                            job.memory
+                        });
+                    }
+
+                    if job.walltime_limit.is_none() {
+                        job.walltime_limit = self.partition_limits(&job)
+                            .and_then(|limits| limits.max_time);
+                    }
+
+                    // VV: Coarse ticks may only see jobs after they would have given up
+                    if let Some(max) = job.max_queue_time {
+                        let time = job.time_created + max;
+                        if time <= self.now {
+                            let reason = queue_timeout_reason(&job, self.now);
+                            debug!(time = self.now, job = job.uid, %reason, "Job rejected");
+                            self.jobs_rejected.push((job, reason));
+                            continue;
+                        }
+                        self.events.push(Reverse(Event::QueueTimeout(time, job.uid)));
+                    }
+
+                    if self.dependencies_done(&job) {
+                        first_new = first_new.min(self.queue_job(job));
+                    } else {
+                        job.last_block_reason = Some(BlockReason::Dependency);
+                        let lost = self.cascade == CascadePolicy::FailCascade
+                            && job.depends_on.iter().any(|uid| self.jobs_failed.contains(uid));
+                        self.jobs_blocked.push(job);
+                        if lost {
+                            self.fail_dependents();
+                        }
+                    }
                     new_queueing += 1;
                 } else {
-                    // println!("  NextQueueing {}", job.time_created);
-                    next_tick = match next_tick {
-                        Some(x) => Some(x.min(job.time_created)),
-                        None => Some(job.time_created),
-                    };
+                    if self.next_arrival != Some(job.time_created) {
+                        self.next_arrival = Some(job.time_created);
+                        self.events.push(Reverse(Event::Arrival(job.time_created)));
+                    }
                     break;
                 }
             }
 
-            let skip;
-            if new_done > 0 {
-                skip = 0;
-            } else {
-                // VV: No jobs finished during this iteration of the current tick, no need to re-process
-                // the first few orig_queueing jobs, just the ones that this iteration discovered
-                skip = orig_queueing;
+            // VV: Jobs keep arriving and finishing between scheduling cycles but none of them start
+            if !self.decided || self.now < self.busy_until {
+                if new_queueing + new_done + new_preempted == 0 && !state_changed {
+                    break;
+                }
+                new_preempted = 0;
+                state_changed = false;
+                continue;
             }
 
-            let (t_max_cores, t_max_mem) = self.registry.get_max_cores_memory();
-            let mut max_cores = t_max_cores;
-            let mut max_memory = t_max_mem;
+            // VV: The jobs of bundles only start together, the policy does not place them 1 by 1
+            let mut bundled = self.take_bundled();
+            // VV: Jobs that would take their user or partition past its limit sit out this pass
+            let limited = self.take_limited();
 
-            // println!("Cores: {}, Memory: {}", max_cores, max_memory);
+            // VV: The owners of active reservations place their jobs first, then the bundles, and
+            // then the policy places the other jobs on the resources that the reservations leave free
+            let rejected = self.jobs_rejected.len();
+            let started_reserved = if self.reservations.iter().any(|r| r.is_active(self.now)) {
+                self.start_reserved()
+            } else {
+                0
+            };
+            let started_bundled = if bundled.is_empty() {
+                0
+            } else {
+                self.start_bundles(&mut bundled)
+            };
 
-            for (i, job) in self.jobs_queuing.iter_mut().skip(skip).enumerate() {
-                if job.cores > max_cores || job.memory > max_memory {
-                    continue;
-                }
+            // VV: With a decision latency the earlier decisions may have left placeable jobs
+            let skip = if new_done + new_preempted + new_cancelled + started_reserved
+                + started_bundled > 0 || state_changed || !limited.is_empty()
+                || !bundled.is_empty() || self.decision_latency.is_some() {
+                0
+            } else {
+                // VV: No jobs finished during this iteration of the current tick, no need to re-process
+                // the first few first_new jobs, just the ones that this iteration discovered
+                first_new
+            };
 
-                if Self::job_allocate(&mut self.registry, job) {
-                    run_now.push(i + skip);
-                    let (t_max_cores, t_max_mem) = self.registry.get_max_cores_memory();
-                    max_cores = t_max_cores;
-                    max_memory = t_max_mem;
-                    self.registry.is_dirty = true;
-                }
+            // VV: The policies read the largest free node off the sorted indices before placing
+            // anything. Unavailable nodes have no free resources and if a stale order puts them
+            // last they hide the free resources of the other nodes
+            if nodes_changed || self.registry.nodes
+                .iter()
+                .any(|node| node.failed || node.draining || node.cooling) {
+                self.registry.ensure_sorted();
             }
 
-            new_running = run_now.len();
+            let held = self.hold_reservations(None);
+
+            let context = PolicyContext {
+                now: self.now,
+                jobs_running: &self.jobs_running,
+                unchanged: skip,
+                user_usage: &self.user_usage,
+                user_weights: &self.user_weights,
+            };
+            let queued = self.jobs_queuing.make_contiguous();
+            let mut run_now: HashMap<JobId, Placement> = self.policy
+                .select(queued, &mut self.registry, &context)
+                .into_iter()
+                .collect();
+            self.release_reservations(held);
+
+            let mut new_running = run_now.len();
+            // VV: Jobs that the policy placed in the same pass may take their user or partition
+            // past its limit together, the ones after the limit give their placement back
+            let mut over_limit = 0;
+            // VV: The number of jobs that the policy may still start in this decision
+            let mut budget = self.decision_latency
+                .map(|_| if started_reserved + started_bundled > 0 { 0 } else { 1 });
 
             if new_running > 0 {
                 let mut q: VecDeque<Job> =
                     VecDeque::with_capacity(self.jobs_queuing.len() - new_running);
-                let mut i = 0;
                 while !self.jobs_queuing.is_empty() {
                     let mut job = self.jobs_queuing.pop_front().unwrap();
-                    let done = self.now + job.duration;
-                    if run_now.contains(&i) {
-                        job.time_started = Some(self.now);
-                        job.time_done = Some(done);
-
-                        let predicate = |job: &Job| -> bool {
-                            job.time_done.unwrap() < done
-                        };
-                        let idx = self.jobs_running.partition_point(predicate);
-                        self.jobs_running.insert(idx, job);
+                    if let Some(placement) = run_now.remove(&job.uid) {
+                        if !self.limits.is_empty()
+                            && exceeds_limits(&self.limits, &self.limits_usage(), &job) {
+                            job.node_cores = Some(placement.node_cores);
+                            job.node_memory = placement.node_memory;
+                            job.gang_cores = placement.gang_cores;
+                            self.release_placement(&mut job);
+                            job.last_block_reason = Some(BlockReason::Limit);
+                            q.push_back(job);
+                            over_limit += 1;
+                        } else if budget == Some(0) {
+                            // VV: The scheduler gets to the job in a later decision
+                            job.node_cores = Some(placement.node_cores);
+                            job.node_memory = placement.node_memory;
+                            job.gang_cores = placement.gang_cores;
+                            self.release_placement(&mut job);
+                            q.push_back(job);
+                            new_running -= 1;
+                        } else {
+                            budget = budget.map(|budget| budget - 1);
+                            self.start_job(job, placement);
+                        }
                     } else {
                         q.push_back(job);
                     }
-
-                    i += 1;
                 }
 
                 self.jobs_queuing.append(&mut q);
-                run_now.clear();
             }
+            for job in limited.into_iter().chain(bundled) {
+                self.queue_job(job);
+            }
+            new_running -= over_limit;
+            new_cancelled = self.jobs_rejected.len() - rejected + over_limit;
+            let new_running = new_running + started_reserved + started_bundled;
+
+            if let Some(latency) = self.decision_latency.filter(|_| new_running > 0) {
+                self.busy_until = self.now + latency * new_running as f32;
+                self.events.push(Reverse(Event::SchedulingCycle(self.busy_until)));
+            }
+
+            // VV: A pass that changed nothing is not a new scheduling decision
+            if self.track_fragmentation
+                && (new_queueing + new_done + new_preempted + new_running > 0 || state_changed)
+                && new_running + self.jobs_queuing.len() > 0
+            {
+                self.sample_fragmentation(new_running);
+            }
+
+            new_preempted = if self.preemption { self.preempt() } else { 0 };
+            new_preempted += self.oom_kill();
+            state_changed = false;
 
-            if new_queueing + new_running + new_done == 0 {
+            if new_queueing + new_running + new_done + new_preempted == 0 {
                 break;
             }
         }
 
-        self.now = next_tick.unwrap_or(self.now);
+        if self.speculation.is_some() && self.decided {
+            self.speculate();
+        }
+
+        if self.decided && !self.jobs_queuing.is_empty() {
+            self.update_block_reasons();
+        }
+
+        if self.schedule_interval.is_some() && !self.cycle_event && !self.jobs_queuing.is_empty() {
+            self.events.push(Reverse(Event::SchedulingCycle(self.next_cycle)));
+            self.cycle_event = true;
+        }
+
+        if let (Some(placement_profile), Some(counted)) = (&mut self.placement_profile, counted) {
+            placement_profile.record(self.now, profile::counters() - counted);
+        }
+
+        if self.is_truncated() {
+            return false;
+        }
+
+        // VV: Events up to now are handled, preemption may also have moved the jobs of some
+        // Completion events back to jobs_queuing but it removes those events too
+        while let Some(Reverse(event)) = self.events.peek() {
+            if event.time() > self.now {
+                let next = match self.tick_granularity {
+                    Some(granularity) => (event.time() / granularity).ceil() * granularity,
+                    None => event.time(),
+                };
+
+                if let Some(until) = self.until.filter(|until| next > *until) {
+                    self.now = until.max(self.now);
+                    return false;
+                }
+                self.now = next;
+                break;
+            }
+            self.events.pop();
+        }
 
-        (self.jobs_queuing.len() + self.jobs_running.len() > 0) || self.job_factory.more_jobs()
+        (self.jobs_queuing.len() + self.jobs_running.len() + self.jobs_blocked.len()
+            + self.jobs_held.len() > 0)
+            || self.job_factory.more_jobs()
     }
 }
@@ -0,0 +1,234 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+
+//! Frames of the occupancy of every node at regular points in simulated time, e.g. to animate
+//! a run. A SnapshotWriter writes 1 JSON ClusterSnapshot per line, state_table() and
+//! occupancy_table() print the state for people to read
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Context;
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::clock::Cadence;
+use crate::clock::SimClock;
+use crate::compression;
+use crate::job::JobId;
+use crate::node::NodeId;
+use crate::scheduler::Scheduler;
+
+/// What a node hosts at the time of a ClusterSnapshot
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeSnapshot {
+    pub uid: NodeId,
+    pub name: String,
+    pub cores: f32,
+    pub memory: f32,
+    /// The cores and memory of the node that running jobs use, memory includes what jobs on other
+    /// nodes borrow from it
+    pub cores_used: f32,
+    pub memory_used: f32,
+    /// The cores and memory that new jobs may still use, failed, draining, and cooling nodes
+    /// keep their unused resources away from new jobs
+    pub cores_free: f32,
+    pub memory_free: f32,
+    /// The named resources of the node that running jobs use and the ones still free
+    pub resources_used: BTreeMap<String, f32>,
+    pub resources_free: BTreeMap<String, f32>,
+    /// The uids of the running jobs that use the cores or memory of the node, in ascending order
+    pub jobs: Vec<JobId>,
+    pub failed: bool,
+    pub draining: bool,
+}
+
+/// The occupancy of every node at @time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClusterSnapshot {
+    pub time: f32,
+    pub nodes: Vec<NodeSnapshot>,
+}
+
+impl ClusterSnapshot {
+    pub fn from_scheduler(sched: &Scheduler) -> Self {
+        let mut nodes: Vec<NodeSnapshot> = sched.registry.nodes
+            .iter()
+            .map(|node| NodeSnapshot {
+                uid: node.uid,
+                name: node.name.clone(),
+                cores: node.cores.capacity,
+                memory: node.memory.capacity,
+                cores_used: 0.0,
+                memory_used: 0.0,
+                cores_free: node.cores.current,
+                memory_free: node.memory.current,
+                resources_used: node.resources.keys().map(|name| (name.clone(), 0.0)).collect(),
+                resources_free: node.resources
+                    .iter()
+                    .map(|(name, resource)| (name.clone(), resource.current))
+                    .collect(),
+                jobs: vec![],
+                failed: node.failed,
+                draining: node.draining,
+            })
+            .collect();
+
+        for job in &sched.jobs_running {
+            for (uid, cores) in job.cores_per_node() {
+                nodes[uid].cores_used += cores;
+                nodes[uid].jobs.push(job.uid);
+            }
+            for (uid, memory) in &job.node_memory {
+                nodes[*uid].memory_used += memory;
+                nodes[*uid].jobs.push(job.uid);
+            }
+            for (uid, share) in job.resource_shares() {
+                for (name, amount) in &job.resources {
+                    if let Some(used) = nodes[uid].resources_used.get_mut(name) {
+                        *used += amount * share;
+                    }
+                }
+            }
+        }
+
+        for node in &mut nodes {
+            node.jobs.sort();
+            node.jobs.dedup();
+        }
+
+        Self { time: sched.now, nodes }
+    }
+}
+
+/// Returns a human-readable table of the nodes and the queues of @sched, e.g. to single-step a
+/// simulation. Each node shows the cores and memory that running jobs use out of its capacity,
+/// queued jobs show why they did not start at the last scheduling cycle
+pub fn state_table(sched: &Scheduler) -> String {
+    let frame = ClusterSnapshot::from_scheduler(sched);
+    let mut out = format!("{:<16} {:>15} {:>15} {:<8} jobs\n", "node", "cores", "memory", "state");
+
+    for node in &frame.nodes {
+        let state = if node.failed {
+            "failed"
+        } else if node.draining {
+            "draining"
+        } else {
+            "up"
+        };
+        let jobs: Vec<String> = node.jobs.iter().map(|uid| uid.to_string()).collect();
+        out.push_str(&format!("{:<16} {:>15} {:>15} {:<8} {}\n", node.name,
+                              format!("{}/{}", node.cores_used, node.cores),
+                              format!("{}/{}", node.memory_used, node.memory),
+                              state, jobs.join(",")));
+    }
+
+    out.push_str(&format!("running: {}\n", sched.jobs_running.len()));
+    for job in &sched.jobs_running {
+        let nodes: Vec<String> = job.cores_per_node()
+            .iter()
+            .map(|(uid, _)| sched.registry.nodes[*uid].name.clone())
+            .collect();
+        out.push_str(&format!("  job {} on {} started {} finishes {}\n", job.uid, nodes.join(","),
+                              job.time_started.unwrap_or_default(),
+                              job.time_done.unwrap_or_default()));
+    }
+
+    out.push_str(&format!("queuing: {}\n", sched.jobs_queuing.len()));
+    for job in &sched.jobs_queuing {
+        let reason = match &job.last_block_reason {
+            Some(reason) => reason.to_string(),
+            None => "not considered yet".to_string(),
+        };
+        out.push_str(&format!("  job {} cores {} memory {} waits: {}\n", job.uid, job.cores,
+                              job.memory, reason));
+    }
+
+    out.push_str(&format!("blocked: {} - held: {} - done: {} - rejected: {}\n",
+                          sched.jobs_blocked.len(), sched.jobs_held.len(),
+                          sched.jobs_done.len(), sched.jobs_rejected.len()));
+    out
+}
+
+/// Returns a human-readable table of what each node of @frame runs and how much of it is still
+/// free, e.g. to inspect the cluster at the end of a run that stops with --until
+pub fn occupancy_table(frame: &ClusterSnapshot) -> String {
+    let mut out = format!("{:<16} {:>21} {:>21} {:<24} jobs\n", "node", "cores used/free/total",
+                          "memory used/free/total", "resources free");
+
+    for node in &frame.nodes {
+        let resources: Vec<String> = node.resources_free
+            .iter()
+            .map(|(name, free)| format!("{}={}", name, free))
+            .collect();
+        let jobs: Vec<String> = node.jobs.iter().map(|uid| uid.to_string()).collect();
+        out.push_str(&format!("{:<16} {:>21} {:>21} {:<24} {}\n", node.name,
+                              format!("{}/{}/{}", node.cores_used, node.cores_free, node.cores),
+                              format!("{}/{}/{}", node.memory_used, node.memory_free,
+                                      node.memory),
+                              resources.join(","), jobs.join(",")));
+    }
+
+    let used: f32 = frame.nodes.iter().map(|node| node.cores_used).sum();
+    let total: f32 = frame.nodes.iter().map(|node| node.cores).sum();
+    let idle = frame.nodes.iter().filter(|node| node.jobs.is_empty()).count();
+    out.push_str(&format!("{} of {} cores used, {} of {} nodes idle\n", used, total, idle,
+                          frame.nodes.len()));
+    out
+}
+
+/// Writes a ClusterSnapshot to a newline-delimited JSON file once every @every simulated seconds
+pub struct SnapshotWriter {
+    out: Box<dyn Write>,
+    cadence: Cadence,
+}
+
+impl SnapshotWriter {
+    /// Creates @path and writes the first frame, i.e. the state of @sched before it starts
+    pub fn new(path: &Path, every: f32, sched: &Scheduler) -> Result<Self> {
+        let out = compression::create(path)
+            .context(format!("Unable to create snapshots file {}", path.display()))?;
+        let cadence = Cadence::new(Box::new(SimClock), every, sched);
+        let mut writer = Self { out, cadence };
+        writer.write(sched)?;
+        Ok(writer)
+    }
+
+    /// Writes a frame if @every simulated seconds passed since the last one
+    pub fn update(&mut self, sched: &Scheduler) -> Result<()> {
+        if self.cadence.due(sched) {
+            self.write(sched)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a frame of the current state of @sched
+    pub fn write(&mut self, sched: &Scheduler) -> Result<()> {
+        let frame = serde_json::to_string(&ClusterSnapshot::from_scheduler(sched))?;
+        writeln!(self.out, "{}", frame)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
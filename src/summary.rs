@@ -0,0 +1,288 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::bail;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::job::JobId;
+use crate::node::NodeId;
+
+/// When a job that finished or failed was created, started, and stopped running
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct JobTimes {
+    pub created: f32,
+    pub started: f32,
+    pub done: f32,
+    /// The job reached its walltime limit
+    pub failed: bool,
+}
+
+impl JobTimes {
+    pub fn wait(&self) -> f32 {
+        self.started - self.created
+    }
+
+    pub fn turnaround(&self) -> f32 {
+        self.done - self.created
+    }
+
+    /// The bounded slowdown (wait + runtime) / max(runtime, @threshold), which is at least 1.
+    /// The threshold keeps very short jobs from dominating the metric
+    pub fn slowdown(&self, threshold: f32) -> f32 {
+        let runtime = self.done - self.started;
+        (self.turnaround() / runtime.max(threshold)).max(1.0)
+    }
+
+    /// Returns true if the job was running at some point between @start and @end
+    pub fn overlaps(&self, start: f32, end: f32) -> bool {
+        self.started < end && self.done > start
+    }
+}
+
+/// The outcome of 1 scheduling decision, see SchedulerBuilder::track_fragmentation()
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FragmentationSample {
+    pub time: f32,
+    /// Jobs that the policy started
+    pub started: usize,
+    /// Jobs that could have started if the free cores and memory of all nodes were on a single
+    /// node, this includes the jobs that did start
+    pub could_start: usize,
+}
+
+impl FragmentationSample {
+    /// Jobs that did not start only because the free resources are spread over many nodes
+    pub fn blocked(&self) -> usize {
+        self.could_start - self.started
+    }
+}
+
+/// How many jobs finished by @time, see Scheduler::throughput()
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ThroughputSample {
+    pub time: f32,
+    pub done: usize,
+}
+
+impl ThroughputSample {
+    /// Writes 1 row per sample to the CSV file at @path with a header, e.g. to plot how the
+    /// cumulative number of finished jobs grows over time
+    pub fn write_csv(samples: &[ThroughputSample], path: &Path) -> Result<()> {
+        let mut file = match File::create(path) {
+            Ok(file) => file,
+            Err(x) => bail!("Unable to create throughput file {} because of {:?}", path.display(), x),
+        };
+
+        writeln!(file, "time,done")?;
+        for sample in samples {
+            writeln!(file, "{},{}", sample.time, sample.done)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A running job at some point of the simulation, see Scheduler::running_jobs_status()
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunningJobStatus {
+    pub uid: JobId,
+    /// The node that provides the cores of the job, the first node for gang jobs
+    pub node: NodeId,
+    pub started: f32,
+    /// The time the job is expected to finish, including the time it spends staging its input
+    pub done: f32,
+    /// The simulated time that the job still needs to finish
+    pub remaining: f32,
+}
+
+/// How long jobs waited in the queue before they started, see Scheduler::wait_histogram().
+/// Bucket i holds the waits in [bounds[i-1], bounds[i]), the first bucket starts at 0 and the
+/// last one has no upper bound. Jobs that have not started yet go to never_started instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaitHistogram {
+    pub bounds: Vec<f32>,
+    pub counts: Vec<usize>,
+    pub never_started: usize,
+}
+
+impl WaitHistogram {
+    /// Creates an empty histogram, @bounds must be positive and ascending
+    pub fn new(bounds: &[f32]) -> Result<Self> {
+        if bounds.iter().any(|bound| !(*bound > 0.0 && bound.is_finite())) {
+            bail!("The bounds of the wait histogram must be positive but found {:?}", bounds)
+        }
+        if bounds.windows(2).any(|pair| pair[0] >= pair[1]) {
+            bail!("The bounds of the wait histogram must be ascending but found {:?}", bounds)
+        }
+
+        Ok(Self { bounds: bounds.to_vec(), counts: vec![0; bounds.len() + 1], never_started: 0 })
+    }
+
+    pub fn add(&mut self, wait: f32) {
+        let bucket = self.bounds.partition_point(|bound| *bound <= wait);
+        self.counts[bucket] += 1;
+    }
+
+    /// The label of each bucket e.g. "[60, 600)"
+    pub fn labels(&self) -> Vec<String> {
+        let mut lower = vec![0.0];
+        lower.extend(&self.bounds);
+
+        lower
+            .iter()
+            .zip(self.bounds.iter().map(|bound| bound.to_string()).chain(["inf".to_owned()]))
+            .map(|(lower, upper)| format!("[{}, {})", lower, upper))
+            .collect()
+    }
+}
+
+impl Display for WaitHistogram {
+    /// Draws 1 bar per bucket, the longest bar is 50 characters wide
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut rows: Vec<(String, usize)> = self.labels().into_iter().zip(self.counts.clone())
+            .collect();
+        rows.push(("never started".to_owned(), self.never_started));
+
+        let widest = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+        let most = rows.iter().map(|(_, count)| *count).max().unwrap_or(0).max(1);
+
+        for (label, count) in rows {
+            let bar = "#".repeat((count * 50).div_ceil(most));
+            let row = format!("  {:<widest$} {:>8} {}", label, count, bar);
+            writeln!(f, "{}", row.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Aggregate metrics of a simulation, see Scheduler::summary(). Times are in simulated seconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunSummary {
+    /// Jobs that finished or failed
+    pub total_jobs: usize,
+    pub jobs_failed: usize,
+    /// The time that the last job finished or failed at
+    pub makespan: f64,
+    pub mean_wait: f64,
+    pub median_wait: f64,
+    pub p95_wait: f64,
+    pub mean_turnaround: f64,
+    /// The bounded slowdown of the jobs, see JobTimes::slowdown()
+    pub mean_slowdown: f64,
+    pub p95_slowdown: f64,
+    /// The fraction of the core-seconds of the cluster that jobs consumed
+    pub core_utilization: f64,
+    /// The fraction of the memory-seconds of the cluster that jobs consumed, a workload whose
+    /// memory_utilization is well above its core_utilization is bound by memory
+    pub memory_utilization: f64,
+    /// The energy that all nodes consumed, including the idle ones, see Node::energy()
+    pub energy_kwh: f64,
+    /// What the nodes cost while they were part of the cluster, see Node::cost_per_hour
+    pub total_cost: f64,
+    /// The total_cost divided by the number of jobs that finished without failing
+    pub cost_per_job: f64,
+    /// The simulation stopped at SchedulerBuilder::max_jobs() or SchedulerBuilder::until() while
+    /// jobs were still waiting or running, the metrics only cover the jobs that finished so far
+    pub truncated: bool,
+}
+
+impl RunSummary {
+    pub const CSV_HEADER: &'static str = "total_jobs,jobs_failed,makespan,mean_wait,median_wait,\
+        p95_wait,mean_turnaround,mean_slowdown,p95_slowdown,core_utilization,memory_utilization,\
+        energy_kwh,total_cost,cost_per_job,truncated";
+
+    /// Returns the nearest-rank @percentile of the already sorted @values, 0.0 if there are none
+    pub fn percentile(values: &[f32], percentile: f64) -> f64 {
+        if values.is_empty() {
+            return 0.0;
+        }
+
+        let rank = (percentile / 100.0 * values.len() as f64).ceil() as usize;
+        values[rank.clamp(1, values.len()) - 1] as f64
+    }
+
+    pub fn to_csv_row(&self) -> String {
+        format!("{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                self.total_jobs, self.jobs_failed, self.makespan, self.mean_wait,
+                self.median_wait, self.p95_wait, self.mean_turnaround, self.mean_slowdown,
+                self.p95_slowdown, self.core_utilization, self.memory_utilization,
+                self.energy_kwh, self.total_cost, self.cost_per_job, self.truncated)
+    }
+
+    /// Appends the summary as 1 row to the CSV file at @path, new files also get a header.
+    /// This way a sweep of experiments can collect all its summaries in the same file
+    pub fn append_csv(&self, path: &Path) -> Result<()> {
+        let exists = path.exists();
+
+        let file = OpenOptions::new().create(true).append(true).open(path);
+        let mut file = match file {
+            Ok(file) => file,
+            Err(x) => bail!("Unable to open summary file {} because of {:?}", path.display(), x),
+        };
+
+        if !exists {
+            writeln!(file, "{}", Self::CSV_HEADER)?;
+        }
+        writeln!(file, "{}", self.to_csv_row())?;
+
+        Ok(())
+    }
+}
+
+/// The outcome of a run in a form that machines can check, e.g. CI pipelines that assert on
+/// the numbers, see Scheduler::exit_summary()
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExitSummary {
+    #[serde(flatten)]
+    pub summary: RunSummary,
+    /// The jobs that finished without failing
+    pub jobs_done: usize,
+    /// The jobs that never entered the queue, e.g. because they do not fit on the cluster
+    pub jobs_rejected: usize,
+    /// The jobs that were still queuing, blocked, or on hold when the simulation stopped
+    pub jobs_waiting: usize,
+    /// The waiting jobs that can never start, see Scheduler::deadlocked()
+    pub jobs_deadlocked: usize,
+    pub oom_kills: usize,
+    /// The simulation ended with jobs that can never start, see Scheduler::has_unschedulable()
+    pub unschedulable: bool,
+}
+
+impl ExitSummary {
+    /// Writes the summary to @path as a JSON object, it replaces the file if it exists
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+
+        if let Err(x) = std::fs::write(path, content + "\n") {
+            bail!("Unable to write summary file {} because of {:?}", path.display(), x)
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use rayon::prelude::*;
+
+use crate::scheduler::Scheduler;
+use crate::summary::RunSummary;
+
+/// The outcome of simulating 1 job file of a sweep
+#[derive(Debug)]
+pub struct SweepResult {
+    pub path: PathBuf,
+    /// The error or panic message of runs that did not complete
+    pub summary: Result<RunSummary, String>,
+}
+
+impl SweepResult {
+    pub fn csv_header() -> String {
+        format!("workload,error,{}", RunSummary::CSV_HEADER)
+    }
+
+    /// The file name of the workload, the full path if it has none
+    pub fn workload(&self) -> String {
+        match self.path.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => self.path.display().to_string(),
+        }
+    }
+
+    pub fn to_csv_row(&self) -> String {
+        let quote = |text: &str| format!("\"{}\"", text.replace('"', "\"\""));
+
+        match &self.summary {
+            Ok(summary) => format!("{},,{}", quote(&self.workload()), summary.to_csv_row()),
+            Err(error) => {
+                let empty = ",".repeat(RunSummary::CSV_HEADER.split(',').count() - 1);
+                format!("{},{},{}", quote(&self.workload()), quote(error), empty)
+            }
+        }
+    }
+}
+
+/// Returns the files in @dir sorted by name, skipping hidden files and sub-directories
+pub fn job_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(dir)
+        .context(format!("Unable to list the job files in {}", dir.display()))?;
+    let mut paths = vec![];
+
+    for entry in entries {
+        let path = entry?.path();
+        let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if path.is_file() && !hidden {
+            paths.push(path);
+        }
+    }
+
+    if paths.is_empty() {
+        bail!("There are no job files in {}", dir.display())
+    }
+
+    paths.sort();
+    Ok(paths)
+}
+
+/// Simulates every job file in @paths on a separate core and returns their summaries in the
+/// order of @paths. @make_scheduler builds the Scheduler of a job file, a run that fails or
+/// panics only affects its own SweepResult
+pub fn run_sweep<F>(paths: &[PathBuf], make_scheduler: F) -> Vec<SweepResult>
+    where F: Fn(&Path) -> Result<Scheduler> + Sync
+{
+    paths
+        .par_iter()
+        .map(|path| {
+            let run = || -> Result<RunSummary> {
+                let mut sched = make_scheduler(path)?;
+                while sched.tick() && !sched.has_unschedulable() {}
+                sched.job_factory.check_stream()?;
+                Ok(sched.summary())
+            };
+
+            let summary = match std::panic::catch_unwind(AssertUnwindSafe(run)) {
+                Ok(Ok(summary)) => Ok(summary),
+                Ok(Err(error)) => Err(format!("{:#}", error)),
+                Err(panic) => Err(match panic.downcast_ref::<&str>() {
+                    Some(message) => format!("panic: {}", message),
+                    None => match panic.downcast_ref::<String>() {
+                        Some(message) => format!("panic: {}", message),
+                        None => "panic".to_owned(),
+                    },
+                }),
+            };
+
+            SweepResult { path: path.clone(), summary }
+        })
+        .collect()
+}
+
+/// Appends 1 row per SweepResult to the CSV file at @path, new files also get a header
+pub fn append_csv(results: &[SweepResult], path: &Path) -> Result<()> {
+    let exists = path.exists();
+
+    let file = OpenOptions::new().create(true).append(true).open(path);
+    let mut file = match file {
+        Ok(file) => file,
+        Err(x) => bail!("Unable to open summary file {} because of {:?}", path.display(), x),
+    };
+
+    if !exists {
+        writeln!(file, "{}", SweepResult::csv_header())?;
+    }
+    for result in results {
+        writeln!(file, "{}", result.to_csv_row())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,172 @@
+/*
+Licensed to the Apache Software Foundation (ASF) under one
+or more contributor license agreements.  See the NOTICE file
+distributed with this work for additional information
+regarding copyright ownership.  The ASF licenses this file
+to you under the Apache License, Version 2.0 (the
+"License"); you may not use this file except in compliance
+with the License.  You may obtain a copy of the License at
+
+  http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing,
+software distributed under the License is distributed on an
+"AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+KIND, either express or implied.  See the License for the
+specific language governing permissions and limitations
+under the License.
+*/
+
+use std::collections::BTreeSet;
+
+use crate::job::Job;
+use crate::job::JobId;
+use crate::node::Node;
+use crate::node::NodeId;
+use crate::profile;
+use crate::registry::NodeRegistry;
+use crate::scheduler::place_apart;
+use crate::scheduler::FirstFit;
+use crate::scheduler::Placement;
+use crate::scheduler::PolicyContext;
+use crate::scheduler::SchedulingPolicy;
+
+/// Works like FirstFit but places the pieces of gang jobs on nodes that are close to each other
+/// in the connection graph. Every node with free cores is a candidate first node, the pieces
+/// then go to the nodes in the same rack as the first node, then in the same zone, then
+/// anywhere else (see Node::locality()), nearest nodes (fewest hops) first, which have enough
+/// free resources. The winner is the candidate that spans the fewest zones, then the fewest
+/// racks, then has the smallest total hop distance (see Placement::hop_distance()). When none
+/// of the candidates can reach enough free cores, the job falls back to the placement of
+/// FirstFit which does not care about the connection graph.
+///
+/// Every gang job that does not fit on a single node costs a breadth first search per
+/// candidate node.
+#[derive(Default)]
+pub struct TopologyAware {}
+
+impl TopologyAware {
+    /// Places the pieces of gang @job on the nodes nearest to @first, returns the Placement
+    /// and its cost: the number of zones and racks it spans and its total hop distance. Nodes
+    /// that the connection graph does not reach are only candidates if they share the rack or
+    /// zone of @first
+    fn gang_place_near(
+        registry: &NodeRegistry,
+        job: &Job,
+        first: NodeId,
+    ) -> Option<(Placement, (usize, usize, usize))> {
+        let hops = registry.hops_from(first);
+        let origin = &registry.nodes[first];
+        let mut nearest: Vec<(usize, usize, NodeId)> = registry.nodes
+            .iter()
+            .filter_map(|node| match (origin.locality(node), hops[node.uid]) {
+                (locality, Some(hops)) => Some((locality, hops, node.uid)),
+                (locality, None) if locality < 2 => Some((locality, usize::MAX, node.uid)),
+                _ => None,
+            })
+            .collect();
+        nearest.sort_unstable();
+
+        let mut rem_cores = job.cores;
+        let mut rem_mem = job.memory;
+        let mut cores_alloc: Vec<(usize, f32)> = Vec::new();
+        let mut mem_alloc: Vec<(usize, f32)> = Vec::new();
+        let mut distance = 0;
+
+        for (_, hops, uid) in nearest {
+            let node = &registry.nodes[uid];
+            let cores = rem_cores.min(node.cores.current);
+
+            if cores <= 0.0 {
+                continue;
+            }
+
+            let memory = if cores == rem_cores { rem_mem } else { job.memory * cores / job.cores };
+
+            profile::count_check();
+            if node.memory.current < memory
+                || !node.has_resources(&job.resources, cores / job.cores)
+                || !node.accepts(job) {
+                continue;
+            }
+
+            cores_alloc.push((uid, cores));
+            mem_alloc.push((uid, memory));
+            rem_cores -= cores;
+            rem_mem -= memory;
+            if hops != usize::MAX {
+                distance += hops;
+            }
+
+            if rem_cores == 0.0 {
+                let spans = |level: fn(&Node) -> &Option<String>| -> usize {
+                    cores_alloc.iter()
+                        .filter_map(|(uid, _)| level(&registry.nodes[*uid]).as_ref())
+                        .collect::<BTreeSet<_>>()
+                        .len()
+                };
+                let cost = (spans(|node| &node.zone), spans(|node| &node.rack), distance);
+                let placement = Placement {
+                    node_cores: cores_alloc[0].0,
+                    node_memory: mem_alloc,
+                    gang_cores: cores_alloc,
+                };
+                return Some((placement, cost));
+            }
+        }
+
+        None
+    }
+
+    pub fn job_place(registry: &mut NodeRegistry, job: &Job) -> Option<Placement> {
+        place_apart(registry, job, Self::job_place_once)
+    }
+
+    fn job_place_once(registry: &mut NodeRegistry, job: &Job) -> Option<Placement> {
+        registry.ensure_sorted();
+
+        let fits_one_node = registry.idx_nodes_with_more_cores(job.cores) < registry.sorted_cores.len();
+        if !job.gang || fits_one_node {
+            return FirstFit::job_place(registry, job);
+        }
+
+        let best = registry.nodes
+            .iter()
+            .filter(|node| node.cores.current > 0.0)
+            .filter_map(|node| Self::gang_place_near(registry, job, node.uid))
+            // VV: min_by_key() keeps the first of equally distant placements, i.e. the lowest node id
+            .min_by_key(|(_, cost)| *cost);
+
+        match best {
+            Some((placement, _)) => Some(placement),
+            None => FirstFit::gang_try_allocate(registry, job),
+        }
+    }
+}
+
+impl SchedulingPolicy for TopologyAware {
+    fn select(
+        &mut self,
+        queued: &[Job],
+        registry: &mut NodeRegistry,
+        context: &PolicyContext,
+    ) -> Vec<(JobId, Placement)> {
+        let mut selected = vec![];
+        let (mut max_cores, mut max_memory) = registry.get_max_cores_memory();
+
+        for job in queued.iter().skip(context.unchanged) {
+            if !job.gang && (job.cores > max_cores || job.memory > max_memory) {
+                continue;
+            }
+
+            profile::count_attempt();
+            if let Some(placement) = Self::job_place(registry, job) {
+                placement.reserve(registry, job);
+                selected.push((job.uid, placement));
+                (max_cores, max_memory) = registry.get_max_cores_memory();
+            }
+        }
+
+        selected
+    }
+}
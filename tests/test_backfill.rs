@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use dismem::backfill::Backfill;
+use dismem::job::Job;
+use dismem::job::reset_job_metadata;
+use dismem::job_factory::JobCollection;
+use dismem::registry::NodeRegistry;
+use dismem::scheduler::FirstFit;
+use dismem::scheduler::Scheduler;
+use dismem::scheduler::SchedulingPolicy;
+
+#[cfg(test)]
+mod test_backfill {
+    use super::*;
+
+    /// One 64-core node running a 1-core job, a 64-core job waiting behind it,
+    /// and a stream of 1-core jobs
+    fn wide_job_then_narrow_jobs(policy: Box<dyn SchedulingPolicy>) -> Result<Scheduler> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("big", 64.0, 64.0)?;
+
+        reset_job_metadata();
+        let mut jobs = vec![
+            Job::new_with_uid(0, 1.0, 1.0, 100.0, false, 0.0),
+            Job::new_with_uid(1, 64.0, 1.0, 10.0, false, 0.0),
+        ];
+
+        for uid in 2..64 {
+            jobs.push(Job::new_with_uid(uid, 1.0, 1.0, 100.0, false, 0.0));
+        }
+
+        for uid in 64..84 {
+            jobs.push(Job::new_with_uid(uid, 1.0, 1.0, 100.0, false, 50.0));
+        }
+
+        Ok(Scheduler::new(reg, Box::new(JobCollection::new(jobs)), policy))
+    }
+
+    fn run_and_record_start_times(sched: &mut Scheduler) -> HashMap<usize, f32> {
+        let mut started = HashMap::new();
+
+        while sched.tick() {
+            for job in &sched.jobs_running {
+                started.insert(job.uid, job.time_started.unwrap());
+            }
+        }
+
+        started
+    }
+
+    #[test]
+    fn backfill_wide_job_is_not_starved() -> Result<()> {
+        let mut sched = wide_job_then_narrow_jobs(Box::new(Backfill::default()))?;
+        let started = run_and_record_start_times(&mut sched);
+
+        assert_eq!(sched.job_factory.jobs_done().len(), 84);
+
+        // VV: The narrow jobs which are queued alongside the wide one fill the node because they
+        // finish by the time the 1st job does
+        for uid in 2..64 {
+            assert_eq!(started[&uid], 0.0);
+        }
+
+        // VV: The narrow jobs that arrive later would delay the wide job so they wait
+        assert_eq!(started[&1], 100.0);
+        for uid in 64..84 {
+            assert_eq!(started[&uid], 110.0);
+        }
+
+        assert_eq!(sched.now, 210.0);
+
+        let mut sched = wide_job_then_narrow_jobs(Box::new(FirstFit::default()))?;
+        let started = run_and_record_start_times(&mut sched);
+        assert!(started[&1] > 100.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn backfill_accounts_for_slow_nodes() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("slow;4;4;speed=0.5")?;
+
+        // VV: Job 2 would finish before the head starts at 200 only if it ran at full speed
+        reset_job_metadata();
+        let jobs = vec![
+            Job::new_with_uid(0, 2.0, 1.0, 100.0, false, 0.0),
+            Job::new_with_uid(1, 4.0, 1.0, 10.0, false, 0.0),
+            Job::new_with_uid(2, 2.0, 1.0, 150.0, false, 10.0),
+        ];
+        let mut sched = Scheduler::new(reg, Box::new(JobCollection::new(jobs)),
+            Box::new(Backfill::default()));
+        let started = run_and_record_start_times(&mut sched);
+
+        assert_eq!(sched.job_factory.jobs_done().len(), 3);
+        assert_eq!(started[&1], 200.0);
+        assert_eq!(started[&2], 220.0);
+        Ok(())
+    }
+}
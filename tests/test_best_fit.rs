@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use dismem::best_fit::BestFit;
+use dismem::job::Job;
+use dismem::job::reset_job_metadata;
+use dismem::job_factory::JobCollection;
+use dismem::registry::NodeRegistry;
+use dismem::scheduler::Scheduler;
+use dismem::scheduler::SchedulerBuilder;
+
+#[cfg(test)]
+mod test_best_fit {
+    use super::*;
+
+    /// 3 long running small jobs followed by a job that needs an entire node
+    fn scheduler(policy: BestFit) -> Result<Scheduler> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("n0", 4.0, 4.0)?;
+        reg.new_node("n1", 4.0, 4.0)?;
+
+        reset_job_metadata();
+        let mut jobs: Vec<Job> = (0..3).map(|_| Job::new(1.0, 1.0, 100.0, false, 0.0)).collect();
+        jobs.push(Job::new(4.0, 4.0, 10.0, false, 1.0));
+
+        Ok(SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .policy(Box::new(policy))
+            .build())
+    }
+
+    /// Returns the nodes of the small jobs and when the large job started
+    fn occupancy(mut sched: Scheduler) -> (HashSet<usize>, f32) {
+        let mut nodes = HashSet::new();
+        let mut large_started = None;
+
+        while sched.tick() {
+            for job in &sched.jobs_running {
+                if job.uid == 3 {
+                    large_started = job.time_started;
+                } else {
+                    nodes.insert(job.node_cores.unwrap());
+                }
+            }
+        }
+
+        (nodes, large_started.unwrap())
+    }
+
+    #[test]
+    fn best_fit_spread_vs_pack() -> Result<()> {
+        let (spread_nodes, spread_large) = occupancy(scheduler(BestFit::spread())?);
+        let (pack_nodes, pack_large) = occupancy(scheduler(BestFit::pack())?);
+
+        // VV: Spreading the small jobs leaves no node free for the large job until they finish
+        assert_eq!(spread_nodes, HashSet::from([0, 1]));
+        assert_eq!(spread_large, 100.0);
+
+        assert_eq!(pack_nodes, HashSet::from([0]));
+        assert_eq!(pack_large, 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn best_fit_falls_back_to_borrowing() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("cpu", 2.0, 1.0)?;
+        reg.new_node("ram", 0.0, 4.0)?;
+        reg.new_connection_from_str("cpu;ram")?;
+
+        reset_job_metadata();
+        let jobs = vec![Job::new(1.0, 3.0, 10.0, true, 0.0)];
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .policy(Box::new(BestFit::pack()))
+            .build();
+
+        sched.tick();
+        let job = sched.jobs_running.front().unwrap();
+        assert_eq!(job.node_cores, Some(0));
+        assert_eq!(job.node_memory, vec![(0, 1.0), (1, 2.0)]);
+        Ok(())
+    }
+
+    /// Returns the node and finish time of each job of a run where jobs 0, 2, and 3 share an
+    /// affinity group and job 1 keeps n0 busy. Job 2 starts while n0 is warm, job 3 after it cooled
+    fn warm_placements(warm_cache: bool) -> Result<HashMap<usize, (usize, f32)>> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("n0", 4.0, 16.0)?;
+        reg.new_node("n1", 4.0, 8.0)?;
+
+        reset_job_metadata();
+        let mut jobs = vec![
+            Job::new(1.0, 4.0, 10.0, false, 0.0),
+            Job::new(1.0, 10.0, 100.0, false, 5.0),
+            Job::new(1.0, 1.0, 10.0, false, 20.0),
+            Job::new(1.0, 1.0, 10.0, false, 100.0),
+        ];
+        for uid in [0, 2, 3] {
+            jobs[uid].affinity = Some("a".to_string());
+        }
+
+        let mut builder = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)));
+        builder = match warm_cache {
+            true => builder.warm_cache(50.0, 2.0).policy(Box::new(BestFit::spread().prefer_warm())),
+            false => builder.policy(Box::new(BestFit::spread())),
+        };
+        let mut sched = builder.build();
+
+        let mut placements = HashMap::new();
+        while sched.tick() {
+            for job in &sched.jobs_running {
+                placements.insert(job.uid, (job.node_cores.unwrap(), job.time_done.unwrap()));
+            }
+        }
+
+        Ok(placements)
+    }
+
+    #[test]
+    fn best_fit_prefers_warm_nodes() -> Result<()> {
+        // VV: Spreading alone picks n1 which has the most free memory when job 2 arrives
+        let cold = warm_placements(false)?;
+        assert_eq!(cold[&2], (1, 30.0));
+
+        let warm = warm_placements(true)?;
+        assert_eq!(warm[&0], (0, 10.0));
+        assert_eq!(warm[&1], (0, 105.0));
+        assert_eq!(warm[&2], (0, 25.0));
+        // VV: Job 2 kept n0 warm until 75, job 3 arrives after that
+        assert_eq!(warm[&3], (1, 110.0));
+        Ok(())
+    }
+}
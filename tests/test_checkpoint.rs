@@ -0,0 +1,117 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use rand::Rng;
+use rand::SeedableRng;
+
+use dismem::failures::NodeFailure;
+use dismem::job_factory::JobStreaming;
+use dismem::job_factory::JobStreamingWithOutput;
+use dismem::job_factory::TraceFormat;
+use dismem::registry::NodeRegistry;
+use dismem::runtime::RuntimeModel;
+use dismem::scheduler::Scheduler;
+use dismem::scheduler::SchedulerBuilder;
+use dismem::SimRng;
+
+#[cfg(test)]
+mod test_checkpoint {
+    use super::*;
+
+    fn registry() -> Result<NodeRegistry> {
+        NodeRegistry::from_paths(Path::new("examples/dismem_racks/nodes.csv"),
+                                 Path::new("examples/dismem_racks/connections.csv"))
+    }
+
+    fn workload() -> String {
+        let mut rng = SimRng::seed_from_u64(1);
+        let mut created = 0.0;
+        let mut lines = vec![];
+
+        for _ in 0..300 {
+            created += rng.gen_range(0..10) as f32;
+            lines.push(format!("?;{};{};{};{};{};user=u{}", rng.gen_range(1..=8),
+                               rng.gen_range(1..=12), rng.gen_range(10..200),
+                               if rng.gen_bool(0.5) { "y" } else { "n" }, created,
+                               rng.gen_range(0..3)));
+        }
+
+        lines.join("\n")
+    }
+
+    /// Backfill with a stochastic runtime model, fair-share usage, and a node failure
+    fn scheduler(output: &Path) -> Result<SchedulerBuilder> {
+        let registry = registry()?;
+        let failure = NodeFailure::from_str("CPU_1_0;300;900", &registry)?;
+        let job_factory = JobStreaming::from_string(workload())?;
+        let job_factory = JobStreamingWithOutput::from_factory_to_path(
+            Box::new(job_factory), output, TraceFormat::Text)?;
+
+        Ok(SchedulerBuilder::new(registry, Box::new(job_factory))
+            .backfill(true)
+            .seed(3)
+            .runtime_model(RuntimeModel::Lognormal { sigma: 0.3, scale: 1.0 })
+            .failures(vec![failure]))
+    }
+
+    fn lines(path: &PathBuf) -> Result<Vec<String>> {
+        Ok(std::fs::read_to_string(path)?.lines().map(|l| l.to_owned()).collect())
+    }
+
+    #[test]
+    fn checkpoint_resume_matches_uninterrupted_run() -> Result<()> {
+        let dir = std::env::temp_dir();
+        let path_reference = dir.join("dismem_checkpoint_reference.txt");
+        let path_head = dir.join("dismem_checkpoint_head.txt");
+        let path_tail = dir.join("dismem_checkpoint_tail.txt");
+        let path_checkpoint = dir.join("dismem_checkpoint.json");
+
+        let mut sched = scheduler(&path_reference)?.build();
+        while sched.tick() {}
+        let reference_summary = sched.summary();
+        let reference_done = sched.jobs_done.len();
+        drop(sched);
+        let reference = lines(&path_reference)?;
+
+        let mut sched = scheduler(&path_head)?.build();
+        while sched.tick() && sched.jobs_done.len() < reference_done / 2 {}
+        sched.save_checkpoint(&path_checkpoint)?;
+        let done = sched.job_factory.jobs_done().len();
+        drop(sched);
+
+        // VV: The tail of the trace starts with the first job that finishes after the checkpoint
+        let mut builder = scheduler(&path_tail)?;
+        builder = builder.seed(1000);
+        let mut sched = builder.restore(&path_checkpoint)?;
+        assert_eq!(sched.seed, 3);
+        while sched.tick() {}
+
+        assert_eq!(sched.summary(), reference_summary);
+        drop(sched);
+
+        let tail = lines(&path_tail)?;
+        assert_eq!(reference.len(), 1 + reference_done);
+        assert!(done > 0 && tail.len() > 1);
+        assert_eq!(tail[0], reference[0]);
+        assert_eq!(tail[1..], reference[1 + done..]);
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint_rejects_other_registry() -> Result<()> {
+        let path_checkpoint = std::env::temp_dir().join("dismem_checkpoint_registry.json");
+        let path_output = std::env::temp_dir().join("dismem_checkpoint_registry.txt");
+
+        let sched = scheduler(&path_output)?.build();
+        sched.save_checkpoint(&path_checkpoint)?;
+
+        let mut other = NodeRegistry::new();
+        other.new_node("lonely", 4.0, 4.0)?;
+        let job_factory = JobStreaming::from_string(workload())?;
+        let restored = Scheduler::load_checkpoint(&path_checkpoint, other, Box::new(job_factory),
+                                                  Box::new(dismem::backfill::Backfill::default()));
+        assert!(restored.is_err());
+        Ok(())
+    }
+}
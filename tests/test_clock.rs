@@ -0,0 +1,42 @@
+use anyhow::Result;
+
+use dismem::clock::Cadence;
+use dismem::clock::Clock;
+use dismem::clock::SimClock;
+use dismem::job::Job;
+use dismem::job::reset_job_metadata;
+use dismem::job_factory::JobCollection;
+use dismem::registry::NodeRegistry;
+use dismem::scheduler::SchedulerBuilder;
+
+#[cfg(test)]
+mod test_clock {
+    use super::*;
+
+    #[test]
+    fn sim_clock_follows_events() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("n0", 1.0, 1.0)?;
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = (0..4).map(|_| Job::new(1.0, 1.0, 10.0, false, 0.0)).collect();
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs))).build();
+
+        let clock = SimClock;
+        let mut cadence = Cadence::new(Box::new(SimClock), 15.0, &sched);
+        let mut times = vec![];
+        let mut reports = vec![];
+
+        while sched.tick() {
+            times.push(clock.now(&sched));
+            if cadence.due(&sched) {
+                reports.push(clock.now(&sched));
+            }
+        }
+
+        // VV: The jobs run back to back, there is no real waiting involved
+        assert_eq!(times, vec![10.0, 20.0, 30.0, 40.0]);
+        assert_eq!(reports, vec![20.0, 40.0]);
+        Ok(())
+    }
+}
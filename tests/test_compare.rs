@@ -0,0 +1,67 @@
+use anyhow::Result;
+
+use dismem::backfill::Backfill;
+use dismem::compare::compare_policies;
+use dismem::compare::jain_fairness;
+use dismem::job::Job;
+use dismem::job::reset_job_metadata;
+use dismem::registry::NodeRegistry;
+use dismem::scheduler::FirstFit;
+use dismem::summary::JobTimes;
+
+#[cfg(test)]
+mod test_compare {
+    use super::*;
+
+    fn registry() -> Result<NodeRegistry> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 4.0, 4.0)?;
+        Ok(reg)
+    }
+
+    #[test]
+    fn compare_first_fit_with_backfill() -> Result<()> {
+        // VV: FirstFit starts job 2 next to job 0 and job 1 waits for both of them, Backfill keeps
+        // the cores of job 1 free because job 2 would finish after job 0
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["?;2;1;100;n;0;user=a", "?;4;1;10;n;1;user=b",
+            "?;2;1;200;n;2;user=a"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+
+        let comparison = compare_policies(registry, &jobs, ("first_fit", Box::new(FirstFit {})),
+                                          ("backfill", Box::new(Backfill::default())), 0)?;
+        assert_eq!(comparison.a.summary.makespan, 212.0);
+        assert_eq!(comparison.b.summary.makespan, 310.0);
+        assert_eq!(comparison.a.summary.total_jobs, 3);
+        assert!(comparison.a.fairness < comparison.b.fairness);
+
+        let again = compare_policies(registry, &jobs, ("first_fit", Box::new(FirstFit {})),
+                                     ("backfill", Box::new(Backfill::default())), 0)?;
+        assert_eq!(again, comparison);
+
+        let table = comparison.to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("metric") && lines[0].contains("first_fit")
+            && lines[0].contains("backfill"), "{}", lines[0]);
+        assert!(lines[1].starts_with("makespan") && lines[1].ends_with("+98.0000"), "{}",
+                lines[1]);
+        assert_eq!(lines.len(), comparison.rows().len() + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn compare_jain_fairness() {
+        let times = |wait: f32| JobTimes { created: 0.0, started: wait, done: wait + 10.0,
+            failed: false };
+        assert_eq!(jain_fairness(&[], 10.0), 1.0);
+
+        // VV: Both users see the same slowdown, then a slowdown of 1 and one of 3
+        let equal = [(Some("a".to_owned()), times(10.0)), (None, times(10.0))];
+        assert_eq!(jain_fairness(&equal, 10.0), 1.0);
+        let unequal = [(Some("a".to_owned()), times(0.0)), (Some("b".to_owned()), times(20.0)),
+            (Some("b".to_owned()), times(20.0))];
+        assert!((jain_fairness(&unequal, 10.0) - 0.8).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,64 @@
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use dismem::compression;
+use dismem::job_factory::JobStreaming;
+use dismem::job_factory::JobStreamingWithOutput;
+use dismem::job_factory::TraceFormat;
+use dismem::registry::NodeRegistry;
+use dismem::scheduler::SchedulerBuilder;
+
+#[cfg(test)]
+mod test_compression {
+    use super::*;
+
+    fn write_gz(name: &str, content: &str) -> Result<PathBuf> {
+        let path = std::env::temp_dir().join(name);
+        let mut encoder = GzEncoder::new(std::fs::File::create(&path)?, Compression::default());
+        encoder.write_all(content.as_bytes())?;
+        encoder.finish()?;
+        Ok(path)
+    }
+
+    #[test]
+    fn compression_extension() {
+        assert_eq!(compression::extension(Path::new("trace.swf.gz")), Some("swf"));
+        assert_eq!(compression::extension(Path::new("trace.swf")), Some("swf"));
+        assert_eq!(compression::extension(Path::new("trace.gz")), None);
+        assert!(compression::is_gzip(Path::new("out.jsonl.gz")));
+        assert_eq!(TraceFormat::from_path(Path::new("out.jsonl.gz")), TraceFormat::JsonLines);
+    }
+
+    #[test]
+    fn compression_round_trip() -> Result<()> {
+        let nodes = write_gz("dismem_compression_nodes.csv.gz", "n0;2;2\nn1;2;2\n")?;
+        let connections = write_gz("dismem_compression_connections.csv.gz", "n0;n1\n")?;
+        let jobs = write_gz("dismem_compression.jobs.gz", "?;2;3;10;y;0\n?;1;1;5;n;0\n")?;
+        let output = std::env::temp_dir().join("dismem_compression_output.jsonl.gz");
+
+        let reg = NodeRegistry::from_paths(&nodes, &connections)?;
+        assert_eq!(reg.nodes.len(), 2);
+
+        let factory = JobStreamingWithOutput::from_factory_to_path(
+            Box::new(JobStreaming::from_path(&jobs)?), &output, TraceFormat::JsonLines)?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(factory)).build();
+        while sched.tick() {}
+        assert_eq!(sched.jobs_done.len(), 2);
+        drop(sched);
+
+        let content = compression::read_to_string(&output)?;
+        let mut ids: Vec<u64> = content
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["id"]
+                .as_u64().unwrap())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![0, 1]);
+        Ok(())
+    }
+}
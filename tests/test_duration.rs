@@ -0,0 +1,35 @@
+use anyhow::Result;
+
+use dismem::duration::parse_time;
+use dismem::job::Job;
+use dismem::job::reset_job_metadata;
+
+#[cfg(test)]
+mod test_duration {
+    use super::*;
+
+    #[test]
+    fn duration_suffixes() {
+        assert_eq!(parse_time("30s"), Ok(30.0));
+        assert_eq!(parse_time("5m"), Ok(300.0));
+        assert_eq!(parse_time("1.5h"), Ok(5400.0));
+        assert_eq!(parse_time("2d"), Ok(172800.0));
+        assert_eq!(parse_time("42.5"), Ok(42.5));
+        assert!(parse_time("5w").is_err());
+        assert!(parse_time("h").is_err());
+    }
+
+    #[test]
+    fn duration_job_runtimes() -> Result<()> {
+        reset_job_metadata();
+        let suffixed: Job = "0;1;1;2h;n;5m;walltime_limit=3h".parse().map_err(anyhow::Error::msg)?;
+        let seconds: Job = "1;1;1;7200;n;300;walltime_limit=10800".parse()
+            .map_err(anyhow::Error::msg)?;
+
+        assert_eq!(suffixed.duration, seconds.duration);
+        assert_eq!(suffixed.runtime(), seconds.runtime());
+        assert_eq!(suffixed.time_created, seconds.time_created);
+        assert_eq!(suffixed.walltime_limit, seconds.walltime_limit);
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use dismem::drf::Drf;
+use dismem::job::Job;
+use dismem::job::reset_job_metadata;
+use dismem::job_factory::JobCollection;
+use dismem::registry::NodeRegistry;
+use dismem::scheduler::SchedulerBuilder;
+
+#[cfg(test)]
+mod test_fairshare {
+    use super::*;
+
+    #[test]
+    fn fairshare_splits_cluster_between_users() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 2.0, 2.0)?;
+
+        // VV: User a floods the queue before user b submits anything
+        reset_job_metadata();
+        let jobs: Vec<Job> = (0..40)
+            .map(|i| {
+                let user = if i < 20 { "a" } else { "b" };
+                format!("?;1;1;10;n;0;user={}", user).parse().unwrap()
+            })
+            .collect();
+
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = SchedulerBuilder::new(reg, Box::new(job_factory))
+            .fair_share(1000.0)
+            .build();
+
+        let mut done_by_half = (0, 0);
+        while sched.tick() {
+            if sched.now <= 100.0 {
+                let done = |user: &str| sched.jobs_done
+                    .iter()
+                    .filter(|uid| (**uid < 20) == (user == "a"))
+                    .count();
+                done_by_half = (done("a"), done("b"));
+            }
+        }
+
+        assert_eq!(sched.jobs_done.len(), 40);
+        // VV: The 2 cores run 1 job of each user at a time, first-come-first-served would
+        // instead run all the jobs of user a first
+        assert_eq!(done_by_half.0, done_by_half.1);
+        assert!(done_by_half.0 >= 9);
+        assert!(sched.user_usage.contains_key("a") && sched.user_usage.contains_key("b"));
+        Ok(())
+    }
+
+    #[test]
+    fn fairshare_splits_cluster_by_user_weights() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 3.0, 3.0)?;
+
+        // VV: Both users saturate the cluster, a has twice the weight of b
+        reset_job_metadata();
+        let jobs: Vec<Job> = (0..120)
+            .map(|i| {
+                let user = if i % 2 == 0 { "a" } else { "b" };
+                format!("?;1;1;10;n;0;user={}", user).parse().unwrap()
+            })
+            .collect();
+
+        let job_factory = JobCollection::new(jobs);
+        let weights = HashMap::from([("a".to_string(), 2.0), ("b".to_string(), 1.0)]);
+        let mut sched = SchedulerBuilder::new(reg, Box::new(job_factory))
+            .fair_share(1e6)
+            .user_weights(weights)
+            .build();
+
+        let mut done_by_300 = (0, 0);
+        while sched.tick() {
+            if sched.now <= 300.0 {
+                let done = |parity: usize| sched.jobs_done
+                    .iter()
+                    .filter(|uid| **uid % 2 == parity)
+                    .count();
+                done_by_300 = (done(0), done(1));
+            }
+        }
+
+        assert_eq!(sched.jobs_done.len(), 120);
+        // VV: The users split the jobs that finish before the queue of a drains 2:1 instead of 1:1
+        let (a, b) = (done_by_300.0 as i32, done_by_300.1 as i32);
+        assert!(a + b >= 80, "{:?}", done_by_300);
+        assert!((a - 2 * b).abs() <= 3, "{:?}", done_by_300);
+        Ok(())
+    }
+
+    #[test]
+    fn drf_equalizes_dominant_shares() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 9.0, 18.0)?;
+
+        // VV: User a is memory-heavy and user b is CPU-heavy, b submits first
+        reset_job_metadata();
+        let jobs: Vec<Job> = (0..20)
+            .map(|i| {
+                let line = if i < 10 { "?;3;1;100;n;0;user=b" } else { "?;1;4;100;n;0;user=a" };
+                line.parse().unwrap()
+            })
+            .collect();
+
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = SchedulerBuilder::new(reg, Box::new(job_factory))
+            .policy(Box::new(Drf::default()))
+            .build();
+
+        sched.tick();
+
+        let running = |user: &str| sched.jobs_running
+            .iter()
+            .filter(|job| job.user.as_deref() == Some(user))
+            .count();
+
+        // VV: a gets 12/18 of the memory and b gets 6/9 of the cores
+        assert_eq!(running("a"), 3);
+        assert_eq!(running("b"), 2);
+        Ok(())
+    }
+}
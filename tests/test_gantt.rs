@@ -0,0 +1,58 @@
+use anyhow::Result;
+
+use dismem::gantt::GanttChart;
+use dismem::job::Job;
+use dismem::job::reset_job_metadata;
+use dismem::job_factory::JobCollection;
+use dismem::registry::NodeRegistry;
+use dismem::scheduler::SchedulerBuilder;
+
+#[cfg(test)]
+mod test_gantt {
+    use super::*;
+
+    #[test]
+    fn gantt_records_and_renders_spans() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("a", 2.0, 2.0)?;
+        reg.new_node("b<1>", 2.0, 2.0)?;
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["?;2;1;10;n;0;user=alice", "?;2;1;10;n;0;user=bob",
+            "?;2;1;10;n;0;user=alice", "?;4;1;5;n;0;gang=y"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .record_spans(true)
+            .build();
+
+        while sched.tick() {}
+
+        // VV: The gang job has 1 span per node
+        let mut spans: Vec<_> = sched.spans.iter().map(|s| (s.uid, s.start, s.end)).collect();
+        spans.sort_by_key(|span| span.0);
+        assert_eq!(spans, vec![(0, 0.0, 10.0), (1, 0.0, 10.0), (2, 10.0, 20.0), (3, 20.0, 25.0),
+                               (3, 20.0, 25.0)]);
+        assert_ne!(sched.spans[3].node, sched.spans[4].node);
+
+        let svg = GanttChart::default().render(&sched.spans, &sched.registry);
+        assert_eq!(svg.matches("<rect").count(), 5);
+        assert!(svg.contains("b&lt;1&gt;"));
+
+        let color = |uid: usize| -> String {
+            let start = svg.find(&format!("<title>Job {} ", uid)).unwrap();
+            let rect = svg[..start].rfind("fill=").unwrap();
+            svg[rect..start].split('"').nth(1).unwrap().to_owned()
+        };
+        assert_eq!(color(0), color(2));
+        assert_ne!(color(0), color(1));
+
+        let chart = GanttChart { max_jobs: Some(2), window: None };
+        assert_eq!(chart.render(&sched.spans, &sched.registry).matches("<rect").count(), 2);
+
+        let chart = GanttChart { max_jobs: None, window: Some((12.0, 22.0)) };
+        assert_eq!(chart.render(&sched.spans, &sched.registry).matches("<rect").count(), 3);
+        Ok(())
+    }
+}
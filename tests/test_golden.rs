@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use dismem::golden::first_divergence;
+use dismem::golden::read_trace;
+use dismem::golden::verify_trace;
+use dismem::job_factory::JobStreaming;
+use dismem::job_factory::JobStreamingWithOutput;
+use dismem::job_factory::TraceFormat;
+use dismem::registry::NodeRegistry;
+use dismem::scheduler::SchedulerBuilder;
+
+#[cfg(test)]
+mod test_golden {
+    use super::*;
+
+    const JOBS: &str = "0;2;2;10;n;0\n1;2;2;20;n;0\n2;1;1;5;n;3\n3;4;4;10;n;4";
+
+    /// Simulates JOBS on 2 nodes with 4 cores and writes the output trace to @output
+    fn simulate(output: &Path, backfill: bool) -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("n0", 4.0, 4.0)?;
+        reg.new_node("n1", 4.0, 4.0)?;
+        let jobs = JobStreamingWithOutput::from_factory_to_path(
+            Box::new(JobStreaming::from_string(JOBS.to_string())?), output,
+            TraceFormat::from_path(output))?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).backfill(backfill).build();
+        while sched.tick() {}
+        Ok(())
+    }
+
+    #[test]
+    fn golden_same_inputs_same_trace() -> Result<()> {
+        for ext in ["txt", "jsonl"] {
+            let golden = std::env::temp_dir().join(format!("dismem_golden.{}", ext));
+            let output = std::env::temp_dir().join(format!("dismem_golden_output.{}", ext));
+            simulate(&golden, false)?;
+            simulate(&output, false)?;
+
+            assert_eq!(read_trace(&golden)?.len(), 4);
+            verify_trace(&golden, &output)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn golden_reports_first_divergence() -> Result<()> {
+        let golden = std::env::temp_dir().join("dismem_golden_diverges.txt");
+        simulate(&golden, false)?;
+
+        // VV: Pretend that job 2 ran on the other node
+        let content = std::fs::read_to_string(&golden)?;
+        let output = std::env::temp_dir().join("dismem_golden_diverges_output.txt");
+        let changed: Vec<String> = content
+            .lines()
+            .map(|line| match line.starts_with("2;") {
+                true => {
+                    let mut tokens: Vec<&str> = line.split(';').collect();
+                    tokens[8] = if tokens[8] == "0" { "1" } else { "0" };
+                    tokens.join(";")
+                }
+                false => line.to_string(),
+            })
+            .collect();
+        std::fs::write(&output, changed.join("\n"))?;
+
+        let divergence = first_divergence(&read_trace(&golden)?, &read_trace(&output)?).unwrap();
+        assert_eq!(divergence.uid, 2);
+        let err = verify_trace(&golden, &output).err().unwrap();
+        assert!(err.to_string().contains("Job 2 diverges"), "{}", err);
+
+        // VV: A trace that lacks jobs diverges at the first missing one
+        let truncated = read_trace(&golden)?;
+        let divergence = first_divergence(&truncated, &truncated[1..]).unwrap();
+        assert_eq!((divergence.uid, divergence.actual), (truncated[0].uid, None));
+
+        let json = std::env::temp_dir().join("dismem_golden_diverges.jsonl");
+        simulate(&json, false)?;
+        assert!(verify_trace(&golden, &json).is_err());
+        Ok(())
+    }
+}
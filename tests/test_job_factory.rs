@@ -1,7 +1,22 @@
 // use dismem::job::reset_job_metadata;
 use anyhow::Result;
 
+use dismem::job::Job;
+use dismem::job::reset_job_metadata;
+use dismem::job_factory::JobCollection;
+use dismem::job_factory::expand_array;
+use dismem::job_factory::expand_paths;
 use dismem::job_factory::JobStreaming;
+use dismem::job_factory::JobStreamingSwf;
+use dismem::job_factory::JobStreamingWithOutput;
+use dismem::job_factory::PoissonArrivals;
+use dismem::job_factory::TraceFormat;
+use dismem::job_factory::Validation;
+use dismem::job_factory::check_jobs;
+use dismem::job_factory::check_trace_fields;
+use dismem::registry::NodeRegistry;
+use dismem::scheduler::SchedulerBuilder;
+use std::path::Path;
 
 #[cfg(test)]
 mod test_job_factory {
@@ -11,10 +26,11 @@ mod test_job_factory {
 
     #[test]
     fn job_factory_streaming() -> Result<()> {
-        let content = "0;0.0;0.0;0.0;y;0.0\n\
+        // VV: Jobs need at least 1 core, see Job::validate()
+        let content = "0;1.0;1.0;1.0;y;0.0\n\
         # this is a comment above an empty line\n\
         \n\
-        1;1.0;1.0;1.0;y;1.0\n
+        1;2.0;2.0;2.0;y;1.0\n
         # 2;1.0;1.0;1.0;y;1.0\n
         # the line above is a comment";
 
@@ -25,16 +41,16 @@ mod test_job_factory {
         for idx in 0.. {
             println!("Is there a job {} {}", idx, factory.more_jobs());
 
-            if factory.more_jobs() == false {
+            if !factory.more_jobs() {
                 break;
             }
 
             let job = factory.job_get();
 
-            assert_eq!(job.memory, 1.0 * idx as f32);
-            assert_eq!(job.cores, 1.0 * idx as f32);
+            assert_eq!(job.memory, 1.0 + idx as f32);
+            assert_eq!(job.cores, 1.0 + idx as f32);
             assert_eq!(job.time_created, 1.0 * idx as f32);
-            assert_eq!(job.duration, 1.0 * idx as f32);
+            assert_eq!(job.duration, 1.0 + idx as f32);
 
             factory.job_mark_done(&job);
         }
@@ -43,4 +59,368 @@ mod test_job_factory {
 
         Ok(())
     }
+
+    #[test]
+    fn job_factory_cyclic_dependencies() -> Result<()> {
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["0;1.0;1.0;1.0;n;0.0", "1;1.0;1.0;1.0;n;0.0;depends_on=[0, 2]",
+            "2;1.0;1.0;1.0;n;0.0;depends_on=[1]"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+
+        assert_eq!(jobs[1].depends_on, vec![0, 2]);
+        assert!(JobCollection::try_new(jobs).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn job_factory_strict_validation() -> Result<()> {
+        let path = std::env::temp_dir().join("dismem_job_factory_strict_validation.jobs");
+        let check = |content: &str, registry: Option<&NodeRegistry>| -> Result<usize> {
+            std::fs::write(&path, content)?;
+            check_jobs(&path, registry)
+        };
+
+        assert_eq!(check("?;1;1;10;n;0\n# comment\n?;2;1;10;n;5\n", None)?, 2);
+
+        let err = check("?;1;1;10;n;0\n\n?;1;1;-10;n;5\n?;1;1;10;n;6\n", None).unwrap_err();
+        assert!(err.to_string().contains("line 3"), "{}", err);
+        assert!(err.to_string().contains("Job 1 has duration -10"), "{}", err);
+
+        let err = check("?;0;1;10;n;0\n", None).unwrap_err();
+        assert!(err.to_string().contains("Job 0 has cores 0"), "{}", err);
+
+        let err = check("?;1;one;10;n;0\n", None).unwrap_err();
+        assert!(err.to_string().contains("Invalid memory \"one\""), "{}", err);
+
+        let mut registry = NodeRegistry::new();
+        registry.new_node("node", 2.0, 4.0)?;
+        assert_eq!(check("?;2;4;10;n;0\n", Some(&registry))?, 1);
+        let err = check("?;1;8;10;n;0\n", Some(&registry)).unwrap_err();
+        assert!(err.to_string().contains("Job 0 requests 8 memory"), "{}", err);
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn job_factory_strict_streaming_stops_at_invalid_job() -> Result<()> {
+        let content = "?;1;1;10;n;0\n?;0;1;10;n;0\n?;1;1;10;n;0\n".to_string();
+        let mut factory = JobStreaming::from_string(content)?;
+        factory.check_stream()?;
+
+        // VV: The stream ends at the invalid job instead of skipping it
+        assert_eq!(factory.job_get().uid, 0);
+        assert!(!factory.more_jobs());
+        let err = factory.check_stream().unwrap_err();
+        assert_eq!(err.to_string(), "Invalid job on line 2: Job 1 has cores 0 but it must be positive");
+        Ok(())
+    }
+
+    #[test]
+    fn job_factory_sorted_streaming_stops_at_late_job() -> Result<()> {
+        let content = "?;1;1;10;n;0\n?;1;1;10;n;10\n?;1;1;10;n;5\n".to_string();
+        let reader = Box::new(std::io::Cursor::new(content));
+        let mut factory = JobStreaming::from_reader(reader).with_sorted();
+        factory.job_get();
+        factory.job_get();
+
+        assert!(!factory.more_jobs());
+        let err = factory.check_stream().unwrap_err();
+        assert_eq!(err.to_string(),
+            "Invalid job on line 3: Job 2 is submitted at 5 before Job 1 at 10, the jobs of a \
+            stream that can only be read once must be sorted by their submit time");
+        Ok(())
+    }
+
+    #[test]
+    fn job_factory_sorted_streaming_skips_late_jobs() -> Result<()> {
+        // VV: Jobs that arrive at the same time as the previous one are in order
+        let content = "?;1;1;10;n;0\n?;1;1;10;n;10\n?;1;1;10;n;5\n?;1;1;10;n;10\n".to_string();
+        let reader = Box::new(std::io::Cursor::new(content));
+        let mut factory = JobStreaming::from_reader_with_validation(reader, Validation::Lenient)
+            .with_sorted();
+
+        let mut uids = vec![];
+        while factory.more_jobs() {
+            uids.push(factory.job_get().uid);
+        }
+
+        assert_eq!(uids, vec![0, 1, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn job_factory_lenient_validation() -> Result<()> {
+        let content = "?;1;1;10;n;0\n\
+            ?;1;1;-10;n;0\n\
+            ?;1;1;10;n;0;depends_on=[1]\n\
+            ?;1;bad;10;n;0\n\
+            ?;1;1;10;n;1;depends_on=[0]\n";
+        let reader = Box::new(std::io::Cursor::new(content.to_string()));
+        let mut factory = JobStreaming::from_reader_with_validation(reader, Validation::Lenient);
+
+        let mut uids = vec![];
+        while factory.more_jobs() {
+            uids.push(factory.job_get().uid);
+        }
+
+        // VV: Job 2 depends on the skipped Job 1, the malformed line does not define a job
+        assert_eq!(uids, vec![0, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn job_factory_array_overrides() -> Result<()> {
+        let content = "0;1.0;1.0;10.0;n;0.0;array=3;array_stride=2;user=alice;duration[1]=20;\
+            user[2]=bob;gpus[2]=1\n\
+            ?;1.0;1.0;1.0;n;4.0\n";
+        let mut factory = JobStreaming::from_string(content.to_string())?;
+
+        let mut jobs = vec![];
+        while factory.more_jobs() {
+            jobs.push(factory.job_get());
+        }
+
+        assert_eq!(jobs.len(), 4);
+        assert_eq!(jobs.iter().map(|j| j.uid).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        assert_eq!(jobs.iter().map(|j| j.array).collect::<Vec<_>>(),
+                   vec![Some((0, 0)), Some((0, 1)), Some((0, 2)), None]);
+        assert_eq!(jobs.iter().map(|j| j.time_created).collect::<Vec<_>>(),
+                   vec![0.0, 2.0, 4.0, 4.0]);
+        assert_eq!(jobs[0].duration, 10.0);
+        assert_eq!(jobs[1].duration, 20.0);
+        assert_eq!(jobs[1].user.as_deref(), Some("alice"));
+        assert_eq!(jobs[2].user.as_deref(), Some("bob"));
+        assert_eq!(jobs[2].resources.get("gpus"), Some(&1.0));
+        assert!(jobs[0].resources.is_empty());
+
+        assert!(expand_array("0;1.0;1.0;1.0;n;0.0").unwrap().is_none());
+        assert!(expand_array("0;1.0;1.0;1.0;n;0.0;array=2;cores[2]=1").is_err());
+        assert!(expand_array("0;1.0;1.0;1.0;n;0.0;array_stride=1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn job_factory_streaming_swf() -> Result<()> {
+        let content = "; Version: 2.2\n\
+        ; Computer: test\n\
+        \n\
+        1 0 10 100 4 -1 -1 4 200 1048576 1 1 1 1 1 1 -1 -1\n\
+        2 5 -1 -1 8 -1 2097152 -1 -1 -1 0 1 1 1 1 1 -1 -1\n\
+        3 5 -1 50 -1 -1 -1 -1 -1 -1 5 1 1 1 1 1 -1 -1\n";
+
+        let mut factory = JobStreamingSwf::from_string(content.to_string())?;
+
+        let mut jobs = vec![];
+        while factory.more_jobs() {
+            jobs.push(factory.job_get());
+        }
+
+        assert_eq!(jobs.len(), 3);
+        assert_eq!((jobs[0].uid, jobs[0].time_created), (0, 0.0));
+        assert_eq!((jobs[0].cores, jobs[0].memory), (4.0, 4.0));
+        assert_eq!((jobs[0].duration, jobs[0].estimate), (100.0, 200.0));
+
+        // VV: Unknown fields fall back to the allocated processors and the used memory
+        assert_eq!((jobs[1].uid, jobs[1].time_created), (1, 5.0));
+        assert_eq!((jobs[1].cores, jobs[1].memory), (8.0, 16.0));
+        assert_eq!((jobs[1].duration, jobs[1].estimate), (0.0, 0.0));
+
+        assert_eq!((jobs[2].cores, jobs[2].memory), (1.0, 0.0));
+        assert_eq!((jobs[2].duration, jobs[2].estimate), (50.0, 50.0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn job_factory_output_jsonl() -> Result<()> {
+        let path = std::env::temp_dir().join("dismem_job_factory_output.jsonl");
+        assert_eq!(TraceFormat::from_path(&path), TraceFormat::JsonLines);
+
+        let inner = JobStreaming::from_string("?;2.0;4.0;10.0;y;1.0;gpus=1".to_string())?;
+        let mut factory = JobStreamingWithOutput::from_factory_to_path(
+            Box::new(inner), &path, TraceFormat::JsonLines)?;
+
+        let mut job = factory.job_get();
+        job.time_started = Some(2.0);
+        job.time_done = Some(12.0);
+        job.node_cores = Some(3);
+        job.node_memory = vec![(3, 1.0), (4, 3.0)];
+        factory.job_mark_done(&job);
+        drop(factory);
+
+        let content = std::fs::read_to_string(&path)?;
+        let lines: Vec<_> = content.lines().collect();
+        assert_eq!(lines.len(), 1);
+
+        let record: serde_json::Value = serde_json::from_str(lines[0])?;
+        assert_eq!(record["id"], 0);
+        assert_eq!(record["submit"], 1.0);
+        assert_eq!(record["start"], 2.0);
+        assert_eq!(record["finish"], 12.0);
+        assert_eq!(record["node"], 3);
+        assert_eq!(record["runtime"], 10.0);
+        assert_eq!(record["speed"], 1.0);
+        assert_eq!(record["node_memory"], serde_json::json!([[3, 1.0], [4, 3.0]]));
+        assert_eq!(record["resources"]["gpus"], 1.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn job_factory_output_fields() -> Result<()> {
+        let fields = |names: &str| names.split(',').map(str::to_owned).collect::<Vec<_>>();
+        let err = JobStreamingWithOutput::from_factory_to_path_with_fields(
+            Box::new(JobStreaming::from_string("".to_string())?),
+            &std::env::temp_dir().join("dismem_job_factory_fields_bad.txt"), TraceFormat::Text,
+            Some(fields("id,nodes"))).err().unwrap();
+        assert!(err.to_string().contains("Unknown trace field \"nodes\""), "{}", err);
+        assert!(check_trace_fields(&fields("id,node,id")).is_err());
+        assert!(check_trace_fields(&[]).is_err());
+
+        for format in [TraceFormat::Text, TraceFormat::JsonLines] {
+            let path = std::env::temp_dir().join(format!("dismem_job_factory_fields_{:?}", format));
+            let inner = JobStreaming::from_string("?;2.0;4.0;10.0;y;1.0;user=alice".to_string())?;
+            let mut factory = JobStreamingWithOutput::from_factory_to_path_with_fields(
+                Box::new(inner), &path, format, Some(fields("id,user,node,wait,slowdown")))?;
+
+            let mut job = factory.job_get();
+            job.time_started = Some(6.0);
+            job.time_done = Some(16.0);
+            job.node_cores = Some(3);
+            factory.job_mark_done(&job);
+            drop(factory);
+
+            let content = std::fs::read_to_string(&path)?;
+            let lines: Vec<_> = content.lines().collect();
+            match format {
+                TraceFormat::Text => {
+                    assert_eq!(lines, vec!["#id;user;node;wait;slowdown", "0;alice;3;5.0;1.5"]);
+                }
+                TraceFormat::JsonLines => {
+                    assert_eq!(serde_json::from_str::<serde_json::Value>(lines[0])?,
+                               serde_json::json!({"id": 0, "user": "alice", "node": 3,
+                                   "wait": 5.0, "slowdown": 1.5}));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn job_factory_output_survives_crash() -> Result<()> {
+        // VV: Returns the jobs in the trace after a run that dies once the third job is done
+        let crash = |flush_every: usize| -> Result<usize> {
+            let path = std::env::temp_dir()
+                .join(format!("dismem_job_factory_crash_{}.txt", flush_every));
+            let mut reg = NodeRegistry::new();
+            reg.new_node("node", 1.0, 1.0)?;
+
+            let inner = JobStreaming::from_string("?;1;1;10;n;0\n".repeat(5))?;
+            let factory = JobStreamingWithOutput::from_factory_to_path(
+                Box::new(inner), &path, TraceFormat::Text)?
+                .with_flush_every(flush_every);
+            let mut sched = SchedulerBuilder::new(reg, Box::new(factory)).build();
+
+            while sched.jobs_done.len() < 3 {
+                sched.tick();
+            }
+            // VV: Like a killed process, nothing gets to flush the rest of the buffer
+            std::mem::forget(sched);
+
+            let content = std::fs::read_to_string(&path)?;
+            Ok(content.lines().filter(|line| !line.starts_with('#')).count())
+        };
+
+        assert_eq!(crash(1)?, 3);
+        assert_eq!(crash(2)?, 2);
+        assert_eq!(crash(10)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn job_factory_identical_traces() -> Result<()> {
+        // VV: Plenty of identical jobs and nodes so that every queue has to break ties
+        let content = format!("{}\n{}",
+            std::fs::read_to_string("examples/dismem_racks/tiny.jobs")?,
+            "?;4;8;50;y;0\n?;2;4;20;y;5\n".repeat(20));
+
+        let run = |name: &str| -> Result<Vec<u8>> {
+            reset_job_metadata();
+            let path = std::env::temp_dir().join(format!("dismem_identical_traces_{}.txt", name));
+            let reg = NodeRegistry::from_paths(Path::new("examples/dismem_racks/nodes.csv"),
+                                               Path::new("examples/dismem_racks/connections.csv"))?;
+            let inner = JobStreaming::from_string(content.clone())?;
+            let factory = JobStreamingWithOutput::from_factory_to_path(
+                Box::new(inner), &path, TraceFormat::Text)?;
+            let mut sched = SchedulerBuilder::new(reg, Box::new(factory)).build();
+
+            while sched.tick() {}
+            drop(sched);
+
+            Ok(std::fs::read(&path)?)
+        };
+
+        let first = run("first")?;
+        assert!(!first.is_empty());
+        assert_eq!(first, run("second")?);
+        Ok(())
+    }
+
+    #[test]
+    fn job_factory_merges_files() -> Result<()> {
+        let dir = std::env::temp_dir().join("dismem_job_factory_merges_files");
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("day1.jobs"), "?;1;1;10;n;0\n?;1;1;10;n;20\n")?;
+        // VV: The 2nd file is not sorted and its '?' UIDs continue from the 1st file
+        std::fs::write(dir.join("day2.jobs"), "?;1;1;10;n;10\n?;1;1;10;n;5\n")?;
+        std::fs::write(dir.join("duplicate.txt"), "1;1;1;10;n;3\n")?;
+
+        let paths = expand_paths(&dir.join("day*.jobs"))?;
+        assert_eq!(paths, vec![dir.join("day1.jobs"), dir.join("day2.jobs")]);
+
+        let factory = JobCollection::from_paths(&paths, Validation::Strict, None)?;
+        let order: Vec<_> = factory.jobs.iter().map(|job| (job.uid, job.time_created)).collect();
+        assert_eq!(order, vec![(0, 0.0), (3, 5.0), (2, 10.0), (1, 20.0)]);
+
+        let pattern = format!("{},{}", dir.join("day1.jobs").display(),
+                              dir.join("duplicate.txt").display());
+        let paths = expand_paths(Path::new(&pattern))?;
+        assert_eq!(paths.len(), 2);
+        let err = JobCollection::from_paths(&paths, Validation::Strict, None).err().unwrap();
+        assert!(err.to_string().contains("Job 1 of"), "{}", err);
+
+        assert!(expand_paths(&dir.join("missing*.jobs")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn job_factory_poisson_arrivals() -> Result<()> {
+        let arrivals = |seed: u64| -> Vec<(usize, f32)> {
+            reset_job_metadata();
+            let jobs: Vec<Job> = (0..4000).map(|_| Job::new(1.0, 1.0, 10.0, false, 0.0)).collect();
+            let mut factory = PoissonArrivals::new(Box::new(JobCollection::new(jobs)), 0.5, seed);
+            let mut times = vec![];
+            while factory.more_jobs() {
+                let job = factory.job_get();
+                times.push((job.uid, job.time_created));
+            }
+            times
+        };
+
+        let times = arrivals(7);
+        assert_eq!(times.len(), 4000);
+        assert!(times.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 <= w[1].1));
+
+        // VV: The mean gap of 4000 exponential gaps is within 5% of 1/rate with high probability
+        let rate = times.len() as f32 / times.last().unwrap().1;
+        assert!((rate - 0.5).abs() < 0.025, "empirical rate {}", rate);
+
+        assert_eq!(arrivals(7), times);
+        assert_ne!(arrivals(8), times);
+        Ok(())
+    }
 }
@@ -0,0 +1,114 @@
+use anyhow::Result;
+
+use dismem::job::reset_job_metadata;
+use dismem::kube::jobs_from_str;
+use dismem::kube::nodes_from_str;
+use dismem::kube::parse_cpu;
+use dismem::kube::parse_memory;
+
+#[cfg(test)]
+mod test_kube {
+    use super::*;
+
+    #[test]
+    fn kube_quantities() -> Result<()> {
+        assert_eq!(parse_cpu("2")?, 2.0);
+        assert_eq!(parse_cpu("500m")?, 0.5);
+        assert_eq!(parse_memory("512Mi")?, 0.5);
+        assert_eq!(parse_memory("2Gi")?, 2.0);
+        assert_eq!(parse_memory("1073741824")?, 1.0);
+        assert!((parse_memory("1G")? - 0.931).abs() < 1e-3);
+
+        for quantity in ["", "two", "-1", "5x", "1.5.0m"] {
+            assert!(parse_cpu(quantity).is_err(), "{}", quantity);
+        }
+        for quantity in ["", "2GB", "-1Gi", "Mi"] {
+            assert!(parse_memory(quantity).is_err(), "{}", quantity);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn kube_nodes() -> Result<()> {
+        let nodes = nodes_from_str(r#"
+kind: List
+items:
+  - kind: Node
+    metadata: {name: n0, labels: {zone: us-east}}
+    status:
+      capacity: {cpu: 8, memory: 32Gi, pods: 110, nvidia.com/gpu: 2}
+      allocatable: {cpu: 7500m, memory: 30Gi}
+---
+kind: Node
+metadata: {name: n1}
+status:
+  capacity: {cpu: "4", memory: 16Gi}
+"#)?;
+
+        assert_eq!(nodes.len(), 2);
+        assert_eq!((nodes[0].name.as_str(), nodes[0].cores, nodes[0].memory), ("n0", 7.5, 30.0));
+        assert_eq!(nodes[0].resources.get("nvidia.com/gpu"), Some(&2.0));
+        assert!(!nodes[0].resources.contains_key("pods"));
+        assert_eq!(nodes[0].labels.get("zone").map(|z| z.as_str()), Some("us-east"));
+        assert_eq!((nodes[1].cores, nodes[1].memory), (4.0, 16.0));
+
+        assert!(nodes_from_str("kind: Pod\nmetadata: {name: p}").is_err());
+        assert!(nodes_from_str("kind: Node\nstatus: {capacity: {cpu: 4}}").is_err());
+        assert!(nodes_from_str("kind: Node\nmetadata: {name: n}\nstatus: {capacity: {memory: 4GB}}")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn kube_jobs() -> Result<()> {
+        reset_job_metadata();
+        let jobs = jobs_from_str(r#"
+kind: Pod
+metadata:
+  name: late
+  annotations: {dismem/duration: "10", dismem/created: "5"}
+spec:
+  containers:
+    - resources: {requests: {cpu: 250m, memory: 256Mi}}
+---
+kind: Pod
+metadata:
+  name: early
+  namespace: alice
+  annotations: {dismem/duration: "20", dismem/borrow: "true"}
+spec:
+  priority: 3
+  activeDeadlineSeconds: 30
+  nodeSelector: {gpu: "true"}
+  containers:
+    - resources: {requests: {cpu: "1"}, limits: {cpu: "2", memory: 1Gi}}
+    - resources: {limits: {memory: 1Gi, nvidia.com/gpu: 1}}
+"#)?;
+
+        // VV: The jobs are in the order the pods get created in
+        assert_eq!(jobs.len(), 2);
+        assert_eq!((jobs[0].uid, jobs[0].cores, jobs[0].memory), (0, 1.0, 2.0));
+        assert_eq!((jobs[0].duration, jobs[0].time_created, jobs[0].can_borrow), (20.0, 0.0, true));
+        assert_eq!(jobs[0].user.as_deref(), Some("alice"));
+        assert_eq!((jobs[0].priority, jobs[0].walltime_limit), (3, Some(30.0)));
+        assert_eq!(jobs[0].resources.get("nvidia.com/gpu"), Some(&1.0));
+        assert_eq!(jobs[0].selector.get("gpu").map(|g| g.as_str()), Some("true"));
+        assert_eq!((jobs[1].uid, jobs[1].cores, jobs[1].memory), (1, 0.25, 0.25));
+        assert_eq!(jobs[1].time_created, 5.0);
+
+        let pod = |annotations: &str, requests: &str| format!(
+            "kind: Pod\nmetadata: {{name: p, annotations: {{{}}}}}\n\
+            spec: {{containers: [{{resources: {{requests: {{{}}}}}}}]}}", annotations, requests);
+
+        reset_job_metadata();
+        assert!(jobs_from_str(&pod("dismem/duration: \"1\"", "cpu: 1")).is_ok());
+        for (annotations, requests) in [("", "cpu: 1"), ("dismem/duration: \"1\"", "cpu: 1x"),
+            ("dismem/duration: \"1\"", "memory: 1"), ("dismem/duration: one", "cpu: 1"),
+            ("dismem/duration: \"1\", dismem/borrow: maybe", "cpu: 1")] {
+            reset_job_metadata();
+            assert!(jobs_from_str(&pod(annotations, requests)).is_err(), "{} {}", annotations,
+                requests);
+        }
+        Ok(())
+    }
+}
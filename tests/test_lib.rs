@@ -0,0 +1,34 @@
+use anyhow::Result;
+
+use dismem::job::reset_job_metadata;
+use dismem::job_factory::JobCollection;
+use dismem::scheduler::FirstFit;
+use dismem::Job;
+use dismem::NodeRegistry;
+use dismem::Scheduler;
+
+#[cfg(test)]
+mod test_lib {
+    use super::*;
+
+    #[test]
+    fn lib_step_scheduler() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 2.0, 2.0)?;
+
+        reset_job_metadata();
+        let jobs = vec![Job::new(2.0, 2.0, 5.0, false, 0.0), Job::new(1.0, 1.0, 5.0, false, 0.0)];
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        assert!(sched.tick());
+        assert_eq!(sched.jobs_running.len(), 1);
+        assert_eq!(sched.jobs_queuing.len(), 1);
+
+        while sched.tick() {}
+
+        assert_eq!(sched.jobs_done.len(), 2);
+        assert_eq!(sched.now, 10.0);
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+
+use dismem::job::Job;
+use dismem::job::reset_job_metadata;
+use dismem::job_factory::JobCollection;
+use dismem::metrics::MetricsSnapshot;
+use dismem::registry::NodeRegistry;
+use dismem::scheduler::FirstFit;
+use dismem::scheduler::Scheduler;
+
+#[cfg(test)]
+mod test_metrics {
+    use super::*;
+
+    fn two_jobs_on_one_node() -> Result<Scheduler> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 4.0, 4.0)?;
+
+        reset_job_metadata();
+        let jobs = vec![Job::new(1.0, 2.0, 10.0, false, 0.0), Job::new(4.0, 1.0, 10.0, false, 0.0)];
+        let job_factory = JobCollection::new(jobs);
+        Ok(Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default())))
+    }
+
+    #[test]
+    fn metrics_snapshot() -> Result<()> {
+        let mut sched = two_jobs_on_one_node()?;
+        assert!(sched.tick());
+
+        let snapshot = MetricsSnapshot::from_scheduler(&sched);
+        assert_eq!(snapshot.jobs_running, 1);
+        assert_eq!(snapshot.jobs_queuing, 1);
+        assert_eq!(snapshot.jobs_done, 0);
+        assert_eq!(snapshot.cores_utilization, 0.25);
+        assert_eq!(snapshot.memory_utilization, 0.5);
+
+        let text = snapshot.to_prometheus();
+        assert!(text.contains("# TYPE dismem_jobs_running gauge\ndismem_jobs_running 1\n"));
+        assert!(text.contains("# TYPE dismem_jobs_done_total counter\ndismem_jobs_done_total 0\n"));
+        assert!(text.contains("dismem_cores_utilization 0.25\n"));
+        Ok(())
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn metrics_server() -> Result<()> {
+        use std::io::Read;
+        use std::io::Write;
+        use std::net::TcpStream;
+
+        use dismem::metrics::MetricsServer;
+
+        let scrape = |server: &MetricsServer, path: &str| -> Result<String> {
+            let mut stream = TcpStream::connect(server.address())?;
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path)?;
+            let mut response = String::new();
+            stream.read_to_string(&mut response)?;
+            Ok(response)
+        };
+
+        let server = MetricsServer::start("127.0.0.1:0")?;
+        let mut sched = two_jobs_on_one_node()?;
+        while sched.tick() {
+            server.update(&sched);
+        }
+
+        let response = scrape(&server, "/metrics")?;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("dismem_jobs_done_total 1\n"));
+
+        assert!(scrape(&server, "/")?.starts_with("HTTP/1.1 404"));
+        Ok(())
+    }
+}
@@ -1,7 +1,13 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
 use anyhow::Result;
+use rand::Rng;
+use rand::SeedableRng;
 
 use dismem::node;
 use dismem::registry;
+use dismem::SimRng;
 
 #[cfg(test)]
 mod test_node {
@@ -29,6 +35,25 @@ mod test_node {
         }
     }
 
+    #[test]
+    fn node_fits_tenths_of_a_core() -> Result<()> {
+        let mut reg = registry::NodeRegistry::new();
+        reg.new_node("test", 1.0, 100.0)?;
+        let node = &mut reg.nodes[0];
+
+        // VV: 10 times the f32 0.1 adds up to more than 1.0 but the fixed-point accounting is exact
+        for _ in 0..10 {
+            assert!(node.can_host_job(0.1, 1.0));
+            node.allocate_job(0.1, 1.0);
+        }
+        assert_eq!(node.cores.current, 0.0);
+        assert!(!node.can_host_job(0.1, 1.0));
+
+        node.free_cores(0.1);
+        assert!(node.can_host_job(0.1, 1.0));
+        Ok(())
+    }
+
     #[test]
     fn insort_nodes() -> Result<()> {
         let mut reg = registry::NodeRegistry::new();
@@ -99,7 +124,7 @@ mod test_node {
         let memory: Vec<&node::Node> = reg.nodes_sorted_memory(0.).collect();
 
         for node in &cores {
-            print!("After sort {}\n", node);
+            println!("After sort {}", node);
         }
 
         assert_eq!(cores[0].name, "more_cores");
@@ -110,4 +135,78 @@ mod test_node {
 
         Ok(())
     }
+
+    #[test]
+    fn registry_from_topology_file() -> Result<()> {
+        let dir = Path::new("examples/dismem_racks");
+        let split = registry::NodeRegistry::from_paths(&dir.join("nodes.csv"),
+                                                       &dir.join("connections.csv"))?;
+        let combined = registry::NodeRegistry::from_path(&dir.join("topology.toml"))?;
+
+        assert_eq!(combined.nodes.len(), split.nodes.len());
+        for (a, b) in combined.nodes.iter().zip(split.nodes.iter()) {
+            assert_eq!((&a.name, a.cores.capacity, a.memory.capacity),
+                       (&b.name, b.cores.capacity, b.memory.capacity));
+        }
+        assert_eq!(combined.connections, split.connections);
+        Ok(())
+    }
+
+    #[test]
+    fn registry_topology_unknown_lender() -> Result<()> {
+        let content = "[[nodes]]\nname = \"a\"\ncores = 1\nmemory = 1\noptions = [\"speed=0.5\"]\n\
+            [[connections]]\nborrower = \"a\"\nlenders = [\"b\"]\n";
+        let err = registry::NodeRegistry::from_toml_str(content).err().unwrap();
+        assert!(err.to_string().contains("unknown lender \"b\""), "{}", err);
+
+        let reg = registry::NodeRegistry::from_toml_str(content.split("[[connections]]")
+            .next().unwrap())?;
+        assert_eq!(reg.nodes[0].speed, 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn registry_index_matches_scan() -> Result<()> {
+        let spec = registry::ClusterSpec {
+            nodes: 200,
+            cores: 1..=8,
+            memory: 1..=8,
+            resources: BTreeMap::new(),
+            topology: registry::TopologyPattern::FullyConnected,
+        };
+        let mut reg = registry::NodeRegistry::generate(&spec, 7)?;
+        let mut rng = SimRng::seed_from_u64(11);
+
+        for _ in 0..500 {
+            // VV: Allocate or free a few cores and memory on random nodes like Placements do
+            for _ in 0..rng.gen_range(1..4) {
+                let uid = rng.gen_range(0..reg.nodes.len());
+                let node = &mut reg.nodes[uid];
+                let (cores, memory) = (node.cores.current, node.memory.current);
+                if rng.gen_bool(0.5) && cores >= 1. && memory >= 1. {
+                    node.allocate_job(1., 1.);
+                } else if cores < node.cores.capacity && memory < node.memory.capacity {
+                    node.free_cores(1.);
+                    node.free_memory(1.);
+                }
+                reg.touch(uid);
+            }
+
+            let (cores, memory) = (rng.gen_range(0..9) as f32, rng.gen_range(0..9) as f32);
+            let indexed: Vec<usize> = reg.nodes_with_at_least(cores, memory).collect();
+
+            let mut expected: Vec<usize> = (0..reg.nodes.len()).collect();
+            expected.sort_by(|a, b| {
+                reg.nodes[*a].cores.current.partial_cmp(&reg.nodes[*b].cores.current)
+                    .unwrap().then(a.cmp(b))
+            });
+            assert_eq!(reg.sorted_cores, expected);
+
+            expected.retain(|uid| {
+                reg.nodes[*uid].cores.current >= cores && reg.nodes[*uid].memory.current >= memory
+            });
+            assert_eq!(indexed, expected);
+        }
+        Ok(())
+    }
 }
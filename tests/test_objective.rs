@@ -0,0 +1,38 @@
+use anyhow::Result;
+
+use dismem::objective::OBJECTIVES;
+use dismem::objective::ObjectivePolicy;
+use dismem::objective::objective_policy;
+
+#[cfg(test)]
+mod test_objective {
+    use super::*;
+
+    #[test]
+    fn objective_picks_documented_policy() -> Result<()> {
+        let expected = [
+            ("makespan", ObjectivePolicy::Backfill),
+            ("utilization", ObjectivePolicy::BestFitPack),
+            ("fairness", ObjectivePolicy::FairShare(86400.0)),
+            ("wait-time", ObjectivePolicy::FirstFit),
+        ];
+        assert_eq!(OBJECTIVES.len(), expected.len());
+
+        for (objective, policy) in expected {
+            assert!(OBJECTIVES.contains(&objective));
+            assert_eq!(objective_policy(objective, false)?, policy, "{}", objective);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn objective_rejects_explicit_policy() {
+        for objective in OBJECTIVES {
+            let err = objective_policy(objective, true).unwrap_err();
+            assert!(err.to_string().contains("cannot be combined with a policy"), "{}", err);
+        }
+
+        let err = objective_policy("throughput", false).unwrap_err();
+        assert!(err.to_string().contains("Unknown objective \"throughput\""), "{}", err);
+    }
+}
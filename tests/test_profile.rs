@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+use dismem::job::Job;
+use dismem::job::reset_job_metadata;
+use dismem::job_factory::JobCollection;
+use dismem::profile;
+use dismem::registry::ClusterSpec;
+use dismem::registry::NodeRegistry;
+use dismem::registry::TopologyPattern;
+use dismem::scheduler::SchedulerBuilder;
+
+#[cfg(test)]
+mod test_profile {
+    use super::*;
+
+    fn cluster() -> Result<NodeRegistry> {
+        let spec = ClusterSpec {
+            nodes: 4,
+            cores: 1..=1,
+            memory: 1..=1,
+            resources: BTreeMap::new(),
+            topology: TopologyPattern::Ring,
+        };
+        NodeRegistry::generate(&spec, 0)
+    }
+
+    #[test]
+    fn profile_counts_placements() -> Result<()> {
+        reset_job_metadata();
+        let jobs: Vec<Job> = (0..8).map(|_| Job::new(1.0, 1.0, 10.0, false, 0.0)).collect();
+        let mut sched = SchedulerBuilder::new(cluster()?, Box::new(JobCollection::new(jobs)))
+            .profile(true)
+            .build();
+        assert!(profile::is_enabled());
+
+        while sched.tick() {}
+
+        // VV: 4 jobs start at 0 and 4 at 10, FirstFit stops trying once no node has a free core
+        let counted = sched.placement_profile.unwrap();
+        assert_eq!(sched.jobs_done.len(), 8);
+        assert_eq!(counted.total.placements, 8);
+        assert_eq!(counted.total.attempts, 8);
+        assert!(counted.total.checks >= counted.total.attempts);
+        assert_eq!(counted.busiest.placements, 4);
+        assert_eq!(counted.busiest_at, 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn profile_is_off_by_default() -> Result<()> {
+        reset_job_metadata();
+        let jobs = vec![Job::new(1.0, 1.0, 10.0, false, 0.0)];
+        let mut sched = SchedulerBuilder::new(cluster()?, Box::new(JobCollection::new(jobs)))
+            .build();
+
+        while sched.tick() {}
+        assert!(sched.placement_profile.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use rand::SeedableRng;
+
+use dismem::job::Job;
+use dismem::job::reset_job_metadata;
+use dismem::job_factory::JobCollection;
+use dismem::registry::NodeRegistry;
+use dismem::runtime::RuntimeModel;
+use dismem::scheduler::SchedulerBuilder;
+use dismem::summary::JobTimes;
+use dismem::SimRng;
+
+#[cfg(test)]
+mod test_runtime {
+    use super::*;
+
+    /// Runs 20 jobs with a declared duration of 10 on a single node and returns their times
+    fn run_lognormal(seed: u64) -> Result<Vec<JobTimes>> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 4.0, 4.0)?;
+
+        reset_job_metadata();
+        let jobs = (0..20).map(|i| Job::new(1.0, 1.0, 10.0, false, i as f32)).collect();
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .seed(seed)
+            .runtime_model(RuntimeModel::Lognormal { sigma: 0.5, scale: 1.0 })
+            .build();
+
+        while sched.tick() {}
+
+        assert_eq!(sched.jobs_done.len(), 20);
+        Ok(sched.job_times)
+    }
+
+    #[test]
+    fn runtime_lognormal_reproducible() -> Result<()> {
+        let times = run_lognormal(11)?;
+
+        assert_eq!(times, run_lognormal(11)?);
+        assert_ne!(times, run_lognormal(12)?);
+        assert!(times.iter().any(|times| times.done - times.started != 10.0));
+        Ok(())
+    }
+
+    #[test]
+    fn runtime_uniform_bounds() -> Result<()> {
+        let model = RuntimeModel::Uniform { low: 0.5, high: 2.0 };
+        model.validate()?;
+
+        let mut rng = SimRng::seed_from_u64(3);
+        for _ in 0..100 {
+            let duration = model.sample(10.0, &mut rng);
+            assert!((5.0..20.0).contains(&duration));
+        }
+
+        assert!(RuntimeModel::Uniform { low: 2.0, high: 1.0 }.validate().is_err());
+        assert!(RuntimeModel::Lognormal { sigma: -0.1, scale: 1.0 }.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn runtime_backfill_uses_estimate() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 2.0, 2.0)?;
+
+        reset_job_metadata();
+        let jobs = vec![
+            Job::new(1.0, 1.0, 100.0, false, 0.0),
+            Job::new(2.0, 2.0, 10.0, false, 0.0),
+            Job::new(1.0, 1.0, 60.0, false, 0.0),
+            Job::new(1.0, 1.0, 200.0, false, 0.0),
+        ];
+        // VV: Every job runs for longer than it declared
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .backfill(true)
+            .seed(5)
+            .runtime_model(RuntimeModel::Uniform { low: 1.7, high: 1.9 })
+            .build();
+
+        let mut started: HashMap<usize, (f32, f32)> = HashMap::new();
+        while sched.tick() {
+            for job in &sched.jobs_running {
+                started.insert(job.uid, (job.time_started.unwrap(), job.time_done.unwrap()));
+            }
+        }
+
+        assert_eq!(sched.jobs_done.len(), 4);
+
+        // VV: Job 2 finishes before the reservation of job 1 per its estimate so it backfills,
+        // job 3 would not and waits for job 1 even though job 2 really outlives the reservation
+        assert_eq!(started[&0].0, 0.0);
+        assert_eq!(started[&2].0, 0.0);
+        assert!(started[&2].1 > 100.0 && started[&0].1 > 170.0);
+        assert_eq!(started[&1].0, started[&0].1.max(started[&2].1));
+        assert!(started[&3].0 >= started[&1].1);
+        Ok(())
+    }
+}
@@ -1,10 +1,35 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
 use anyhow::Result;
+use rand::Rng;
 
+use dismem::job::BlockReason;
+use dismem::job::Deadlock;
 use dismem::job::Job;
+use dismem::job::JobId;
 use dismem::job::reset_job_metadata;
+use dismem::failures::ConcurrencyLimit;
+use dismem::failures::HoldEvent;
+use dismem::failures::NodeFailure;
+use dismem::failures::NodeMaintenance;
+use dismem::failures::PartitionLimits;
+use dismem::failures::Reservation;
+use dismem::failures::ScalingEvent;
 use dismem::job_factory::JobCollection;
+use dismem::job_factory::JobStreaming;
+use dismem::job_factory::JobStreamingWithOutput;
+use dismem::job_factory::TraceFormat;
 use dismem::registry::NodeRegistry;
+use dismem::scheduler::CascadePolicy;
+use dismem::scheduler::FirstFit;
+use dismem::scheduler::Placement;
+use dismem::scheduler::PolicyContext;
 use dismem::scheduler::Scheduler;
+use dismem::scheduler::SchedulerBuilder;
+use dismem::scheduler::SchedulingPolicy;
+use dismem::summary::RunningJobStatus;
 
 #[cfg(test)]
 mod test_scheduler {
@@ -25,7 +50,7 @@ mod test_scheduler {
     }
 
     fn jobfactory_init_homogeneous(
-        jobs_created: &Vec<f32>,
+        jobs_created: &[f32],
         cores: f32,
         memory: f32,
         duration: f32,
@@ -51,7 +76,7 @@ mod test_scheduler {
 
         let job_factory = jobfactory_init_homogeneous(&job_created, 1.0, 1.0, 5.0, false);
 
-        let mut sched = Scheduler::new(reg, Box::new(job_factory));
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
 
         while sched.tick() {}
 
@@ -70,7 +95,7 @@ mod test_scheduler {
         let job_factory = jobfactory_init_homogeneous(
             &job_created, 1.0, 1.0, 5.0, false);
 
-        let mut sched = Scheduler::new(reg, Box::new(job_factory));
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
 
         while sched.tick() {}
 
@@ -98,14 +123,17 @@ mod test_scheduler {
 
         let job_factory = JobCollection::new(jobs);
 
-        let mut sched = Scheduler::new(reg, Box::new(job_factory));
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
 
         while sched.tick() && !sched.has_unschedulable() {}
 
         assert_eq!(sched.job_factory.jobs_done().len(), num_jobs);
 
         assert_eq!(sched.now, 5.0);
-        assert_eq!(sched.jobs_queuing.len(), 1);
+        // VV: The oversized job never enters the queue
+        assert!(sched.jobs_queuing.is_empty());
+        assert_eq!(sched.jobs_rejected.len(), 1);
+        assert!(!sched.has_unschedulable());
 
         Ok(())
     }
@@ -124,7 +152,7 @@ mod test_scheduler {
 
         let job_factory = jobfactory_init_homogeneous(&job_created, 1.0, 1.0, 5.0, true);
 
-        let mut sched = Scheduler::new(reg, Box::new(job_factory));
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
 
         while sched.tick() {}
 
@@ -147,12 +175,1637 @@ mod test_scheduler {
 
         let job_factory = jobfactory_init_homogeneous(&job_created, 1.0, 1.0, 5.0, true);
 
-        let mut sched = Scheduler::new(reg, Box::new(job_factory));
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        while sched.tick() {}
+
+        assert_eq!(sched.job_factory.jobs_done().len(), 4);
+        assert_eq!(sched.now, 10.0);
+        Ok(())
+    }
+
+    /// Starts at most one job at a time, always on the node with UID 0
+    struct OneAtATime {}
+
+    impl SchedulingPolicy for OneAtATime {
+        fn select(
+            &mut self,
+            queued: &[Job],
+            registry: &mut NodeRegistry,
+            context: &PolicyContext,
+        ) -> Vec<(JobId, Placement)> {
+            let node = &registry.nodes[0];
+            match queued.get(context.unchanged) {
+                Some(job) if node.can_host_job(job.cores, job.memory) => {
+                    let placement = Placement {
+                        node_cores: 0,
+                        node_memory: vec![(0, job.memory)],
+                        gang_cores: vec![],
+                    };
+                    placement.reserve(registry, job);
+                    vec![(job.uid, placement)]
+                }
+                _ => vec![],
+            }
+        }
+    }
+
+    #[test]
+    fn scheduler_custom_policy() -> Result<()> {
+        let reg = registry_init_homogeneous(2, 2., 2.)?;
+        let job_created: Vec<_> = vec![0.0, 0.0, 0.0];
+
+        let job_factory = jobfactory_init_homogeneous(&job_created, 1.0, 1.0, 5.0, false);
+
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(OneAtATime {}));
+
+        while sched.tick() {
+            assert!(sched.jobs_running.iter().all(|job| job.node_cores == Some(0)));
+        }
+
+        assert_eq!(sched.job_factory.jobs_done().len(), 3);
+        // VV: The policy starts a single job each time it sees the queue so the jobs run back to back
+        assert_eq!(sched.now, 15.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_priority_jumps_queue() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 1., 1.)?;
+
+        reset_job_metadata();
+        let mut jobs: Vec<_> = (0..4)
+            .map(|uid| Job::new_with_uid(uid, 1.0, 1.0, 10.0, false, uid.min(1) as f32))
+            .collect();
+        let mut urgent = Job::new_with_uid(4, 1.0, 1.0, 10.0, false, 2.0);
+        urgent.priority = 10;
+        jobs.push(urgent);
+
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        let mut urgent_started = None;
+        while sched.tick() {
+            if let Some(job) = sched.jobs_running.iter().find(|job| job.uid == 4) {
+                urgent_started = job.time_started;
+            }
+        }
+
+        assert_eq!(sched.job_factory.jobs_done().len(), 5);
+        // VV: The urgent job skips the 3 jobs that were queued before it
+        assert_eq!(urgent_started, Some(10.0));
+        assert_eq!(sched.now, 50.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_diamond_dependencies() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 2., 2.)?;
+
+        reset_job_metadata();
+        let a = Job::new_with_uid(0, 1.0, 1.0, 10.0, false, 0.0);
+        let mut b = Job::new_with_uid(1, 1.0, 1.0, 5.0, false, 0.0);
+        let mut c = Job::new_with_uid(2, 1.0, 1.0, 20.0, false, 0.0);
+        let mut d = Job::new_with_uid(3, 1.0, 1.0, 1.0, false, 0.0);
+        b.depends_on = vec![0];
+        c.depends_on = vec![0];
+        d.depends_on = vec![1, 2];
+
+        let job_factory = JobCollection::try_new(vec![a, b, c, d])?;
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        let mut started = vec![None; 4];
+        while sched.tick() {
+            for job in &sched.jobs_running {
+                started[job.uid] = job.time_started;
+            }
+        }
+
+        assert_eq!(sched.job_factory.jobs_done().len(), 4);
+        assert_eq!(started, vec![Some(0.0), Some(10.0), Some(10.0), Some(30.0)]);
+        assert_eq!(sched.now, 31.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_gang_waits_for_enough_nodes() -> Result<()> {
+        let reg = registry_init_homogeneous(4, 32., 64.)?;
+
+        reset_job_metadata();
+        let blocker = Job::new_with_uid(0, 32.0, 1.0, 10.0, false, 0.0);
+        let mut gang = Job::new_with_uid(1, 100.0, 100.0, 5.0, false, 0.0);
+        gang.gang = true;
+
+        let job_factory = JobCollection::new(vec![blocker, gang]);
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        let mut gang_started = None;
+        while sched.tick() {
+            if let Some(job) = sched.jobs_running.iter().find(|job| job.uid == 1) {
+                gang_started = job.time_started;
+                assert_eq!(job.gang_cores.len(), 4);
+                assert_eq!(job.gang_cores.iter().map(|(_, cores)| cores).sum::<f32>(), 100.0);
+                assert_eq!(job.node_memory.iter().map(|(_, memory)| memory).sum::<f32>(), 100.0);
+            }
+        }
+
+        assert_eq!(sched.job_factory.jobs_done().len(), 2);
+        // VV: 3 idle nodes have 96 cores which is not enough for the gang
+        assert_eq!(gang_started, Some(10.0));
+        assert_eq!(sched.now, 15.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_preempts_low_priority() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 2., 2.)?;
+
+        reset_job_metadata();
+        let low = Job::new_with_uid(0, 2.0, 1.0, 10.0, false, 0.0);
+        let mut high = Job::new_with_uid(1, 2.0, 1.0, 3.0, false, 2.0);
+        high.priority = 10;
+
+        let job_factory = JobCollection::new(vec![low, high]);
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+        sched.preemption = true;
+
+        let mut requeued = false;
+        let mut low_done = None;
+        while sched.tick() {
+            requeued |= sched.now > 0.0 && sched.jobs_queuing.iter().any(|job| job.uid == 0);
+            if let Some(job) = sched.jobs_running.iter().find(|job| job.uid == 0) {
+                low_done = job.time_done;
+            }
+        }
+
+        assert!(requeued);
+        assert_eq!(sched.job_factory.jobs_done().len(), 2);
+        // VV: The low priority job restarts from scratch after the high priority one finishes
+        assert_eq!(low_done, Some(15.0));
+        assert_eq!(sched.now, 15.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_gpu_jobs_wait_for_gpus() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("CPU;4;4")?;
+        reg.new_node_from_str("GPU;4;4;gpus=1")?;
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["?;1;1;10;n;0;gpus=1", "?;1;1;10;n;0;gpus=1", "?;1;1;10;n;0",
+            "?;1;1;10;n;0", "?;1;1;10;n;0"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        let mut started = vec![None; 5];
+        while sched.tick() {
+            for job in &sched.jobs_running {
+                started[job.uid] = job.time_started;
+                if job.resources.contains_key("gpus") {
+                    assert_eq!(job.node_cores, Some(1));
+                }
+            }
+        }
+
+        assert_eq!(sched.job_factory.jobs_done().len(), 5);
+        assert_eq!(started, vec![Some(0.0), Some(10.0), Some(0.0), Some(0.0), Some(0.0)]);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_label_selectors() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("n0;4;4;label:zone=us-west")?;
+        reg.new_node_from_str("n1;4;4;label:zone=us-east;label:gpu=true")?;
+        reg.new_node_from_str("n2;4;4")?;
+        assert!(reg.new_node_from_str("n3;4;4;label:=x").is_err());
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["?;1;1;10;n;0;label:gpu=true", "?;1;1;10;n;0;label:zone=us-east",
+            "?;4;4;10;n;0;label:zone=us-east", "?;1;1;10;n;0;label:zone=eu-west",
+            "?;1;1;10;n;0;label:zone=us-west;label:gpu=true"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        assert!("?;1;1;10;n;0;label:=x".parse::<Job>().is_err());
+
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        let mut started = vec![None; 5];
+        while sched.tick() {
+            for job in &sched.jobs_running {
+                started[job.uid] = Some((job.time_started.unwrap(), job.node_cores.unwrap()));
+            }
+        }
+
+        // VV: The large job waits for n1 even though the other nodes are free
+        assert_eq!(started, vec![Some((0.0, 1)), Some((0.0, 1)), Some((10.0, 1)), None, None]);
+
+        let rejected: Vec<JobId> = sched.jobs_rejected.iter().map(|(job, _)| job.uid).collect();
+        assert_eq!(rejected, vec![3, 4]);
+        assert!(sched.jobs_rejected[0].1.contains("zone=eu-west"));
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_anti_affinity_spreads_jobs() -> Result<()> {
+        let reg = registry_init_homogeneous(3, 4.0, 4.0)?;
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["?;1;1;10;n;0;anti_affinity=web", "?;1;1;10;n;0;anti_affinity=web",
+            "?;1;1;10;n;0;anti_affinity=web", "?;1;1;10;n;0;anti_affinity=db",
+            "?;1;1;10;n;5;anti_affinity=web"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        let mut placed = [None; 5];
+        while sched.tick() {
+            for job in &sched.jobs_running {
+                placed[job.uid] = Some((job.node_cores.unwrap(), job.colocated));
+            }
+        }
+
+        let web: HashSet<_> = placed[..3].iter().map(|p| p.unwrap().0).collect();
+        assert_eq!(web.len(), 3);
+        assert!(placed[..4].iter().all(|p| !p.unwrap().1));
+
+        // VV: Every node already runs a web job so the last one has to share a node
+        assert!(placed[4].unwrap().1);
+        assert!(sched.registry.nodes.iter().all(|node| node.groups.is_empty()));
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_exclusive_job_keeps_node_to_itself() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 32.0, 64.0)?;
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["?;1;1;100;n;0;exclusive=y", "?;1;1;10;n;0", "?;1;1;10;n;0",
+            "?;1;1;10;n;0;exclusive=y", "?;1;1;10;n;5"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        assert!("?;1;1;10;n;0;exclusive=maybe".parse::<Job>().is_err());
+        assert!("?;1;1;10;n;0;exclusive=y;bundle=b".parse::<Job>().unwrap().validate().is_err());
+
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        // VV: The exclusive job only uses 1 of the 32 cores but the other jobs still wait
+        assert!(sched.tick());
+        assert_eq!(sched.jobs_running.len(), 1);
+        assert_eq!(sched.registry.nodes[0].cores.current, 0.0);
+        let reasons: Vec<_> = sched.jobs_queuing.iter()
+            .map(|job| job.last_block_reason.clone().unwrap())
+            .collect();
+        assert_eq!(reasons, vec![BlockReason::Cores, BlockReason::Cores, BlockReason::Exclusive]);
+
+        let mut started = [None; 5];
+        while sched.tick() {
+            for job in &sched.jobs_running {
+                started[job.uid] = job.time_started;
+                assert!(!job.exclusive || sched.jobs_running.len() == 1);
+            }
+        }
+
+        // VV: The second exclusive job waits for the node to be idle again
+        assert_eq!(started, [Some(0.0), Some(100.0), Some(100.0), Some(110.0), Some(100.0)]);
+        assert!(sched.registry.nodes[0].is_idle());
+        assert!(!sched.registry.nodes[0].exclusive);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_rejects_unknown_resources() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("FPGA;4;4;fpgas=2;licenses=1")?;
+
+        reset_job_metadata();
+        let known: Job = "?;1;1;10;n;0;fpgas=2;licenses=1".parse().unwrap();
+        let unknown: Job = "?;1;1;10;n;0;gpus=1".parse().unwrap();
+
+        assert!(reg.check_resources(&known).is_ok());
+        assert!(reg.check_resources(&unknown).is_err());
+        assert_eq!(reg.get_max_resource("fpgas"), 2.0);
 
+        // VV: A typo in an optional key of a job line turns into a resource that no node has,
+        // the job is rejected instead of stopping the simulation
+        let jobs = JobCollection::new(vec![known, unknown]);
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
         while sched.tick() {}
+        assert_eq!(sched.jobs_done.len(), 1);
+        assert_eq!(sched.jobs_rejected.len(), 1);
+        assert_eq!(sched.jobs_rejected[0].0.uid, 1);
+        assert!(sched.jobs_rejected[0].1.contains("no node provides gpus"));
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_rejects_oversized_jobs() -> Result<()> {
+        let reg = registry_init_homogeneous(2, 2.0, 2.0)?;
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["?;1;1;10;n;0", "?;3;1;10;n;0", "?;2;6;10;y;0", "?;2;2;10;n;1",
+            "?;1;1;10;n;2;depends_on=[1]", "?;4;2;10;n;3;gang=y"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        while sched.tick() {
+            assert!(!sched.has_unschedulable());
+        }
+
+        let mut done: Vec<JobId> = sched.jobs_done.iter().copied().collect();
+        done.sort();
+        assert_eq!(done, vec![0, 3, 5]);
+
+        let rejected: Vec<JobId> = sched.jobs_rejected.iter().map(|(job, _)| job.uid).collect();
+        assert_eq!(rejected, vec![1, 2, 4]);
+        assert!(sched.jobs_rejected[0].1.contains("3 cores"));
+        assert!(sched.jobs_rejected[2].1.contains("rejected Job 1"));
+        assert!(sched.jobs_queuing.is_empty() && sched.jobs_blocked.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_jumps_to_next_event() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["?;1;1;10;n;0", "?;1;1;10;n;0", "?;1;1;10;n;1000000",
+            "?;1;1;10;n;3000000"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        let mut times = vec![];
+        while sched.tick() {
+            times.push(sched.now);
+        }
 
+        // VV: Every tick handles exactly 1 point in time at which a job arrives or finishes
+        assert_eq!(times, vec![10.0, 20.0, 1000000.0, 1000010.0, 3000000.0, 3000010.0]);
+        assert_eq!(sched.next_event_time(), None);
         assert_eq!(sched.job_factory.jobs_done().len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_node_utilization() -> Result<()> {
+        let reg = registry_init_homogeneous(3, 2.0, 2.0)?;
+
+        reset_job_metadata();
+        let jobs = vec![Job::new(2.0, 1.0, 10.0, false, 0.0), Job::new(1.0, 2.0, 5.0, false, 0.0)];
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        while sched.tick() {}
+
+        let utilization = sched.node_utilization();
         assert_eq!(sched.now, 10.0);
+        assert_eq!(utilization.len(), 3);
+
+        let mut values: Vec<f64> = utilization.values().cloned().collect();
+        values.sort_by(|a, b| a.total_cmp(b));
+        // VV: One node had all its cores busy the entire time, another had all its memory
+        // busy for half the time, and the last one never received a job
+        assert_eq!(values, vec![0.0, 0.5, 1.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_tracks_fragmentation() -> Result<()> {
+        let reg = registry_init_homogeneous(2, 2.0, 2.0)?;
+
+        reset_job_metadata();
+        let jobs = vec![
+            Job::new(1.0, 1.0, 10.0, false, 0.0),
+            Job::new(1.0, 1.0, 5.0, false, 0.0),
+            Job::new(1.0, 1.0, 10.0, false, 0.0),
+            Job::new(1.0, 1.0, 5.0, false, 0.0),
+            Job::new(2.0, 2.0, 5.0, false, 0.0),
+        ];
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = SchedulerBuilder::new(reg, Box::new(job_factory))
+            .track_fragmentation(true)
+            .build();
+
+        while sched.tick() {}
+
+        // VV: At t=5 each node has 1 free core, together they could run the last job
+        let samples: Vec<(f32, usize, usize)> = sched.fragmentation
+            .iter()
+            .map(|s| (s.time, s.started, s.could_start))
+            .collect();
+        assert_eq!(samples, vec![(0.0, 4, 4), (5.0, 0, 1), (10.0, 1, 1)]);
+        assert_eq!(sched.fragmentation[1].blocked(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_array_job() -> Result<()> {
+        let reg = registry_init_homogeneous(4, 2.0, 2.0)?;
+        let content = "0;1.0;1.0;10.0;n;0.0;array=100;array_stride=0.5\n\
+            ?;2.0;2.0;5.0;n;60.0\n";
+        let job_factory = JobStreaming::from_string(content.to_string())?;
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        while sched.tick() {}
+
+        let mut done = sched.job_factory.jobs_done().clone();
+        assert_eq!(done.len(), 101);
+        done.sort();
+        done.dedup();
+        assert_eq!(done, (0..101).collect::<Vec<JobId>>());
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_node_speed() -> Result<()> {
+        let finished_on = |node: &str| -> Result<f32> {
+            let mut reg = NodeRegistry::new();
+            reg.new_node_from_str(node)?;
+
+            reset_job_metadata();
+            let jobs = vec![Job::new(1.0, 1.0, 10.0, false, 0.0)];
+            let job_factory = JobCollection::new(jobs);
+            let mut sched =
+                Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+            while sched.tick() {}
+            assert_eq!(sched.jobs_done.len(), 1);
+            Ok(sched.now)
+        };
+
+        assert_eq!(finished_on("slow;1;1")?, 10.0);
+        assert_eq!(finished_on("slow;1;1;speed=1.0")?, 10.0);
+        assert_eq!(finished_on("fast;1;1;speed=2.0")?, 5.0);
+
+        let mut reg = NodeRegistry::new();
+        assert!(reg.new_node_from_str("broken;1;1;speed=0").is_err());
+        assert!(reg.nodes.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_builder_tick_granularity() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+
+        reset_job_metadata();
+        let jobs = vec![Job::new(1.0, 1.0, 10.0, false, 0.0), Job::new(1.0, 1.0, 10.0, false, 1.0)];
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = SchedulerBuilder::new(reg, Box::new(job_factory))
+            .backfill(true)
+            .preemption(true)
+            .seed(42)
+            .tick_granularity(4.0)
+            .build();
+
+        assert!(sched.preemption);
+        assert_eq!(sched.seed, 42);
+
+        let mut times = vec![];
+        while sched.tick() {
+            times.push(sched.now);
+        }
+
+        // VV: The 2nd job arrives at 1.0 but the scheduler only notices it at 4.0, it starts
+        // at 12.0 because the 1st job finishes at 10.0
+        assert_eq!(times, vec![4.0, 12.0, 24.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_schedule_interval_batches_decisions() -> Result<()> {
+        let reg = registry_init_homogeneous(2, 1.0, 1.0)?;
+
+        reset_job_metadata();
+        let jobs = vec![Job::new(1.0, 1.0, 10.0, false, 0.0), Job::new(1.0, 1.0, 10.0, false, 0.0),
+                        Job::new(1.0, 1.0, 10.0, false, 150.0), Job::new(1.0, 1.0, 10.0, false, 250.0)];
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = SchedulerBuilder::new(reg, Box::new(job_factory))
+            .schedule_interval(100.0)
+            .build();
+
+        let mut started = vec![];
+        while sched.tick() {
+            assert!(!sched.has_unschedulable());
+            for job in &sched.jobs_running {
+                if !started.contains(&(job.uid, job.time_started.unwrap())) {
+                    started.push((job.uid, job.time_started.unwrap()));
+                }
+            }
+        }
+
+        // VV: Both nodes are free all along yet every job waits for the next cycle, the jobs
+        // finish in between cycles
+        started.sort_by_key(|(uid, _)| *uid);
+        assert_eq!(started, vec![(0, 100.0), (1, 100.0), (2, 200.0), (3, 300.0)]);
+        assert_eq!(sched.jobs_done.len(), 4);
+        assert_eq!(sched.now, 310.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_decision_latency_bounds_placement_rate() -> Result<()> {
+        // VV: Returns the start times of 20 jobs that all fit on the cluster right away
+        let run = |latency: Option<f32>| -> Result<Vec<f32>> {
+            let reg = registry_init_homogeneous(4, 100.0, 100.0)?;
+
+            reset_job_metadata();
+            let jobs = (0..20).map(|_| Job::new(1.0, 1.0, 1000.0, false, 0.0)).collect();
+            let mut builder = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)));
+            if let Some(latency) = latency {
+                builder = builder.decision_latency(latency);
+            }
+            let mut sched = builder.build();
+
+            while sched.tick() {
+                assert!(!sched.has_unschedulable());
+            }
+            assert_eq!(sched.jobs_done.len(), 20);
+            let mut started: Vec<f32> = sched.job_times.iter().map(|t| t.started).collect();
+            started.sort_by(f32::total_cmp);
+            Ok(started)
+        };
+
+        assert_eq!(run(None)?, vec![0.0; 20]);
+
+        // VV: Resources are abundant yet the scheduler starts 1 job every 2 seconds
+        let started = run(Some(2.0))?;
+        assert_eq!(started, (0..20).map(|idx| idx as f32 * 2.0).collect::<Vec<_>>());
+        for window in [1.0, 10.0, 25.0] {
+            let placed = started.iter().filter(|start| **start < window).count();
+            assert!(placed as f32 <= (window / 2.0).ceil(), "{} jobs before {}", placed, window);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_memory_oversubscription() -> Result<()> {
+        let run = |node: &str| -> Result<Vec<(f32, f32)>> {
+            let mut reg = NodeRegistry::new();
+            reg.new_node_from_str(node)?;
+
+            reset_job_metadata();
+            let jobs = vec![Job::new(1.0, 3.0, 10.0, false, 0.0), Job::new(1.0, 3.0, 10.0, false, 0.0)];
+            let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs))).build();
+
+            let mut times = vec![(0.0, 0.0); 2];
+            while sched.tick() {
+                for job in &sched.jobs_running {
+                    times[job.uid] = (job.time_started.unwrap(), job.time_done.unwrap());
+                }
+            }
+            assert_eq!(sched.jobs_done.len(), 2);
+            Ok(times)
+        };
+
+        assert!(run("node;4;4;oversubscribe=0.5").is_err());
+        assert_eq!(run("node;4;4")?, vec![(0.0, 10.0), (10.0, 20.0)]);
+
+        // VV: Both jobs start right away but together they overcommit the memory by 2 / 4 = 50%
+        // and run 1.5 times slower
+        assert_eq!(run("node;4;4;oversubscribe=1.5")?, vec![(0.0, 15.0), (0.0, 15.0)]);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_stops_at_until() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+
+        reset_job_metadata();
+        let jobs = vec![Job::new(1.0, 1.0, 10.0, false, 0.0), Job::new(1.0, 1.0, 10.0, false, 0.0),
+                        Job::new(1.0, 1.0, 10.0, false, 100.0)];
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .until(15.0)
+            .build();
+
+        let mut times = vec![];
+        while sched.tick() {
+            times.push(sched.now);
+        }
+
+        // VV: The 2nd job would finish at 20 and the 3rd job would arrive at 100
+        assert_eq!(times, vec![10.0]);
+        assert_eq!(sched.now, 15.0);
+        assert!(!sched.tick());
+        assert_eq!(sched.now, 15.0);
+        assert_eq!(sched.jobs_done.len(), 1);
+        assert_eq!(sched.in_progress(), vec![(1, 5.0)]);
+        assert!(sched.job_factory.more_jobs());
+        assert!(sched.summary().truncated);
+        assert!(!sched.has_unschedulable());
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_running_jobs_status() -> Result<()> {
+        let reg = registry_init_homogeneous(2, 1.0, 1.0)?;
+
+        reset_job_metadata();
+        let jobs = vec![Job::new(1.0, 1.0, 30.0, false, 0.0), Job::new(1.0, 1.0, 10.0, false, 5.0)];
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs))).build();
+
+        // VV: The 1st tick starts job 0 and moves to the arrival of job 1, the 2nd starts job 1
+        sched.tick();
+        sched.tick();
+        assert_eq!(sched.now, 15.0);
+
+        let status = sched.running_jobs_status();
+        assert_eq!(status, vec![
+            RunningJobStatus { uid: 1, node: 0, started: 5.0, done: 15.0, remaining: 0.0 },
+            RunningJobStatus { uid: 0, node: 1, started: 0.0, done: 30.0, remaining: 15.0 },
+        ]);
+        // VV: Asking for the status is read-only
+        assert_eq!(sched.running_jobs_status(), status);
+        assert_eq!(sched.now, 15.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_speculates_stragglers() -> Result<()> {
+        let run = |speculation: Option<f32>| -> Result<Scheduler> {
+            let mut reg = NodeRegistry::new();
+            for line in ["n0;1;1", "n1;1;1", "n2;1;1", "slow;1;1;speed=0.1"] {
+                reg.new_node_from_str(line)?;
+            }
+
+            let jobs = JobStreaming::from_string("?;1;1;10;n;0;array=4".to_string())?;
+            let mut builder = SchedulerBuilder::new(reg, Box::new(jobs));
+            if let Some(threshold) = speculation {
+                builder = builder.speculation(threshold);
+            }
+            let mut sched = builder.build();
+
+            while sched.tick() {}
+            Ok(sched)
+        };
+
+        let sched = run(None)?;
+        assert_eq!(sched.now, 100.0);
+        assert!(sched.speculated.is_empty());
+
+        // VV: The peers finish at 10, the job on the slow node straggles at 15 and its copy
+        // finishes at 25 which kills the original attempt
+        let sched = run(Some(1.5))?;
+        assert_eq!(sched.now, 25.0);
+        assert_eq!(sched.jobs_done.len(), 4);
+        assert_eq!(sched.speculated.len(), 1);
+        assert!(sched.speculated.values().all(|copy_won| *copy_won));
+        assert!(sched.job_times.iter().any(|t| t.started == 0.0 && t.done == 25.0));
+        assert!(sched.jobs_running.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_node_cooldown_gap() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("node;1;1;cooldown=5")?;
+
+        let jobs = JobStreaming::from_string("0;1;1;10;n;0\n1;1;1;10;n;0".to_string())?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
+        while sched.tick() {}
+
+        assert_eq!(sched.jobs_done.len(), 2);
+        let mut spans: Vec<(f32, f32)> = sched.job_times
+            .iter()
+            .map(|t| (t.started, t.done))
+            .collect();
+        spans.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!(spans[1].0 - spans[0].1 >= 5.0);
+        assert_eq!(spans[1], (15.0, 25.0));
+        assert_eq!(sched.registry.nodes[0].cool_until, 30.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_rejects_at_queue_depth() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+        let burst: Vec<String> = (0..6).map(|uid| format!("{};1;1;10;n;0", uid)).collect();
+        let late = "6;1;1;10;n;25";
+        let jobs = JobStreaming::from_string(format!("{}\n{}", burst.join("\n"), late))?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).max_queue_depth(2).build();
+        while sched.tick() {}
+
+        // VV: 2 of the burst queue, job 6 arrives after the node finished 2 jobs and the queue
+        // has room again
+        let mut rejected: Vec<_> = sched.jobs_rejected.iter().map(|(job, _)| job.uid).collect();
+        rejected.sort();
+        assert_eq!(rejected, vec![2, 3, 4, 5]);
+        assert!(sched.jobs_rejected.iter().all(|(_, reason)| reason.contains("queuing")));
+        assert_eq!(sched.jobs_done.len(), 3);
+        assert!(sched.jobs_queuing.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_cancels_jobs_past_deadline() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+        // VV: Job 1 can only start at 10 when job 0 finishes, that is too late for its deadline
+        // at 15. Job 2 also waits until 10 but its deadline leaves it enough time
+        let lines = "0;1;1;10;n;0\n1;1;1;10;n;0;deadline=15\n2;1;1;10;n;0;deadline=20";
+        let jobs = JobStreaming::from_string(lines.to_string())?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).cancel_late(true).build();
+        while sched.tick() {}
+
+        assert_eq!(sched.jobs_rejected.len(), 1);
+        let (job, reason) = &sched.jobs_rejected[0];
+        assert_eq!(job.uid, 1);
+        assert!(job.time_started.is_none());
+        assert!(reason.contains("deadline"));
+        assert_eq!(sched.jobs_done.len(), 2);
+        assert_eq!(sched.now, 20.0);
+
+        // VV: Without the policy the late job runs anyway
+        let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+        let jobs = JobStreaming::from_string(lines.to_string())?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
+        while sched.tick() {}
+        assert!(sched.jobs_rejected.is_empty());
+        assert_eq!(sched.jobs_done.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_oom_kills_overcommitted_node() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("n0;4;4")?;
+        // VV: The requests of both jobs fit on n0 but job 0 actually uses 3 instead of 1
+        let lines = "0;1;1;10;n;0;memory_limit=3;memory_usage=3\n1;1;2;10;n;0";
+        let output = std::env::temp_dir().join("dismem_scheduler_oom_kills.jsonl");
+        let jobs = JobStreamingWithOutput::from_factory_to_path(
+            Box::new(JobStreaming::from_string(lines.to_string())?), &output,
+            TraceFormat::JsonLines)?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
+
+        assert!(sched.tick());
+        assert_eq!(sched.oom_kills, 1);
+        assert_eq!(sched.jobs_running.len(), 1);
+        assert_eq!(sched.jobs_running[0].uid, 1);
+        // VV: The victim now requests what it uses, it waits for job 1 instead of overcommitting
+        assert_eq!(sched.jobs_queuing[0].uid, 0);
+        assert_eq!(sched.jobs_queuing[0].memory, 3.0);
+
+        while sched.tick() {}
+        assert_eq!(sched.jobs_done.len(), 2);
+        assert_eq!(sched.oom_kills, 1);
+        assert_eq!(sched.now, 20.0);
+        drop(sched);
+
+        let content = std::fs::read_to_string(&output)?;
+        let victim: serde_json::Value = content
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .find(|job| job["id"] == 0)
+            .unwrap();
+        assert_eq!(victim["oom_kills"], 1);
+        assert_eq!(victim["start"], 10.0);
+
+        // VV: Jobs without a usage draw it between their request and limit
+        let reg = registry_init_homogeneous(1, 1.0, 8.0)?;
+        let jobs = JobStreaming::from_string("0;1;1;10;n;0;memory_limit=2".to_string())?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
+        assert!(sched.tick());
+        let usage = sched.jobs_running[0].memory_usage.unwrap();
+        assert!((1.0..=2.0).contains(&usage));
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_memory_profile_spikes_late() -> Result<()> {
+        reset_job_metadata();
+        let job: Job = "0;1;2;100;n;0;memory_profile=[0:2,0.8:6]".parse().unwrap();
+        assert_eq!(job.memory, 6.0);
+        assert_eq!(job.memory_profile, vec![(0.0, 2.0), (0.8, 6.0)]);
+        for line in ["1;1;2;10;n;0;memory_profile=[0.5:1]",
+                     "2;1;2;10;n;0;memory_profile=[0:1,0:2]",
+                     "3;1;2;10;n;0;memory_profile=[0:1,1:2]",
+                     "4;1;2;10;n;0;memory_profile=[0:1];memory_usage=1"] {
+            assert!(line.parse::<Job>().unwrap().validate().is_err(), "{}", line);
+        }
+        assert!("5;1;2;10;n;0;memory_profile=[0-1]".parse::<Job>().is_err());
+
+        // VV: Both reservations fit on n0, job 1 actually uses 4 instead of 2 but there is room
+        // for it until job 0 reaches its spike at 80% of its runtime
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("n0;4;8")?;
+        let lines = "0;1;2;100;n;0;memory_profile=[0:2,0.8:6]\n\
+            1;1;2;200;n;0;memory_limit=4;memory_usage=4";
+        let jobs = JobStreaming::from_string(lines.to_string())?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
+
+        assert!(sched.tick());
+        assert_eq!(sched.jobs_running.len(), 2);
+        assert_eq!(sched.now, 80.0);
+        assert_eq!(sched.oom_kills, 0);
+        assert_eq!(sched.jobs_running[0].memory_used(79.0), 2.0);
+        assert_eq!(sched.jobs_running[0].memory_used(80.0), 6.0);
+
+        assert!(sched.tick());
+        assert_eq!(sched.oom_kills, 1);
+        assert_eq!(sched.jobs_running.len(), 1);
+        assert_eq!(sched.jobs_queuing[0].uid, 1);
+
+        while sched.tick() {}
+        assert_eq!(sched.jobs_done.len(), 2);
+        assert_eq!(sched.oom_kills, 1);
+        assert_eq!(sched.now, 300.0);
+
+        // VV: The memory utilization follows the ramp instead of the reserved peak
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("n0;1;8")?;
+        let jobs = JobStreaming::from_string("0;1;2;100;n;0;memory_profile=[0:2,0.8:6]".into())?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
+        while sched.tick() {}
+        let summary = sched.summary();
+        assert!((summary.memory_utilization - (2.0 * 80.0 + 6.0 * 20.0) / (8.0 * 100.0)).abs()
+            < 1e-6);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_retries_flaky_jobs() -> Result<()> {
+        let reg = registry_init_homogeneous(4, 1.0, 1.0)?;
+        let lines: Vec<String> = (0..40)
+            .map(|uid| format!("{};1;1;10;n;0;failure_probability=0.7;max_retries=2", uid))
+            .collect();
+        let output = std::env::temp_dir().join("dismem_scheduler_retries_flaky_jobs.jsonl");
+        let jobs = JobStreamingWithOutput::from_factory_to_path(
+            Box::new(JobStreaming::from_string(lines.join("\n"))?), &output,
+            TraceFormat::JsonLines)?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).seed(7).build();
+        while sched.tick() {}
+
+        assert_eq!(sched.jobs_done.len() + sched.jobs_failed.len(), 40);
+        assert!(!sched.jobs_done.is_empty() && !sched.jobs_failed.is_empty());
+        assert!(sched.retries > 0);
+        drop(sched);
+
+        let content = std::fs::read_to_string(&output)?;
+        let jobs: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(jobs.len(), 40);
+        for job in &jobs {
+            let attempts = job["attempts"].as_u64().unwrap();
+            match job["status"].as_str().unwrap() {
+                "completed" => assert!((1..=3).contains(&attempts)),
+                "failed" => assert_eq!(attempts, 3),
+                status => panic!("Unexpected status {}", status),
+            }
+        }
+        let retries: u64 = jobs.iter().map(|job| job["attempts"].as_u64().unwrap() - 1).sum();
+        assert!(retries > 0);
+
+        let job = "?;1;1;10;n;0;failure_probability=1.5".parse::<Job>().unwrap();
+        assert!(job.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_skips_idle_periods() -> Result<()> {
+        // VV: The cluster idles for 1e6 seconds between the 2 jobs, ticks do not walk through the
+        // gap even when ticks and scheduling cycles are 1 second apart
+        for (granularity, interval) in [(None, None), (Some(1.0), None), (None, Some(1.0))] {
+            let reg = registry_init_homogeneous(2, 4.0, 4.0)?;
+            let jobs = JobStreaming::from_string("0;4;4;100;n;0\n1;4;4;100;n;1000000".to_string())?;
+            let mut builder = SchedulerBuilder::new(reg, Box::new(jobs));
+            if let Some(granularity) = granularity {
+                builder = builder.tick_granularity(granularity);
+            }
+            if let Some(interval) = interval {
+                builder = builder.schedule_interval(interval);
+            }
+            let mut sched = builder.build();
+
+            let mut ticks = 1;
+            while sched.tick() {
+                ticks += 1;
+            }
+            assert!(ticks <= 5, "{} ticks", ticks);
+            assert_eq!(sched.now, 1000100.0);
+
+            // VV: The idle period still counts towards the duration of the run
+            let summary = sched.summary();
+            assert_eq!(summary.makespan, 1000100.0);
+            let expected = 2.0 * 4.0 * 100.0 / (8.0 * 1000100.0);
+            assert!((summary.core_utilization - expected).abs() < 1e-9,
+                    "{} != {}", summary.core_utilization, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_scratch_disk() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("n0;4;4;scratch=100")?;
+        let jobs = JobStreaming::from_string([
+            "0;1;1;10;n;0;scratch=60",
+            // VV: Cores are free but the scratch disk is not until job 0 finishes
+            "1;1;1;10;n;0;scratch=60",
+            "2;1;1;10;n;0;scratch=200",
+            // VV: Job 3 keeps its scratch disk until job 5 releases it
+            "3;1;1;10;n;30;scratch=50;keep=scratch",
+            "4;1;1;10;n;40;scratch=60",
+            "5;1;1;10;n;40;depends_on=3;release=3",
+        ].join("\n"))?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
+
+        let mut started = HashMap::new();
+        let mut checked = false;
+        while sched.tick() {
+            for job in &sched.jobs_running {
+                started.insert(job.uid, job.time_started.unwrap());
+            }
+            if sched.now == 50.0 {
+                checked = true;
+                assert!(sched.kept.contains_key(&3));
+                assert_eq!(sched.registry.nodes[0].resources["scratch"].current, 50.0);
+            }
+        }
+
+        let rejected: Vec<JobId> = sched.jobs_rejected.iter().map(|(job, _)| job.uid).collect();
+        assert_eq!(rejected, vec![2]);
+        assert!(checked);
+        assert_eq!(started[&1], 10.0);
+        assert_eq!(started[&4], 50.0);
+        assert!(sched.kept.is_empty());
+        assert_eq!(sched.registry.nodes[0].resources["scratch"].current, 100.0);
+
+        let job: Job = "?;1;1;10;n;0;keep=scratch".parse().unwrap();
+        assert!(job.validate().is_err());
+        let job: Job = "?;1;1;10;n;0;scratch=1;release=0".parse().unwrap();
+        assert!(job.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_abandons_jobs_past_max_queue_time() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+        // VV: The node is busy until 30, job 3 gives up at 15 while job 4 waits until 40
+        let mut lines: Vec<String> = (0..3).map(|uid| format!("{};1;1;10;n;0", uid)).collect();
+        lines.push("3;1;1;10;n;5;max_queue_time=10".to_string());
+        lines.push("4;1;1;10;n;5;max_queue_time=1h".to_string());
+        let jobs = JobStreaming::from_string(lines.join("\n"))?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
+        while sched.tick() {}
+
+        assert_eq!(sched.jobs_rejected.len(), 1);
+        let (job, reason) = &sched.jobs_rejected[0];
+        assert_eq!(job.uid, 3);
+        assert!(reason.contains("exceeded max queue time"));
+        assert!(reason.ends_with("at 15"));
+        assert_eq!(sched.jobs_done.len(), 4);
+        assert!(sched.jobs_done.contains(&4));
+        assert_eq!(sched.now, 40.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_partition_confines_jobs() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        for line in ["cpu0;8;8", "cpu1;8;8", "gpu0;1;1;partitions=gpu,debug"] {
+            reg.new_node_from_str(line)?;
+        }
+
+        let mut lines: Vec<String> = (0..4)
+            .map(|uid| format!("{};1;1;10;n;0;partition=gpu", uid))
+            .collect();
+        lines.push("4;1;1;10;n;0".to_string());
+        let jobs = JobStreaming::from_string(lines.join("\n"))?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).record_spans(true).build();
+        while sched.tick() {}
+
+        // VV: The gpu jobs run 1 after the other on gpu0 even though cpu0 and cpu1 are idle
+        assert_eq!(sched.jobs_done.len(), 5);
+        for span in sched.spans.iter().filter(|span| span.uid < 4) {
+            assert_eq!(sched.registry.nodes[span.node].name, "gpu0");
+        }
+        assert_eq!(sched.now, 40.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_partition_limits() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("cpu0;4;4")?;
+        reg.new_node_from_str("dbg0;4;4;partitions=debug")?;
+        let limits = PartitionLimits::from_str("debug;15;2", &reg)?;
+        assert!(PartitionLimits::from_str("gpu;15;2", &reg).is_err());
+
+        let jobs = JobStreaming::from_string([
+            "0;4;1;10;n;0;partition=debug",
+            "1;1;1;10;n;0;partition=debug;walltime_limit=20",
+            "2;1;1;30;n;0;partition=debug",
+            "3;1;1;10;n;0;partition=gpu",
+        ].join("\n"))?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs))
+            .partitions(vec![limits])
+            .build();
+        while sched.tick() {}
+
+        let mut rejected: Vec<_> = sched.jobs_rejected.iter().map(|(job, _)| job.uid).collect();
+        rejected.sort();
+        assert_eq!(rejected, vec![0, 1, 3]);
+        // VV: Job 2 inherits the max time of the partition as its walltime_limit
+        assert!(sched.jobs_failed.contains(&2));
+        assert_eq!(sched.now, 15.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_starts_bundles_together() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("n0;4;4")?;
+        reg.new_node_from_str("n1;4;4")?;
+        assert!("?;1;1;10;n;0;gang=y;bundle=b".parse::<Job>().unwrap().validate().is_err());
+
+        // VV: Jobs 0 and 1 leave 1 free core on each node, that is enough for each job of the
+        // bundle but not for all 3 until job 1 finishes
+        let jobs = JobStreaming::from_string("0;3;1;100;n;0\n1;3;1;50;n;0\n\
+            2;1;1;10;n;1;bundle=b\n3;1;1;10;n;1;bundle=b\n4;1;1;10;n;1;bundle=b\n\
+            5;1;1;10;n;2".to_string())?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
+
+        let mut started = HashMap::new();
+        while sched.tick() {
+            for job in &sched.jobs_running {
+                started.insert(job.uid, (job.time_started.unwrap(), job.node_cores.unwrap()));
+            }
+
+            let bundled: Vec<&Job> = sched.jobs_queuing.iter().filter(|job| job.uid < 5).collect();
+            assert!(bundled.is_empty() || bundled.len() == 3, "{} jobs of the bundle wait",
+                bundled.len());
+            assert!(bundled.iter().all(|job| job.last_block_reason == Some(BlockReason::Bundle)));
+        }
+
+        // VV: Job 5 does not belong to the bundle and starts on a free core right away
+        assert_eq!(started[&5].0, 2.0);
+        let node = started[&1].1;
+        for uid in 2..5 {
+            assert_eq!(started[&uid], (50.0, node));
+        }
+        assert_eq!(sched.jobs_done.len(), 6);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_caps_running_cores_of_user() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("n0;8;8")?;
+        reg.new_node_from_str("dbg0;4;4;partitions=debug")?;
+        let limits = vec![
+            ConcurrencyLimit::from_str("user;alice;4", &reg)?,
+            ConcurrencyLimit::from_str("partition;debug;1", &reg)?,
+        ];
+        assert!(ConcurrencyLimit::from_str("partition;gpu;1", &reg).is_err());
+        assert!(ConcurrencyLimit::from_str("group;alice;1", &reg).is_err());
+        assert!(ConcurrencyLimit::from_str("user;alice;0", &reg).is_err());
+
+        // VV: alice floods the queue, bob's jobs arrive later but start right away
+        let mut lines = vec![
+            "0;1;1;5;n;0;partition=debug".to_string(),
+            "1;1;1;5;n;0;partition=debug".to_string(),
+        ];
+        lines.extend((2..8).map(|uid| format!("{};2;1;10;n;0;user=alice", uid)));
+        lines.push("8;5;1;10;n;0;user=alice".to_string());
+        lines.push("9;2;1;10;n;1;user=bob".to_string());
+        lines.push("10;2;1;10;n;1;user=bob".to_string());
+        let jobs = JobStreaming::from_string(lines.join("\n"))?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).limits(limits).build();
+
+        let mut started = HashMap::new();
+        while sched.tick() {
+            let alice: f32 = sched.jobs_running.iter()
+                .filter(|job| job.user.as_deref() == Some("alice"))
+                .map(|job| job.cores)
+                .sum();
+            assert!(alice <= 4.0, "alice runs {} cores at {}", alice, sched.now);
+            for job in &sched.jobs_running {
+                started.insert(job.uid, job.time_started.unwrap());
+            }
+            if sched.now == 1.0 {
+                let waiting = sched.jobs_queuing.iter().filter(|job| job.uid >= 2);
+                assert_eq!(waiting.clone().count(), 4);
+                assert!(waiting.into_iter()
+                    .all(|job| job.last_block_reason == Some(BlockReason::Limit)));
+            }
+        }
+
+        assert_eq!(sched.jobs_rejected.len(), 1);
+        assert_eq!(sched.jobs_rejected[0].0.uid, 8);
+        assert_eq!((started[&9], started[&10]), (1.0, 1.0));
+        // VV: The debug partition runs 1 core at a time even though dbg0 has 4
+        assert_eq!((started[&0], started[&1]), (0.0, 5.0));
+        assert_eq!(sched.now, 30.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_packs_fractional_cores() -> Result<()> {
+        let run = |cores: f32, count: usize| -> Result<Scheduler> {
+            let mut reg = NodeRegistry::new();
+            reg.new_node("node", 1.0, 100.0)?;
+            let lines: Vec<String> = (0..count)
+                .map(|uid| format!("{};{};1;10;n;0", uid, cores))
+                .collect();
+            let jobs = JobStreaming::from_string(lines.join("\n"))?;
+            let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
+            assert!(sched.tick());
+            Ok(sched)
+        };
+
+        // VV: 4 quarters of a core fit on the node, the fifth job waits for one of them
+        let sched = run(0.25, 5)?;
+        assert_eq!(sched.jobs_running.len(), 4);
+        assert_eq!(sched.jobs_queuing.len(), 1);
+
+        // VV: 10 times the f32 0.1 is more than 1.0, the fixed-point accounting still fits them
+        let sched = run(0.1, 11)?;
+        assert_eq!(sched.jobs_running.len(), 10);
+        assert_eq!(sched.jobs_queuing.len(), 1);
+        assert_eq!(sched.registry.nodes[0].cores.current, 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_block_reasons() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("n0;2;4")?;
+        reg.new_node_from_str("n1;2;4;label:zone=x")?;
+
+        let jobs = JobStreaming::from_string([
+            "0;2;1;10;n;0;label:zone=x",
+            "1;1;1;10;n;0",
+            "2;1;1;10;n;0;depends_on=[0]",
+            "3;1;1;10;n;0;label:zone=x",
+            "4;2;1;10;n;0",
+            "5;1;4;10;n;0",
+        ].join("\n"))?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
+        assert!(sched.tick());
+        assert_eq!(sched.jobs_running.len(), 2);
+
+        let reason = |uid: JobId| sched.jobs_queuing.iter()
+            .chain(&sched.jobs_blocked)
+            .find(|job| job.uid == uid)
+            .and_then(|job| job.last_block_reason.clone());
+        assert_eq!(reason(2), Some(BlockReason::Dependency));
+        assert_eq!(reason(3), Some(BlockReason::Label));
+        assert_eq!(reason(4), Some(BlockReason::Cores));
+        assert_eq!(reason(5), Some(BlockReason::Memory));
+        assert_eq!(sched.block_reasons().values().sum::<usize>(), 4);
+
+        while sched.tick() {}
+        assert!(sched.block_reasons().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_seed_reproducible() -> Result<()> {
+        let draw = |seed: u64| -> Result<Vec<u64>> {
+            let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+            let job_factory = JobCollection::new(vec![]);
+            let mut sched = SchedulerBuilder::new(reg, Box::new(job_factory)).seed(seed).build();
+            Ok((0..4).map(|_| sched.rng.gen()).collect())
+        };
+
+        assert_eq!(draw(7)?, draw(7)?);
+        assert_ne!(draw(7)?, draw(8)?);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_node_failure_requeues_gang() -> Result<()> {
+        let reg = registry_init_homogeneous(2, 2.0, 2.0)?;
+        let failure = NodeFailure::from_str("1;5;20", &reg)?;
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = vec!["?;4;2;10;n;0;gang=y".parse().unwrap()];
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = SchedulerBuilder::new(reg, Box::new(job_factory))
+            .failures(vec![failure])
+            .build();
+
+        // VV: The tick that handles the failure at 5.0 returns after moving now to the recovery
+        let mut requeued_at = None;
+        let mut started = None;
+        while sched.tick() {
+            if requeued_at.is_none() && sched.jobs_queuing.len() == 1 && sched.now > 0.0 {
+                requeued_at = Some(sched.now);
+                // VV: The piece of the gang on the healthy node is released too
+                assert_eq!(sched.registry.nodes[0].cores.current, 2.0);
+                assert!(sched.registry.nodes[1].failed);
+            }
+            if let Some(job) = sched.jobs_running.front() {
+                started = job.time_started;
+            }
+        }
+
+        assert_eq!(requeued_at, Some(20.0));
+        assert_eq!(started, Some(20.0));
+        assert_eq!(sched.now, 30.0);
+        assert!(!sched.registry.nodes[1].failed);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_data_staging() -> Result<()> {
+        let run_on = |node: &str| -> Result<(f32, f32, serde_json::Value)> {
+            let mut reg = NodeRegistry::new();
+            reg.new_node_from_str(node)?;
+
+            reset_job_metadata();
+            let job: Job = "?;1;1;10;n;0;input_data=20".parse().unwrap();
+            let job_factory = JobCollection::new(vec![job]);
+            let mut sched =
+                Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+            let mut trace = None;
+            while sched.tick() {
+                if let Some(job) = sched.jobs_running.front() {
+                    trace = Some(job.to_json());
+                }
+            }
+            Ok((sched.now, sched.summary().mean_turnaround as f32, trace.unwrap()))
+        };
+
+        // VV: The job holds its node while 20 units of data arrive and then runs for 10s
+        let (fast_done, fast_turnaround, fast) = run_on("fast;1;1;bandwidth=10")?;
+        let (slow_done, slow_turnaround, slow) = run_on("slow;1;1;bandwidth=1")?;
+        assert_eq!(fast_done, 12.0);
+        assert_eq!(slow_done, 30.0);
+        assert!(slow_turnaround > fast_turnaround);
+
+        assert_eq!(fast["start"], 0.0);
+        assert_eq!(fast["run_start"], 2.0);
+        assert_eq!(fast["finish"], 12.0);
+        assert_eq!(slow["run_start"], 20.0);
+
+        let (done, _, _) = run_on("instant;1;1")?;
+        assert_eq!(done, 10.0);
+        Ok(())
+    }
+
+    /// Returns when each job started (the last time if it restarted) and on which node
+    fn run_with_scaling(scaling: &[&str]) -> Result<Vec<(f32, usize)>> {
+        let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+        let scaling = scaling.iter()
+            .map(|line| line.parse())
+            .collect::<Result<Vec<_>>>()?;
+
+        reset_job_metadata();
+        let jobs = vec![Job::new(1.0, 1.0, 100.0, false, 0.0), Job::new(1.0, 1.0, 100.0, false, 0.0)];
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .scaling(scaling)?
+            .build();
+
+        let mut started = vec![(0.0, 0); 2];
+        while sched.tick() {
+            for job in &sched.jobs_running {
+                started[job.uid] = (job.time_started.unwrap(), job.node_cores.unwrap());
+            }
+        }
+        assert_eq!(sched.jobs_done.len(), 2);
+        Ok(started)
+    }
+
+    #[test]
+    fn scheduler_scaling_adds_node() -> Result<()> {
+        // VV: The second job waits for the new node instead of the first job
+        assert_eq!(run_with_scaling(&["10;add;extra;1;1"])?, vec![(0.0, 0), (10.0, 1)]);
+
+        // VV: Killed jobs restart elsewhere, drained nodes let them finish but take no new jobs
+        assert_eq!(run_with_scaling(&["10;add;extra;1;1", "20;remove;extra;kill"])?,
+                   vec![(0.0, 0), (100.0, 0)]);
+        assert_eq!(run_with_scaling(&["10;add;extra;1;1", "20;remove;extra;drain"])?,
+                   vec![(0.0, 0), (10.0, 1)]);
+        assert_eq!(run_with_scaling(&["0;remove;0;kill", "50;add;extra;1;1", "60;add;0"])?,
+                   vec![(50.0, 1), (60.0, 0)]);
+
+        // VV: Broken timelines are errors before the simulation starts, nodes that an earlier
+        // event adds may leave later
+        let builder = |scaling: &[&str]| -> Result<SchedulerBuilder> {
+            let scaling = scaling.iter()
+                .map(|line| line.parse())
+                .collect::<Result<Vec<_>>>()?;
+            SchedulerBuilder::new(registry_init_homogeneous(1, 1.0, 1.0)?,
+                                  Box::new(JobCollection::new(vec![])))
+                .scaling(scaling)
+        };
+        assert!(builder(&["10;remove;extra;kill"]).is_err());
+        assert!(builder(&["20;remove;extra", "10;add;extra;1;1"]).is_ok());
+        assert!(builder(&["10;add;extra;1;1;cost=-1"]).is_err());
+        assert!(builder(&["10;add;0;1;1"]).is_err());
+        assert!(builder(&["10;add;0"]).is_err());
+
+        let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+        assert!("10;resize;0".parse::<ScalingEvent>().is_err());
+        assert!("10;remove;0;pause".parse::<ScalingEvent>().is_err());
+        assert_eq!("10;add;extra;1;1".parse::<ScalingEvent>()?.node(), "extra");
+        assert!(reg.registry.contains_key("0"));
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_reservation_blocks_other_users() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 2.0, 2.0)?;
+        let reservation = Reservation::from_str("0;0;20;1;1;alice", &reg)?;
+        assert!(Reservation::from_str("0;20;10;1;1;alice", &reg).is_err());
+        assert!(Reservation::from_str("0;0;20;4;1;alice", &reg).is_err());
+        assert!(Reservation::from_str("0;0;20;1;1;", &reg).is_err());
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["?;1;1;30;n;0;user=bob", "?;1;1;5;n;0;user=bob",
+            "?;1;1;5;n;2;user=alice"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .reservations(vec![reservation])
+            .build();
+
+        let mut started = vec![];
+        while sched.tick() {
+            for job in &sched.jobs_running {
+                if !started.contains(&(job.uid, job.time_started.unwrap())) {
+                    started.push((job.uid, job.time_started.unwrap()));
+                }
+            }
+        }
+
+        // VV: The 2nd job of bob waits for the reservation to end even after alice is done with it
+        started.sort_by_key(|(uid, _)| *uid);
+        assert_eq!(started, vec![(0, 0.0), (1, 20.0), (2, 2.0)]);
+        assert_eq!(sched.jobs_done.len(), 3);
+        assert_eq!(sched.registry.nodes[0].cores.current, 2.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_hold_and_release_jobs() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+        assert!("5;pause;0".parse::<HoldEvent>().is_err());
+        assert!("5;hold;first".parse::<HoldEvent>().is_err());
+        let holds: Vec<HoldEvent> = ["0;hold;0", "15;release;0"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+
+        reset_job_metadata();
+        let jobs = vec![Job::new(1.0, 1.0, 10.0, false, 0.0), Job::new(1.0, 1.0, 10.0, false, 0.0)];
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .holds(holds)
+            .build();
+
+        let mut started = vec![];
+        while sched.tick() {
+            // VV: The node is idle from 10 until the release of the held job
+            if sched.now > 0.0 && sched.now < 15.0 {
+                assert_eq!(sched.jobs_held.len(), 1);
+                assert!(sched.jobs_queuing.is_empty());
+                assert!(!sched.has_unschedulable());
+            }
+            for job in &sched.jobs_running {
+                if !started.contains(&(job.uid, job.time_started.unwrap())) {
+                    started.push((job.uid, job.time_started.unwrap()));
+                }
+            }
+        }
+
+        assert_eq!(started, vec![(1, 0.0), (0, 15.0)]);
+        assert!(sched.jobs_held.is_empty() && sched.held.is_empty());
+        assert_eq!(sched.jobs_done.len(), 2);
+
+        // VV: Releasing a job that is not held changes nothing
+        assert!(!sched.release_job(0));
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_detects_deadlocks() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+        // VV: Job 2 is on hold forever, job 6 is released while job 4 keeps the node busy
+        let holds: Vec<HoldEvent> = ["0;hold;2", "0;hold;6", "50;release;6"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+
+        // VV: Jobs 0 and 1 wait for each other, job 3 waits for the held job 2, and job 5 waits
+        // for the core of job 4 which frees up eventually
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["?;1;1;10;n;0;depends_on=1", "?;1;1;10;n;0;depends_on=0",
+            "?;1;1;10;n;0", "?;1;1;10;n;0;depends_on=2", "?;1;1;100;n;0", "?;1;1;10;n;0",
+            "?;1;1;10;n;0"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .holds(holds)
+            .build();
+
+        let expected = BTreeMap::from([
+            (0, Deadlock::Waits(vec![1])),
+            (1, Deadlock::Waits(vec![0])),
+            (2, Deadlock::Held),
+            (3, Deadlock::Waits(vec![2])),
+        ]);
+        assert!(sched.tick());
+        assert!(!sched.has_unschedulable());
+        assert_eq!(sched.deadlocked(), expected);
+
+        while sched.tick() && !sched.has_unschedulable() {}
+        assert!(sched.has_unschedulable());
+        assert_eq!(sched.jobs_done.len(), 3);
+        assert_eq!(sched.deadlocked(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_cascades_failures() -> Result<()> {
+        // VV: Returns the jobs that failed and the ones that are still blocked once A (job 0)
+        // fails both of its runs, B (job 1) depends on A and C (job 2) on B
+        let run = |cascade: CascadePolicy| -> Result<(Vec<JobId>, Vec<JobId>, Scheduler)> {
+            let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+
+            reset_job_metadata();
+            let jobs: Vec<Job> = ["?;1;1;10;n;0;failure_probability=1;max_retries=1",
+                "?;1;1;10;n;0;depends_on=0", "?;1;1;10;n;5;depends_on=1"]
+                .iter()
+                .map(|line| line.parse().unwrap())
+                .collect();
+            let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+                .cascade(cascade)
+                .build();
+
+            while sched.tick() && !sched.has_unschedulable() {}
+            let mut failed: Vec<JobId> = sched.jobs_failed.iter().copied().collect();
+            failed.sort();
+            let mut blocked: Vec<JobId> = sched.jobs_blocked.iter().map(|job| job.uid).collect();
+            blocked.sort();
+            Ok((failed, blocked, sched))
+        };
+
+        let (failed, blocked, sched) = run(CascadePolicy::FailCascade)?;
+        assert_eq!(failed, vec![0, 1, 2]);
+        assert!(blocked.is_empty());
+        assert!(sched.deadlocked().is_empty());
+
+        // VV: B and C wait for a retry of A that succeeds, which never comes
+        let (failed, blocked, sched) = run(CascadePolicy::Hold)?;
+        assert_eq!(failed, vec![0]);
+        assert_eq!(blocked, vec![1, 2]);
+        assert_eq!(sched.retries, 1);
+        assert!(sched.has_unschedulable());
+        assert_eq!(sched.deadlocked(), BTreeMap::from([
+            (1, Deadlock::Lost(0)),
+            (2, Deadlock::Waits(vec![1])),
+        ]));
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_maintenance_drains_node() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 2.0, 2.0)?;
+        let maintenance = NodeMaintenance::from_str("0;5;10;50", &reg)?;
+        assert!(NodeMaintenance::from_str("0;10;5;50", &reg).is_err());
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["?;1;1;20;n;0", "?;1;1;5;n;6"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = SchedulerBuilder::new(reg, Box::new(job_factory))
+            .maintenance(vec![maintenance])
+            .build();
+
+        let mut started = vec![];
+        while sched.tick() {
+            let node = &sched.registry.nodes[0];
+            // VV: The running job outlives the start of the window but it is not killed
+            if sched.now > 5.0 && sched.now < 50.0 {
+                assert!(node.draining && !node.failed);
+                assert_eq!(node.cores.current, 0.0);
+            }
+            // VV: The next tick() handles the return of the node, it stays empty until then
+            if sched.now == 50.0 {
+                assert!(sched.jobs_running.is_empty());
+                assert_eq!(sched.jobs_queuing.len(), 1);
+            }
+            for job in &sched.jobs_running {
+                if !started.contains(&(job.uid, job.time_started.unwrap())) {
+                    started.push((job.uid, job.time_started.unwrap()));
+                }
+            }
+        }
+
+        assert_eq!(started, vec![(0, 0.0), (1, 50.0)]);
+        assert_eq!(sched.jobs_done.len(), 2);
+        assert!(sched.jobs_failed.is_empty());
+        assert!(!sched.registry.nodes[0].draining);
+        assert_eq!(sched.registry.nodes[0].cores.current, 2.0);
+        assert_eq!(sched.now, 55.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_waiting_is_not_unschedulable() -> Result<()> {
+        // VV: Runs until the end or until the scheduler gives up on the jobs that still wait
+        let run = |maintenance: Vec<&str>| -> Result<Scheduler> {
+            let reg = registry_init_homogeneous(1, 4.0, 4.0)?;
+            let maintenance = maintenance
+                .iter()
+                .map(|line| NodeMaintenance::from_str(line, &reg))
+                .collect::<Result<Vec<_>>>()?;
+
+            reset_job_metadata();
+            let jobs: Vec<Job> = ["?;3;3;10;n;0", "?;4;4;5;n;0", "?;1;1;5;n;30"]
+                .iter()
+                .map(|line| line.parse().unwrap())
+                .collect();
+            let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+                .maintenance(maintenance)
+                .build();
+
+            while sched.tick() && !sched.has_unschedulable() {}
+            Ok(sched)
+        };
+
+        // VV: The big job waits for the 1st job to free the node
+        let sched = run(vec![])?;
+        assert_eq!(sched.jobs_done.len(), 3);
+        assert!(!sched.has_unschedulable());
+
+        // VV: Nothing runs while the node is in maintenance but the jobs start once it returns
+        let sched = run(vec!["0;16;20;50"])?;
+        assert_eq!(sched.jobs_done.len(), 3);
+        assert_eq!(sched.now, 55.0);
+        assert!(!sched.has_unschedulable());
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_walltime_limit_fails_job() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["?;1;1;20;n;0;walltime_limit=15", "?;1;1;10;n;0;walltime_limit=15"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = Scheduler::new(reg, Box::new(job_factory), Box::new(FirstFit::default()));
+
+        let mut killed_at = None;
+        while sched.tick() {
+            if let Some(job) = sched.jobs_running.iter().find(|job| job.uid == 0) {
+                killed_at = job.time_done;
+            }
+        }
+
+        assert_eq!(killed_at, Some(15.0));
+        assert_eq!(sched.jobs_failed, [0].into_iter().collect());
+        assert_eq!(sched.jobs_done, [1].into_iter().collect());
+        assert_eq!(sched.now, 25.0);
+        Ok(())
+    }
+
+    #[test]
+    fn scheduler_sub_second_granularity() -> Result<()> {
+        let reg = registry_init_homogeneous(1, 1.0, 1.0)?;
+
+        reset_job_metadata();
+        let jobs = vec![Job::new(1.0, 1.0, 0.2, false, 0.0), Job::new(1.0, 1.0, 0.2, false, 0.1)];
+        let job_factory = JobCollection::new(jobs);
+        let mut sched = SchedulerBuilder::new(reg, Box::new(job_factory))
+            .tick_granularity(0.25)
+            .build();
+
+        let mut times = vec![];
+        while sched.tick() {
+            times.push(sched.now);
+        }
+
+        assert_eq!(times, vec![0.25, 0.5]);
+        assert_eq!(sched.jobs_done.len(), 2);
         Ok(())
     }
 }
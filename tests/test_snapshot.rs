@@ -0,0 +1,109 @@
+use anyhow::Result;
+
+use dismem::job_factory::JobStreaming;
+use dismem::registry::NodeRegistry;
+use dismem::scheduler::SchedulerBuilder;
+use dismem::snapshot::ClusterSnapshot;
+use dismem::snapshot::occupancy_table;
+use dismem::snapshot::state_table;
+use dismem::snapshot::SnapshotWriter;
+
+#[cfg(test)]
+mod test_snapshot {
+    use super::*;
+
+    #[test]
+    fn snapshot_frames() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("cpu;4;1")?;
+        reg.new_node_from_str("ram;0;8")?;
+        reg.new_connection_from_str("cpu;ram")?;
+
+        let jobs = JobStreaming::from_string(["0;2;3;10;y;0", "1;1;1;30;y;0"].join("\n"))?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
+
+        let path = std::env::temp_dir().join("dismem_snapshot_frames.jsonl");
+        let mut writer = SnapshotWriter::new(&path, 15.0, &sched)?;
+        assert!(sched.tick());
+
+        // VV: Job 0 takes the memory of cpu and borrows the rest from ram, job 1 borrows all of
+        // its memory
+        let frame = ClusterSnapshot::from_scheduler(&sched);
+        assert_eq!(frame.nodes[0].cores_used, 3.0);
+        assert_eq!(frame.nodes[0].jobs, vec![0, 1]);
+        assert_eq!(frame.nodes[1].cores_used, 0.0);
+        assert_eq!(frame.nodes[1].jobs, vec![0, 1]);
+        assert_eq!(frame.nodes[0].memory_used + frame.nodes[1].memory_used, 4.0);
+
+        while sched.tick() {
+            writer.update(&sched)?;
+        }
+        writer.write(&sched)?;
+        writer.flush()?;
+        drop(writer);
+
+        let content = std::fs::read_to_string(&path)?;
+        let frames: Vec<serde_json::Value> = content
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        let times: Vec<f64> = frames.iter().map(|f| f["time"].as_f64().unwrap()).collect();
+        assert_eq!(times, vec![0.0, 30.0, 30.0]);
+        assert!(frames[2]["nodes"][0]["jobs"].as_array().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_state_table() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("n0;2;2")?;
+        let jobs = JobStreaming::from_string(["0;2;1;10;n;0", "1;1;1;10;n;0"].join("\n"))?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).build();
+        assert!(sched.tick());
+
+        // VV: Job 0 takes both cores of n0, job 1 waits for them
+        let table = state_table(&sched);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[1].starts_with("n0"));
+        assert!(lines[1].contains("2/2") && lines[1].contains("1/2"));
+        assert_eq!(lines[2], "running: 1");
+        assert_eq!(lines[3], "  job 0 on n0 started 0 finishes 10");
+        assert_eq!(lines[4], "queuing: 1");
+        assert!(lines[5].starts_with("  job 1 cores 1 memory 1 waits: "));
+        assert!(lines[6].contains("done: 0"));
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_occupancy_at_until() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("gpu;4;8;gpus=2")?;
+        reg.new_node_from_str("cpu;4;8")?;
+
+        let jobs = JobStreaming::from_string(["0;3;2;100;n;0;gpus=1", "1;1;1;10;n;0",
+            "2;2;4;100;n;20"].join("\n"))?;
+        let mut sched = SchedulerBuilder::new(reg, Box::new(jobs)).until(50.0).build();
+        while sched.tick() {}
+
+        // VV: Job 1 finished by 50, jobs 0 and 2 are still running
+        let snapshot = sched.occupancy_snapshot();
+        assert_eq!(snapshot.time, 50.0);
+        let gpu = &snapshot.nodes[0];
+        assert_eq!((gpu.cores_used, gpu.cores_free, gpu.memory_used, gpu.memory_free),
+                   (3.0, 1.0, 2.0, 6.0));
+        assert_eq!(gpu.resources_used["gpus"], 1.0);
+        assert_eq!(gpu.resources_free["gpus"], 1.0);
+        assert_eq!(gpu.jobs, vec![0]);
+        let cpu = &snapshot.nodes[1];
+        assert_eq!((cpu.cores_used, cpu.cores_free, cpu.memory_free), (2.0, 2.0, 4.0));
+        assert_eq!(cpu.jobs, vec![2]);
+
+        let table = occupancy_table(&snapshot);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[1].starts_with("gpu") && lines[1].contains("3/1/4")
+            && lines[1].contains("gpus=1") && lines[1].ends_with(" 0"), "{}", lines[1]);
+        assert!(lines[2].contains("2/2/4") && lines[2].ends_with(" 2"), "{}", lines[2]);
+        assert_eq!(lines[3], "5 of 8 cores used, 0 of 2 nodes idle");
+        Ok(())
+    }
+}
@@ -0,0 +1,323 @@
+use anyhow::Result;
+
+use dismem::failures::ScalingEvent;
+use dismem::job::Job;
+use dismem::job::reset_job_metadata;
+use dismem::job_factory::JobCollection;
+use dismem::registry::NodeRegistry;
+use dismem::scheduler::SchedulerBuilder;
+use dismem::summary::RunSummary;
+use dismem::summary::ThroughputSample;
+use dismem::summary::WaitHistogram;
+
+#[cfg(test)]
+mod test_summary {
+    use super::*;
+
+    #[test]
+    fn summary_of_serial_jobs() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 1.0, 1.0)?;
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = (0..3).map(|_| Job::new(1.0, 1.0, 10.0, false, 0.0)).collect();
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs))).build();
+
+        while sched.tick() {}
+
+        let summary = sched.summary();
+        assert_eq!(summary, RunSummary {
+            total_jobs: 3,
+            jobs_failed: 0,
+            makespan: 30.0,
+            mean_wait: 10.0,
+            median_wait: 10.0,
+            p95_wait: 20.0,
+            mean_turnaround: 20.0,
+            mean_slowdown: 2.0,
+            p95_slowdown: 3.0,
+            core_utilization: 1.0,
+            memory_utilization: 1.0,
+            energy_kwh: 0.0,
+            total_cost: 0.0,
+            cost_per_job: 0.0,
+            truncated: false,
+        });
+
+        let path = std::env::temp_dir().join("dismem_summary_of_serial_jobs.csv");
+        let _ = std::fs::remove_file(&path);
+        summary.append_csv(&path)?;
+        summary.append_csv(&path)?;
+
+        let content = std::fs::read_to_string(&path)?;
+        let lines: Vec<_> = content.lines().collect();
+        assert_eq!(lines, vec![RunSummary::CSV_HEADER, "3,0,30,10,10,20,20,2,3,1,1,0,0,0,false",
+                               "3,0,30,10,10,20,20,2,3,1,1,0,0,0,false"]);
+        Ok(())
+    }
+
+    #[test]
+    fn summary_of_steady_state_window() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 2.0, 2.0)?;
+
+        reset_job_metadata();
+        let jobs = vec![
+            Job::new(1.0, 1.0, 10.0, false, 0.0),
+            Job::new(1.0, 1.0, 30.0, false, 0.0),
+            Job::new(1.0, 1.0, 10.0, false, 20.0),
+            Job::new(1.0, 1.0, 10.0, false, 35.0),
+        ];
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .stats_window(5.0, 25.0)
+            .build();
+
+        while sched.tick() {}
+
+        // VV: The last job runs after the window, the others use 5 + 20 + 5 core-seconds of the
+        // 2 * 20 core-seconds in the window
+        let summary = sched.summary();
+        assert_eq!(summary.total_jobs, 3);
+        assert_eq!(summary.makespan, 45.0);
+        assert_eq!(summary.mean_wait, 0.0);
+        assert_eq!(summary.mean_turnaround, 50.0 / 3.0);
+        assert_eq!(summary.core_utilization, 0.75);
+        assert_eq!(summary.memory_utilization, 0.75);
+        Ok(())
+    }
+
+    #[test]
+    fn summary_memory_bound_utilization() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 8.0, 8.0)?;
+
+        // VV: Each job takes 1/8 of the cores but 3/4 of the memory, so they run one at a time
+        reset_job_metadata();
+        let jobs: Vec<Job> = (0..2).map(|_| Job::new(1.0, 6.0, 10.0, false, 0.0)).collect();
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs))).build();
+
+        while sched.tick() {}
+
+        let summary = sched.summary();
+        assert_eq!(summary.makespan, 20.0);
+        assert_eq!(summary.core_utilization, 0.125);
+        assert_eq!(summary.memory_utilization, 0.75);
+        Ok(())
+    }
+
+    #[test]
+    fn summary_bounded_slowdown() -> Result<()> {
+        let run = |threshold: f32| -> Result<RunSummary> {
+            let mut reg = NodeRegistry::new();
+            reg.new_node("node", 1.0, 1.0)?;
+
+            reset_job_metadata();
+            let jobs: Vec<Job> = (0..2).map(|_| Job::new(1.0, 1.0, 2.0, false, 0.0)).collect();
+            let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+                .slowdown_threshold(threshold)
+                .build();
+
+            while sched.tick() {}
+            Ok(sched.summary())
+        };
+
+        // VV: The 2nd job waits 2 seconds and then runs for 2 seconds
+        let summary = run(1.0)?;
+        assert_eq!(summary.mean_slowdown, 1.5);
+        assert_eq!(summary.p95_slowdown, 2.0);
+
+        // VV: Both jobs are shorter than the threshold and the slowdown can never drop below 1
+        let summary = run(10.0)?;
+        assert_eq!(summary.mean_slowdown, 1.0);
+        assert_eq!(summary.p95_slowdown, 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn summary_truncated_at_max_jobs() -> Result<()> {
+        let run = |max_jobs: usize| -> Result<RunSummary> {
+            let mut reg = NodeRegistry::new();
+            reg.new_node("node", 1.0, 1.0)?;
+
+            reset_job_metadata();
+            let jobs: Vec<Job> = (0..3).map(|_| Job::new(1.0, 1.0, 10.0, false, 0.0)).collect();
+            let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+                .max_jobs(max_jobs)
+                .build();
+
+            while sched.tick() {}
+            assert!(!sched.has_unschedulable());
+            Ok(sched.summary())
+        };
+
+        // VV: The simulation stops when the 2nd job finishes even though the 3rd one is running
+        let summary = run(2)?;
+        assert!(summary.truncated);
+        assert_eq!(summary.total_jobs, 2);
+        assert_eq!(summary.makespan, 20.0);
+
+        let summary = run(3)?;
+        assert!(!summary.truncated);
+        assert_eq!(summary.total_jobs, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn summary_of_energy() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("busy;2;2;idle_watts=100;max_watts=300")?;
+        reg.new_node_from_str("idle;1;1;idle_watts=50;max_watts=50")?;
+        assert!(reg.new_node_from_str("broken;1;1;idle_watts=50").is_err());
+        assert!(reg.new_node_from_str("broken;1;1;idle_watts=50;max_watts=10").is_err());
+
+        reset_job_metadata();
+        let jobs = vec![Job::new(2.0, 1.0, 36.0, false, 0.0)];
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs))).build();
+
+        while sched.tick() {}
+
+        // VV: The busy node draws 300W for 36s and the idle node draws 50W even though it never
+        // runs a job
+        let summary = sched.summary();
+        assert!((summary.energy_kwh - (300.0 * 36.0 + 50.0 * 36.0) / 3.6e6).abs() < 1e-12);
+        Ok(())
+    }
+
+    #[test]
+    fn summary_json_of_unschedulable_run() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 1.0, 1.0)?;
+
+        // VV: Job 1 is too big for the cluster, job 2 fails, and job 3 waits for it forever
+        reset_job_metadata();
+        let jobs: Vec<Job> = ["?;1;1;10;n;0", "?;2;1;10;n;0", "?;1;1;10;n;0;walltime_limit=5",
+            "?;1;1;10;n;0;depends_on=[2]"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs))).build();
+        while sched.tick() && !sched.has_unschedulable() {}
+
+        let path = std::env::temp_dir().join("dismem_summary_json_of_unschedulable_run.json");
+        let _ = std::fs::remove_file(&path);
+        let exit = sched.exit_summary();
+        assert!(exit.unschedulable);
+        exit.write_json(&path)?;
+
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        assert_eq!(json["unschedulable"], true);
+        assert_eq!(json["jobs_done"], 1);
+        assert_eq!(json["jobs_rejected"], 1);
+        assert_eq!(json["jobs_waiting"], 1);
+        assert_eq!(json["jobs_deadlocked"], 1);
+        assert_eq!(json["total_jobs"], 2);
+        assert_eq!(json["jobs_failed"], 1);
+        assert_eq!(json["makespan"], 15.0);
+        assert_eq!(json["core_utilization"], 1.0);
+        Ok(())
+    }
+
+    /// Runs 4 jobs that each use 1 core for 10s on the @nodes with the @scaling events
+    fn run_on_nodes(nodes: &[&str], scaling: &[&str]) -> Result<RunSummary> {
+        let mut reg = NodeRegistry::new();
+        for node in nodes {
+            reg.new_node_from_str(node)?;
+        }
+        let scaling = scaling.iter()
+            .map(|line| line.parse())
+            .collect::<Result<Vec<ScalingEvent>>>()?;
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = (0..4).map(|_| Job::new(1.0, 1.0, 10.0, false, 0.0)).collect();
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .scaling(scaling)?
+            .build();
+
+        while sched.tick() {}
+        Ok(sched.summary())
+    }
+
+    #[test]
+    fn summary_of_node_cost() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        assert!(reg.new_node_from_str("broken;1;1;cost=-1").is_err());
+        assert!(reg.new_node_from_str("broken;1;1;cost=inf").is_err());
+
+        // VV: Both node sets finish the jobs at 10s but the big nodes cost less per core
+        let cheap = run_on_nodes(&["a;2;2;cost=360", "b;2;2;cost=360"], &[])?;
+        let expensive = run_on_nodes(&["a;1;1;cost=360", "b;1;1;cost=360", "c;1;1;cost=360",
+            "d;1;1;cost=360"], &[])?;
+        assert_eq!(cheap.makespan, expensive.makespan);
+        assert!((cheap.total_cost - 2.0).abs() < 1e-9);
+        assert!((expensive.total_cost - 4.0).abs() < 1e-9);
+        assert!((cheap.cost_per_job - 0.5).abs() < 1e-9);
+        assert!(cheap.total_cost < expensive.total_cost);
+
+        // VV: The extra node costs money between 10s and 15s while it is part of the cluster even
+        // though it never runs a job, the base node costs money for the entire 20s
+        let scaled = run_on_nodes(&["base;2;2;cost=360"],
+            &["10;add;extra;2;2;cost=720", "15;remove;extra;kill"])?;
+        assert_eq!(scaled.makespan, 20.0);
+        assert!((scaled.total_cost - (2.0 + 1.0)).abs() < 1e-9);
+        assert!((scaled.cost_per_job - 0.75).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn summary_wait_histogram() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 1.0, 1.0)?;
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = (0..4).map(|_| Job::new(1.0, 1.0, 10.0, false, 0.0)).collect();
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs))).build();
+
+        // VV: Stop while the third job runs and the last one is still in the queue
+        while sched.now <= 20.0 {
+            sched.tick();
+        }
+
+        let histogram = sched.wait_histogram(&[10.0, 20.0])?;
+        assert_eq!(histogram.counts, vec![1, 1, 1]);
+        assert_eq!(histogram.never_started, 1);
+        assert_eq!(histogram.labels(), vec!["[0, 10)", "[10, 20)", "[20, inf)"]);
+        assert_eq!(histogram.to_string().lines().nth(3).unwrap().trim(), "never started        1 \
+            ##################################################");
+
+        while sched.tick() {}
+        let histogram = sched.wait_histogram(&[10.0, 20.0])?;
+        assert_eq!((histogram.counts, histogram.never_started), (vec![1, 1, 2], 0));
+
+        assert!(WaitHistogram::new(&[10.0, 10.0]).is_err());
+        assert!(WaitHistogram::new(&[0.0]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn summary_throughput_series() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node("node", 2.0, 2.0)?;
+
+        reset_job_metadata();
+        let mut jobs: Vec<Job> = (0..2).map(|_| Job::new(1.0, 1.0, 10.0, false, 0.0)).collect();
+        jobs.push(Job::new(1.0, 1.0, 5.0, false, 12.0));
+        jobs.push(Job::new(1.0, 1.0, 20.0, false, 12.0));
+        let jobs = Box::new(JobCollection::new(jobs));
+        let mut sched = SchedulerBuilder::new(reg, jobs).until(25.0).build();
+        while sched.tick() {}
+
+        // VV: The first 2 jobs finish together, the last one is still running when the run stops
+        assert_eq!(sched.throughput(), vec![
+            ThroughputSample { time: 10.0, done: 2 },
+            ThroughputSample { time: 17.0, done: 3 },
+        ]);
+        assert_eq!(sched.now, 25.0);
+        assert_eq!(sched.summary().makespan, 17.0);
+
+        let path = std::env::temp_dir().join("dismem_summary_throughput_series.csv");
+        ThroughputSample::write_csv(&sched.throughput(), &path)?;
+        let content = std::fs::read_to_string(&path)?;
+        assert_eq!(content.lines().collect::<Vec<_>>(), vec!["time,done", "10,2", "17,3"]);
+        Ok(())
+    }
+}
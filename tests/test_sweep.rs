@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use dismem::job_factory::JobStreaming;
+use dismem::registry::NodeRegistry;
+use dismem::scheduler::SchedulerBuilder;
+use dismem::sweep;
+use dismem::sweep::SweepResult;
+
+#[cfg(test)]
+mod test_sweep {
+    use super::*;
+
+    fn scheduler(path: &Path) -> Result<dismem::scheduler::Scheduler> {
+        let registry = NodeRegistry::from_paths(
+            Path::new("examples/dismem_racks/nodes.csv"),
+            Path::new("examples/dismem_racks/connections.csv"))?;
+        let job_factory = JobStreaming::from_path(path)?;
+        Ok(SchedulerBuilder::new(registry, Box::new(job_factory)).build())
+    }
+
+    #[test]
+    fn sweep_isolates_failed_runs() -> Result<()> {
+        let dir = std::env::temp_dir().join("dismem_sweep_isolates_failed_runs");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("nested"))?;
+
+        std::fs::write(dir.join("b.jobs"), "?;1;1;10;n;0\n?;1;1;10;n;0\n")?;
+        std::fs::write(dir.join("a.jobs"), "?;2;2;30;n;0\n")?;
+        std::fs::write(dir.join("c.jobs"), "?;not-a-number;1;10;n;0\n")?;
+        std::fs::write(dir.join(".hidden"), "")?;
+
+        let paths = sweep::job_files(&dir)?;
+        let names: Vec<_> = paths.iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["a.jobs", "b.jobs", "c.jobs"]);
+
+        let results = sweep::run_sweep(&paths, scheduler);
+        let workloads: Vec<_> = results.iter().map(SweepResult::workload).collect();
+        assert_eq!(workloads, names);
+
+        let a = results[0].summary.as_ref().unwrap();
+        assert_eq!(a.total_jobs, 1);
+        assert_eq!(a.makespan, 30.0);
+
+        let b = results[1].summary.as_ref().unwrap();
+        assert_eq!(b.total_jobs, 2);
+        assert_eq!(b.makespan, 10.0);
+
+        assert!(results[2].summary.is_err());
+        assert!(results[2].to_csv_row().starts_with("\"c.jobs\",\"Invalid job on line 1"));
+
+        let path_csv = dir.join("summary.csv");
+        sweep::append_csv(&results, &path_csv)?;
+        let csv = std::fs::read_to_string(&path_csv)?;
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], SweepResult::csv_header());
+        assert_eq!(lines[0].split(',').count(), lines[1].split(',').count());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn sweep_of_empty_directory() -> Result<()> {
+        let dir = std::env::temp_dir().join("dismem_sweep_of_empty_directory");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+
+        assert!(sweep::job_files(&dir).is_err());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
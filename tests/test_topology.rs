@@ -0,0 +1,179 @@
+use std::ops::RangeInclusive;
+
+use anyhow::Result;
+
+use dismem::job::Job;
+use dismem::job::reset_job_metadata;
+use dismem::job_factory::JobCollection;
+use dismem::registry::ClusterSpec;
+use dismem::registry::NodeRegistry;
+use dismem::registry::TopologyPattern;
+use dismem::scheduler::SchedulerBuilder;
+use dismem::topology::TopologyAware;
+
+#[cfg(test)]
+mod test_topology {
+    use super::*;
+
+    /// Creates 2 racks of 2 nodes, the nodes of a rack are connected to each other
+    fn registry_init_racks() -> Result<NodeRegistry> {
+        let mut reg = NodeRegistry::new();
+        for name in ["a0", "b0", "a1", "b1"] {
+            reg.new_node(name, 2.0, 2.0)?;
+        }
+        reg.new_connection_from_str("a0;a1")?;
+        reg.new_connection_from_str("b0;b1")?;
+
+        Ok(reg)
+    }
+
+    #[test]
+    fn topology_hops_from() -> Result<()> {
+        let reg = registry_init_racks()?;
+
+        assert_eq!(reg.hops_from(0), vec![Some(0), None, Some(1), None]);
+        Ok(())
+    }
+
+    #[test]
+    fn topology_gang_stays_in_rack() -> Result<()> {
+        let reg = registry_init_racks()?;
+
+        reset_job_metadata();
+        let jobs: Vec<Job> = vec!["?;4;4;10;n;0;gang=y".parse().unwrap()];
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .policy(Box::new(TopologyAware::default()))
+            .build();
+
+        sched.tick();
+
+        let job = &sched.jobs_running[0];
+        let mut nodes: Vec<usize> = job.gang_cores.iter().map(|(uid, _)| *uid).collect();
+        nodes.sort_unstable();
+        assert!(nodes == vec![0, 2] || nodes == vec![1, 3]);
+        assert_eq!(job.hops, Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn topology_gang_falls_back_to_any_nodes() -> Result<()> {
+        let reg = registry_init_racks()?;
+
+        // VV: No rack has 6 free cores, the job must still run on nodes of both racks
+        reset_job_metadata();
+        let jobs: Vec<Job> = vec!["?;6;6;10;n;0;gang=y".parse().unwrap()];
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .policy(Box::new(TopologyAware::default()))
+            .build();
+
+        sched.tick();
+
+        let job = &sched.jobs_running[0];
+        assert_eq!(job.gang_cores.len(), 3);
+        assert_eq!(job.hops, None);
+        Ok(())
+    }
+
+    /// Creates racks ra and rb in zone z0 and rack rc in zone z1, each with 2 nodes of 4 cores.
+    /// The nodes of each rack are not next to each other and there are no connections
+    fn registry_init_zones() -> Result<NodeRegistry> {
+        let mut reg = NodeRegistry::new();
+        for line in ["a0;4;4;rack=ra;zone=z0", "b0;4;4;rack=rb;zone=z0", "c0;4;4;rack=rc;zone=z1",
+            "a1;4;4;rack=ra;zone=z0", "b1;4;4;rack=rb;zone=z0", "c1;4;4;rack=rc;zone=z1"] {
+            reg.new_node_from_str(line)?;
+        }
+        Ok(reg)
+    }
+
+    fn gang_nodes(reg: NodeRegistry, line: &str) -> Vec<String> {
+        reset_job_metadata();
+        let jobs: Vec<Job> = vec![line.parse().unwrap()];
+        let mut sched = SchedulerBuilder::new(reg, Box::new(JobCollection::new(jobs)))
+            .policy(Box::new(TopologyAware::default()))
+            .build();
+        sched.tick();
+
+        let job = &sched.jobs_running[0];
+        let mut nodes: Vec<String> = job.gang_cores
+            .iter()
+            .map(|(uid, _)| sched.registry.nodes[*uid].name.clone())
+            .collect();
+        nodes.sort();
+        nodes
+    }
+
+    #[test]
+    fn topology_gang_fills_rack_then_zone() -> Result<()> {
+        // VV: FirstFit would take a0, b0, and c0
+        assert_eq!(gang_nodes(registry_init_zones()?, "?;8;8;10;n;0;gang=y"), vec!["a0", "a1"]);
+
+        // VV: The job fills rack ra and spills over to rack rb which is in the same zone
+        let nodes = gang_nodes(registry_init_zones()?, "?;12;12;10;n;0;gang=y");
+        assert_eq!(nodes, vec!["a0", "a1", "b0"]);
+        Ok(())
+    }
+
+    #[test]
+    fn topology_rack_in_one_zone() -> Result<()> {
+        let mut reg = NodeRegistry::new();
+        reg.new_node_from_str("a0;4;4;rack=ra;zone=z0")?;
+        assert!(reg.new_node_from_str("a1;4;4;rack=ra;zone=z1").is_err());
+        assert!(reg.new_node_from_str("a2;4;4;rack=ra").is_err());
+        assert!(reg.new_node_from_str("a3;4;4;rack=").is_err());
+
+        let a4 = reg.new_node_from_str("a4;4;4;rack=ra;zone=z0")?.uid;
+        reg.new_node_from_str("b0;4;4;rack=rb;zone=z0")?;
+        reg.new_node_from_str("c0;4;4")?;
+        let locality: Vec<usize> = reg.nodes.iter().map(|node| reg.nodes[a4].locality(node)).collect();
+        assert_eq!(locality, vec![0, 0, 1, 2]);
+
+        let reg = NodeRegistry::from_toml_str("[[nodes]]\nname = \"a\"\ncores = 1\nmemory = 1\n\
+            rack = \"ra\"\nzone = \"z0\"\n")?;
+        assert_eq!((reg.nodes[0].rack.as_deref(), reg.nodes[0].zone.as_deref()),
+                   (Some("ra"), Some("z0")));
+        Ok(())
+    }
+
+    #[test]
+    fn topology_generate_cluster() -> Result<()> {
+        let mut spec = ClusterSpec {
+            nodes: 10,
+            cores: 4..=16,
+            memory: 8..=8,
+            resources: [("gpus".to_string(), 0..=2)].into(),
+            topology: TopologyPattern::FatTree(2),
+        };
+
+        let reg = NodeRegistry::generate(&spec, 7)?;
+        assert_eq!(reg.nodes.len(), 10);
+        assert_eq!(reg.nodes[9].name, "node9");
+        assert!(reg.nodes.iter().all(|node| (4.0..=16.0).contains(&node.cores.capacity)
+            && node.memory.capacity == 8.0 && node.resources["gpus"].capacity <= 2.0));
+        assert!(reg.nodes.iter().any(|node| node.cores.capacity != reg.nodes[0].cores.capacity));
+
+        // VV: The same seed produces the same cluster
+        let cores = |reg: &NodeRegistry| -> Vec<f32> {
+            reg.nodes.iter().map(|node| node.cores.capacity).collect()
+        };
+        assert_eq!(cores(&NodeRegistry::generate(&spec, 7)?), cores(&reg));
+
+        // VV: 2 nodes per rack and 2 racks per zone, nodes borrow from their rack only
+        assert_eq!((reg.nodes[5].rack.as_deref(), reg.nodes[5].zone.as_deref()),
+                   (Some("rack2"), Some("zone1")));
+        assert_eq!(reg.connections[&5], vec![4]);
+        assert_eq!(reg.hops_from(4)[6], None);
+
+        spec.topology = TopologyPattern::Ring;
+        let reg = NodeRegistry::generate(&spec, 7)?;
+        assert_eq!(reg.connections[&0], vec![9, 1]);
+        assert_eq!(reg.hops_from(0)[5], Some(5));
+
+        spec.topology = TopologyPattern::FullyConnected;
+        let reg = NodeRegistry::generate(&spec, 7)?;
+        assert_eq!(reg.connections[&3].len(), 9);
+
+        spec.cores = RangeInclusive::new(4, 2);
+        assert!(NodeRegistry::generate(&spec, 7).is_err());
+        Ok(())
+    }
+}